@@ -0,0 +1,79 @@
+//! Compares sequential and parallel signature verification for a block's worth
+//! of transactions. Run with `cargo bench --bench signature_verification`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use pqcrypto_dilithium::dilithium5;
+use qanto::qantodag::{HomomorphicEncrypted, QuantumResistantSignature};
+use qanto::transaction::{self, Input, Output, Transaction};
+use qanto::types::{Address, TxId};
+use std::collections::HashMap;
+
+const BATCH_SIZES: [usize; 3] = [64, 512, 2_048];
+
+/// A distinct input per transaction, so no two share a signing payload.
+fn input_id(i: usize) -> TxId {
+    let mut bytes = [0u8; 32];
+    bytes[24..].copy_from_slice(&(i as u64).to_be_bytes());
+    TxId::new(bytes)
+}
+
+fn signed_transactions(count: usize) -> Vec<Transaction> {
+    let (public_key, secret_key) = dilithium5::keypair();
+    (0..count)
+        .map(|i| {
+            let mut tx = Transaction {
+                id: TxId::ZERO,
+                sender: Address::new([0xab; 32]),
+                receiver: Address::new([0xcd; 32]),
+                amount: 1_000 + i as u64,
+                fee: 10,
+                inputs: vec![Input {
+                    tx_id: input_id(i),
+                    output_index: 0,
+                    htlc_preimage: None,
+                }],
+                outputs: vec![Output {
+                    address: Address::new([0xcd; 32]),
+                    amount: 1_000 + i as u64,
+                    homomorphic_encrypted: HomomorphicEncrypted::new(1_000 + i as u64, &[]),
+                    htlc: None,
+                }],
+                qr_signature: QuantumResistantSignature {
+                    signer_public_key: vec![],
+                    signature: vec![],
+                },
+                timestamp: 1_700_000_000 + i as u64,
+                metadata: HashMap::new(),
+                valid_until: None,
+            };
+            let message = tx.signing_bytes().expect("signing bytes");
+            tx.qr_signature = QuantumResistantSignature::sign(&secret_key, &public_key, &message)
+                .expect("signing succeeds");
+            tx
+        })
+        .collect()
+}
+
+fn signature_verification_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("signature_verification");
+    group.sample_size(10);
+    for size in BATCH_SIZES {
+        let txs = signed_transactions(size);
+        let tx_refs: Vec<&Transaction> = txs.iter().collect();
+        group.throughput(Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::new("sequential", size), &tx_refs, |b, txs| {
+            b.iter(|| {
+                txs.iter()
+                    .try_for_each(|tx| tx.verify_signature())
+                    .expect("valid signatures")
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", size), &tx_refs, |b, txs| {
+            b.iter(|| transaction::verify_signatures_parallel(txs).expect("valid signatures"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, signature_verification_benchmark);
+criterion_main!(benches);
@@ -1,43 +1,118 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use qanto::config::Config;
+use clap::{Args, Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
+use qanto::config::{Config, ConfigSources, NetworkPreset};
+use qanto::data_dir::{DataDir, DataDirLock};
+use qanto::export::{self, ExportFilter};
+use qanto::integrity;
 use qanto::node::Node;
+use qanto::schema::{self, CURRENT_SCHEMA_VERSION};
+use qanto::storage::RocksDbStore;
+use qanto::tx_index;
+use qanto::utxo_set;
 use qanto::wallet::Wallet;
 use secrecy::{ExposeSecret, SecretString};
 use std::fs;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use thiserror::Error;
-use tracing_subscriber::EnvFilter;
+use tracing::warn;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{fmt, reload, EnvFilter, Registry};
+
+/// Swaps the log filter of the running process.
+type LogFilterHandle = reload::Handle<EnvFilter, Registry>;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Qanto Node CLI", long_about = None)]
 struct Cli {
+    #[arg(short, long, global = true, default_value = "config.toml")]
+    config: String,
+    /// Network preset the configuration builds on: local, testnet or mainnet.
+    #[arg(long, global = true)]
+    network: Option<NetworkPreset>,
+    /// Overrides a setting, e.g. `--set p2p.mesh_n=12`. May be repeated.
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    overrides: Vec<String>,
+    /// Data directory, overriding `data_dir` from the configuration.
+    #[arg(long, global = true)]
+    data_dir: Option<PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
 
+/// The wallet a node runs with.
+#[derive(Args, Debug)]
+struct NodeArgs {
+    /// Wallet file [default: <data_dir>/keys/wallet.key]
+    #[arg(short, long)]
+    wallet: Option<PathBuf>,
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Starts the Qanto node
     Start {
-        #[arg(short, long, default_value = "config.toml")]
-        config: String,
-        #[arg(short, long, default_value = "wallet.key")]
-        wallet: String,
-        #[arg(long, default_value = "p2p_identity.key")]
-        p2p_identity: String,
-        #[arg(long, default_value = "peer_cache.json")]
-        peer_cache: String,
+        #[command(flatten)]
+        node: NodeArgs,
         /// Clean the database directory before starting to prevent corruption errors.
         #[arg(long)]
         clean: bool,
     },
+    /// Writes stored blocks to a portable, checksummed file. Stop the node first.
+    Export {
+        #[arg(short, long, default_value = "chain.qexp")]
+        output: PathBuf,
+        #[arg(long, default_value_t = 0)]
+        from_height: u64,
+        #[arg(long)]
+        to_height: Option<u64>,
+        /// Only export blocks of this chain.
+        #[arg(long)]
+        chain: Option<u32>,
+    },
+    /// Validates and applies the blocks of an export file, as if they had
+    /// arrived from peers.
+    Import {
+        file: PathBuf,
+        #[command(flatten)]
+        node: NodeArgs,
+        /// Keep the node running once the import is done.
+        #[arg(long)]
+        start: bool,
+    },
+    /// Rebuilds the transaction, address-history and spent-output indexes
+    /// from the blocks in the node database. Stop the node first.
+    Reindex,
+    /// Node database maintenance. Stop the node first.
+    Db {
+        #[command(subcommand)]
+        command: DbCommands,
+    },
     /// Generates a new wallet
     GenerateWallet {
-        #[arg(short, long, default_value = "wallet.key")]
-        output: String,
+        /// [default: <data_dir>/keys/wallet.key]
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum DbCommands {
+    /// Upgrades the database to the current schema version.
+    Migrate {
+        /// List the pending migrations without changing the database.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Re-validates every stored block, replays the UTXO set and supply from
+    /// genesis and reports any inconsistency.
+    Verify {
+        /// Delete every block after the last finalized checkpoint before the
+        /// first fault.
+        #[arg(long)]
+        truncate: bool,
     },
 }
 
@@ -69,70 +144,253 @@ fn prompt_for_password(confirm: bool) -> Result<SecretString, CliError> {
     Ok(password)
 }
 
+fn progress_bar() -> Result<ProgressBar> {
+    Ok(ProgressBar::new(0).with_style(ProgressStyle::with_template(
+        "{bar:40} {pos}/{len} blocks ({eta})",
+    )?))
+}
+
+impl Cli {
+    /// The configuration layers named on the command line. A missing file is
+    /// skipped rather than created.
+    fn config_sources(&self) -> ConfigSources {
+        ConfigSources {
+            preset: self.network,
+            file: Path::new(&self.config)
+                .exists()
+                .then(|| self.config.clone()),
+            overrides: self.overrides.clone(),
+        }
+    }
+}
+
+/// Opens the node database of `data_dir` for maintenance, holding the data
+/// directory lock so a running node cannot use it at the same time.
+fn open_store(data_dir: &DataDir) -> Result<(DataDirLock, RocksDbStore)> {
+    let lock = data_dir.lock()?;
+    let store = RocksDbStore::open(data_dir.db())?;
+    Ok((lock, store))
+}
+
+/// Loads the configuration and wallet and initializes the node's services.
+async fn open_node(
+    args: NodeArgs,
+    config_path: String,
+    sources: &ConfigSources,
+    data_dir: Option<PathBuf>,
+    log_filter: LogFilterHandle,
+) -> Result<Node> {
+    if !Path::new(&config_path).exists() {
+        eprintln!("Configuration file not found: {config_path}");
+        std::process::exit(1);
+    }
+    println!("Configuration loaded from '{config_path}'.");
+
+    // Correctly load config and wallet with password.
+    let node_config = Config::load_layered(sources)?;
+    let data_dir = DataDir::new(data_dir.unwrap_or_else(|| node_config.data_dir.clone().into()));
+    let wallet_path = args.wallet.unwrap_or_else(|| data_dir.wallet());
+    let password = prompt_for_password(false)?;
+    // Pass the SecretString directly, without re-wrapping it.
+    let wallet_instance = Wallet::from_file(&wallet_path, &password)?;
+    let wallet_arc = Arc::new(wallet_instance);
+
+    println!("Initializing Qanto services...");
+    let node = Node::new(node_config, sources.clone(), wallet_arc, data_dir).await?;
+    follow_log_level(&node, log_filter);
+    Ok(node)
+}
+
+/// Keeps the log filter at the configured `logging.level`, including after
+/// configuration reloads. `RUST_LOG` takes precedence until the level is
+/// changed by a reload.
+fn follow_log_level(node: &Node, log_filter: LogFilterHandle) {
+    let mut updates = node.config_updates();
+    let mut level = updates.borrow_and_update().logging.level.clone();
+    let set_level = move |level: &str| match EnvFilter::try_new(level) {
+        Ok(filter) => {
+            if let Err(e) = log_filter.reload(filter) {
+                warn!("Failed to change the log level to '{level}': {e}");
+            }
+        }
+        Err(e) => warn!("Invalid log level '{level}': {e}"),
+    };
+    if std::env::var_os(EnvFilter::DEFAULT_ENV).is_none() {
+        set_level(&level);
+    }
+    tokio::spawn(async move {
+        while updates.changed().await.is_ok() {
+            let new_level = updates.borrow_and_update().logging.level.clone();
+            if new_level != level {
+                set_level(&new_level);
+                level = new_level;
+            }
+        }
+    });
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // Correctly initialize logging.
     let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
-    tracing_subscriber::fmt().with_env_filter(filter).init();
+    let (filter, log_filter) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer())
+        .init();
 
     let cli = Cli::parse();
+    let sources = cli.config_sources();
+    // Maintenance commands work without a configuration file.
+    let data_dir = || -> Result<DataDir> {
+        Ok(DataDir::new(match &cli.data_dir {
+            Some(dir) => dir.clone(),
+            None => Config::load_layered(&sources)?.data_dir.into(),
+        }))
+    };
 
     match cli.command {
-        Commands::Start {
-            config,
-            wallet,
-            p2p_identity,
-            peer_cache,
-            clean,
-        } => {
+        Commands::Start { node, clean } => {
             println!("Qanto node starting...");
-            let config_path = Path::new(&config);
-            if !config_path.exists() {
-                eprintln!("Configuration file not found: {}", config_path.display());
-                std::process::exit(1);
-            }
-            println!("Configuration loaded from '{}'.", config_path.display());
-
-            let db_path = "qantodag_db_evolved";
             if clean {
-                println!("'--clean' flag detected. Removing old database directory: {db_path}");
-                if Path::new(db_path).exists() {
-                    if let Err(e) = fs::remove_dir_all(db_path) {
-                        eprintln!("Failed to remove database directory '{db_path}': {e}");
+                let data_dir = data_dir()?;
+                let db_path = data_dir.db();
+                println!(
+                    "'--clean' flag detected. Removing old database directory: {}",
+                    db_path.display()
+                );
+                if db_path.exists() {
+                    let _lock = data_dir.lock()?;
+                    if let Err(e) = fs::remove_dir_all(&db_path) {
+                        eprintln!(
+                            "Failed to remove database directory '{}': {e}",
+                            db_path.display()
+                        );
                         std::process::exit(1);
                     }
                     println!("Database directory removed successfully.");
                 }
             }
 
-            // Correctly load config and wallet with password.
-            let password = prompt_for_password(false)?;
-            let node_config = Config::load(&config)?;
-            // Pass the SecretString directly, without re-wrapping it.
-            let wallet_instance = Wallet::from_file(&wallet, &password)?;
-            let wallet_arc = Arc::new(wallet_instance);
-
-            println!("Initializing Qanto services...");
-            let node = Node::new(
-                node_config,
-                config.clone(),
-                wallet_arc,
-                &p2p_identity,
-                peer_cache,
-            )
-            .await?;
-
+            let node = open_node(node, cli.config, &sources, cli.data_dir, log_filter).await?;
             node.start().await?;
         }
+        Commands::Export {
+            output,
+            from_height,
+            to_height,
+            chain,
+        } => {
+            let (_lock, store) = open_store(&data_dir()?)?;
+            let filter = ExportFilter {
+                from_height,
+                to_height: to_height.unwrap_or(u64::MAX),
+                chain,
+            };
+            let progress = progress_bar()?;
+            let stats = export::export(&store, &output, filter, &progress)?;
+            progress.finish();
+            println!(
+                "Exported {} blocks to '{}'.",
+                stats.blocks,
+                output.display()
+            );
+            if stats.pruned > 0 {
+                println!(
+                    "Left out {} matching blocks whose bodies are pruned.",
+                    stats.pruned
+                );
+            }
+        }
+        Commands::Import { file, node, start } => {
+            let node = open_node(node, cli.config, &sources, cli.data_dir, log_filter).await?;
+            println!("Importing blocks from '{}'...", file.display());
+            let progress = progress_bar()?;
+            let stats = export::import(&node.dag, &node.utxos, file, &progress).await?;
+            progress.finish();
+            println!(
+                "Applied {} blocks; {} were already known.",
+                stats.applied, stats.skipped
+            );
+            if start {
+                node.start().await?;
+            }
+        }
+        Commands::Reindex => {
+            let data_dir = data_dir()?;
+            println!(
+                "Rebuilding history indexes in '{}'...",
+                data_dir.db().display()
+            );
+            let (_lock, store) = open_store(&data_dir)?;
+            let stats = tx_index::reindex(&store)?;
+            println!(
+                "Indexed {} transactions from {} blocks.",
+                stats.transactions, stats.blocks
+            );
+        }
+        Commands::Db {
+            command: DbCommands::Migrate { dry_run },
+        } => {
+            let data_dir = data_dir()?;
+            let db_path = data_dir.db();
+            let (_lock, store) = open_store(&data_dir)?;
+            match schema::schema_version(&store)? {
+                Some(version) => println!(
+                    "Database '{}' is at schema v{version}; this node writes v{CURRENT_SCHEMA_VERSION}.",
+                    db_path.display()
+                ),
+                None => println!("Database '{}' is empty.", db_path.display()),
+            }
+            let steps = schema::migrate(&store, !dry_run)?;
+            if steps.is_empty() {
+                println!("No migrations to run.");
+            }
+            for step in &steps {
+                println!(
+                    "  v{}: {} ({} records)",
+                    step.to_version, step.description, step.records
+                );
+            }
+            if dry_run && !steps.is_empty() {
+                println!("Dry run: no changes were written.");
+            }
+        }
+        Commands::Db {
+            command: DbCommands::Verify { truncate },
+        } => {
+            // The configuration holds the genesis parameters the replay starts from.
+            let node_config = Config::load_layered(&sources)?;
+            let data_dir = DataDir::new(cli.data_dir.unwrap_or(node_config.data_dir.into()));
+            let (_lock, store) = open_store(&data_dir)?;
+            println!("Verifying '{}'...", data_dir.db().display());
+            let genesis =
+                utxo_set::genesis_utxos(node_config.num_chains, node_config.genesis_validator);
+            let report = integrity::verify(&store, genesis)?;
+            println!("{report}");
+            if truncate {
+                let removed = integrity::truncate(&store, &report)?;
+                println!("Removed {removed} entries after the checkpoint.");
+            } else if !report.is_consistent() {
+                std::process::exit(1);
+            }
+        }
         Commands::GenerateWallet { output } => {
+            let output = match output {
+                Some(output) => output,
+                None => data_dir()?.wallet(),
+            };
             println!("Generating new wallet...");
             let password = prompt_for_password(true)?;
             let wallet = Wallet::new()?;
+            if let Some(parent) = output.parent() {
+                fs::create_dir_all(parent)?;
+            }
             // Correctly save the wallet with the SecretString.
             wallet.save_to_file(&output, &password)?;
             println!(
                 "New wallet saved to '{}'. Address: {}",
-                output,
+                output.display(),
                 wallet.address()
             );
             println!("IMPORTANT: Please back up this file securely and remember your password.");
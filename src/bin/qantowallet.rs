@@ -1,9 +1,16 @@
 use anyhow::{anyhow, Context, Result};
+use bech32::Hrp;
 use clap::{Parser, Subcommand};
 use pqcrypto_traits::sign::{PublicKey, SecretKey};
 use qanto::{
-    qantodag::UTXO,
-    transaction::{self, Input, Output, Transaction, TransactionConfig},
+    codec,
+    config::DEFAULT_NETWORK_ID,
+    fee_estimator::{self, FeeEstimate},
+    fee_policy::{FeePolicy, DEV_ADDRESS},
+    htlc::{self, HashTimeLock},
+    qantodag::{AddressUtxos, HomomorphicEncrypted, InclusionWindow, UTXO},
+    transaction::{self, Input, Output, Transaction, TransactionConfig, TxExpiry},
+    types::{self, Address, OutPoint},
     wallet::{Wallet, WalletError},
 };
 use reqwest::Client;
@@ -12,12 +19,14 @@ use std::collections::{HashMap, HashSet};
 use std::io::{self, Write};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
 // --- Constants ---
-const DEV_ADDRESS: &str = "74fd2aae70ae8e0930b87a3dcb3b77f5b71d956659849f067360d3486604db41";
-const DEV_FEE_RATE: f64 = 0.0304;
+/// Size guess for the first fee-rate pass, before the transaction is signed.
+const TYPICAL_TX_SIZE_BYTES: usize = 8_000;
+const DEFAULT_CONFIRMATION_TARGET: u32 = 3;
 
 // --- CLI Structure ---
 
@@ -33,6 +42,9 @@ struct Cli {
     command: Commands,
     #[arg(long, global = true, default_value = "http://127.0.0.1:8081")]
     node_url: String,
+    /// Network whose address prefix is used to show and check addresses.
+    #[arg(long, global = true, default_value = DEFAULT_NETWORK_ID)]
+    network_id: String,
 }
 
 #[derive(Subcommand, Debug)]
@@ -70,12 +82,62 @@ enum Commands {
         to: String,
         #[arg()]
         amount: u64,
+        /// Number of blocks the fee estimate should aim to confirm within.
+        #[arg(long, default_value_t = DEFAULT_CONFIRMATION_TARGET)]
+        target_blocks: u32,
+        /// Pays this exact fee instead of asking the node for an estimate.
+        #[arg(long)]
+        fee: Option<u64>,
+        /// Makes the transaction unminable after this many blocks, so it can be
+        /// safely re-issued if it has not confirmed by then.
+        #[arg(long, value_name = "BLOCKS")]
+        expires_in: Option<u64>,
     },
     /// [receive] Monitors for incoming transactions to this wallet.
     Receive {
         #[arg(short, long, value_name = "WALLET_FILE", default_value = "wallet.key")]
         wallet: PathBuf,
     },
+    /// [swap] Hash-time-locked atomic swaps enforced on-chain.
+    Swap {
+        #[command(subcommand)]
+        action: SwapCommands,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SwapCommands {
+    /// Locks funds for a responder, redeemable with the swap secret until the timelock.
+    Initiate {
+        #[arg(short, long, value_name = "WALLET_FILE", default_value = "wallet.key")]
+        wallet: PathBuf,
+        #[arg()]
+        responder: String,
+        #[arg()]
+        amount: u64,
+        /// Hex Keccak256 hash of the swap secret. A new secret is generated if omitted.
+        #[arg(long)]
+        secret_hash: Option<String>,
+        /// Seconds from now until the initiator may reclaim the funds.
+        #[arg(long, default_value_t = 86400)]
+        timelock_secs: u64,
+    },
+    /// Claims a locked output by revealing the swap secret.
+    Redeem {
+        #[arg(short, long, value_name = "WALLET_FILE", default_value = "wallet.key")]
+        wallet: PathBuf,
+        #[arg()]
+        swap_id: OutPoint,
+        #[arg()]
+        secret: String,
+    },
+    /// Reclaims a locked output after its timelock has expired.
+    Refund {
+        #[arg(short, long, value_name = "WALLET_FILE", default_value = "wallet.key")]
+        wallet: PathBuf,
+        #[arg()]
+        swap_id: OutPoint,
+    },
 }
 
 // --- Main Logic ---
@@ -83,30 +145,83 @@ enum Commands {
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
-    let result = match cli.command {
-        Commands::Generate { output } => generate_wallet(output).await,
-        Commands::Show { wallet, keys } => show_wallet_info(wallet, keys).await,
+    if let Err(e) = run(cli).await {
+        eprintln!("\nNEURAL-VAULT™ Error: {e:?}");
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let hrp = types::network_hrp(&cli.network_id);
+    match cli.command {
+        Commands::Generate { output } => generate_wallet(output, hrp).await,
+        Commands::Show { wallet, keys } => show_wallet_info(wallet, keys, hrp).await,
         Commands::Import {
             mnemonic,
             private_key,
-        } => import_wallet(mnemonic, private_key).await,
-        Commands::Balance { address } => get_balance(&cli.node_url, address).await,
-        Commands::Send { wallet, to, amount } => {
-            send_transaction(&cli.node_url, wallet, to, amount).await
+        } => import_wallet(mnemonic, private_key, hrp).await,
+        Commands::Balance { address } => {
+            get_balance(&cli.node_url, parse_address(&address, hrp)?, hrp).await
         }
-        Commands::Receive { wallet } => receive_transactions(&cli.node_url, wallet).await,
-    };
-
-    if let Err(e) = result {
-        eprintln!("\nNEURAL-VAULT™ Error: {e:?}");
-        std::process::exit(1);
+        Commands::Send {
+            wallet,
+            to,
+            amount,
+            target_blocks,
+            fee,
+            expires_in,
+        } => {
+            let to = parse_address(&to, hrp)?;
+            println!(
+                "Preparing to send {amount} QNTO to address {}",
+                to.to_bech32(hrp)
+            );
+            send_transaction(
+                &cli.node_url,
+                wallet,
+                to,
+                amount,
+                target_blocks,
+                fee,
+                expires_in,
+            )
+            .await
+        }
+        Commands::Receive { wallet } => receive_transactions(&cli.node_url, wallet, hrp).await,
+        Commands::Swap { action } => match action {
+            SwapCommands::Initiate {
+                wallet,
+                responder,
+                amount,
+                secret_hash,
+                timelock_secs,
+            } => {
+                initiate_swap(
+                    &cli.node_url,
+                    wallet,
+                    parse_address(&responder, hrp)?,
+                    amount,
+                    secret_hash,
+                    timelock_secs,
+                )
+                .await
+            }
+            SwapCommands::Redeem {
+                wallet,
+                swap_id,
+                secret,
+            } => claim_swap(&cli.node_url, wallet, swap_id, Some(secret)).await,
+            SwapCommands::Refund { wallet, swap_id } => {
+                claim_swap(&cli.node_url, wallet, swap_id, None).await
+            }
+        },
     }
-    Ok(())
 }
 
 // --- Command Implementations ---
 
-async fn generate_wallet(output: PathBuf) -> Result<()> {
+async fn generate_wallet(output: PathBuf, hrp: Hrp) -> Result<()> {
     println!("🛡️ NEURAL-VAULT™: Generating new Quantum-Aware Dual-Layer Key...");
     let password = prompt_for_password(true, "Create a secure password to encrypt the new vault:")?;
     let new_wallet = Wallet::new()?;
@@ -116,7 +231,10 @@ async fn generate_wallet(output: PathBuf) -> Result<()> {
         .context("Failed to save new NEURAL-VAULT™ file")?;
 
     println!("\n✅ NEURAL-VAULT™ Generated Successfully!");
-    println!("   Address (Ed25519): {}", new_wallet.address());
+    println!(
+        "   Address (Ed25519): {}",
+        new_wallet.address().to_bech32(hrp)
+    );
     println!("   Saved to: {}", output.display());
     println!("\n⚠️ CRITICAL: Your wallet is created but not yet backed up.");
     println!("   To ensure you can recover your funds, run the 'show --keys' command.");
@@ -126,7 +244,7 @@ async fn generate_wallet(output: PathBuf) -> Result<()> {
     Ok(())
 }
 
-async fn show_wallet_info(wallet_path: PathBuf, show_keys: bool) -> Result<()> {
+async fn show_wallet_info(wallet_path: PathBuf, show_keys: bool, hrp: Hrp) -> Result<()> {
     if !wallet_path.exists() {
         return Err(anyhow::anyhow!(
             "Wallet file not found at: {:?}",
@@ -146,7 +264,11 @@ async fn show_wallet_info(wallet_path: PathBuf, show_keys: bool) -> Result<()> {
     println!("|                   QANTO WALLET DETAILS                   |");
     println!("+----------------------------------------------------------+");
     println!("\nWallet File:     {wallet_path:?}");
-    println!("Public Address:  {}", loaded_wallet.address());
+    println!(
+        "Public Address:  {}",
+        loaded_wallet.address().to_bech32(hrp)
+    );
+    println!("Legacy Hex:      {}", loaded_wallet.address());
 
     if show_keys {
         let private_key_hex = hex::encode(loaded_wallet.get_signing_key()?.to_bytes());
@@ -169,7 +291,7 @@ async fn show_wallet_info(wallet_path: PathBuf, show_keys: bool) -> Result<()> {
     Ok(())
 }
 
-async fn import_wallet(use_mnemonic: bool, use_private_key: bool) -> Result<()> {
+async fn import_wallet(use_mnemonic: bool, use_private_key: bool, hrp: Hrp) -> Result<()> {
     let password = prompt_for_password(true, "Create a password to encrypt the imported vault:")?;
     let wallet = if use_mnemonic {
         println!("Please enter your 12-word BIP39 mnemonic phrase:");
@@ -188,13 +310,22 @@ async fn import_wallet(use_mnemonic: bool, use_private_key: bool) -> Result<()>
     println!("🏷️ GATT: Tagging wallet for Governance-Aware Transaction Tracking™...");
     wallet.save_to_file("wallet.key", &password)?;
     println!("\n✅ NEURAL-VAULT™ Imported Successfully!");
-    println!("   Address: {}", wallet.address());
+    println!("   Address: {}", wallet.address().to_bech32(hrp));
     println!("   Saved to: wallet.key");
     Ok(())
 }
 
-async fn get_balance(node_url: &str, address: String) -> Result<()> {
-    println!("🌐 Instant-MeshSync™: Checking balance for {address}...");
+/// Parses an address given on the command line. Bech32m addresses must be
+/// for the selected network; legacy hex is accepted as-is.
+fn parse_address(address: &str, hrp: Hrp) -> Result<Address> {
+    Address::parse_for_network(address, hrp).with_context(|| format!("Invalid address '{address}'"))
+}
+
+async fn get_balance(node_url: &str, address: Address, hrp: Hrp) -> Result<()> {
+    println!(
+        "🌐 Instant-MeshSync™: Checking balance for {}...",
+        address.to_bech32(hrp)
+    );
     let client = Client::new();
     let url = format!("{node_url}/balance/{address}");
 
@@ -222,144 +353,188 @@ async fn get_balance(node_url: &str, address: String) -> Result<()> {
 async fn send_transaction(
     node_url: &str,
     wallet_path: PathBuf,
-    to: String,
+    to: Address,
     amount: u64,
+    target_blocks: u32,
+    fee: Option<u64>,
+    expires_in: Option<u64>,
 ) -> Result<()> {
-    println!("Preparing to send {amount} QNTO to address {to}");
-    let password = prompt_for_password(false, "Enter password to unlock vault for sending:")?;
-    let wallet = Wallet::from_file(&wallet_path, &password).context(format!(
-        "Failed to load vault from '{}'",
-        wallet_path.display()
-    ))?;
-    let sender_address = wallet.address();
+    let wallet = unlock_wallet(&wallet_path, "Enter password to unlock vault for sending:")?;
+    let client = Client::new();
+    let fee_choice = match fee {
+        Some(fee) => FeeChoice::Fixed(fee),
+        None => estimate_fee_choice(&client, node_url, amount, target_blocks).await,
+    };
+    let valid_until = match expires_in {
+        Some(0) => return Err(anyhow!("--expires-in must be at least 1 block")),
+        Some(blocks) => {
+            let window = fetch_inclusion_window(&client, node_url).await?;
+            Some(TxExpiry::Height(window.next_height + blocks - 1))
+        }
+        None => None,
+    };
+    let payment = Payment {
+        to,
+        amount,
+        lock: None,
+        valid_until,
+    };
+    let tx = build_payment(&client, node_url, &wallet, &payment, fee_choice).await?;
+    println!("Transaction created with ID: {} (fee: {})", tx.id, tx.fee);
+    if let Some(TxExpiry::Height(last_height)) = valid_until {
+        println!("   Expires after height {last_height}. If it has not confirmed by then,");
+        println!("   it can no longer be mined and the payment can be safely sent again.");
+    }
+
+    println!("🛡️ Anti-Malware TX Shield: Verifying transaction behavior...");
+    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    println!("   Behavioral signature check passed.");
+
+    let tx_id_response = broadcast_transaction(&client, node_url, &tx).await?;
+    println!("\n✅ Transaction submitted successfully!");
+    println!("   Transaction ID: {tx_id_response}");
+    Ok(())
+}
+
+async fn initiate_swap(
+    node_url: &str,
+    wallet_path: PathBuf,
+    responder: Address,
+    amount: u64,
+    secret_hash: Option<String>,
+    timelock_secs: u64,
+) -> Result<()> {
+    let wallet = unlock_wallet(&wallet_path, "Enter password to unlock vault for the swap:")?;
+    let secret_hash = match secret_hash {
+        Some(hash) => hash,
+        None => {
+            let secret = hex::encode(rand::random::<[u8; 32]>());
+            println!("🔑 Generated swap secret: {secret}");
+            println!("   Keep it private until the counterparty has locked their side.");
+            htlc::hash_secret(&secret)
+        }
+    };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let lock = HashTimeLock {
+        recipient: responder,
+        refund_address: wallet.address(),
+        secret_hash,
+        timelock: now + timelock_secs,
+    };
+
+    let client = Client::new();
+    let fee_choice =
+        estimate_fee_choice(&client, node_url, amount, DEFAULT_CONFIRMATION_TARGET).await;
+    let payment = Payment {
+        to: responder,
+        amount,
+        lock: Some(lock.clone()),
+        valid_until: None,
+    };
+    let tx = build_payment(&client, node_url, &wallet, &payment, fee_choice).await?;
+    let tx_id_response = broadcast_transaction(&client, node_url, &tx).await?;
+    println!("\n✅ Swap initiated!");
+    println!("   Swap ID:     {tx_id_response}_0");
+    println!("   Secret hash: {}", lock.secret_hash);
+    println!("   Refundable after unix time {}", lock.timelock);
+    Ok(())
+}
+
+/// Spends an HTLC output back to the wallet, either redeeming it with the
+/// swap secret or refunding it after the timelock.
+async fn claim_swap(
+    node_url: &str,
+    wallet_path: PathBuf,
+    swap_id: OutPoint,
+    secret: Option<String>,
+) -> Result<()> {
+    let wallet = unlock_wallet(&wallet_path, "Enter password to unlock vault for the swap:")?;
+    let my_address = wallet.address();
     let client = Client::new();
 
-    let utxo_url = format!("{node_url}/utxos/{sender_address}");
+    let utxo_url = format!("{node_url}/utxo/{swap_id}");
     let res = client
         .get(&utxo_url)
         .send()
         .await
-        .context("Failed to fetch UTXOs")?;
+        .context("Failed to fetch swap output")?;
     if !res.status().is_success() {
         return Err(anyhow!(
-            "Node failed to provide UTXOs: {}",
-            res.text().await?
+            "Swap output {} is not spendable: {}",
+            swap_id,
+            res.status()
         ));
     }
-    let available_utxos: HashMap<String, UTXO> =
-        res.json().await.context("Failed to parse UTXOs")?;
-    if available_utxos.is_empty() {
-        return Err(anyhow!("No funds available for address {}", sender_address));
+    let utxo: UTXO = res.json().await.context("Failed to parse swap output")?;
+    if utxo.htlc.is_none() {
+        return Err(anyhow!("Output {} is not hash-time-locked", swap_id));
     }
 
-    let fee = transaction::calculate_dynamic_fee(amount);
-    let dev_fee = (amount as f64 * DEV_FEE_RATE).round() as u64;
-    let total_needed = amount + fee + dev_fee;
-    let mut inputs = vec![];
-    let mut total_input_amount = 0;
-    for (_utxo_id, utxo) in available_utxos {
-        if total_input_amount >= total_needed {
-            break;
-        }
-        total_input_amount += utxo.amount;
-        inputs.push(Input {
-            tx_id: utxo.tx_id,
-            output_index: utxo.output_index,
-        });
-    }
-    if total_input_amount < total_needed {
-        return Err(anyhow!(
-            "Insufficient funds. Needed: {}, Available: {}",
-            total_needed,
-            total_input_amount
-        ));
-    }
+    let policy = fetch_fee_policy(&client, node_url).await;
+    let fee = transaction::calculate_dynamic_fee(utxo.amount).max(policy.consensus_min_fee);
+    let dev_fee = policy.dev_fee(utxo.amount);
+    let payout = utxo
+        .amount
+        .checked_sub(fee + dev_fee)
+        .filter(|p| *p > 0)
+        .ok_or_else(|| {
+            anyhow!(
+                "Swap amount {} does not cover the fees {}",
+                utxo.amount,
+                fee + dev_fee
+            )
+        })?;
 
     let he_public_key = wallet.get_signing_key()?.verifying_key();
-    let he_pub_key_material: &[u8] = he_public_key.as_bytes();
+    let inputs = vec![Input {
+        tx_id: utxo.tx_id,
+        output_index: utxo.output_index,
+        htlc_preimage: secret.clone(),
+    }];
     let mut outputs = vec![Output {
-        address: to.clone(),
-        amount,
-        homomorphic_encrypted: qanto::qantodag::HomomorphicEncrypted::new(
-            amount,
-            he_pub_key_material,
-        ),
+        address: my_address,
+        amount: payout,
+        homomorphic_encrypted: HomomorphicEncrypted::new(payout, he_public_key.as_bytes()),
+        htlc: None,
     }];
     if dev_fee > 0 {
         outputs.push(Output {
-            address: DEV_ADDRESS.to_string(),
+            address: DEV_ADDRESS,
             amount: dev_fee,
-            homomorphic_encrypted: qanto::qantodag::HomomorphicEncrypted::new(
-                dev_fee,
-                he_pub_key_material,
-            ),
+            homomorphic_encrypted: HomomorphicEncrypted::new(dev_fee, he_public_key.as_bytes()),
+            htlc: None,
         });
     }
-    let change = total_input_amount - total_needed;
-    if change > 0 {
-        outputs.push(Output {
-            address: sender_address.clone(),
-            amount: change,
-            homomorphic_encrypted: qanto::qantodag::HomomorphicEncrypted::new(
-                change,
-                he_pub_key_material,
-            ),
-        });
-    }
-
-    let mut metadata_map = HashMap::new();
-    metadata_map.insert("gatt_uuid".to_string(), Uuid::new_v4().to_string());
 
     let (signing_key, public_key) = wallet.get_keypair()?;
-
     let tx_config = TransactionConfig {
-        sender: sender_address,
-        receiver: to,
-        amount,
+        sender: my_address,
+        receiver: my_address,
+        amount: payout,
         fee,
         inputs,
         outputs,
         signing_key_bytes: signing_key.as_bytes(),
         public_key_bytes: public_key.as_bytes(),
         tx_timestamps: Arc::new(RwLock::new(HashMap::new())),
-        metadata: Some(metadata_map),
+        metadata: None,
+        valid_until: None,
     };
     let tx = Transaction::new(tx_config)
         .await
         .context("Failed to create transaction")?;
-    println!("Transaction created with ID: {}", tx.id);
-
-    println!("🛡️ Anti-Malware TX Shield: Verifying transaction behavior...");
-    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
-    println!("   Behavioral signature check passed.");
-
-    let tx_url = format!("{node_url}/transaction");
-    println!("Broadcasting transaction to {tx_url}...");
-    let res = client
-        .post(&tx_url)
-        .json(&tx)
-        .send()
-        .await
-        .context("Failed to send transaction")?;
-
-    if res.status().is_success() {
-        let tx_id_response: String = res
-            .json()
-            .await
-            .context("Failed to parse transaction ID from response")?;
-        println!("\n✅ Transaction submitted successfully!");
-        println!("   Transaction ID: {tx_id_response}");
+    let tx_id_response = broadcast_transaction(&client, node_url, &tx).await?;
+    let action = if secret.is_some() {
+        "redeemed"
     } else {
-        let error_text = res
-            .text()
-            .await
-            .unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(anyhow!("Node rejected transaction: {}", error_text));
-    }
+        "refunded"
+    };
+    println!("\n✅ Swap {swap_id} {action} for {payout} QNTO.");
+    println!("   Transaction ID: {tx_id_response}");
     Ok(())
 }
 
-async fn receive_transactions(node_url: &str, wallet_path: PathBuf) -> Result<()> {
+async fn receive_transactions(node_url: &str, wallet_path: PathBuf, hrp: Hrp) -> Result<()> {
     println!(
         "Enter password to monitor incoming transactions for '{}':",
         wallet_path.display()
@@ -369,7 +544,10 @@ async fn receive_transactions(node_url: &str, wallet_path: PathBuf) -> Result<()
     let my_address = wallet.address();
     let client = Client::new();
     let mut known_tx_ids = HashSet::new();
-    println!("\n📡 Listening for incoming transactions to {my_address} (Press Ctrl+C to stop)...");
+    println!(
+        "\n📡 Listening for incoming transactions to {} (Press Ctrl+C to stop)...",
+        my_address.to_bech32(hrp)
+    );
 
     loop {
         let dag_info_url = format!("{node_url}/dag");
@@ -399,7 +577,7 @@ async fn receive_transactions(node_url: &str, wallet_path: PathBuf) -> Result<()
                                                         );
                                                         println!("   From: {}", tx.sender);
                                                         println!("   Transaction ID: {}", tx.id);
-                                                        known_tx_ids.insert(tx.id.clone());
+                                                        known_tx_ids.insert(tx.id);
                                                     }
                                                 }
                                             }
@@ -418,6 +596,275 @@ async fn receive_transactions(node_url: &str, wallet_path: PathBuf) -> Result<()
 
 // --- Utility Functions ---
 
+fn unlock_wallet(wallet_path: &PathBuf, prompt_text: &str) -> Result<Wallet> {
+    let password = prompt_for_password(false, prompt_text)?;
+    Wallet::from_file(wallet_path, &password).context(format!(
+        "Failed to load vault from '{}'",
+        wallet_path.display()
+    ))
+}
+
+async fn fetch_spendable_utxos(
+    client: &Client,
+    node_url: &str,
+    address: &Address,
+) -> Result<HashMap<OutPoint, UTXO>> {
+    let utxo_url = format!("{node_url}/utxos/{address}");
+    let res = client
+        .get(&utxo_url)
+        .send()
+        .await
+        .context("Failed to fetch UTXOs")?;
+    if !res.status().is_success() {
+        return Err(anyhow!(
+            "Node failed to provide UTXOs: {}",
+            res.text().await?
+        ));
+    }
+    let address_utxos: AddressUtxos = res.json().await.context("Failed to parse UTXOs")?;
+    if !address_utxos.immature.is_empty() {
+        println!(
+            "ℹ️ {} in {} coinbase output(s) is still maturing and cannot be spent yet.",
+            address_utxos.immature_balance,
+            address_utxos.immature.len()
+        );
+    }
+    let mut utxos = address_utxos.spendable;
+    // Locked swap outputs can only be spent through `swap redeem`.
+    utxos.retain(|_, utxo| utxo.htlc.is_none());
+    Ok(utxos)
+}
+
+/// Fetches the node's protocol fee policy, falling back to the default policy.
+async fn fetch_fee_policy(client: &Client, node_url: &str) -> FeePolicy {
+    let url = format!("{node_url}/fee-policy");
+    match client.get(&url).send().await {
+        Ok(res) if res.status().is_success() => match res.json().await {
+            Ok(policy) => return policy,
+            Err(e) => println!("⚠️ Failed to parse fee policy ({e}). Using defaults."),
+        },
+        Ok(res) => println!(
+            "⚠️ Node returned {} for fee policy. Using defaults.",
+            res.status()
+        ),
+        Err(e) => println!("⚠️ Fee policy unavailable ({e}). Using defaults."),
+    }
+    FeePolicy::default()
+}
+
+/// How the network fee of an outgoing payment is chosen.
+#[derive(Debug, Clone, Copy)]
+enum FeeChoice {
+    Fixed(u64),
+    /// Fee rate in fee units per 100 bytes of the signed transaction.
+    Rate(u64),
+}
+
+async fn fetch_fee_estimate(client: &Client, node_url: &str, target: u32) -> Result<FeeEstimate> {
+    let url = format!("{node_url}/fee-estimate?target={target}");
+    let res = client
+        .get(&url)
+        .send()
+        .await
+        .context("Failed to fetch fee estimate")?;
+    if !res.status().is_success() {
+        return Err(anyhow!("Node returned {} for fee estimate", res.status()));
+    }
+    res.json().await.context("Failed to parse fee estimate")
+}
+
+/// Asks the node for a fee rate, falling back to the tiered fee if the node
+/// cannot provide an estimate.
+async fn estimate_fee_choice(
+    client: &Client,
+    node_url: &str,
+    amount: u64,
+    target_blocks: u32,
+) -> FeeChoice {
+    match fetch_fee_estimate(client, node_url, target_blocks).await {
+        Ok(estimate) => {
+            println!(
+                "⛽ Fee estimate for {} block(s): {} per 100 bytes",
+                estimate.target_blocks, estimate.fee_rate
+            );
+            FeeChoice::Rate(estimate.fee_rate)
+        }
+        Err(e) => {
+            println!("⚠️ Fee estimate unavailable ({e}). Using the tiered fee instead.");
+            FeeChoice::Fixed(transaction::calculate_dynamic_fee(amount))
+        }
+    }
+}
+
+async fn fetch_inclusion_window(client: &Client, node_url: &str) -> Result<InclusionWindow> {
+    let url = format!("{node_url}/inclusion-window");
+    let res = client
+        .get(&url)
+        .send()
+        .await
+        .context("Failed to fetch the inclusion window")?;
+    if !res.status().is_success() {
+        return Err(anyhow!(
+            "Node returned {} for the inclusion window",
+            res.status()
+        ));
+    }
+    res.json()
+        .await
+        .context("Failed to parse the inclusion window")
+}
+
+/// An outgoing payment before inputs and fees are chosen.
+struct Payment {
+    to: Address,
+    amount: u64,
+    lock: Option<HashTimeLock>,
+    valid_until: Option<TxExpiry>,
+}
+
+/// Builds and signs `payment`. With a fee rate, the transaction is re-signed
+/// until its fee covers its own serialized size.
+async fn build_payment(
+    client: &Client,
+    node_url: &str,
+    wallet: &Wallet,
+    payment: &Payment,
+    fee_choice: FeeChoice,
+) -> Result<Transaction> {
+    let sender_address = wallet.address();
+    let available_utxos = fetch_spendable_utxos(client, node_url, &sender_address).await?;
+    if available_utxos.is_empty() {
+        return Err(anyhow!("No funds available for address {}", sender_address));
+    }
+
+    let policy = fetch_fee_policy(client, node_url).await;
+    let mut fee = match fee_choice {
+        FeeChoice::Fixed(fee) => fee,
+        FeeChoice::Rate(rate) => fee_estimator::fee_for_size(rate, TYPICAL_TX_SIZE_BYTES),
+    }
+    .max(policy.consensus_min_fee);
+    loop {
+        let tx = assemble_payment(wallet, &available_utxos, payment, fee, &policy).await?;
+        let FeeChoice::Rate(rate) = fee_choice else {
+            return Ok(tx);
+        };
+        let required = fee_estimator::fee_for_size(rate, codec::encoded_len(&tx)?);
+        if fee >= required {
+            return Ok(tx);
+        }
+        fee = required;
+    }
+}
+
+/// Selects inputs, adds the developer fee output and returns change to the sender.
+async fn assemble_payment(
+    wallet: &Wallet,
+    available_utxos: &HashMap<OutPoint, UTXO>,
+    payment: &Payment,
+    fee: u64,
+    policy: &FeePolicy,
+) -> Result<Transaction> {
+    let amount = payment.amount;
+    let sender_address = wallet.address();
+    let dev_fee = policy.dev_fee(amount);
+    let total_needed = amount + fee + dev_fee;
+    let mut inputs = vec![];
+    let mut total_input_amount = 0;
+    for (_utxo_id, utxo) in available_utxos {
+        if total_input_amount >= total_needed {
+            break;
+        }
+        total_input_amount += utxo.amount;
+        inputs.push(Input {
+            tx_id: utxo.tx_id,
+            output_index: utxo.output_index,
+            htlc_preimage: None,
+        });
+    }
+    if total_input_amount < total_needed {
+        return Err(anyhow!(
+            "Insufficient funds. Needed: {}, Available: {}",
+            total_needed,
+            total_input_amount
+        ));
+    }
+
+    let he_public_key = wallet.get_signing_key()?.verifying_key();
+    let he_pub_key_material: &[u8] = he_public_key.as_bytes();
+    let mut outputs = vec![Output {
+        address: payment.to,
+        amount,
+        homomorphic_encrypted: HomomorphicEncrypted::new(amount, he_pub_key_material),
+        htlc: payment.lock.clone(),
+    }];
+    if dev_fee > 0 {
+        outputs.push(Output {
+            address: DEV_ADDRESS,
+            amount: dev_fee,
+            homomorphic_encrypted: HomomorphicEncrypted::new(dev_fee, he_pub_key_material),
+            htlc: None,
+        });
+    }
+    let change = total_input_amount - total_needed;
+    if change > 0 {
+        outputs.push(Output {
+            address: sender_address,
+            amount: change,
+            homomorphic_encrypted: HomomorphicEncrypted::new(change, he_pub_key_material),
+            htlc: None,
+        });
+    }
+
+    let mut metadata_map = HashMap::new();
+    metadata_map.insert("gatt_uuid".to_string(), Uuid::new_v4().to_string());
+
+    let (signing_key, public_key) = wallet.get_keypair()?;
+
+    let tx_config = TransactionConfig {
+        sender: sender_address,
+        receiver: payment.to,
+        amount,
+        fee,
+        inputs,
+        outputs,
+        signing_key_bytes: signing_key.as_bytes(),
+        public_key_bytes: public_key.as_bytes(),
+        tx_timestamps: Arc::new(RwLock::new(HashMap::new())),
+        metadata: Some(metadata_map),
+        valid_until: payment.valid_until,
+    };
+    Transaction::new(tx_config)
+        .await
+        .context("Failed to create transaction")
+}
+
+async fn broadcast_transaction(
+    client: &Client,
+    node_url: &str,
+    tx: &Transaction,
+) -> Result<String> {
+    let tx_url = format!("{node_url}/transaction");
+    println!("Broadcasting transaction to {tx_url}...");
+    let res = client
+        .post(&tx_url)
+        .json(tx)
+        .send()
+        .await
+        .context("Failed to send transaction")?;
+
+    if res.status().is_success() {
+        res.json()
+            .await
+            .context("Failed to parse transaction ID from response")
+    } else {
+        let error_text = res
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        Err(anyhow!("Node rejected transaction: {}", error_text))
+    }
+}
+
 fn prompt_for_password(confirm: bool, prompt_text: &str) -> Result<SecretString, WalletError> {
     if !prompt_text.is_empty() {
         println!("{prompt_text}");
@@ -15,14 +15,16 @@ use qanto::{
     consensus::Consensus,
     mempool::Mempool,
     miner::Miner,
-    qantodag::{QantoDAG, QantoDagConfig, UTXO},
+    qantodag::{QantoDAG, QantoDagConfig, StorageMode, DEFAULT_COINBASE_MATURITY, UTXO},
     saga::PalletSaga,
+    storage::MemoryStore,
     transaction::{Input, Output, Transaction, TransactionConfig},
+    types::{OutPoint, TxId},
+    utxo_set::UtxoSet,
     wallet::Wallet,
 };
 // Import the necessary traits to use their methods like `.as_bytes()`
 use pqcrypto_traits::sign::{PublicKey, SecretKey};
-use rocksdb::{Options, DB};
 use std::{
     collections::HashMap,
     io::{self, Write},
@@ -57,12 +59,12 @@ async fn run_autonomous_simulation() -> Result<()> {
     println!(
         "{} {}",
         "Validator Address:".dimmed(),
-        validator_address.cyan()
+        validator_address.to_string().cyan()
     );
     println!(
         "{} {}",
         "Receiver Address: ".dimmed(),
-        receiver_address.cyan()
+        receiver_address.to_string().cyan()
     );
 
     // Get the full keypair once.
@@ -72,44 +74,52 @@ async fn run_autonomous_simulation() -> Result<()> {
         #[cfg(feature = "infinite-strata")]
         None,
     ));
-    let db_path = format!("./db_assistant_sim_{}", rand::random::<u16>());
-    let db = DB::open_default(&db_path)?;
-
     // Correctly create the config struct and pass it to QantoDAG::new.
     let dag_config = QantoDagConfig {
-        initial_validator: validator_address.clone(),
+        initial_validator: validator_address,
         target_block_time: 60,
         num_chains: 1,
         qr_signing_key: &signing_key,
         qr_public_key: &public_key,
+        storage_mode: StorageMode::default(),
+        tx_index: false,
+        coinbase_maturity: DEFAULT_COINBASE_MATURITY,
     };
-    let dag_arc = QantoDAG::new(dag_config, saga_pallet.clone(), db)?;
+    let dag_arc = QantoDAG::new(
+        dag_config,
+        saga_pallet.clone(),
+        Arc::new(MemoryStore::new()),
+    )?;
 
     let mempool_arc = Arc::new(RwLock::new(Mempool::new(3600, 10_000_000, 1000)));
-    let utxos_arc = Arc::new(RwLock::new(HashMap::<String, UTXO>::new()));
+    let utxos_arc = Arc::new(RwLock::new(UtxoSet::default()));
 
+    let genesis_tx_id = TxId::new([0x01; 32]);
     let genesis_utxo = UTXO {
-        address: validator_address.clone(),
+        address: validator_address,
         amount: 1_000_000,
-        tx_id: "genesis".to_string(),
+        tx_id: genesis_tx_id,
         output_index: 0,
         explorer_link: "".to_string(),
+        htlc: None,
+        coinbase: None,
     };
     utxos_arc
         .write()
         .await
-        .insert("genesis_0".to_string(), genesis_utxo);
+        .insert(OutPoint::new(genesis_tx_id, 0), genesis_utxo);
 
     let fee = 10;
     // Correctly populate the TransactionConfig, including the new public_key_bytes field.
     let tx_config = TransactionConfig {
-        sender: validator_address.clone(),
-        receiver: receiver_address.clone(),
+        sender: validator_address,
+        receiver: receiver_address,
         amount: 100,
         fee,
         inputs: vec![Input {
-            tx_id: "genesis".to_string(),
+            tx_id: genesis_tx_id,
             output_index: 0,
+            htlc_preimage: None,
         }],
         outputs: vec![
             Output {
@@ -119,26 +129,29 @@ async fn run_autonomous_simulation() -> Result<()> {
                     100,
                     public_key.as_bytes(),
                 ),
+                htlc: None,
             },
             Output {
-                address: validator_address.clone(),
+                address: validator_address,
                 amount: 1_000_000 - 100 - fee,
                 homomorphic_encrypted: qanto::qantodag::HomomorphicEncrypted::new(
                     1_000_000 - 100 - fee,
                     public_key.as_bytes(),
                 ),
+                htlc: None,
             },
         ],
         metadata: Some(HashMap::new()),
         signing_key_bytes: signing_key.as_bytes(),
         public_key_bytes: public_key.as_bytes(),
         tx_timestamps: Arc::new(RwLock::new(HashMap::new())),
+        valid_until: None,
     };
     let signed_transaction = Transaction::new(tx_config).await?;
     println!(
         "{} {}",
         "Transaction created:".dimmed(),
-        signed_transaction.id.yellow()
+        signed_transaction.id.to_string().yellow()
     );
 
     {
@@ -168,7 +181,7 @@ async fn run_autonomous_simulation() -> Result<()> {
         "SAGA is now solving Proof-of-Work... this may take a moment.".dimmed()
     );
     let miner = Miner::new(qanto::miner::MinerConfig {
-        address: validator_address.clone(),
+        address: validator_address,
         dag: dag_arc.clone(),
         difficulty_hex: format!("{:x}", candidate_block.difficulty),
         target_block_time: 60,
@@ -200,22 +213,21 @@ async fn run_autonomous_simulation() -> Result<()> {
         .await?;
     println!("{} SAGA consensus validation passed.", "✓".green());
 
-    let block_id = candidate_block.id.clone();
+    let block_id = candidate_block.id;
     if dag_arc.add_block(candidate_block, &utxos_arc).await? {
         println!(
             "{} Block {} added to the DAG successfully!",
             "✓".green(),
-            block_id.yellow()
+            block_id.to_string().yellow()
         );
     } else {
         println!(
             "{} Block {} was rejected by the DAG.",
             "✗".red(),
-            block_id.red()
+            block_id.to_string().red()
         );
     }
 
-    DB::destroy(&Options::default(), &db_path)?;
     println!(
         "{}",
         "\n+----------------------------------------------------+".truecolor(129, 140, 248)
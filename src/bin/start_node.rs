@@ -1,7 +1,12 @@
 use anyhow::Context;
 use clap::Parser;
 use log::{error, info, warn};
-use qanto::{config::Config, node::Node, wallet::Wallet};
+use qanto::{
+    config::{Config, ConfigSources},
+    data_dir::DataDir,
+    node::Node,
+    wallet::Wallet,
+};
 use secrecy::Secret;
 use std::path::Path;
 use std::sync::Arc;
@@ -15,9 +20,13 @@ struct Args {
     #[clap(long, default_value = "config.toml")]
     config_path: String,
 
-    /// Path to the wallet file.
-    #[clap(long, default_value = "wallet.key")]
-    wallet_path: String,
+    /// Path to the wallet file [default: <data_dir>/keys/wallet.key].
+    #[clap(long)]
+    wallet_path: Option<String>,
+
+    /// Data directory, overriding `data_dir` from the configuration.
+    #[clap(long)]
+    data_dir: Option<String>,
 }
 
 /// The main asynchronous function that sets up and runs the node.
@@ -44,35 +53,42 @@ async fn main() -> anyhow::Result<()> {
     .init();
     info!("Starting QantoDAG node (from start_node.rs)...");
 
+    let data_dir = DataDir::new(args.data_dir.as_deref().unwrap_or(&config.data_dir));
+
     // Load the wallet.
-    if !Path::new(&args.wallet_path).exists() {
+    let wallet_path = args
+        .wallet_path
+        .map(Into::into)
+        .unwrap_or_else(|| data_dir.wallet());
+    if !wallet_path.exists() {
         anyhow::bail!(
             "Wallet file not found at path: {}. Please create or import a wallet.",
-            &args.wallet_path
+            wallet_path.display()
         );
     }
 
     let passphrase = rpassword::prompt_password("Enter passphrase to unlock wallet: ")?;
     let secret_passphrase = Secret::new(passphrase);
 
-    let wallet = Wallet::from_file(&args.wallet_path, &secret_passphrase)
+    let wallet = Wallet::from_file(&wallet_path, &secret_passphrase)
         .context("Failed to load wallet from file. Check the wallet path and passphrase.")?;
     let wallet_arc = Arc::new(wallet);
 
     // SECURITY: Ensure private key files have restrictive permissions.
-    let identity_key_path = "start_node_p2p_identity.key";
-    if Path::new(identity_key_path).exists() {
-        warn!("SECURITY: Reusing existing P2P identity key at '{identity_key_path}'. For production, ensure this file is secure and has restricted permissions.");
+    let identity_key_path = data_dir.p2p_identity();
+    if identity_key_path.exists() {
+        warn!("SECURITY: Reusing existing P2P identity key at '{}'. For production, ensure this file is secure and has restricted permissions.", identity_key_path.display());
     }
 
     // Initialize the node with its configuration and wallet.
-    let peer_cache_path = "start_node_peer_cache.json".to_string();
     let node = Node::new(
         config,
-        args.config_path.clone(),
+        ConfigSources {
+            file: Some(args.config_path.clone()),
+            ..ConfigSources::default()
+        },
         wallet_arc,
-        identity_key_path,
-        peer_cache_path,
+        data_dir,
     )
     .await?;
 
@@ -0,0 +1,247 @@
+//! --- Qanto Canonical Codec ---
+//! v1.0.0 - Versioned Binary Encoding
+//! Blocks, transactions and network messages are stored in the node database and gossiped
+//! in a compact binary form: a one-byte format version followed by a bincode
+//! body (varint integers, little endian). The encoding is canonical:
+//! - **Maps** are written in key order, so equal values encode to equal bytes.
+//! - **Hex strings** (ids, hashes, addresses) are written as raw bytes.
+//! - **Every field** is always written; nothing is skipped when empty.
+//!
+//! Human-readable formats such as the JSON API and the mempool journal are not
+//! affected. Blocks written as JSON by earlier versions are re-encoded by
+//! `migrate_legacy_blocks`, the first step of the schema migrations.
+
+use crate::qantodag::QantoBlock;
+use crate::storage::{self, StateStore, StoreBatch, StoreError};
+use bincode::Options;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use thiserror::Error;
+
+/// Version byte prefixed to every encoded value.
+pub const CODEC_VERSION: u8 = 1;
+/// Largest payload `decode` accepts. Callers with tighter bounds, such as the
+/// gossip layer, use `decode_with_limit`.
+pub const MAX_ENCODED_SIZE: usize = 32 * 1024 * 1024;
+/// Number of re-encoded blocks written per batch during migration.
+const MIGRATION_BATCH_SIZE: usize = 1_000;
+
+#[derive(Error, Debug)]
+pub enum CodecError {
+    #[error("Encoded payload is empty")]
+    Empty,
+    #[error("Unsupported encoding version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Encoded size {size} exceeds the limit of {limit} bytes")]
+    TooLarge { size: usize, limit: usize },
+    #[error("Binary encoding error: {0}")]
+    Bincode(#[from] bincode::Error),
+    #[error("Legacy JSON decoding error: {0}")]
+    LegacyJson(#[from] serde_json::Error),
+}
+
+fn options(limit: usize) -> impl Options {
+    bincode::DefaultOptions::new().with_limit(limit as u64)
+}
+
+/// Encodes `value` as a version byte followed by its canonical binary form.
+pub fn encode<T: Serialize>(value: &T) -> Result<Vec<u8>, CodecError> {
+    let body_size = options(MAX_ENCODED_SIZE).serialized_size(value)? as usize;
+    let mut bytes = Vec::with_capacity(body_size + 1);
+    bytes.push(CODEC_VERSION);
+    options(MAX_ENCODED_SIZE).serialize_into(&mut bytes, value)?;
+    Ok(bytes)
+}
+
+/// Size of `encode(value)` without allocating the encoding.
+pub fn encoded_len<T: Serialize>(value: &T) -> Result<usize, CodecError> {
+    Ok(options(MAX_ENCODED_SIZE).serialized_size(value)? as usize + 1)
+}
+
+pub fn decode<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, CodecError> {
+    decode_with_limit(bytes, MAX_ENCODED_SIZE)
+}
+
+/// Decodes `bytes`, rejecting payloads larger than `limit` before any
+/// allocation driven by their contents takes place.
+pub fn decode_with_limit<T: DeserializeOwned>(bytes: &[u8], limit: usize) -> Result<T, CodecError> {
+    if bytes.len() > limit {
+        return Err(CodecError::TooLarge {
+            size: bytes.len(),
+            limit,
+        });
+    }
+    match bytes.split_first() {
+        None => Err(CodecError::Empty),
+        Some((&CODEC_VERSION, body)) => Ok(options(limit).deserialize(body)?),
+        Some((&version, _)) => Err(CodecError::UnsupportedVersion(version)),
+    }
+}
+
+/// Re-encodes blocks that earlier versions stored as JSON. Returns the number
+/// of blocks migrated, or that would be with `apply` unset; running it on an
+/// already migrated database is a no-op.
+pub fn migrate_legacy_blocks(store: &dyn StateStore, apply: bool) -> Result<usize, StoreError> {
+    let mut batch = StoreBatch::default();
+    let mut migrated = 0;
+    for item in store.scan_prefix(b"") {
+        let (key, value) = item?;
+        if !storage::is_block_key(&key) || value.first() != Some(&b'{') {
+            continue;
+        }
+        let Ok(block) = serde_json::from_slice::<QantoBlock>(&value) else {
+            continue;
+        };
+        migrated += 1;
+        if !apply {
+            continue;
+        }
+        batch.put(&key, encode(&block)?);
+        if batch.len() >= MIGRATION_BATCH_SIZE {
+            store.write(std::mem::take(&mut batch))?;
+        }
+    }
+    if !batch.is_empty() {
+        store.write(batch)?;
+    }
+    Ok(migrated)
+}
+
+/// Serializes a `HashMap` in key order so its encoding is canonical.
+pub fn sorted_map<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    K: Serialize + Ord,
+    V: Serialize,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Lowercase hex strings are written as their raw bytes; anything else is
+/// written verbatim, so every string round-trips exactly.
+#[derive(Serialize, Deserialize)]
+enum CompactHex {
+    Bytes(#[serde(with = "serde_bytes")] Vec<u8>),
+    Text(String),
+}
+
+impl CompactHex {
+    fn new(value: &str) -> Self {
+        let canonical_hex = value.len() % 2 == 0
+            && value
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+        match hex::decode(value) {
+            Ok(bytes) if canonical_hex => CompactHex::Bytes(bytes),
+            _ => CompactHex::Text(value.to_string()),
+        }
+    }
+
+    fn into_string(self) -> String {
+        match self {
+            CompactHex::Bytes(bytes) => hex::encode(bytes),
+            CompactHex::Text(text) => text,
+        }
+    }
+}
+
+/// `#[serde(with)]` helper writing a hex `String` field as raw bytes in binary
+/// formats and as a plain string in human-readable ones.
+pub mod compact_hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &str, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(value)
+        } else {
+            CompactHex::new(value).serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+        if deserializer.is_human_readable() {
+            String::deserialize(deserializer)
+        } else {
+            Ok(CompactHex::deserialize(deserializer)?.into_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::types::BlockHash;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Record {
+        #[serde(with = "compact_hex")]
+        id: String,
+        refs: Vec<(u32, BlockHash)>,
+        #[serde(serialize_with = "sorted_map")]
+        metadata: HashMap<String, String>,
+    }
+
+    fn record() -> Record {
+        Record {
+            id: "ab".repeat(32),
+            refs: vec![(3, BlockHash::new([7; 32]))],
+            metadata: (0..16)
+                .map(|i| (format!("key{i}"), i.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_preserves_every_string() {
+        for id in ["ab".repeat(32), "00ff".into(), "ABCD".into(), "abc".into()] {
+            let original = Record { id, ..record() };
+            let bytes = encode(&original).unwrap();
+            assert_eq!(bytes[0], CODEC_VERSION);
+            assert_eq!(bytes.len(), encoded_len(&original).unwrap());
+            assert_eq!(decode::<Record>(&bytes).unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn test_encoding_is_canonical_and_compact() {
+        let a = record();
+        let mut entries: Vec<_> = a.metadata.clone().into_iter().collect();
+        entries.reverse();
+        let mut b = record();
+        b.metadata = HashMap::with_capacity(entries.len());
+        b.metadata.extend(entries);
+        assert_eq!(encode(&a).unwrap(), encode(&b).unwrap());
+
+        let hash_only = Record {
+            id: "ab".repeat(32),
+            refs: vec![],
+            metadata: HashMap::new(),
+        };
+        let hash_only_len = encoded_len(&hash_only).unwrap();
+        assert!(hash_only_len < 40);
+
+        // A reference costs its chain id and the 32 hash bytes.
+        let with_ref = Record {
+            refs: vec![(3, BlockHash::new([7; 32]))],
+            ..hash_only
+        };
+        assert_eq!(encoded_len(&with_ref).unwrap(), hash_only_len + 33);
+    }
+
+    #[test]
+    fn test_rejects_unknown_version_and_oversized_payloads() {
+        let mut bytes = encode(&record()).unwrap();
+        assert!(matches!(
+            decode_with_limit::<Record>(&bytes, 8),
+            Err(CodecError::TooLarge { .. })
+        ));
+        bytes[0] = CODEC_VERSION + 1;
+        assert!(matches!(
+            decode::<Record>(&bytes),
+            Err(CodecError::UnsupportedVersion(_))
+        ));
+        assert!(matches!(decode::<Record>(&[]), Err(CodecError::Empty)));
+    }
+}
@@ -1,19 +1,43 @@
 // src/config.rs
 
 //! --- Qanto Node Configuration ---
-//! v2.0.0 - Production & Standalone Ready
+//! v2.1.0 - Layered Configuration
 //! This module defines the configuration structure for a Qanto node.
 //! It uses serde for deserialization from a TOML file and includes
 //! robust validation logic to ensure that all configured parameters
 //! are sane and within operational limits for a standalone system.
-
+//!
+//! A configuration is assembled from layers, each overriding the ones
+//! before it:
+//!
+//! 1. a network preset (`local`, `testnet` or `mainnet`), chosen on the
+//!    command line or with `QANTO_NETWORK`;
+//! 2. the TOML file;
+//! 3. `QANTO_*` environment variables, where a double underscore descends
+//!    into a section: `QANTO_API_ADDRESS`, `QANTO_P2P__MESH_N`;
+//! 4. command-line `key=value` overrides: `p2p.mesh_n=12`.
+//!
+//! Validation reports every problem at once rather than the first one.
+
+use crate::data_dir::DEFAULT_DATA_DIR;
+use crate::qantodag::{StorageMode, DEFAULT_COINBASE_MATURITY};
+use crate::snapshot::TrustedCheckpoint;
+use crate::types::{self, Address, MAINNET_HRP};
 use anyhow::{Context, Result};
+use bech32::Hrp;
+use governor::Quota;
 use serde::{Deserialize, Serialize};
+use std::env;
+use std::fmt;
 use std::fs;
 use std::net::SocketAddr;
+use std::num::NonZeroU32;
 use std::path::Path;
+use std::str::FromStr;
 use thiserror::Error;
-use tracing::instrument;
+use toml::{Table, Value};
+use tracing::{instrument, warn};
+use tracing_subscriber::EnvFilter;
 
 // --- Constants for Validation ---
 // EVOLVED: Time is now in MILLISECONDS to support high BPS.
@@ -25,6 +49,20 @@ const MAX_DIFFICULTY: u64 = u64::MAX / 2; // More realistic max
 const MAX_MINING_THREADS: usize = 256;
 const MIN_CHAINS: u32 = 1;
 const MAX_CHAINS: u32 = 32;
+/// Coinbase maturity of the single-node development network, short enough to
+/// spend mined rewards without a long wait.
+const LOCAL_COINBASE_MATURITY: u64 = 10;
+
+/// The network joined when `network_id` is not configured.
+pub const DEFAULT_NETWORK_ID: &str = "qanto-testnet-phoenix";
+
+/// Prefix of the environment variables that override settings.
+pub const ENV_PREFIX: &str = "QANTO_";
+/// Names the network preset when none is given on the command line.
+pub const ENV_NETWORK: &str = "QANTO_NETWORK";
+/// The variable `p2p.hmac_secret` was read from before configuration was
+/// layered. It is still honoured, below `QANTO_P2P__HMAC_SECRET`.
+const LEGACY_HMAC_SECRET_ENV: &str = "HMAC_SECRET";
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -42,6 +80,80 @@ pub enum ConfigError {
     },
     #[error("Validation failed: {0}")]
     Validation(String),
+    #[error("Invalid configuration: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+    #[error("Unknown network preset '{0}' (expected local, testnet or mainnet)")]
+    UnknownPreset(String),
+}
+
+/// A named set of defaults for a network, the lowest configuration layer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NetworkPreset {
+    /// A single-node development network.
+    Local,
+    #[default]
+    Testnet,
+    Mainnet,
+}
+
+impl NetworkPreset {
+    /// The settings the other layers are applied on top of.
+    pub fn config(self) -> Config {
+        match self {
+            Self::Local => Config {
+                p2p_address: "/ip4/127.0.0.1/tcp/8008".to_string(),
+                network_id: "qanto-local".to_string(),
+                difficulty: 1,
+                coinbase_maturity: LOCAL_COINBASE_MATURITY,
+                mining_threads: 1,
+                snapshot: SnapshotConfig {
+                    enabled: false,
+                    ..SnapshotConfig::default()
+                },
+                data_dir: format!("{DEFAULT_DATA_DIR}_local"),
+                ..Config::default()
+            },
+            Self::Testnet => Config::default(),
+            Self::Mainnet => Config {
+                network_id: "qanto-mainnet".to_string(),
+                data_dir: format!("{DEFAULT_DATA_DIR}_mainnet"),
+                ..Config::default()
+            },
+        }
+    }
+}
+
+impl fmt::Display for NetworkPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Local => "local",
+            Self::Testnet => "testnet",
+            Self::Mainnet => "mainnet",
+        })
+    }
+}
+
+impl FromStr for NetworkPreset {
+    type Err = ConfigError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "local" => Ok(Self::Local),
+            "testnet" => Ok(Self::Testnet),
+            "mainnet" => Ok(Self::Mainnet),
+            _ => Err(ConfigError::UnknownPreset(name.to_string())),
+        }
+    }
+}
+
+/// The layers `Config::load_layered` merges besides the environment.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigSources {
+    /// Falls back to `QANTO_NETWORK`, then to the testnet.
+    pub preset: Option<NetworkPreset>,
+    pub file: Option<String>,
+    /// Command-line `key=value` assignments, applied last.
+    pub overrides: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -54,10 +166,15 @@ pub struct Config {
     pub network_id: String,
 
     // --- Consensus & DAG Configuration ---
-    pub genesis_validator: String,
+    pub genesis_validator: Address,
     pub target_block_time: u64, // Now in milliseconds
     pub difficulty: u64,
     pub max_amount: u64,
+    /// Blocks a chain must grow past a coinbase before its outputs can be
+    /// spent. A genesis parameter every block commits to, so all nodes of a
+    /// network must agree on it.
+    #[serde(default = "default_coinbase_maturity")]
+    pub coinbase_maturity: u64,
 
     // --- Performance & Hardware ---
     pub use_gpu: bool,
@@ -71,20 +188,57 @@ pub struct Config {
     // --- Logging & P2P Internals ---
     pub logging: LoggingConfig,
     pub p2p: P2pConfig,
+
+    // --- Mempool Persistence ---
+    #[serde(default)]
+    pub mempool: MempoolConfig,
+
+    // --- State Snapshots ---
+    #[serde(default)]
+    pub snapshot: SnapshotConfig,
+
+    // --- Block Storage ---
+    #[serde(default)]
+    pub storage: StorageMode,
+
+    // --- History Indexes ---
+    #[serde(default)]
+    pub index: IndexConfig,
+
+    // --- Rate Limits ---
+    #[serde(default)]
+    pub rate_limits: RateLimitConfig,
+
+    // --- Storage Paths ---
+    /// Root of everything the node keeps on disk; see `crate::data_dir`.
+    #[serde(default = "default_data_dir")]
+    pub data_dir: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+fn default_data_dir() -> String {
+    DEFAULT_DATA_DIR.to_string()
+}
+
+fn default_coinbase_maturity() -> u64 {
+    DEFAULT_COINBASE_MATURITY
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct LoggingConfig {
     pub level: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct P2pConfig {
     pub heartbeat_interval: u64, // in milliseconds
     pub mesh_n_low: usize,
     pub mesh_n: usize,
     pub mesh_n_high: usize,
     pub mesh_outbound_min: usize,
+    /// Key authenticating gossip messages, usually supplied through
+    /// `QANTO_P2P__HMAC_SECRET`. It is never written back to a file.
+    #[serde(default, skip_serializing)]
+    pub hmac_secret: Option<String>,
 }
 
 impl Default for P2pConfig {
@@ -95,10 +249,108 @@ impl Default for P2pConfig {
             mesh_n: 8,
             mesh_n_high: 16,
             mesh_outbound_min: 4,
+            hmac_secret: None,
         }
     }
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// API requests per second, across all clients.
+    pub api_per_second: u32,
+    /// Gossip messages accepted from each peer per second, by kind.
+    pub gossip_blocks_per_second: u32,
+    pub gossip_transactions_per_second: u32,
+    pub gossip_state_per_second: u32,
+    pub gossip_credentials_per_second: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            api_per_second: 50,
+            gossip_blocks_per_second: 10,
+            gossip_transactions_per_second: 50,
+            gossip_state_per_second: 5,
+            gossip_credentials_per_second: 20,
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// The per-peer gossip rates, the only limits the P2P server is built with.
+    pub fn gossip_rates(&self) -> [u32; 4] {
+        [
+            self.gossip_blocks_per_second,
+            self.gossip_transactions_per_second,
+            self.gossip_state_per_second,
+            self.gossip_credentials_per_second,
+        ]
+    }
+
+    /// A quota of `rate` per second. Validation rules out zero rates, which
+    /// are raised to one here.
+    pub fn quota(rate: u32) -> Quota {
+        Quota::per_second(NonZeroU32::new(rate).unwrap_or(NonZeroU32::MIN))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MempoolConfig {
+    /// Keep pending transactions in a journal in the node database across restarts.
+    pub persist: bool,
+    pub flush_interval_secs: u64,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            persist: true,
+            flush_interval_secs: 60,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotConfig {
+    /// Take snapshots at finalized checkpoints and serve them to syncing peers.
+    pub enabled: bool,
+    pub dir: String,
+    /// Minimum number of blocks between two snapshots.
+    pub interval_blocks: u64,
+    /// Number of snapshots kept on disk.
+    pub retain: usize,
+    /// Checkpoints this node may restore from when it starts empty. Without
+    /// any, it syncs from genesis.
+    #[serde(default)]
+    pub trusted: Vec<TrustedCheckpoint>,
+}
+
+impl Default for SnapshotConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: "snapshots".to_string(),
+            interval_blocks: 1_000,
+            retain: 2,
+            trusted: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexConfig {
+    /// Maintain transaction, address-history and spent-output indexes.
+    /// Run `qanto reindex` after enabling this on an existing database.
+    pub enabled: bool,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
 // --- Testnet Defaults ---
 impl Default for Config {
     fn default() -> Self {
@@ -107,12 +359,12 @@ impl Default for Config {
             api_address: "127.0.0.1:8080".to_string(),
             peers: vec![],
             local_full_p2p_address: None,
-            network_id: "qanto-testnet-phoenix".to_string(),
-            genesis_validator: "0000000000000000000000000000000000000000000000000000000000000000"
-                .to_string(),
+            network_id: DEFAULT_NETWORK_ID.to_string(),
+            genesis_validator: Address::ZERO,
             target_block_time: 1000, // Evolved to 1 second for higher throughput
             difficulty: 1000,
             max_amount: 100_000_000_000,
+            coinbase_maturity: DEFAULT_COINBASE_MATURITY,
             use_gpu: false,
             zk_enabled: false,
             mining_threads: num_cpus::get().max(1),
@@ -122,6 +374,12 @@ impl Default for Config {
                 level: "info".to_string(),
             },
             p2p: P2pConfig::default(),
+            mempool: MempoolConfig::default(),
+            snapshot: SnapshotConfig::default(),
+            storage: StorageMode::default(),
+            index: IndexConfig::default(),
+            rate_limits: RateLimitConfig::default(),
+            data_dir: default_data_dir(),
         }
     }
 }
@@ -138,21 +396,117 @@ impl Config {
                     path: path.to_string(),
                     source,
                 })?;
-            return Ok(default_config);
         }
 
-        let content = fs::read_to_string(path)
-            .context("Failed to read configuration file.")
-            .map_err(|source| ConfigError::Load {
-                path: path.to_string(),
-                source,
-            })?;
-        let config: Config = toml::from_str(&content)
-            .context("Failed to parse TOML from configuration file.")
+        Self::load_layered(&ConfigSources {
+            file: Some(path.to_string()),
+            ..ConfigSources::default()
+        })
+    }
+
+    /// Assembles a configuration from `sources` and the `QANTO_*` variables
+    /// of the environment, including any from a `.env` file.
+    #[instrument]
+    pub fn load_layered(sources: &ConfigSources) -> Result<Self, ConfigError> {
+        dotenvy::dotenv().ok();
+        Self::from_layers(sources, env::vars())
+    }
+
+    fn from_layers(
+        sources: &ConfigSources,
+        vars: impl IntoIterator<Item = (String, String)>,
+    ) -> Result<Self, ConfigError> {
+        let mut vars: Vec<(String, String)> = vars
+            .into_iter()
+            .filter(|(key, _)| key.starts_with(ENV_PREFIX) || key == LEGACY_HMAC_SECRET_ENV)
+            .collect();
+        // The legacy variable sorts first, below its `QANTO_` replacement.
+        vars.sort();
+
+        let preset = match sources.preset {
+            Some(preset) => preset,
+            None => match vars.iter().find(|(key, _)| key == ENV_NETWORK) {
+                Some((_, name)) => name.parse()?,
+                None => NetworkPreset::default(),
+            },
+        };
+        let mut table = Table::try_from(preset.config())
+            .context("Failed to serialize the network preset.")
             .map_err(|source| ConfigError::Load {
-                path: path.to_string(),
+                path: preset.to_string(),
                 source,
             })?;
+
+        if let Some(path) = &sources.file {
+            let file = fs::read_to_string(path)
+                .context("Failed to read configuration file.")
+                .and_then(|content| {
+                    toml::from_str::<Table>(&content)
+                        .context("Failed to parse TOML from configuration file.")
+                })
+                .map_err(|source| ConfigError::Load {
+                    path: path.clone(),
+                    source,
+                })?;
+            merge_tables(&mut table, file);
+        }
+
+        let mut errors = Vec::new();
+        for (key, value) in &vars {
+            let path: Vec<String> = if key == LEGACY_HMAC_SECRET_ENV {
+                warn!(
+                    "{LEGACY_HMAC_SECRET_ENV} is deprecated; set QANTO_P2P__HMAC_SECRET instead."
+                );
+                vec!["p2p".to_string(), "hmac_secret".to_string()]
+            } else if key == ENV_NETWORK {
+                continue;
+            } else {
+                key[ENV_PREFIX.len()..]
+                    .to_ascii_lowercase()
+                    .split("__")
+                    .map(str::to_string)
+                    .collect()
+            };
+            if let Err(e) = set_setting(&mut table, &path, value) {
+                errors.push(format!("{key}: {e}"));
+            }
+        }
+        for assignment in &sources.overrides {
+            let Some((key, value)) = assignment.split_once('=') else {
+                errors.push(format!(
+                    "override '{assignment}' is not of the form key=value"
+                ));
+                continue;
+            };
+            let path: Vec<String> = key.trim().split('.').map(str::to_string).collect();
+            if let Err(e) = set_setting(&mut table, &path, value.trim()) {
+                errors.push(format!("override '{assignment}': {e}"));
+            }
+        }
+        // Addresses deserialize from hex only, so a bech32m genesis validator
+        // is checked against the configured network and stored as hex.
+        if let Some(Value::String(validator)) = table.get("genesis_validator") {
+            let network_id = table
+                .get("network_id")
+                .and_then(Value::as_str)
+                .unwrap_or(DEFAULT_NETWORK_ID);
+            match Address::parse_for_network(validator, types::network_hrp(network_id)) {
+                Ok(address) => {
+                    table.insert(
+                        "genesis_validator".to_string(),
+                        Value::String(address.to_string()),
+                    );
+                }
+                Err(e) => errors.push(format!("genesis_validator: {e}")),
+            }
+        }
+        if !errors.is_empty() {
+            return Err(ConfigError::Invalid(errors));
+        }
+
+        let config: Config = Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Invalid(vec![e.message().to_string()]))?;
         config.validate()?;
         Ok(config)
     }
@@ -174,60 +528,191 @@ impl Config {
         Ok(())
     }
 
+    /// Sets the dotted `key` in the file at `path` and leaves the rest of the
+    /// file alone. The node records settings it learns this way, so values
+    /// from other layers are never written into the file.
+    pub fn write_setting(path: &str, key: &str, value: &str) -> Result<(), ConfigError> {
+        let save_error = |source| ConfigError::Save {
+            path: path.to_string(),
+            source,
+        };
+        let mut table = match fs::read_to_string(path) {
+            Ok(content) => toml::from_str::<Table>(&content)
+                .context("Failed to parse TOML from configuration file.")
+                .map_err(save_error)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Table::new(),
+            Err(e) => return Err(save_error(e.into())),
+        };
+        let key_path: Vec<String> = key.split('.').map(str::to_string).collect();
+        set_setting(&mut table, &key_path, value)
+            .map_err(|e| save_error(anyhow::anyhow!("{key}: {e}")))?;
+        let toml_string = toml::to_string_pretty(&table)
+            .context("Failed to serialize configuration to TOML.")
+            .map_err(save_error)?;
+        fs::write(path, toml_string)
+            .context("Failed to write configuration to file.")
+            .map_err(save_error)
+    }
+
+    /// The bech32m prefix of addresses on this node's network.
+    pub fn address_hrp(&self) -> Hrp {
+        types::network_hrp(&self.network_id)
+    }
+
+    /// Checks every setting, reporting all problems found.
     pub fn validate(&self) -> Result<(), ConfigError> {
-        self.api_address.parse::<SocketAddr>().map_err(|_| {
-            ConfigError::Validation(format!(
+        let mut errors = Vec::new();
+
+        if self.api_address.parse::<SocketAddr>().is_err() {
+            errors.push(format!(
                 "Invalid API address format: '{}'",
                 self.api_address
-            ))
-        })?;
+            ));
+        }
 
         if !(MIN_TARGET_BLOCK_TIME..=MAX_TARGET_BLOCK_TIME).contains(&self.target_block_time) {
-            return Err(ConfigError::Validation(format!(
+            errors.push(format!(
                 "target_block_time (in ms) must be between {MIN_TARGET_BLOCK_TIME} and {MAX_TARGET_BLOCK_TIME}"
-            )));
+            ));
         }
 
         if self.peers.len() > MAX_PEERS {
-            return Err(ConfigError::Validation(format!(
-                "Number of peers cannot exceed {MAX_PEERS}"
-            )));
+            errors.push(format!("Number of peers cannot exceed {MAX_PEERS}"));
         }
 
         if !(MIN_DIFFICULTY..=MAX_DIFFICULTY).contains(&self.difficulty) {
-            return Err(ConfigError::Validation(format!(
+            errors.push(format!(
                 "Difficulty must be between {MIN_DIFFICULTY} and {MAX_DIFFICULTY}"
-            )));
+            ));
+        }
+
+        if self.coinbase_maturity == 0 {
+            errors.push("coinbase_maturity must be at least 1".to_string());
         }
 
         if self.mining_threads == 0 || self.mining_threads > MAX_MINING_THREADS {
-            return Err(ConfigError::Validation(format!(
+            errors.push(format!(
                 "mining_threads must be between 1 and {MAX_MINING_THREADS}"
-            )));
+            ));
         }
 
         if !(MIN_CHAINS..=MAX_CHAINS).contains(&self.num_chains) {
-            return Err(ConfigError::Validation(format!(
+            errors.push(format!(
                 "num_chains must be between {MIN_CHAINS} and {MAX_CHAINS}"
-            )));
+            ));
         }
 
         if self.mining_chain_id >= self.num_chains {
-            return Err(ConfigError::Validation(
-                "mining_chain_id must be less than num_chains".to_string(),
-            ));
+            errors.push("mining_chain_id must be less than num_chains".to_string());
         }
 
-        if self.genesis_validator.len() != 64 || hex::decode(&self.genesis_validator).is_err() {
-            return Err(ConfigError::Validation(
-                "Invalid genesis_validator address format".to_string(),
-            ));
+        if self.mempool.persist && self.mempool.flush_interval_secs == 0 {
+            errors.push("mempool persistence requires a non-zero flush_interval_secs".to_string());
         }
 
-        Ok(())
+        if self.snapshot.enabled
+            && (self.snapshot.dir.trim().is_empty()
+                || self.snapshot.interval_blocks == 0
+                || self.snapshot.retain == 0)
+        {
+            errors.push(
+                "snapshots require a dir, a non-zero interval_blocks and a non-zero retain"
+                    .to_string(),
+            );
+        }
+
+        for checkpoint in &self.snapshot.trusted {
+            if checkpoint.meta_hash.len() != 64
+                || !checkpoint.meta_hash.bytes().all(|b| b.is_ascii_hexdigit())
+            {
+                errors.push(format!(
+                    "snapshot.trusted meta_hash for {} must be 64 hex characters",
+                    checkpoint.block_id
+                ));
+            }
+        }
+
+        if self.storage == (StorageMode::Pruned { keep_epochs: 0 }) {
+            errors.push("pruned storage requires a non-zero keep_epochs".to_string());
+        }
+
+        if EnvFilter::try_new(&self.logging.level).is_err() {
+            errors.push(format!("Invalid logging.level: '{}'", self.logging.level));
+        }
+
+        let limits = &self.rate_limits;
+        if [
+            limits.api_per_second,
+            limits.gossip_blocks_per_second,
+            limits.gossip_transactions_per_second,
+            limits.gossip_state_per_second,
+            limits.gossip_credentials_per_second,
+        ]
+        .contains(&0)
+        {
+            errors.push("rate limits must be non-zero".to_string());
+        }
+
+        if self.data_dir.trim().is_empty() {
+            errors.push("data_dir must not be empty".to_string());
+        }
+
+        if self.address_hrp() == MAINNET_HRP && self.p2p.hmac_secret.is_none() {
+            errors.push("p2p.hmac_secret must be set on mainnet".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
     }
 }
 
+/// Merges `overlay` into `base`, descending into sections both define.
+fn merge_tables(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overlay)) => merge_tables(base, overlay),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Sets the setting at `path` to `raw`. Where the setting is already a
+/// string `raw` is taken literally; anywhere else it is read as a TOML value
+/// (`12`, `true`, `["a", "b"]`) and kept as a string if it is not one.
+fn set_setting(table: &mut Table, path: &[String], raw: &str) -> Result<(), String> {
+    if path.iter().any(|part| part.is_empty()) {
+        return Err("empty key".to_string());
+    }
+    let Some((leaf, sections)) = path.split_last() else {
+        return Err("empty key".to_string());
+    };
+    let mut table = table;
+    for section in sections {
+        table = match table
+            .entry(section.clone())
+            .or_insert_with(|| Value::Table(Table::new()))
+        {
+            Value::Table(inner) => inner,
+            _ => return Err(format!("'{section}' is not a section")),
+        };
+    }
+    let value = match table.get(leaf) {
+        Some(Value::String(_)) => Value::String(raw.to_string()),
+        _ => format!("value = {raw}")
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut parsed| parsed.remove("value"))
+            .unwrap_or_else(|| Value::String(raw.to_string())),
+    };
+    table.insert(leaf.clone(), value);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -270,6 +755,103 @@ mod tests {
         assert!(config.validate().is_err());
         config.mining_chain_id = 1;
 
+        config.storage = StorageMode::Pruned { keep_epochs: 0 };
+        assert!(config.validate().is_err());
+        config.storage = StorageMode::Pruned { keep_epochs: 100 };
+
         assert!(config.validate().is_ok());
     }
+
+    #[test]
+    fn test_storage_mode_from_toml() {
+        let config = Config {
+            storage: StorageMode::Pruned { keep_epochs: 500 },
+            ..Config::default()
+        };
+        let toml_string = toml::to_string_pretty(&config).unwrap();
+        assert!(toml_string.contains("[storage]\nmode = \"pruned\"\nkeep_epochs = 500"));
+
+        let loaded: Config = toml::from_str(&toml_string).unwrap();
+        assert_eq!(loaded.storage, StorageMode::Pruned { keep_epochs: 500 });
+    }
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let mut file = NamedTempFile::new().unwrap();
+        std::io::Write::write_all(
+            &mut file,
+            b"api_address = \"127.0.0.1:9000\"\nmining_threads = 2\n[p2p]\nmesh_n = 6\n",
+        )
+        .unwrap();
+        let sources = ConfigSources {
+            preset: Some(NetworkPreset::Local),
+            file: Some(file.path().to_str().unwrap().to_string()),
+            overrides: vec!["p2p.mesh_n=12".to_string()],
+        };
+        let vars = [
+            ("QANTO_MINING_THREADS", "3"),
+            ("QANTO_P2P__MESH_N", "10"),
+            ("QANTO_NETWORK_ID", "42"),
+            ("HMAC_SECRET", "legacy"),
+            ("QANTO_P2P__HMAC_SECRET", "current"),
+            ("PATH", "/usr/bin"),
+        ]
+        .map(|(key, value)| (key.to_string(), value.to_string()));
+
+        let config = Config::from_layers(&sources, vars).unwrap();
+        assert_eq!(config.difficulty, 1); // preset
+        assert_eq!(config.api_address, "127.0.0.1:9000"); // file
+        assert_eq!(config.mining_threads, 3); // environment
+        assert_eq!(config.network_id, "42"); // kept a string
+        assert_eq!(config.p2p.hmac_secret.as_deref(), Some("current"));
+        assert_eq!(config.p2p.mesh_n, 12); // command line
+        assert_eq!(config.p2p.mesh_n_high, P2pConfig::default().mesh_n_high);
+
+        let bad = ConfigSources {
+            overrides: vec!["mining_threads".to_string(), "p2p.mesh_n.x=1".to_string()],
+            ..ConfigSources::default()
+        };
+        match Config::from_layers(&bad, []) {
+            Err(ConfigError::Invalid(errors)) => assert_eq!(errors.len(), 2),
+            other => panic!("expected override errors, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_genesis_validator_must_match_network() {
+        let validator = Address::new([0x42; 32]);
+        let sources = |network: &str, hrp| ConfigSources {
+            preset: Some(NetworkPreset::Local),
+            overrides: vec![
+                format!("network_id={network}"),
+                format!("genesis_validator={}", validator.to_bech32(hrp)),
+            ],
+            ..ConfigSources::default()
+        };
+        let config =
+            Config::from_layers(&sources("qanto-testnet", types::TESTNET_HRP), []).unwrap();
+        assert_eq!(config.genesis_validator, validator);
+        assert!(matches!(
+            Config::from_layers(&sources("qanto-mainnet", types::TESTNET_HRP), []),
+            Err(ConfigError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_validation_reports_every_error() {
+        let config = Config {
+            difficulty: 0,
+            mining_threads: 0,
+            data_dir: String::new(),
+            ..NetworkPreset::Mainnet.config()
+        };
+        match config.validate() {
+            Err(ConfigError::Invalid(errors)) => assert_eq!(errors.len(), 4),
+            other => panic!("expected validation errors, got {other:?}"),
+        }
+        assert!(matches!(
+            "devnet".parse::<NetworkPreset>(),
+            Err(ConfigError::UnknownPreset(_))
+        ));
+    }
 }
@@ -23,10 +23,11 @@
 //!     the PoW difficulty for each miner based on their reputation (Saga Credit Score - SCS).
 //!     This makes the network more efficient and secure without replacing PoW.
 
-use crate::qantodag::{QantoBlock, QantoDAG, QantoDAGError, UTXO};
+use crate::qantodag::{QantoBlock, QantoDAG, QantoDAGError};
 use crate::saga::{PalletSaga, SagaError};
 use crate::transaction::TransactionError;
-use std::collections::HashMap;
+use crate::types::Address;
+use crate::utxo_set::UtxoSet;
 use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::RwLock;
@@ -73,7 +74,7 @@ impl Consensus {
         &self,
         block: &QantoBlock,
         dag_arc: &Arc<QantoDAG>,
-        utxos: &Arc<RwLock<HashMap<String, UTXO>>>,
+        utxos: &Arc<RwLock<UtxoSet>>,
     ) -> Result<(), ConsensusError> {
         // --- Rule 1: Structural & Cryptographic Integrity (Fastest Check) ---
         self.validate_block_structure(block, dag_arc).await?;
@@ -87,7 +88,7 @@ impl Consensus {
         let utxos_guard = utxos.read().await;
         for tx in block.transactions.iter().skip(1) {
             // Skip coinbase
-            tx.verify(dag_arc, &utxos_guard).await?;
+            tx.verify(&utxos_guard, block.spend_context()).await?;
         }
         drop(utxos_guard);
 
@@ -114,7 +115,7 @@ impl Consensus {
         block: &QantoBlock,
         dag_arc: &Arc<QantoDAG>,
     ) -> Result<(), ConsensusError> {
-        if block.id.is_empty() || block.merkle_root.is_empty() || block.validator.is_empty() {
+        if block.id.is_zero() || block.merkle_root.is_empty() || block.validator.is_zero() {
             return Err(ConsensusError::InvalidBlockStructure(
                 "Core fields (ID, Merkle Root, Validator) cannot be empty".to_string(),
             ));
@@ -131,8 +132,9 @@ impl Consensus {
             ));
         }
 
-        let expected_merkle_root = QantoBlock::compute_merkle_root(&block.transactions)
-            .map_err(|e| ConsensusError::InvalidBlockStructure(e.to_string()))?;
+        let expected_merkle_root =
+            QantoBlock::compute_merkle_root(&block.transactions, block.height)
+                .map_err(|e| ConsensusError::InvalidBlockStructure(e.to_string()))?;
         if block.merkle_root != expected_merkle_root {
             return Err(ConsensusError::InvalidBlockStructure(
                 "Merkle root mismatch".to_string(),
@@ -162,7 +164,7 @@ impl Consensus {
     /// It will return an error that can be logged as a warning.
     async fn validate_proof_of_stake(
         &self,
-        validator_address: &str,
+        validator_address: &Address,
         dag: &QantoDAG,
     ) -> Result<(), ConsensusError> {
         // --- NEW LOGIC: NO MINIMUM STAKE REQUIRED ---
@@ -222,7 +224,7 @@ impl Consensus {
 
     /// Retrieves the effective PoW difficulty for a given miner.
     /// This is the core of PoSe, where SAGA's intelligence modifies the base PoW.
-    pub async fn get_effective_difficulty(&self, miner_address: &str) -> u64 {
+    pub async fn get_effective_difficulty(&self, miner_address: &Address) -> u64 {
         let rules = self.saga.economy.epoch_rules.read().await;
         let base_difficulty = rules.get("base_difficulty").map_or(10.0, |r| r.value) as u64;
 
@@ -232,7 +234,7 @@ impl Consensus {
             .credit_scores
             .read()
             .await
-            .get(miner_address)
+            .get(&miner_address.to_string())
             .map_or(0.5, |s| s.score);
 
         let difficulty_modifier = 1.0 - (scs - 0.5);
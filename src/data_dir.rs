@@ -0,0 +1,161 @@
+//! --- Qanto Data Directory ---
+//! v1.0.0 - Path Layout
+//! Everything a node keeps on disk lives under one data directory, so several
+//! nodes can run side by side on one host, each with its own directory:
+//!
+//! ```text
+//! <data_dir>/
+//! ├── LOCK                   held by the process using the directory
+//! ├── db/                    node database: blocks, indexes, swaps and the
+//! │                          mempool journal
+//! ├── keys/
+//! │   ├── wallet.key         default wallet
+//! │   └── p2p_identity.key   libp2p identity
+//! ├── peers.json             peer cache
+//! ├── saga/                  SAGA model files
+//! └── snapshots/             state snapshots (`snapshot.dir`, relative to here)
+//! ```
+//!
+//! `DataDir::lock` takes an exclusive lock on `LOCK` that lasts until the
+//! returned guard is dropped, so a second process pointed at the same
+//! directory fails fast instead of corrupting it.
+
+use std::fs::{self, File, TryLockError};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The data directory used when none is configured.
+pub const DEFAULT_DATA_DIR: &str = "qanto_data";
+
+const LOCK_FILE: &str = "LOCK";
+
+#[derive(Error, Debug)]
+pub enum DataDirError {
+    #[error("Data directory I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Data directory '{path}' is in use by another process ({holder})")]
+    Locked { path: PathBuf, holder: String },
+}
+
+/// The paths of a node's data directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataDir {
+    root: PathBuf,
+}
+
+impl DataDir {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn db(&self) -> PathBuf {
+        self.root.join("db")
+    }
+
+    pub fn keys(&self) -> PathBuf {
+        self.root.join("keys")
+    }
+
+    pub fn wallet(&self) -> PathBuf {
+        self.keys().join("wallet.key")
+    }
+
+    pub fn p2p_identity(&self) -> PathBuf {
+        self.keys().join("p2p_identity.key")
+    }
+
+    pub fn peer_cache(&self) -> PathBuf {
+        self.root.join("peers.json")
+    }
+
+    pub fn saga(&self) -> PathBuf {
+        self.root.join("saga")
+    }
+
+    /// The snapshot directory `dir` from the configuration; a relative path
+    /// is taken relative to the data directory.
+    pub fn snapshots(&self, dir: &str) -> PathBuf {
+        self.root.join(dir)
+    }
+
+    /// Creates the directory and its `keys/` and `saga/` subdirectories.
+    pub fn create(&self) -> io::Result<()> {
+        fs::create_dir_all(self.keys())?;
+        fs::create_dir_all(self.saga())
+    }
+
+    /// Creates the directory if needed and locks it for this process.
+    pub fn lock(&self) -> Result<DataDirLock, DataDirError> {
+        self.create()?;
+        let path = self.root.join(LOCK_FILE);
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = fs::read_to_string(&path)
+                    .ok()
+                    .filter(|pid| !pid.trim().is_empty())
+                    .map_or_else(
+                        || "unknown pid".to_string(),
+                        |pid| format!("pid {}", pid.trim()),
+                    );
+                return Err(DataDirError::Locked {
+                    path: self.root.clone(),
+                    holder,
+                });
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        file.set_len(0)?;
+        writeln!(file, "{}", std::process::id())?;
+        Ok(DataDirLock { _file: file })
+    }
+}
+
+impl Default for DataDir {
+    fn default() -> Self {
+        Self::new(DEFAULT_DATA_DIR)
+    }
+}
+
+/// Holds the lock on a data directory until dropped.
+#[derive(Debug)]
+pub struct DataDirLock {
+    _file: File,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout_and_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = DataDir::new(dir.path());
+        assert_eq!(data_dir.db(), dir.path().join("db"));
+        assert_eq!(
+            data_dir.snapshots("snapshots"),
+            dir.path().join("snapshots")
+        );
+        assert_eq!(
+            data_dir.snapshots("/mnt/snapshots"),
+            Path::new("/mnt/snapshots")
+        );
+
+        let lock = data_dir.lock().unwrap();
+        assert!(data_dir.keys().is_dir());
+        assert!(matches!(data_dir.lock(), Err(DataDirError::Locked { .. })));
+        drop(lock);
+        assert!(data_dir.lock().is_ok());
+    }
+}
@@ -1,14 +1,27 @@
+//! --- Qanto Emission ---
+//! v2.0.0 - Height-Based Schedule
+//! The block reward is a function of block height alone, so every node derives
+//! the same reward for a block no matter when it sees it. Each chain pays
+//! `INITIAL_REWARD` per block, reduced by 3% every `HALVING_INTERVAL` blocks,
+//! until the chain has issued its share of `TOTAL_SUPPLY` after the genesis
+//! outputs. Rewards are computed in `SCALE` fixed-point, never in floats.
+//!
+//! `Emission` also tracks the supply issued by validated coinbases, counting
+//! only what a coinbase mints beyond the fees it collects, and audits it
+//! against the schedule and the cap.
+
+use crate::utxo_set::GENESIS_AMOUNT;
 use log::debug;
 use prometheus::{register_int_counter, IntCounter};
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::instrument;
 
 // Made constants public
-pub const INITIAL_REWARD: u64 = 50; // Initial block reward
+pub const INITIAL_REWARD: u64 = 50; // Block reward on every chain from height 1
 pub const TOTAL_SUPPLY: u64 = 100_000_000_000; // Total supply cap set to 100 Billion
-pub const HALVING_PERIOD: u64 = 7_884_000; // 3 months in seconds
-pub const HALVING_FACTOR: f64 = 0.97; // 3% reduction per halving
+pub const HALVING_INTERVAL: u64 = 7_884_000; // 3 months of blocks at one block per second
+pub const HALVING_NUMERATOR: u64 = 97; // 3% reduction per halving
+pub const HALVING_DENOMINATOR: u64 = 100;
 pub const SCALE: u64 = 1_000_000; // Fixed-point scale for precision
 
 lazy_static::lazy_static! {
@@ -22,15 +35,29 @@ lazy_static::lazy_static! {
     ).unwrap();
 }
 
+/// Issued supply compared with the emission schedule, as served by `/supply`.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SupplyAudit {
+    /// The genesis outputs plus everything minted by validated coinbases.
+    pub issued: u64,
+    /// The genesis outputs plus the scheduled reward of every validated block.
+    pub scheduled: u64,
+    pub total_supply: u64,
+    /// What may still be issued before the cap is reached.
+    pub remaining: u64,
+    pub within_schedule: bool,
+    pub within_cap: bool,
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct Emission {
     initial_reward: u64,
     total_supply: u64,
-    halving_period: u64,
-    halving_factor: f64,
-    genesis_timestamp: u64,
-    current_supply: u64,
+    halving_interval: u64,
+    genesis_supply: u64,
     num_chains: u32,
+    current_supply: u64,
+    scheduled_supply: u64,
     last_halving_period: u64,
 }
 
@@ -39,81 +66,138 @@ impl Emission {
     pub fn new(
         initial_reward: u64,
         total_supply: u64,
-        halving_period: u64,
-        halving_factor: f64,
-        genesis_timestamp: u64,
+        halving_interval: u64,
+        genesis_supply: u64,
         num_chains: u32,
     ) -> Self {
         Self {
             initial_reward: initial_reward.max(1),
             total_supply,
-            halving_period: halving_period.max(1),
-            halving_factor: halving_factor.clamp(0.0, 1.0),
-            genesis_timestamp,
-            current_supply: 0,
+            halving_interval: halving_interval.max(1),
+            genesis_supply,
             num_chains: num_chains.max(1),
+            current_supply: genesis_supply,
+            scheduled_supply: genesis_supply,
             last_halving_period: 0,
         }
     }
 
+    /// The network schedule for `num_chains` chains, each starting with one
+    /// genesis output.
     #[instrument]
-    pub fn default_with_timestamp(genesis_timestamp: u64, num_chains: u32) -> Self {
+    pub fn for_chains(num_chains: u32) -> Self {
         Self::new(
             INITIAL_REWARD,
             TOTAL_SUPPLY,
-            HALVING_PERIOD,
-            HALVING_FACTOR,
-            genesis_timestamp,
+            HALVING_INTERVAL,
+            GENESIS_AMOUNT.saturating_mul(num_chains as u64),
             num_chains,
         )
     }
 
-    #[instrument]
-    pub fn calculate_reward(&self, timestamp: u64) -> Result<u64, String> {
-        if timestamp < self.genesis_timestamp {
-            return Err("Timestamp cannot be before genesis".into());
-        }
-
-        let elapsed_time = timestamp.saturating_sub(self.genesis_timestamp);
-        let elapsed_periods = elapsed_time / self.halving_period;
+    fn period(&self, height: u64) -> u64 {
+        height.saturating_sub(1) / self.halving_interval
+    }
 
-        let mut reward = self.initial_reward as f64;
-        for _ in 0..elapsed_periods {
-            reward *= self.halving_factor;
+    /// The per-block reward during halving period `period`, before the cap.
+    fn period_reward(&self, period: u64) -> u64 {
+        let mut scaled = self.initial_reward as u128 * SCALE as u128;
+        for _ in 0..period {
+            if scaled < SCALE as u128 {
+                return 0;
+            }
+            scaled = scaled * HALVING_NUMERATOR as u128 / HALVING_DENOMINATOR as u128;
         }
+        (scaled / SCALE as u128) as u64
+    }
 
-        let reward = reward.round() as u64;
-        let per_chain_reward = reward
-            .checked_div(self.num_chains as u64)
-            .unwrap_or(1)
-            .max(1);
+    /// The most one chain's coinbases may mint in total.
+    fn chain_cap(&self) -> u64 {
+        self.total_supply.saturating_sub(self.genesis_supply) / self.num_chains as u64
+    }
 
-        if elapsed_periods > self.last_halving_period {
-            HALVING_EVENTS.inc();
-            debug!("Halving event: period {elapsed_periods}, current reward per chain: {per_chain_reward}");
+    /// The uncapped rewards of heights 1 to `height` on one chain.
+    fn cumulative(&self, height: u64) -> u64 {
+        let (full_periods, partial) = (
+            height / self.halving_interval,
+            height % self.halving_interval,
+        );
+        let mut total = 0u64;
+        for period in 0..=full_periods {
+            let reward = self.period_reward(period);
+            if reward == 0 {
+                break;
+            }
+            let blocks = if period == full_periods {
+                partial
+            } else {
+                self.halving_interval
+            };
+            total = total.saturating_add(reward.saturating_mul(blocks));
         }
+        total
+    }
 
-        Ok(per_chain_reward)
+    /// The scheduled reward of a block at `height` on any chain, excluding
+    /// fees. Genesis blocks are not rewarded, and the reward falls to zero
+    /// once the chain has issued its share of the supply.
+    #[instrument]
+    pub fn reward_at(&self, height: u64) -> u64 {
+        if height == 0 {
+            return 0;
+        }
+        let issued_before = self.cumulative(height - 1);
+        self.period_reward(self.period(height))
+            .min(self.chain_cap().saturating_sub(issued_before))
     }
 
+    /// The supply the schedule allows once every chain has reached `height`,
+    /// genesis outputs included.
     #[instrument]
-    pub fn update_supply(&mut self, reward: u64) -> Result<(), String> {
-        let new_supply = self.current_supply.saturating_add(reward);
+    pub fn scheduled_supply_at(&self, height: u64) -> u64 {
+        let per_chain = self.cumulative(height).min(self.chain_cap());
+        self.genesis_supply
+            .saturating_add(per_chain.saturating_mul(self.num_chains as u64))
+    }
 
+    /// Records the coinbase of a validated block at `height` that minted
+    /// `minted` beyond the fees it collected. Leaves the tally untouched if
+    /// the block would take the supply past the cap.
+    #[instrument]
+    pub fn update_supply(&mut self, height: u64, minted: u64) -> Result<(), String> {
+        let new_supply = self.current_supply.saturating_add(minted);
         if new_supply > self.total_supply {
-            self.current_supply = self.total_supply;
             return Err("Total supply cap reached or exceeded".to_string());
         }
+        self.scheduled_supply = self.scheduled_supply.saturating_add(self.reward_at(height));
+
+        let period = self.period(height);
+        if period > self.last_halving_period {
+            HALVING_EVENTS.inc();
+            debug!(
+                "Halving event: period {period}, reward per block: {}",
+                self.period_reward(period)
+            );
+            self.last_halving_period = period;
+        }
 
         self.current_supply = new_supply;
         SUPPLY_UPDATED.inc();
         debug!(
-            "Updated supply: {}. Reward added to total supply: {}",
-            self.current_supply, reward
+            "Updated supply: {}. Minted by the block at height {height}: {minted}",
+            self.current_supply
         );
         Ok(())
     }
 
+    /// Starts the tally from a snapshot: `issued` and `scheduled` are the
+    /// supply of the checkpoint's past, genesis outputs included.
+    #[instrument]
+    pub fn seed(&mut self, issued: u64, scheduled: u64) {
+        self.current_supply = issued;
+        self.scheduled_supply = scheduled;
+    }
+
     #[instrument]
     pub fn current_supply(&self) -> u64 {
         self.current_supply
@@ -125,29 +209,61 @@ impl Emission {
     }
 
     #[instrument]
-    pub fn update_last_halving_period(&mut self, timestamp: u64) {
-        if self.halving_period == 0 {
-            return;
-        }
-        let elapsed_time = timestamp.saturating_sub(self.genesis_timestamp);
-        let current_calculated_period = elapsed_time / self.halving_period;
-        if current_calculated_period > self.last_halving_period {
-            debug!(
-                "Emission state: Last halving period updated from {} to {}",
-                self.last_halving_period, current_calculated_period
-            );
-            self.last_halving_period = current_calculated_period;
+    pub fn audit(&self) -> SupplyAudit {
+        SupplyAudit {
+            issued: self.current_supply,
+            scheduled: self.scheduled_supply,
+            total_supply: self.total_supply,
+            remaining: self.total_supply.saturating_sub(self.current_supply),
+            within_schedule: self.current_supply <= self.scheduled_supply,
+            within_cap: self.current_supply <= self.total_supply,
         }
     }
 
     #[instrument]
-    pub fn quantum_resistant_adjustment(&self, entropy_seed: u64) -> u64 {
-        let now_secs = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let base_reward = self.calculate_reward(now_secs).unwrap_or(1);
+    pub fn quantum_resistant_adjustment(&self, height: u64, entropy_seed: u64) -> u64 {
+        let base_reward = self.reward_at(height).max(1);
         let adjustment = (entropy_seed % 1000).saturating_add(base_reward) % SCALE.max(1);
         base_reward.saturating_add(adjustment).max(1)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_by_height() {
+        let emission = Emission::for_chains(4);
+        assert_eq!(emission.reward_at(0), 0);
+        assert_eq!(emission.reward_at(1), INITIAL_REWARD);
+        assert_eq!(emission.reward_at(HALVING_INTERVAL), INITIAL_REWARD);
+        assert_eq!(emission.reward_at(HALVING_INTERVAL + 1), 48);
+        assert_eq!(
+            emission.scheduled_supply_at(HALVING_INTERVAL + 1),
+            4 * GENESIS_AMOUNT + 4 * (INITIAL_REWARD * HALVING_INTERVAL + 48)
+        );
+
+        // A small cap is reached part-way through a block's reward.
+        let capped = Emission::new(50, 10 + 2 * 120, 2, 10, 2);
+        let rewards: Vec<u64> = (1..=5).map(|height| capped.reward_at(height)).collect();
+        assert_eq!(rewards, vec![50, 50, 20, 0, 0]);
+        assert_eq!(capped.scheduled_supply_at(100), capped.total_supply());
+
+        let mut tracked = Emission::new(50, 1_000, 2, 10, 1);
+        tracked.update_supply(1, 50).unwrap();
+        tracked.update_supply(3, 40).unwrap();
+        let audit = tracked.audit();
+        assert_eq!((audit.issued, audit.scheduled), (100, 108));
+        assert!(audit.within_schedule && audit.within_cap);
+        tracked.update_supply(4, 60).unwrap();
+        assert!(!tracked.audit().within_schedule);
+        let before = tracked.audit();
+        assert!(tracked.update_supply(5, 1_000).is_err());
+        let after = tracked.audit();
+        assert_eq!(
+            (after.issued, after.scheduled),
+            (before.issued, before.scheduled)
+        );
+    }
+}
@@ -0,0 +1,350 @@
+//! --- Qanto Chain Export ---
+//! v1.0.0 - Portable Block Streams
+//! This module moves blocks between nodes as files, for bootstrapping,
+//! archiving and replaying the exact input of a consensus bug.
+//!
+//! - **File format:** the magic `QEXP`, a little-endian `u16` format version
+//!   and a `u64` block count, then one record per block: its little-endian
+//!   `u32` length, the block in the canonical codec and the Keccak256 hash of
+//!   those bytes.
+//! - **Order:** every block is written after the parents and cross-chain
+//!   references its state root builds on, earliest timestamp first among the
+//!   rest, so an import applies blocks in an order `add_block` accepts.
+//! - **Import:** blocks are validated and applied through `QantoDAG::add_block`,
+//!   exactly as if they had arrived from a peer. Blocks the node already has
+//!   are skipped. The first stored parent must already be known to the node,
+//!   so an export that does not start at height 1 imports only onto a node
+//!   that holds the blocks below it.
+
+use crate::codec::{self, CodecError};
+use crate::qantodag::{self, BlockHeader, QantoBlock, QantoDAG, QantoDAGError, PRUNED_DB_PREFIX};
+use crate::storage::{BlockStore, StateStore, StoreError};
+use crate::types::BlockHash;
+use crate::utxo_set::UtxoSet;
+use indicatif::ProgressBar;
+use sha3::{Digest, Keccak256};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::sync::{mpsc, RwLock};
+use tokio::task;
+
+pub const EXPORT_FORMAT_VERSION: u16 = 1;
+const EXPORT_MAGIC: &[u8; 4] = b"QEXP";
+/// Blocks read ahead of the one being applied during an import.
+const IMPORT_QUEUE: usize = 64;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("Export I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Export encoding error: {0}")]
+    Codec(#[from] CodecError),
+    #[error("Storage error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Not a chain export file")]
+    BadMagic,
+    #[error("Unsupported export format version {0}")]
+    UnsupportedVersion(u16),
+    #[error("Block {0} of the export does not match its checksum")]
+    ChecksumMismatch(u64),
+    #[error("Block {index} ({block_id}) was rejected: {source}")]
+    Rejected {
+        index: u64,
+        block_id: BlockHash,
+        source: QantoDAGError,
+    },
+    #[error("Task join error: {0}")]
+    Join(#[from] task::JoinError),
+}
+
+/// Which stored blocks `export` writes.
+#[derive(Debug, Clone, Copy)]
+pub struct ExportFilter {
+    pub from_height: u64,
+    pub to_height: u64,
+    /// Only blocks of this chain, or of every chain if `None`.
+    pub chain: Option<u32>,
+}
+
+impl Default for ExportFilter {
+    fn default() -> Self {
+        Self {
+            from_height: 0,
+            to_height: u64::MAX,
+            chain: None,
+        }
+    }
+}
+
+impl ExportFilter {
+    fn matches(&self, height: u64, chain_id: u32) -> bool {
+        (self.from_height..=self.to_height).contains(&height)
+            && self.chain.is_none_or(|chain| chain == chain_id)
+    }
+}
+
+/// Counts reported by `export`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportStats {
+    pub blocks: u64,
+    /// Matching blocks left out because their bodies were pruned.
+    pub pruned: u64,
+}
+
+/// Counts reported by `import`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportStats {
+    pub applied: u64,
+    /// Blocks the node already had.
+    pub skipped: u64,
+}
+
+fn checksum(bytes: &[u8]) -> [u8; 32] {
+    Keccak256::digest(bytes).into()
+}
+
+/// Writes `blocks` as an export stream.
+pub fn write_blocks(
+    writer: &mut impl Write,
+    blocks: &[QantoBlock],
+    progress: &ProgressBar,
+) -> Result<(), ExportError> {
+    writer.write_all(EXPORT_MAGIC)?;
+    writer.write_all(&EXPORT_FORMAT_VERSION.to_le_bytes())?;
+    writer.write_all(&(blocks.len() as u64).to_le_bytes())?;
+    for block in blocks {
+        let bytes = codec::encode(block)?;
+        let len = u32::try_from(bytes.len()).map_err(|_| CodecError::TooLarge {
+            size: bytes.len(),
+            limit: u32::MAX as usize,
+        })?;
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(&bytes)?;
+        writer.write_all(&checksum(&bytes))?;
+        progress.inc(1);
+    }
+    Ok(())
+}
+
+/// Writes the stored blocks matching `filter` to `path`, replacing any file
+/// there only once the export is complete.
+pub fn export(
+    store: &dyn StateStore,
+    path: &Path,
+    filter: ExportFilter,
+    progress: &ProgressBar,
+) -> Result<ExportStats, ExportError> {
+    let mut stats = ExportStats::default();
+    let mut blocks = Vec::new();
+    for block in store.blocks() {
+        let block = block?;
+        if filter.matches(block.height, block.chain_id) {
+            blocks.push(block);
+        }
+    }
+    for item in store.scan_prefix(PRUNED_DB_PREFIX.as_bytes()) {
+        let (_, value) = item?;
+        let header: BlockHeader = codec::decode(&value)?;
+        if filter.matches(header.height, header.chain_id) {
+            stats.pruned += 1;
+        }
+    }
+    let blocks = qantodag::link_order(blocks, |block| block);
+    stats.blocks = blocks.len() as u64;
+    progress.set_length(stats.blocks);
+
+    let partial = path.with_extension("partial");
+    {
+        let mut writer = BufWriter::new(File::create(&partial)?);
+        write_blocks(&mut writer, &blocks, progress)?;
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+    }
+    fs::rename(partial, path)?;
+    Ok(stats)
+}
+
+/// Reads the blocks of an export stream in order, checking each checksum.
+pub struct BlockReader<R> {
+    reader: R,
+    count: u64,
+    index: u64,
+}
+
+impl<R: Read> BlockReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, ExportError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != EXPORT_MAGIC {
+            return Err(ExportError::BadMagic);
+        }
+        let mut version = [0u8; 2];
+        reader.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != EXPORT_FORMAT_VERSION {
+            return Err(ExportError::UnsupportedVersion(version));
+        }
+        let mut count = [0u8; 8];
+        reader.read_exact(&mut count)?;
+        Ok(Self {
+            reader,
+            count: u64::from_le_bytes(count),
+            index: 0,
+        })
+    }
+
+    /// The number of blocks the stream holds.
+    pub fn block_count(&self) -> u64 {
+        self.count
+    }
+
+    fn read_block(&mut self) -> Result<QantoBlock, ExportError> {
+        let mut len = [0u8; 4];
+        self.reader.read_exact(&mut len)?;
+        let len = u32::from_le_bytes(len) as usize;
+        if len > codec::MAX_ENCODED_SIZE {
+            return Err(CodecError::TooLarge {
+                size: len,
+                limit: codec::MAX_ENCODED_SIZE,
+            }
+            .into());
+        }
+        let mut bytes = vec![0u8; len];
+        self.reader.read_exact(&mut bytes)?;
+        let mut expected = [0u8; 32];
+        self.reader.read_exact(&mut expected)?;
+        if checksum(&bytes) != expected {
+            return Err(ExportError::ChecksumMismatch(self.index));
+        }
+        Ok(codec::decode(&bytes)?)
+    }
+}
+
+impl BlockReader<BufReader<File>> {
+    pub fn open(path: &Path) -> Result<Self, ExportError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Iterator for BlockReader<R> {
+    type Item = Result<QantoBlock, ExportError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index == self.count {
+            return None;
+        }
+        let block = self.read_block();
+        // A damaged stream cannot be resynchronized, so reading ends here.
+        self.index = if block.is_ok() {
+            self.index + 1
+        } else {
+            self.count
+        };
+        Some(block)
+    }
+}
+
+/// Validates and applies the blocks of the export at `path` through
+/// `QantoDAG::add_block`, stopping at the first block that is rejected.
+pub async fn import(
+    dag: &QantoDAG,
+    utxos: &Arc<RwLock<UtxoSet>>,
+    path: PathBuf,
+    progress: &ProgressBar,
+) -> Result<ImportStats, ExportError> {
+    let reader = task::spawn_blocking(move || BlockReader::open(&path)).await??;
+    progress.set_length(reader.block_count());
+
+    // The file is read on a blocking thread while blocks are being applied.
+    let (tx, mut rx) = mpsc::channel(IMPORT_QUEUE);
+    let read_task = task::spawn_blocking(move || {
+        for block in reader {
+            if tx.blocking_send(block).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut stats = ImportStats::default();
+    let mut index = 0;
+    while let Some(block) = rx.recv().await {
+        let block = block?;
+        let block_id = block.id;
+        match dag.add_block(block, utxos).await {
+            Ok(true) => stats.applied += 1,
+            Ok(false) => stats.skipped += 1,
+            Err(source) => {
+                return Err(ExportError::Rejected {
+                    index,
+                    block_id,
+                    source,
+                })
+            }
+        }
+        index += 1;
+        progress.inc(1);
+    }
+    read_task.await?;
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fee_policy::FeePolicy;
+    use crate::qantodag::QantoBlockCreationData;
+    use crate::types::Address;
+    use pqcrypto_dilithium::dilithium5;
+
+    fn block(height: u64) -> QantoBlock {
+        let (public_key, secret_key) = dilithium5::keypair();
+        QantoBlock::new(QantoBlockCreationData {
+            chain_id: 0,
+            parents: vec![BlockHash::new([height as u8; 32])],
+            cross_chain_references: vec![],
+            transactions: vec![],
+            difficulty: 0,
+            validator: Address::new([7; 32]),
+            miner: Address::new([7; 32]),
+            qr_signing_key: &secret_key,
+            qr_public_key: &public_key,
+            timestamp: height,
+            current_epoch: 0,
+            height,
+            fee_policy: FeePolicy::default(),
+            coinbase_maturity: qantodag::DEFAULT_COINBASE_MATURITY,
+            utxo_root: String::new(),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stream_round_trip_and_checksum() {
+        let blocks = vec![block(1), block(2), block(3)];
+        let mut bytes = Vec::new();
+        write_blocks(&mut bytes, &blocks, &ProgressBar::hidden()).unwrap();
+
+        let reader = BlockReader::new(bytes.as_slice()).unwrap();
+        assert_eq!(reader.block_count(), 3);
+        let ids: Vec<BlockHash> = reader.map(|block| block.unwrap().id).collect();
+        assert_eq!(ids, blocks.iter().map(|b| b.id).collect::<Vec<_>>());
+
+        // Flip a byte inside the second record's body.
+        let header = EXPORT_MAGIC.len() + 2 + 8;
+        let first_record = 4 + codec::encoded_len(&blocks[0]).unwrap() + 32;
+        bytes[header + first_record + 10] ^= 0xFF;
+        let results: Vec<_> = BlockReader::new(bytes.as_slice()).unwrap().collect();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[1], Err(ExportError::ChecksumMismatch(1))));
+
+        assert!(matches!(
+            BlockReader::new(&b"QSNP"[..]),
+            Err(ExportError::BadMagic)
+        ));
+    }
+}
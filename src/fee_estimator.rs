@@ -0,0 +1,184 @@
+//! --- Qanto Fee Estimator ---
+//! v1.0.0 - Inclusion-Based Fee Estimation
+//! This module estimates the fee rate a transaction needs to confirm within a
+//! target number of blocks. It combines two signals:
+//! - **Recent blocks:** the lowest fee rate each confirmed block accepted. Blocks
+//!   that were not near capacity accepted any fee, so they count as zero.
+//! - **Mempool backlog:** pending fee-rate buckets. A transaction must outbid the
+//!   backlog that fills the next `target` blocks.
+//!
+//! Fee rates use the mempool's scale: fee units per 100 encoded bytes.
+
+use crate::codec;
+use crate::qantodag::{QantoBlock, MAX_BLOCK_SIZE, MAX_TRANSACTIONS_PER_BLOCK};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Number of recent blocks kept for historical estimates.
+pub const FEE_HISTORY_BLOCKS: usize = 100;
+/// Largest confirmation target accepted by `estimate`.
+pub const MAX_CONFIRMATION_TARGET: u32 = 100;
+/// The lowest fee rate the estimator will ever suggest.
+pub const MIN_FEE_RATE: u64 = 1;
+/// Required probability of confirming within the target.
+const SUCCESS_PROBABILITY: f64 = 0.95;
+
+/// Computes the fee rate of a transaction on the mempool's scale.
+pub fn fee_rate(fee: u64, size_bytes: usize) -> u64 {
+    (fee * 100).checked_div(size_bytes as u64).unwrap_or(0)
+}
+
+/// Computes the fee a transaction of `size_bytes` needs to pay at `fee_rate`.
+pub fn fee_for_size(fee_rate: u64, size_bytes: usize) -> u64 {
+    (fee_rate * size_bytes as u64).div_ceil(100)
+}
+
+#[derive(Clone, Debug)]
+struct BlockFeeStats {
+    height: u64,
+    /// Lowest fee rate the block accepted, or 0 if the block had spare room.
+    min_accepted_rate: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct FeeEstimate {
+    pub target_blocks: u32,
+    /// Recommended fee rate, in fee units per 100 bytes.
+    pub fee_rate: u64,
+    /// Rate needed to outbid the current mempool backlog.
+    pub mempool_rate: u64,
+    /// Rate that recent blocks accepted with the required probability.
+    pub historical_rate: u64,
+    pub blocks_sampled: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct FeeEstimator {
+    history: VecDeque<BlockFeeStats>,
+}
+
+impl FeeEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the fee rates accepted by a newly applied block.
+    pub fn record_block(&mut self, block: &QantoBlock) {
+        let mut block_size = 0usize;
+        let mut min_rate: Option<u64> = None;
+        for tx in block.transactions.iter().filter(|tx| !tx.is_coinbase()) {
+            let size = codec::encoded_len(tx).unwrap_or(0);
+            block_size += size;
+            let rate = fee_rate(tx.fee, size);
+            min_rate = Some(min_rate.map_or(rate, |m| m.min(rate)));
+        }
+
+        let near_capacity = block.transactions.len() >= MAX_TRANSACTIONS_PER_BLOCK * 9 / 10
+            || block_size >= MAX_BLOCK_SIZE * 9 / 10;
+        let min_accepted_rate = if near_capacity {
+            min_rate.unwrap_or(0)
+        } else {
+            0
+        };
+
+        self.history.push_back(BlockFeeStats {
+            height: block.height,
+            min_accepted_rate,
+        });
+        while self.history.len() > FEE_HISTORY_BLOCKS {
+            self.history.pop_front();
+        }
+    }
+
+    /// Height of the most recently recorded block, if any.
+    pub fn last_height(&self) -> Option<u64> {
+        self.history.back().map(|s| s.height)
+    }
+
+    /// Estimates the fee rate needed to confirm within `target` blocks.
+    /// `mempool_buckets` lists pending `(fee_rate, tx_count)` pairs in any order.
+    pub fn estimate(&self, target: u32, mempool_buckets: &[(u64, usize)]) -> FeeEstimate {
+        let target = target.clamp(1, MAX_CONFIRMATION_TARGET);
+
+        let mut buckets = mempool_buckets.to_vec();
+        buckets.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        let capacity = target as usize * MAX_TRANSACTIONS_PER_BLOCK;
+        let mut queued = 0usize;
+        let mut mempool_rate = 0;
+        for (rate, count) in buckets {
+            queued += count;
+            if queued >= capacity {
+                mempool_rate = rate + 1;
+                break;
+            }
+        }
+
+        // A block accepts rate `r` with probability `q` (the share of recent
+        // blocks whose minimum accepted rate is at most `r`). Confirming within
+        // `target` blocks with probability P needs 1 - (1 - q)^target >= P.
+        let historical_rate = if self.history.is_empty() {
+            0
+        } else {
+            let mut rates: Vec<u64> = self.history.iter().map(|s| s.min_accepted_rate).collect();
+            rates.sort_unstable();
+            let q = 1.0 - (1.0 - SUCCESS_PROBABILITY).powf(1.0 / target as f64);
+            let index = ((q * rates.len() as f64).ceil() as usize).clamp(1, rates.len()) - 1;
+            rates[index]
+        };
+
+        FeeEstimate {
+            target_blocks: target,
+            fee_rate: mempool_rate.max(historical_rate).max(MIN_FEE_RATE),
+            mempool_rate,
+            historical_rate,
+            blocks_sampled: self.history.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimator_with(rates: &[u64]) -> FeeEstimator {
+        let mut estimator = FeeEstimator::new();
+        for (height, rate) in rates.iter().enumerate() {
+            estimator.history.push_back(BlockFeeStats {
+                height: height as u64,
+                min_accepted_rate: *rate,
+            });
+        }
+        estimator
+    }
+
+    #[test]
+    fn test_empty_estimator_returns_floor() {
+        let estimate = FeeEstimator::new().estimate(3, &[]);
+        assert_eq!(estimate.fee_rate, MIN_FEE_RATE);
+        assert_eq!(estimate.blocks_sampled, 0);
+    }
+
+    #[test]
+    fn test_longer_targets_are_cheaper() {
+        let rates: Vec<u64> = (1..=100).collect();
+        let estimator = estimator_with(&rates);
+        let fast = estimator.estimate(1, &[]);
+        let slow = estimator.estimate(10, &[]);
+        assert!(fast.historical_rate > slow.historical_rate);
+        assert_eq!(estimator.estimate(0, &[]).target_blocks, 1);
+    }
+
+    #[test]
+    fn test_mempool_backlog_raises_estimate() {
+        let estimator = FeeEstimator::new();
+        let backlog = [(50, MAX_TRANSACTIONS_PER_BLOCK), (10, 5)];
+        assert_eq!(estimator.estimate(1, &backlog).mempool_rate, 51);
+        assert_eq!(estimator.estimate(2, &backlog).mempool_rate, 0);
+    }
+
+    #[test]
+    fn test_fee_for_size_rounds_up() {
+        assert_eq!(fee_for_size(3, 150), 5);
+        assert_eq!(fee_rate(fee_for_size(7, 1234), 1234), 7);
+    }
+}
@@ -0,0 +1,272 @@
+//! --- Qanto Protocol Fee Policy ---
+//! v1.0.0 - Consensus-Enforced Fees
+//! This module is the single source of truth for transaction fees. It defines:
+//! - **Minimum relay fee rate:** the lowest fee rate a node admits to its mempool.
+//! - **Consensus minimum fee:** the lowest absolute fee a transaction may pay to
+//!   be included in a valid block.
+//! - **Developer fee share:** the share of each transfer (and of each coinbase
+//!   reward) that must be paid to `DEV_ADDRESS`.
+//!
+//! Parameters are read from SAGA epoch rules, so governance can evolve them.
+//! Every block commits to the policy of its epoch, derived from the epoch rules
+//! alone, and validators check its transactions against that policy. Economic
+//! edicts are local to a node, so they only scale the policy it relays under.
+
+use crate::fee_estimator;
+use crate::saga::EpochRule;
+use crate::transaction::Transaction;
+use crate::types::Address;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::HashMap;
+use thiserror::Error;
+
+pub const DEV_ADDRESS: Address =
+    Address::from_hex_const("74fd2aae70ae8e0930b87a3dcb3b77f5b71d956659849f067360d3486604db41");
+pub const DEV_FEE_RATE: f64 = 0.0304;
+
+/// Epoch rule holding the minimum relay fee rate (fee units per 100 bytes).
+pub const RULE_MIN_RELAY_FEE_RATE: &str = "min_relay_fee_rate";
+/// Epoch rule holding the consensus minimum absolute fee.
+pub const RULE_CONSENSUS_MIN_FEE: &str = "base_tx_fee_min";
+/// Epoch rule holding the developer fee share as a fraction (0.0304 = 3.04%).
+pub const RULE_DEV_FEE_RATE: &str = "dev_fee_rate";
+
+const BASIS_POINTS: u64 = 10_000;
+
+// Suggested fee tiers for wallets that cannot reach a fee estimator.
+const FEE_TIER1_THRESHOLD: u64 = 1_000_000;
+const FEE_TIER2_THRESHOLD: u64 = 100_000_000;
+const FEE_RATE_TIER1: f64 = 0.01;
+const FEE_RATE_TIER2: f64 = 0.02;
+const FEE_RATE_TIER3: f64 = 0.03;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum FeePolicyError {
+    #[error("Fee {fee} is below the consensus minimum of {minimum}")]
+    BelowConsensusMinimum { fee: u64, minimum: u64 },
+    #[error("Fee rate {rate} is below the minimum relay fee rate of {minimum}")]
+    BelowRelayMinimum { rate: u64, minimum: u64 },
+    #[error("Developer fee output pays {paid}, but {required} is required")]
+    DevFeeShortfall { paid: u64, required: u64 },
+}
+
+/// Fee parameters in force for an epoch.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub struct FeePolicy {
+    /// Minimum fee rate for mempool admission, in fee units per 100 bytes.
+    pub min_relay_fee_rate: u64,
+    /// Minimum absolute fee for a transaction to be valid in a block.
+    pub consensus_min_fee: u64,
+    /// Developer fee share, in basis points of the value transferred to others.
+    pub dev_fee_basis_points: u64,
+}
+
+impl Default for FeePolicy {
+    fn default() -> Self {
+        Self {
+            min_relay_fee_rate: fee_estimator::MIN_FEE_RATE,
+            consensus_min_fee: 1,
+            dev_fee_basis_points: (DEV_FEE_RATE * BASIS_POINTS as f64).round() as u64,
+        }
+    }
+}
+
+impl FeePolicy {
+    /// Builds the policy from SAGA epoch rules. `fee_multiplier` comes from an
+    /// active economic edict and scales the minimum fees during spam attacks.
+    pub fn from_epoch_rules(rules: &HashMap<String, EpochRule>, fee_multiplier: f64) -> Self {
+        let defaults = Self::default();
+        let multiplier = if fee_multiplier.is_finite() && fee_multiplier > 0.0 {
+            fee_multiplier
+        } else {
+            1.0
+        };
+        let rule_or =
+            |name: &str, default: u64| rules.get(name).map_or(default as f64, |r| r.value.max(0.0));
+
+        Self {
+            min_relay_fee_rate: (rule_or(RULE_MIN_RELAY_FEE_RATE, defaults.min_relay_fee_rate)
+                * multiplier)
+                .round() as u64,
+            consensus_min_fee: (rule_or(RULE_CONSENSUS_MIN_FEE, defaults.consensus_min_fee)
+                * multiplier)
+                .round() as u64,
+            dev_fee_basis_points: rules
+                .get(RULE_DEV_FEE_RATE)
+                .map_or(defaults.dev_fee_basis_points, |r| {
+                    (r.value.clamp(0.0, 1.0) * BASIS_POINTS as f64).round() as u64
+                }),
+        }
+    }
+
+    /// The developer fee owed on `amount`, rounded to the nearest unit.
+    pub fn dev_fee(&self, amount: u64) -> u64 {
+        let scaled = amount as u128 * self.dev_fee_basis_points as u128;
+        ((scaled + BASIS_POINTS as u128 / 2) / BASIS_POINTS as u128) as u64
+    }
+
+    /// Checks the consensus fee rules for a non-coinbase transaction.
+    pub fn check_transaction(&self, tx: &Transaction) -> Result<(), FeePolicyError> {
+        if tx.fee < self.consensus_min_fee {
+            return Err(FeePolicyError::BelowConsensusMinimum {
+                fee: tx.fee,
+                minimum: self.consensus_min_fee,
+            });
+        }
+        self.check_dev_share(tx, transferred(tx))
+    }
+
+    /// Checks that the coinbase of a block pays the developer share of `reward`.
+    pub fn check_coinbase(
+        &self,
+        coinbase: &Transaction,
+        reward: u64,
+    ) -> Result<(), FeePolicyError> {
+        self.check_dev_share(coinbase, reward)
+    }
+
+    /// Checks the mempool relay rule for a transaction of `size_bytes`.
+    pub fn check_relay(&self, tx: &Transaction, size_bytes: usize) -> Result<(), FeePolicyError> {
+        let rate = fee_estimator::fee_rate(tx.fee, size_bytes);
+        if rate < self.min_relay_fee_rate {
+            return Err(FeePolicyError::BelowRelayMinimum {
+                rate,
+                minimum: self.min_relay_fee_rate,
+            });
+        }
+        Ok(())
+    }
+
+    /// Hash committing to every parameter, included in signed block data.
+    pub fn commitment(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.min_relay_fee_rate.to_be_bytes());
+        hasher.update(self.consensus_min_fee.to_be_bytes());
+        hasher.update(self.dev_fee_basis_points.to_be_bytes());
+        hasher.finalize().into()
+    }
+
+    fn check_dev_share(&self, tx: &Transaction, amount: u64) -> Result<(), FeePolicyError> {
+        let required = self.dev_fee(amount);
+        let paid: u64 = tx
+            .outputs
+            .iter()
+            .filter(|o| o.address == DEV_ADDRESS)
+            .map(|o| o.amount)
+            .sum();
+        if paid < required {
+            return Err(FeePolicyError::DevFeeShortfall { paid, required });
+        }
+        Ok(())
+    }
+}
+
+/// The value `tx` moves to others: every output except change back to the
+/// sender and the developer fee itself. The declared `amount` is not used,
+/// since the sender chooses it freely.
+pub fn transferred(tx: &Transaction) -> u64 {
+    tx.outputs
+        .iter()
+        .filter(|o| o.address != tx.sender && o.address != DEV_ADDRESS)
+        .map(|o| o.amount)
+        .sum()
+}
+
+/// Suggests a percentage-tiered fee for `amount`. Wallets fall back to this
+/// when no fee estimate is available; it is not a consensus rule.
+pub fn calculate_dynamic_fee(amount: u64) -> u64 {
+    let rate = if amount < FEE_TIER1_THRESHOLD {
+        FEE_RATE_TIER1
+    } else if amount < FEE_TIER2_THRESHOLD {
+        FEE_RATE_TIER2
+    } else {
+        FEE_RATE_TIER3
+    };
+    (amount as f64 * rate).round() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qantodag::{HomomorphicEncrypted, QuantumResistantSignature};
+    use crate::transaction::Output;
+    use crate::types::TxId;
+
+    #[test]
+    fn test_dev_fee_matches_legacy_rate() {
+        let policy = FeePolicy::default();
+        for amount in [0u64, 1, 50, 1_000, 123_456_789] {
+            assert_eq!(
+                policy.dev_fee(amount),
+                (amount as f64 * DEV_FEE_RATE).round() as u64
+            );
+        }
+    }
+
+    #[test]
+    fn test_policy_from_epoch_rules() {
+        let mut rules = HashMap::new();
+        rules.insert(
+            RULE_CONSENSUS_MIN_FEE.to_string(),
+            EpochRule {
+                value: 4.0,
+                description: String::new(),
+            },
+        );
+        rules.insert(
+            RULE_DEV_FEE_RATE.to_string(),
+            EpochRule {
+                value: 0.05,
+                description: String::new(),
+            },
+        );
+        let policy = FeePolicy::from_epoch_rules(&rules, 2.0);
+        assert_eq!(policy.consensus_min_fee, 8);
+        assert_eq!(policy.min_relay_fee_rate, 2);
+        assert_eq!(policy.dev_fee_basis_points, 500);
+        assert_ne!(policy.commitment(), FeePolicy::default().commitment());
+    }
+
+    #[test]
+    fn test_dev_share_ignores_declared_amount() {
+        let sender = Address::new([1; 32]);
+        let output = |address, amount| Output {
+            address,
+            amount,
+            homomorphic_encrypted: HomomorphicEncrypted { ciphertext: vec![] },
+            htlc: None,
+        };
+        let mut tx = Transaction {
+            id: TxId::new([0; 32]),
+            sender,
+            receiver: Address::new([2; 32]),
+            amount: 1,
+            fee: 10,
+            inputs: vec![],
+            outputs: vec![
+                output(Address::new([2; 32]), 10_000),
+                output(sender, 5_000),
+                output(DEV_ADDRESS, 0),
+            ],
+            qr_signature: QuantumResistantSignature {
+                signer_public_key: vec![],
+                signature: vec![],
+            },
+            timestamp: 0,
+            metadata: HashMap::new(),
+            valid_until: None,
+        };
+        let policy = FeePolicy::default();
+        assert_eq!(transferred(&tx), 10_000);
+        assert_eq!(
+            policy.check_transaction(&tx),
+            Err(FeePolicyError::DevFeeShortfall {
+                paid: 0,
+                required: 304
+            })
+        );
+        tx.outputs[2].amount = 304;
+        assert_eq!(policy.check_transaction(&tx), Ok(()));
+    }
+}
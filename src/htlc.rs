@@ -0,0 +1,252 @@
+//! --- Qanto Hash-Time-Locked Contracts ---
+//! v1.0.0 - On-Chain Swap Enforcement
+//! This module defines the HTLC output type that makes cross-chain atomic swaps
+//! enforceable by consensus instead of by node-local bookkeeping.
+//!
+//! An HTLC output can be spent along exactly two paths:
+//! - **Redeem:** the recipient (swap responder) reveals the preimage of
+//!   `secret_hash` in a block timestamped before `timelock`.
+//! - **Refund:** the refund address (swap initiator) reclaims the funds in a
+//!   block timestamped at or after `timelock`.
+//!
+//! The swap watcher half of this module turns HTLC activity observed in
+//! accepted blocks into `CrossChainSwap` state transitions, extracting revealed
+//! secrets so the counterparty can complete the other leg of the swap.
+
+use crate::qantodag::{CrossChainSwap, QantoBlock, SwapState, UTXO};
+use crate::types::{Address, OutPoint};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
+
+/// Prefix for swap records persisted in the node database.
+pub const SWAP_DB_PREFIX: &str = "swap:";
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum HtlcError {
+    #[error("HTLC secret hash must be a 64-character hex string")]
+    InvalidSecretHash,
+    #[error("HTLC timelock must be non-zero")]
+    InvalidTimelock,
+    #[error("HTLC output address must match the lock recipient")]
+    RecipientMismatch,
+    #[error("Only the HTLC recipient can redeem with a preimage")]
+    NotRecipient,
+    #[error("Only the HTLC refund address can reclaim funds")]
+    NotRefundAddress,
+    #[error("Preimage does not match the HTLC secret hash")]
+    PreimageMismatch,
+    #[error("HTLC timelock has expired, only a refund is possible")]
+    Expired,
+    #[error("HTLC timelock has not yet expired (unlocks at {0})")]
+    NotYetExpired(u64),
+}
+
+/// Spending conditions attached to an HTLC output.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+pub struct HashTimeLock {
+    /// Address that may redeem the output by revealing the secret.
+    pub recipient: Address,
+    /// Address that may reclaim the output after the timelock.
+    pub refund_address: Address,
+    /// Hex-encoded Keccak256 hash of the swap secret.
+    pub secret_hash: String,
+    /// Unix timestamp (seconds) from which only the refund path is valid,
+    /// compared with the timestamp of the including block.
+    pub timelock: u64,
+}
+
+/// The spend path an HTLC input took, as observed by the swap watcher.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HtlcSpend {
+    Redeemed { secret: String },
+    Refunded,
+}
+
+/// Hashes a swap secret the same way HTLC outputs commit to it.
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Keccak256::digest(secret.as_bytes()))
+}
+
+impl HashTimeLock {
+    /// Checks the lock is well-formed for an output paying to `output_address`.
+    pub fn validate_output(&self, output_address: &Address) -> Result<(), HtlcError> {
+        if self.secret_hash.len() != 64 || hex::decode(&self.secret_hash).is_err() {
+            return Err(HtlcError::InvalidSecretHash);
+        }
+        if self.timelock == 0 {
+            return Err(HtlcError::InvalidTimelock);
+        }
+        if self.recipient != *output_address {
+            return Err(HtlcError::RecipientMismatch);
+        }
+        Ok(())
+    }
+
+    /// Authorizes spending the locked output in a block with timestamp
+    /// `block_timestamp`. The timestamp of the including block is bounded by
+    /// consensus, unlike the signer-chosen transaction timestamp or a node's
+    /// clock, so every node reaches the same verdict, also when replaying.
+    pub fn authorize_spend(
+        &self,
+        spender: &Address,
+        preimage: Option<&str>,
+        block_timestamp: u64,
+    ) -> Result<HtlcSpend, HtlcError> {
+        match preimage {
+            Some(secret) => {
+                if self.recipient != *spender {
+                    return Err(HtlcError::NotRecipient);
+                }
+                if hash_secret(secret) != self.secret_hash {
+                    return Err(HtlcError::PreimageMismatch);
+                }
+                if block_timestamp >= self.timelock {
+                    return Err(HtlcError::Expired);
+                }
+                Ok(HtlcSpend::Redeemed {
+                    secret: secret.to_string(),
+                })
+            }
+            None => {
+                if self.refund_address != *spender {
+                    return Err(HtlcError::NotRefundAddress);
+                }
+                if block_timestamp < self.timelock {
+                    return Err(HtlcError::NotYetExpired(self.timelock));
+                }
+                Ok(HtlcSpend::Refunded)
+            }
+        }
+    }
+}
+
+/// Extracts swap state transitions from a block that is being applied.
+///
+/// `spent_htlcs` holds the HTLC UTXOs consumed by the block, keyed by outpoint,
+/// as captured while the UTXO set was being updated. The returned swaps are
+/// keyed by the outpoint of the HTLC output (`{tx_id}_{index}`).
+pub fn watch_block(block: &QantoBlock, spent_htlcs: &[(OutPoint, UTXO)]) -> Vec<CrossChainSwap> {
+    let mut updates = Vec::new();
+
+    for tx in &block.transactions {
+        for (index, output) in tx.outputs.iter().enumerate() {
+            if let Some(lock) = &output.htlc {
+                updates.push(CrossChainSwap {
+                    swap_id: OutPoint::new(tx.id, index as u32).to_string(),
+                    source_chain: block.chain_id,
+                    target_chain: block.chain_id,
+                    amount: output.amount,
+                    initiator: lock.refund_address,
+                    responder: lock.recipient,
+                    timelock: lock.timelock,
+                    state: SwapState::Initiated,
+                    secret_hash: lock.secret_hash.clone(),
+                    secret: None,
+                });
+            }
+        }
+
+        for input in &tx.inputs {
+            let utxo_id = input.outpoint();
+            let Some((_, utxo)) = spent_htlcs.iter().find(|(id, _)| *id == utxo_id) else {
+                continue;
+            };
+            let Some(lock) = &utxo.htlc else {
+                continue;
+            };
+            let (state, secret) = match &input.htlc_preimage {
+                Some(secret) => (SwapState::Redeemed, Some(secret.clone())),
+                None => (SwapState::Refunded, None),
+            };
+            updates.push(CrossChainSwap {
+                swap_id: utxo_id.to_string(),
+                source_chain: block.chain_id,
+                target_chain: block.chain_id,
+                amount: utxo.amount,
+                initiator: lock.refund_address,
+                responder: lock.recipient,
+                timelock: lock.timelock,
+                state,
+                secret_hash: lock.secret_hash.clone(),
+                secret,
+            });
+        }
+    }
+    updates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECIPIENT: Address = Address::new([0xaa; 32]);
+    const REFUNDER: Address = Address::new([0xbb; 32]);
+
+    fn sample_lock() -> HashTimeLock {
+        HashTimeLock {
+            recipient: RECIPIENT,
+            refund_address: REFUNDER,
+            secret_hash: hash_secret("open sesame"),
+            timelock: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_redeem_path() {
+        let lock = sample_lock();
+        assert_eq!(
+            lock.authorize_spend(&RECIPIENT, Some("open sesame"), 999),
+            Ok(HtlcSpend::Redeemed {
+                secret: "open sesame".to_string()
+            })
+        );
+        assert_eq!(
+            lock.authorize_spend(&RECIPIENT, Some("wrong"), 999),
+            Err(HtlcError::PreimageMismatch)
+        );
+        assert_eq!(
+            lock.authorize_spend(&REFUNDER, Some("open sesame"), 999),
+            Err(HtlcError::NotRecipient)
+        );
+        assert_eq!(
+            lock.authorize_spend(&RECIPIENT, Some("open sesame"), 1_000),
+            Err(HtlcError::Expired)
+        );
+    }
+
+    #[test]
+    fn test_refund_path() {
+        let lock = sample_lock();
+        assert_eq!(
+            lock.authorize_spend(&REFUNDER, None, 1_000),
+            Ok(HtlcSpend::Refunded)
+        );
+        assert_eq!(
+            lock.authorize_spend(&REFUNDER, None, 999),
+            Err(HtlcError::NotYetExpired(1_000))
+        );
+        assert_eq!(
+            lock.authorize_spend(&RECIPIENT, None, 1_000),
+            Err(HtlcError::NotRefundAddress)
+        );
+    }
+
+    #[test]
+    fn test_output_validation() {
+        let lock = sample_lock();
+        assert!(lock.validate_output(&RECIPIENT).is_ok());
+        assert_eq!(
+            lock.validate_output(&REFUNDER),
+            Err(HtlcError::RecipientMismatch)
+        );
+        let bad = HashTimeLock {
+            secret_hash: "xyz".to_string(),
+            ..sample_lock()
+        };
+        assert_eq!(
+            bad.validate_output(&RECIPIENT),
+            Err(HtlcError::InvalidSecretHash)
+        );
+    }
+}
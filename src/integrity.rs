@@ -0,0 +1,620 @@
+//! --- Qanto Database Integrity ---
+//! v1.0.0 - Offline Verification
+//! Re-validates the database of a stopped node from scratch, for `qanto db verify`.
+//!
+//! - **Blocks:** every stored block is decoded and checked on its own: its key
+//!   and ID, merkle root, signature, proof-of-work, coinbase and parent links.
+//!   A pruned block is stored as its header alone, which cannot reproduce
+//!   the ID and signature that commit to the body, so only its key,
+//!   proof-of-work and parent links are checked.
+//! - **State:** blocks are replayed over the genesis outputs with every block
+//!   after the parents and cross-chain references its state root builds on,
+//!   earliest timestamp first among the rest, and the UTXO state of each
+//!   block's past after it must hash to the root the block commits to. Replay
+//!   stops at the first faulty or pruned block.
+//! - **Supply:** the replayed supply is the genesis outputs plus what every
+//!   coinbase mints beyond the fees it collects. It must stay under the
+//!   emission cap and cover the value held in the UTXO set.
+//! - **Repair:** `truncate` deletes every block after the last finalized
+//!   checkpoint before the first fault, together with its index entries.
+
+use crate::codec::{self, CodecError};
+use crate::emission::TOTAL_SUPPLY;
+use crate::miner::Miner;
+use crate::qantodag::{self, BlockHeader, QantoBlock, FINALIZATION_DEPTH, PRUNED_DB_PREFIX, UTXO};
+use crate::storage::{self, StateStore, StoreBatch, StoreError};
+use crate::tx_index;
+use crate::types::{BlockHash, OutPoint};
+use crate::utxo_set::{PastView, UtxoChange, UtxoSet, UtxoSetError};
+use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use thiserror::Error;
+
+const TRUNCATE_BATCH_SIZE: usize = 10_000;
+
+#[derive(Error, Debug)]
+pub enum IntegrityError {
+    #[error("Storage error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Encoding error: {0}")]
+    Codec(#[from] CodecError),
+    #[error("No finalized checkpoint precedes the first fault")]
+    NoCheckpoint,
+}
+
+/// Something wrong with one stored block.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    #[error("entry does not decode as a block: {0}")]
+    Undecodable(String),
+    #[error("stored under another key than its ID {0}")]
+    KeyMismatch(BlockHash),
+    #[error("ID does not match the block's contents")]
+    IdMismatch,
+    #[error("merkle root does not match the transactions")]
+    MerkleRoot,
+    #[error("signature does not verify")]
+    Signature,
+    #[error("proof-of-work not satisfied")]
+    ProofOfWork,
+    #[error("first transaction is not a coinbase")]
+    MissingCoinbase,
+    #[error("coinbase pays {paid}, block reward is {reward}")]
+    Coinbase { reward: u64, paid: u64 },
+    #[error("parent {0} is not stored")]
+    MissingParent(BlockHash),
+    #[error("parent {0} is on another chain")]
+    ForeignParent(BlockHash),
+    #[error("timestamp is not after parent {0}")]
+    Timestamp(BlockHash),
+    #[error("height is {found}, expected {expected}")]
+    Height { expected: u64, found: u64 },
+    #[error("spends {0}, which is not unspent")]
+    MissingInput(OutPoint),
+    #[error("commits to state root {committed}, replay computes {computed}")]
+    StateRoot { committed: String, computed: String },
+    #[error("{0}")]
+    History(#[from] UtxoSetError),
+}
+
+/// A fault and the store key of the block it was found in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockFault {
+    pub key: String,
+    pub height: Option<u64>,
+    pub fault: Fault,
+}
+
+/// The outcome of `verify`.
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub blocks: usize,
+    pub pruned: usize,
+    pub transactions: usize,
+    pub faults: Vec<BlockFault>,
+    /// Blocks whose changes were replayed onto the UTXO set.
+    pub replayed: usize,
+    /// Why replay ended before the last block, if it did.
+    pub replay_stopped: Option<String>,
+    pub utxo_count: usize,
+    pub utxo_value: u64,
+    pub utxo_root: String,
+    pub supply: u64,
+    /// The last finalized block before the first fault, with its height.
+    pub checkpoint: Option<(BlockHash, u64)>,
+    /// Keys `truncate` deletes: the blocks after the checkpoint and every
+    /// undecodable entry.
+    beyond_checkpoint: Vec<Vec<u8>>,
+}
+
+impl VerifyReport {
+    pub fn supply_ok(&self) -> bool {
+        self.supply <= TOTAL_SUPPLY && self.utxo_value <= self.supply
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.faults.is_empty() && self.supply_ok()
+    }
+}
+
+impl fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Blocks:      {} ({} pruned), {} transactions",
+            self.blocks, self.pruned, self.transactions
+        )?;
+        writeln!(f, "Faults:      {}", self.faults.len())?;
+        for fault in &self.faults {
+            match fault.height {
+                Some(height) => writeln!(f, "  {} (height {height}): {}", fault.key, fault.fault)?,
+                None => writeln!(f, "  {}: {}", fault.key, fault.fault)?,
+            }
+        }
+        write!(
+            f,
+            "Replayed:    {} of {} blocks",
+            self.replayed, self.blocks
+        )?;
+        match &self.replay_stopped {
+            Some(reason) => writeln!(f, "; stopped: {reason}")?,
+            None => writeln!(f)?,
+        }
+        writeln!(
+            f,
+            "UTXO set:    {} outputs worth {}, state root {}",
+            self.utxo_count, self.utxo_value, self.utxo_root
+        )?;
+        writeln!(f, "Supply:      {} (cap {TOTAL_SUPPLY})", self.supply)?;
+        if !self.supply_ok() {
+            writeln!(f, "  supply exceeds the cap or does not cover the UTXO set")?;
+        }
+        match self.checkpoint {
+            Some((id, height)) => writeln!(f, "Checkpoint:  {id} at height {height}")?,
+            None => writeln!(f, "Checkpoint:  none")?,
+        }
+        write!(
+            f,
+            "Result:      {}",
+            if self.is_consistent() {
+                "consistent"
+            } else {
+                "INCONSISTENT"
+            }
+        )
+    }
+}
+
+/// A stored block, or the header kept in place of a pruned one.
+struct Entry {
+    key: Vec<u8>,
+    header: BlockHeader,
+    /// `None` when the body was pruned.
+    block: Option<QantoBlock>,
+}
+
+impl Entry {
+    /// The key the entry is stored under if it matches its ID.
+    fn expected_key(&self) -> String {
+        match self.block {
+            Some(_) => self.header.id.to_string(),
+            None => format!("{PRUNED_DB_PREFIX}{}", self.header.id),
+        }
+    }
+}
+
+/// The faults `entry` has on its own and against its stored parents.
+fn check_block(entry: &Entry, stored: &HashMap<BlockHash, &BlockHeader>) -> Vec<Fault> {
+    let header = &entry.header;
+    let mut faults = Vec::new();
+    if entry.key != entry.expected_key().as_bytes() {
+        faults.push(Fault::KeyMismatch(header.id));
+    }
+
+    if let Some(block) = &entry.block {
+        if block.computed_id().ok() != Some(block.id) {
+            faults.push(Fault::IdMismatch);
+        }
+        if QantoBlock::compute_merkle_root(&block.transactions, block.height)
+            .ok()
+            .as_ref()
+            != Some(&block.merkle_root)
+        {
+            faults.push(Fault::MerkleRoot);
+        }
+        if !block.verify_signature().unwrap_or(false) {
+            faults.push(Fault::Signature);
+        }
+        match block.transactions.first() {
+            Some(coinbase) if coinbase.is_coinbase() => {
+                let paid: u64 = coinbase.outputs.iter().map(|o| o.amount).sum();
+                if paid != block.reward {
+                    faults.push(Fault::Coinbase {
+                        reward: block.reward,
+                        paid,
+                    });
+                }
+            }
+            _ => faults.push(Fault::MissingCoinbase),
+        }
+    }
+
+    let target = Miner::calculate_target_from_difficulty(header.difficulty);
+    let pow_ok =
+        hex::decode(header.hash()).is_ok_and(|hash| Miner::hash_meets_target(&hash, &target));
+    if !pow_ok {
+        faults.push(Fault::ProofOfWork);
+    }
+
+    // Genesis blocks are rebuilt at startup rather than stored, so a block at
+    // height 1 may name parents that are not in the database.
+    let mut max_parent_height = Some(0);
+    for parent_id in &header.parents {
+        let Some(parent) = stored.get(parent_id) else {
+            if header.height != 1 {
+                faults.push(Fault::MissingParent(*parent_id));
+            }
+            max_parent_height = None;
+            continue;
+        };
+        if parent.chain_id != header.chain_id {
+            faults.push(Fault::ForeignParent(*parent_id));
+        }
+        if header.timestamp <= parent.timestamp {
+            faults.push(Fault::Timestamp(*parent_id));
+        }
+        max_parent_height = max_parent_height.map(|max: u64| max.max(parent.height));
+    }
+    if let Some(max_parent_height) = max_parent_height.filter(|_| !header.parents.is_empty()) {
+        if header.height != max_parent_height + 1 {
+            faults.push(Fault::Height {
+                expected: max_parent_height + 1,
+                found: header.height,
+            });
+        }
+    }
+    faults
+}
+
+/// Whether every spend in `changes` finds an unspent output, either in `past`
+/// or created earlier in the same block. Returns the first that does not.
+fn missing_input(past: &PastView, changes: &[UtxoChange]) -> Option<OutPoint> {
+    let mut created = HashSet::new();
+    for change in changes {
+        match change {
+            UtxoChange::Create(outpoint, _) => {
+                created.insert(*outpoint);
+            }
+            UtxoChange::Spend(outpoint) => {
+                if !created.remove(outpoint) && past.get(outpoint).is_none() {
+                    return Some(*outpoint);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Re-validates every block in `store` and replays them over `genesis`, the
+/// outputs the node seeds its UTXO set with. Nothing is written.
+pub fn verify(
+    store: &dyn StateStore,
+    genesis: Vec<(OutPoint, UTXO)>,
+) -> Result<VerifyReport, IntegrityError> {
+    let mut report = VerifyReport::default();
+    let mut entries = Vec::new();
+    for item in store.scan_prefix(b"") {
+        let (key, value) = item?;
+        let decoded = if key.starts_with(PRUNED_DB_PREFIX.as_bytes()) {
+            codec::decode::<BlockHeader>(&value).map(|header| (header, None))
+        } else if storage::is_block_key(&key) {
+            codec::decode::<QantoBlock>(&value)
+                .map(|block| (BlockHeader::from(&block), Some(block)))
+        } else {
+            continue;
+        };
+        match decoded {
+            Ok((header, block)) => entries.push(Entry { key, header, block }),
+            Err(e) => {
+                report.faults.push(BlockFault {
+                    key: String::from_utf8_lossy(&key).into_owned(),
+                    height: None,
+                    fault: Fault::Undecodable(e.to_string()),
+                });
+                report.beyond_checkpoint.push(key);
+            }
+        }
+    }
+    let entries = qantodag::link_order(entries, |entry| &entry.header);
+    report.blocks = entries.len();
+    report.pruned = entries.iter().filter(|entry| entry.block.is_none()).count();
+    report.transactions = entries
+        .iter()
+        .filter_map(|entry| entry.block.as_ref())
+        .map(|block| block.transactions.len())
+        .sum();
+
+    let stored: HashMap<BlockHash, &BlockHeader> = entries
+        .iter()
+        .map(|entry| (entry.header.id, &entry.header))
+        .collect();
+    let positions: HashMap<BlockHash, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(position, entry)| (entry.header.id, position))
+        .collect();
+    let block_faults: Vec<(usize, Fault)> = entries
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(position, entry)| {
+            check_block(entry, &stored)
+                .into_iter()
+                .map(move |fault| (position, fault))
+        })
+        .collect();
+    let mut first_fault = block_faults
+        .iter()
+        .map(|(position, _)| *position)
+        .min()
+        .unwrap_or(entries.len());
+
+    let mut utxos: UtxoSet = genesis.into_iter().collect();
+    report.supply = utxos.values().map(|utxo| utxo.amount).sum();
+    let mut replay_faults = Vec::new();
+    for (position, entry) in entries.iter().enumerate() {
+        if position == first_fault {
+            report.replay_stopped = Some(format!("block {} has faults", entry.header.id));
+            break;
+        }
+        let Some(block) = &entry.block else {
+            report.replay_stopped = Some(format!(
+                "the body of block {} at height {} is pruned",
+                entry.header.id, entry.header.height
+            ));
+            break;
+        };
+        let changes = block.utxo_changes();
+        let replay_fault = match utxos.past(&block.links()) {
+            Err(e) => Some(Fault::from(e)),
+            Ok(past) => match missing_input(&past, &changes) {
+                Some(outpoint) => Some(Fault::MissingInput(outpoint)),
+                None => {
+                    let computed = past.state_root_after(&changes);
+                    (computed != block.utxo_root).then(|| Fault::StateRoot {
+                        committed: block.utxo_root.clone(),
+                        computed,
+                    })
+                }
+            },
+        };
+        if let Some(fault) = replay_fault {
+            replay_faults.push((position, fault));
+            first_fault = position;
+            report.replay_stopped = Some(format!("block {} has faults", block.id));
+            break;
+        }
+        utxos.apply_block(block.id, block.links(), changes);
+        report.supply = report.supply.saturating_add(block.minted());
+        report.replayed += 1;
+    }
+    report.utxo_count = utxos.len();
+    report.utxo_value = utxos.values().map(|utxo| utxo.amount).sum();
+    report.utxo_root = utxos.state_root();
+
+    for (position, fault) in block_faults.into_iter().chain(replay_faults) {
+        let entry = &entries[position];
+        report.faults.push(BlockFault {
+            key: String::from_utf8_lossy(&entry.key).into_owned(),
+            height: Some(entry.header.height),
+            fault,
+        });
+    }
+
+    // Finality as `QantoDAG::finalize_blocks` decides it, over the blocks
+    // before the first fault: every block on a first-parent path of
+    // `FINALIZATION_DEPTH` blocks is final.
+    let mut finalized = HashSet::new();
+    for position in 0..first_fault {
+        let mut path = vec![position];
+        while path.len() < FINALIZATION_DEPTH as usize {
+            let current = &entries[*path.last().unwrap_or(&position)].header;
+            match current
+                .parents
+                .first()
+                .and_then(|parent| positions.get(parent))
+                .filter(|&&parent| parent < first_fault)
+            {
+                Some(&parent) => path.push(parent),
+                None => break,
+            }
+        }
+        if path.len() >= FINALIZATION_DEPTH as usize {
+            finalized.extend(path);
+        }
+    }
+    let checkpoint = finalized.into_iter().max_by_key(|&position| {
+        let header = &entries[position].header;
+        (header.height, header.id)
+    });
+    report.checkpoint = checkpoint.map(|position| {
+        let header = &entries[position].header;
+        (header.id, header.height)
+    });
+
+    if !report.faults.is_empty() {
+        let keep = checkpoint.map_or(0, |position| position + 1);
+        report
+            .beyond_checkpoint
+            .extend(entries.into_iter().skip(keep).map(|entry| entry.key));
+    }
+    Ok(report)
+}
+
+/// Deletes the blocks and pruned headers after `report`'s checkpoint, with
+/// the blocks' index entries. Returns how many entries were removed.
+pub fn truncate(store: &dyn StateStore, report: &VerifyReport) -> Result<usize, IntegrityError> {
+    if report.faults.is_empty() {
+        return Ok(0);
+    }
+    if report.checkpoint.is_none() {
+        return Err(IntegrityError::NoCheckpoint);
+    }
+    let mut batch = StoreBatch::default();
+    for key in &report.beyond_checkpoint {
+        // Pruned headers have no index entries left to remove.
+        let value = if storage::is_block_key(key) {
+            store.get(key)?
+        } else {
+            None
+        };
+        if let Some(block) = value.and_then(|value| codec::decode::<QantoBlock>(&value).ok()) {
+            tx_index::unindex_block(&mut batch, &block)?;
+        }
+        batch.delete(key);
+        if batch.len() >= TRUNCATE_BATCH_SIZE {
+            store.write(std::mem::take(&mut batch))?;
+        }
+    }
+    if !batch.is_empty() {
+        store.write(batch)?;
+    }
+    Ok(report.beyond_checkpoint.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fee_policy::FeePolicy;
+    use crate::qantodag::{self, CoinbaseOrigin, HomomorphicEncrypted, QantoBlockCreationData};
+    use crate::storage::{BlockStore, MemoryStore};
+    use crate::transaction::{Output, Transaction};
+    use crate::types::Address;
+    use crate::utxo_set::genesis_utxos;
+    use pqcrypto_dilithium::dilithium5;
+    use pqcrypto_traits::sign::{PublicKey, SecretKey};
+
+    /// A chain of `length` valid blocks on top of the genesis outputs.
+    fn chain(length: u64) -> Vec<QantoBlock> {
+        let (public_key, secret_key) = dilithium5::keypair();
+        let validator = Address::new([7; 32]);
+        let mut utxos: UtxoSet = genesis_utxos(1, validator).into_iter().collect();
+        let mut parent = BlockHash::new([0xAA; 32]);
+        let mut blocks = Vec::new();
+        for height in 1..=length {
+            let coinbase = Transaction::new_coinbase(
+                validator,
+                50,
+                secret_key.as_bytes(),
+                public_key.as_bytes(),
+                vec![Output {
+                    address: validator,
+                    amount: 50,
+                    homomorphic_encrypted: HomomorphicEncrypted::new(0, &[]),
+                    htlc: None,
+                }],
+            )
+            .unwrap();
+            let transactions = vec![coinbase];
+            let utxo_root = utxos
+                .state_root_after(
+                    &[parent],
+                    &qantodag::utxo_changes(
+                        &transactions,
+                        CoinbaseOrigin {
+                            block_id: BlockHash::ZERO,
+                            chain_id: 0,
+                            height,
+                            maturity: qantodag::DEFAULT_COINBASE_MATURITY,
+                        },
+                    ),
+                )
+                .unwrap();
+            let mut block = QantoBlock::new(QantoBlockCreationData {
+                chain_id: 0,
+                parents: vec![parent],
+                cross_chain_references: vec![],
+                transactions,
+                difficulty: 0,
+                validator,
+                miner: validator,
+                qr_signing_key: &secret_key,
+                qr_public_key: &public_key,
+                timestamp: height,
+                current_epoch: 0,
+                height,
+                fee_policy: FeePolicy::default(),
+                coinbase_maturity: qantodag::DEFAULT_COINBASE_MATURITY,
+                utxo_root,
+            })
+            .unwrap();
+            block.reward = 50;
+            utxos.apply_block(block.id, block.links(), block.utxo_changes());
+            parent = block.id;
+            blocks.push(block);
+        }
+        blocks
+    }
+
+    #[test]
+    fn test_verify_and_truncate() {
+        let store = MemoryStore::new();
+        let mut blocks = chain(10);
+        let mut batch = StoreBatch::default();
+        for block in &blocks {
+            batch.put_block(block).unwrap();
+        }
+        store.write(batch).unwrap();
+
+        let genesis = || genesis_utxos(1, Address::new([7; 32]));
+        let report = verify(&store, genesis()).unwrap();
+        assert!(report.is_consistent(), "{report}");
+        assert_eq!(report.replayed, 10);
+        assert_eq!(report.supply, 100 + 10 * 50);
+        assert_eq!(report.checkpoint.map(|(_, height)| height), Some(10));
+
+        // The reward is not signed, so only the coinbase check catches this.
+        let tampered = blocks.last_mut().unwrap();
+        tampered.reward = 60;
+        store
+            .put(
+                tampered.id.to_string().as_bytes(),
+                &codec::encode(&*tampered).unwrap(),
+            )
+            .unwrap();
+        let report = verify(&store, genesis()).unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(
+            report.faults[0].fault,
+            Fault::Coinbase {
+                reward: 60,
+                paid: 50
+            }
+        );
+        assert_eq!(report.replayed, 9);
+        assert_eq!(report.checkpoint.map(|(_, height)| height), Some(9));
+
+        assert_eq!(truncate(&store, &report).unwrap(), 1);
+        assert_eq!(store.blocks().count(), 9);
+        assert!(verify(&store, genesis()).unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_pruned_headers_are_checked_without_bodies() {
+        let store = MemoryStore::new();
+        let blocks = chain(10);
+        let mut batch = StoreBatch::default();
+        for block in &blocks[..3] {
+            batch.put(
+                format!("{PRUNED_DB_PREFIX}{}", block.id),
+                codec::encode(&BlockHeader::from(block)).unwrap(),
+            );
+        }
+        for block in &blocks[3..] {
+            batch.put_block(block).unwrap();
+        }
+        store.write(batch).unwrap();
+        assert_eq!(store.blocks().count(), 7);
+
+        let genesis = || genesis_utxos(1, Address::new([7; 32]));
+        let report = verify(&store, genesis()).unwrap();
+        assert!(report.is_consistent(), "{report}");
+        assert_eq!((report.blocks, report.pruned), (10, 3));
+        assert_eq!(report.transactions, 7);
+        assert_eq!(report.replayed, 0);
+        assert!(report.replay_stopped.unwrap().contains("pruned"));
+
+        // A header filed under another block's ID is caught like a block.
+        store
+            .put(
+                format!("{PRUNED_DB_PREFIX}{}", blocks[0].id).as_bytes(),
+                &codec::encode(&BlockHeader::from(&blocks[1])).unwrap(),
+            )
+            .unwrap();
+        let report = verify(&store, genesis()).unwrap();
+        assert!(report
+            .faults
+            .iter()
+            .any(|fault| fault.fault == Fault::KeyMismatch(blocks[1].id)));
+    }
+}
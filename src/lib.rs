@@ -1,17 +1,32 @@
 // The primary modules that define the Qanto node and its behavior.
+pub mod codec;
 pub mod config;
 pub mod consensus;
+pub mod data_dir;
 pub mod emission;
+pub mod export;
+pub mod fee_estimator;
+pub mod fee_policy;
 pub mod hame;
+pub mod htlc;
+pub mod integrity;
 pub mod keygen;
 pub mod mempool;
+pub mod merkle;
 pub mod miner;
 pub mod node;
 pub mod omega;
 pub mod p2p;
 pub mod qantodag;
+pub mod reload;
 pub mod saga;
+pub mod schema;
+pub mod snapshot;
+pub mod storage;
 pub mod transaction;
+pub mod tx_index;
+pub mod types;
+pub mod utxo_set;
 pub mod wallet;
 pub mod x_phyrus;
 
@@ -1,17 +1,41 @@
 //! --- Qanto Mempool ---
-//! v2.5.0 - Production Hardening & Fixes
-//! This version corrects a variable ownership bug and continues to harden the
-//! mempool's logic for continuous, high-throughput operation by activating
-//! the transaction pruning mechanism.
-
-use crate::qantodag::{QantoDAG, UTXO};
-use crate::transaction::{Transaction, TransactionError};
-use std::collections::{BTreeMap, HashMap};
+//! v2.7.0 - Unconfirmed Chains & Child-Pays-For-Parent
+//! Transactions may spend outputs of other pooled transactions. Each entry is
+//! validated against the confirmed UTXO set plus the outputs of its unconfirmed
+//! ancestors, and block templates are ordered by ancestor-package fee rate so
+//! a high-fee child can pull its low-fee parents into a block (CPFP). Package
+//! limits bound the ancestor and descendant sets any single entry can have.
+//!
+//! When persistence is enabled the node writes the pool to a journal in its
+//! state store periodically and on shutdown, and replays it through the normal admission
+//! checks on startup so stale or invalidated entries are dropped.
+//!
+//! Every outpoint spent by a pooled transaction is indexed so that conflicting
+//! spends are rejected on entry. A conflicting transaction may replace the
+//! pooled spends (and their descendants) only when it pays a strictly higher
+//! absolute fee and a strictly higher fee rate.
+
+use crate::codec;
+use crate::fee_estimator;
+use crate::qantodag::{InclusionWindow, QantoDAG, UTXO};
+use crate::storage::{StateStore, StoreError};
+use crate::transaction::{Input, SpendContext, Transaction, TransactionError};
+use crate::types::{OutPoint, TxId};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tokio::sync::RwLock;
-use tracing::{info, instrument, warn};
+use tracing::{debug, info, instrument, warn};
+
+/// Maximum number of transactions in any ancestor or descendant package,
+/// including the transaction itself.
+pub const MAX_PACKAGE_COUNT: usize = 25;
+/// Maximum combined serialized size of an ancestor or descendant package.
+pub const MAX_PACKAGE_SIZE_BYTES: usize = 101_000;
+/// Store key of the mempool journal.
+pub const MEMPOOL_JOURNAL_KEY: &[u8] = b"mempool:journal";
 
 #[derive(Error, Debug)]
 pub enum MempoolError {
@@ -23,6 +47,30 @@ pub enum MempoolError {
     Tx(#[from] TransactionError),
     #[error("Timestamp error")]
     TimestampError,
+    #[error("Transaction {0} is already in the mempool")]
+    AlreadyKnown(TxId),
+    #[error("Transaction spends outpoint {0} more than once")]
+    DuplicateInput(OutPoint),
+    #[error("Replacement fee {offered} must exceed the replaced fee total of {required}")]
+    ReplacementFeeTooLow { offered: u64, required: u64 },
+    #[error("Replacement fee rate {offered} must exceed the replaced fee rate of {required}")]
+    ReplacementFeeRateTooLow { offered: u64, required: u64 },
+    #[error("Transaction package limit exceeded: {0}")]
+    PackageLimit(String),
+    #[error("Replacement spends an output of transaction {0}, which it would evict")]
+    ReplacesAncestor(TxId),
+    #[error("Fee policy violated: {0}")]
+    FeePolicy(#[from] crate::fee_policy::FeePolicyError),
+    #[error("Transaction {0} has expired and can no longer be mined")]
+    Expired(TxId),
+    #[error("The outputs spent by transaction {0} changed while it was being verified")]
+    InputsChanged(TxId),
+    #[error("Mempool journal storage error: {0}")]
+    JournalStore(#[from] StoreError),
+    #[error("Mempool journal task failed: {0}")]
+    JournalTask(#[from] tokio::task::JoinError),
+    #[error("Mempool journal serialization error: {0}")]
+    JournalSerialization(#[from] serde_json::Error),
 }
 
 // A wrapper to make transactions orderable by fee-per-byte and track their age.
@@ -30,6 +78,7 @@ pub enum MempoolError {
 struct PrioritizedTransaction {
     tx: Transaction,
     fee_per_byte: u64,
+    size: usize,
     timestamp: u64,
 }
 
@@ -47,11 +96,61 @@ impl PartialOrd for PrioritizedTransaction {
 
 #[derive(Clone, Debug)]
 pub struct Mempool {
-    transactions: Arc<RwLock<HashMap<String, PrioritizedTransaction>>>,
-    priority_queue: Arc<RwLock<BTreeMap<u64, Vec<String>>>>,
+    transactions: Arc<RwLock<HashMap<TxId, PrioritizedTransaction>>>,
+    priority_queue: Arc<RwLock<BTreeMap<u64, Vec<TxId>>>>,
     max_age: Duration,
     max_size_bytes: usize,
     current_size_bytes: Arc<RwLock<usize>>,
+    /// Maps each outpoint spent by a pooled transaction to that transaction's ID.
+    spent_outpoints: Arc<RwLock<HashMap<OutPoint, TxId>>>,
+}
+
+/// A pooled transaction as written to the journal.
+#[derive(Serialize, Deserialize, Debug)]
+struct JournalEntry {
+    tx: Transaction,
+    received_at: u64,
+}
+
+/// The mempool transactions a new transaction would replace.
+struct Replacement {
+    /// Directly conflicting transactions plus all of their descendants.
+    evicted: Vec<TxId>,
+}
+
+fn fee_rate(tx: &Transaction, tx_size: usize) -> u64 {
+    fee_estimator::fee_rate(tx.fee, tx_size)
+}
+
+fn input_outpoints(tx: &Transaction) -> Vec<OutPoint> {
+    tx.inputs.iter().map(Input::outpoint).collect()
+}
+
+/// Returns the pooled ancestors of `tx`, parents before children.
+fn pooled_ancestors(
+    transactions: &HashMap<TxId, PrioritizedTransaction>,
+    tx: &Transaction,
+) -> Vec<TxId> {
+    fn visit(
+        transactions: &HashMap<TxId, PrioritizedTransaction>,
+        tx: &Transaction,
+        seen: &mut HashSet<TxId>,
+        ordered: &mut Vec<TxId>,
+    ) {
+        for input in &tx.inputs {
+            if let Some(parent) = transactions.get(&input.tx_id) {
+                if seen.insert(input.tx_id) {
+                    visit(transactions, &parent.tx, seen, ordered);
+                    ordered.push(input.tx_id);
+                }
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let mut ordered = Vec::new();
+    visit(transactions, tx, &mut seen, &mut ordered);
+    ordered
 }
 
 impl Mempool {
@@ -63,6 +162,7 @@ impl Mempool {
             max_age: Duration::from_secs(max_age_secs),
             max_size_bytes,
             current_size_bytes: Arc::new(RwLock::new(0)),
+            spent_outpoints: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
@@ -84,10 +184,10 @@ impl Mempool {
             .as_secs();
         let max_age_secs = self.max_age.as_secs();
 
-        let ids_to_prune: Vec<String> = transactions
+        let ids_to_prune: Vec<TxId> = transactions
             .iter()
             .filter(|(_, p_tx)| now.saturating_sub(p_tx.timestamp) > max_age_secs)
-            .map(|(id, _)| id.clone())
+            .map(|(id, _)| *id)
             .collect();
 
         if ids_to_prune.is_empty() {
@@ -98,84 +198,485 @@ impl Mempool {
             "Pruning {} stale transactions from mempool...",
             ids_to_prune.len()
         );
+        self.remove_with_descendants(&mut transactions, ids_to_prune)
+            .await;
+    }
+
+    /// Evicts transactions whose expiry has passed for every chain, together
+    /// with their pooled descendants. Returns the number of evicted transactions.
+    #[instrument(skip(self))]
+    pub async fn evict_expired(&self, window: InclusionWindow) -> usize {
+        let mut transactions = self.transactions.write().await;
+        let expired_ids: Vec<TxId> = transactions
+            .iter()
+            .filter(|(_, p_tx)| p_tx.tx.is_expired_at(window.next_height, window.epoch))
+            .map(|(id, _)| *id)
+            .collect();
+        if expired_ids.is_empty() {
+            return 0;
+        }
+
+        info!(
+            "Evicting {} expired transactions from mempool (next height {}, epoch {})",
+            expired_ids.len(),
+            window.next_height,
+            window.epoch
+        );
+        self.remove_with_descendants(&mut transactions, expired_ids)
+            .await
+    }
+
+    /// Removes `roots` and every pooled descendant. Returns the number removed.
+    async fn remove_with_descendants(
+        &self,
+        transactions: &mut HashMap<TxId, PrioritizedTransaction>,
+        roots: Vec<TxId>,
+    ) -> usize {
         let mut priority_queue = self.priority_queue.write().await;
         let mut current_size = self.current_size_bytes.write().await;
+        let mut spent_outpoints = self.spent_outpoints.write().await;
 
-        for id in ids_to_prune {
-            if let Some(removed_ptx) = transactions.remove(&id) {
-                let tx_size = serde_json::to_vec(&removed_ptx.tx)
-                    .unwrap_or_default()
-                    .len();
-                *current_size = current_size.saturating_sub(tx_size);
-
-                if let Some(ids_at_fee) = priority_queue.get_mut(&removed_ptx.fee_per_byte) {
-                    ids_at_fee.retain(|tx_id| tx_id != &id);
-                    if ids_at_fee.is_empty() {
-                        priority_queue.remove(&removed_ptx.fee_per_byte);
+        let to_remove = Self::with_descendants(transactions, &spent_outpoints, roots);
+        let removed = to_remove.len();
+        for id in to_remove {
+            Self::remove_entry(
+                transactions,
+                &mut priority_queue,
+                &mut current_size,
+                &mut spent_outpoints,
+                &id,
+            );
+        }
+        removed
+    }
+
+    /// Removes a single transaction from every mempool index.
+    fn remove_entry(
+        transactions: &mut HashMap<TxId, PrioritizedTransaction>,
+        priority_queue: &mut BTreeMap<u64, Vec<TxId>>,
+        current_size: &mut usize,
+        spent_outpoints: &mut HashMap<OutPoint, TxId>,
+        id: &TxId,
+    ) -> Option<Transaction> {
+        let removed_ptx = transactions.remove(id)?;
+        *current_size = current_size.saturating_sub(removed_ptx.size);
+
+        if let Some(ids_at_fee) = priority_queue.get_mut(&removed_ptx.fee_per_byte) {
+            ids_at_fee.retain(|tx_id| tx_id != id);
+            if ids_at_fee.is_empty() {
+                priority_queue.remove(&removed_ptx.fee_per_byte);
+            }
+        }
+        for outpoint in input_outpoints(&removed_ptx.tx) {
+            if spent_outpoints.get(&outpoint) == Some(id) {
+                spent_outpoints.remove(&outpoint);
+            }
+        }
+        Some(removed_ptx.tx)
+    }
+
+    /// Expands `roots` with every pooled transaction that spends, directly or
+    /// transitively, an output of one of them. The walk is breadth-first, so
+    /// roots come first in the result.
+    fn with_descendants(
+        transactions: &HashMap<TxId, PrioritizedTransaction>,
+        spent_outpoints: &HashMap<OutPoint, TxId>,
+        roots: Vec<TxId>,
+    ) -> Vec<TxId> {
+        let mut seen: HashSet<TxId> = HashSet::new();
+        let mut ordered = Vec::new();
+        let mut queue: VecDeque<TxId> = roots.into();
+        while let Some(id) = queue.pop_front() {
+            if !seen.insert(id) {
+                continue;
+            }
+            if let Some(ptx) = transactions.get(&id) {
+                for index in 0..ptx.tx.outputs.len() {
+                    if let Some(child) = spent_outpoints.get(&OutPoint::new(id, index as u32)) {
+                        queue.push_back(*child);
                     }
                 }
             }
+            ordered.push(id);
         }
+        ordered
+    }
+
+    /// Checks `tx` against the outpoint index. Non-conflicting transactions
+    /// yield an empty replacement; conflicting ones must satisfy the
+    /// replace-by-fee rules to be admitted.
+    fn check_conflicts(
+        tx: &Transaction,
+        tx_size: usize,
+        transactions: &HashMap<TxId, PrioritizedTransaction>,
+        spent_outpoints: &HashMap<OutPoint, TxId>,
+    ) -> Result<Replacement, MempoolError> {
+        if transactions.contains_key(&tx.id) {
+            return Err(MempoolError::AlreadyKnown(tx.id));
+        }
+
+        let mut own_outpoints = HashSet::new();
+        let mut conflicts = Vec::new();
+        for outpoint in input_outpoints(tx) {
+            if !own_outpoints.insert(outpoint) {
+                return Err(MempoolError::DuplicateInput(outpoint));
+            }
+            if let Some(existing) = spent_outpoints.get(&outpoint) {
+                if !conflicts.contains(existing) {
+                    conflicts.push(*existing);
+                }
+            }
+        }
+        if conflicts.is_empty() {
+            return Ok(Replacement {
+                evicted: Vec::new(),
+            });
+        }
+
+        let offered_rate = fee_rate(tx, tx_size);
+        let required_rate = conflicts
+            .iter()
+            .filter_map(|id| transactions.get(id))
+            .map(|ptx| ptx.fee_per_byte)
+            .max()
+            .unwrap_or(0);
+        if offered_rate <= required_rate {
+            return Err(MempoolError::ReplacementFeeRateTooLow {
+                offered: offered_rate,
+                required: required_rate,
+            });
+        }
+
+        let evicted = Self::with_descendants(transactions, spent_outpoints, conflicts);
+        let required_fee: u64 = evicted
+            .iter()
+            .filter_map(|id| transactions.get(id))
+            .map(|ptx| ptx.tx.fee)
+            .sum();
+        if tx.fee <= required_fee {
+            return Err(MempoolError::ReplacementFeeTooLow {
+                offered: tx.fee,
+                required: required_fee,
+            });
+        }
+        Ok(Replacement { evicted })
+    }
+
+    /// Resolves the UTXOs spent by `tx` from the confirmed set or, failing
+    /// that, from the outputs of pooled transactions.
+    fn input_view(
+        tx: &Transaction,
+        utxos: &HashMap<OutPoint, UTXO>,
+        transactions: &HashMap<TxId, PrioritizedTransaction>,
+    ) -> HashMap<OutPoint, UTXO> {
+        let mut view = HashMap::with_capacity(tx.inputs.len());
+        for input in &tx.inputs {
+            let utxo_id = input.outpoint();
+            if let Some(utxo) = utxos.get(&utxo_id) {
+                view.insert(utxo_id, utxo.clone());
+            } else if let Some(parent) = transactions.get(&input.tx_id) {
+                if (input.output_index as usize) < parent.tx.outputs.len() {
+                    view.insert(utxo_id, parent.tx.generate_utxo(input.output_index));
+                }
+            }
+        }
+        view
+    }
+
+    /// Enforces the ancestor and descendant package limits for a new entry.
+    fn check_package_limits(
+        tx_size: usize,
+        ancestors: &[TxId],
+        transactions: &HashMap<TxId, PrioritizedTransaction>,
+        spent_outpoints: &HashMap<OutPoint, TxId>,
+    ) -> Result<(), MempoolError> {
+        if ancestors.len() + 1 > MAX_PACKAGE_COUNT {
+            return Err(MempoolError::PackageLimit(format!(
+                "{} unconfirmed ancestors (max {})",
+                ancestors.len(),
+                MAX_PACKAGE_COUNT - 1
+            )));
+        }
+        let ancestor_size: usize = ancestors
+            .iter()
+            .filter_map(|id| transactions.get(id))
+            .map(|ptx| ptx.size)
+            .sum();
+        if ancestor_size + tx_size > MAX_PACKAGE_SIZE_BYTES {
+            return Err(MempoolError::PackageLimit(format!(
+                "ancestor package of {} bytes (max {})",
+                ancestor_size + tx_size,
+                MAX_PACKAGE_SIZE_BYTES
+            )));
+        }
+        for ancestor in ancestors {
+            let descendants =
+                Self::with_descendants(transactions, spent_outpoints, vec![*ancestor]);
+            let descendant_size: usize = descendants
+                .iter()
+                .filter_map(|id| transactions.get(id))
+                .map(|ptx| ptx.size)
+                .sum();
+            if descendants.len() + 1 > MAX_PACKAGE_COUNT
+                || descendant_size + tx_size > MAX_PACKAGE_SIZE_BYTES
+            {
+                return Err(MempoolError::PackageLimit(format!(
+                    "descendant package of ancestor {ancestor} is full"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Picks the entries that must leave the pool, lowest fee rate first and
+    /// together with their descendants, to free `needed` bytes on top of the
+    /// `replaced` entries. Nothing is removed here, so a transaction rejected
+    /// as `MempoolFull` costs the pool nothing.
+    fn plan_eviction(
+        needed: usize,
+        fee_per_byte: u64,
+        ancestors: &[TxId],
+        replaced: &[TxId],
+        transactions: &HashMap<TxId, PrioritizedTransaction>,
+        priority_queue: &BTreeMap<u64, Vec<TxId>>,
+        spent_outpoints: &HashMap<OutPoint, TxId>,
+    ) -> Result<Vec<TxId>, MempoolError> {
+        let mut leaving: HashSet<TxId> = replaced.iter().copied().collect();
+        let mut freed = 0;
+        let mut evicted = Vec::new();
+        for (&rate, ids) in priority_queue {
+            for id in ids.iter().rev() {
+                if freed >= needed {
+                    return Ok(evicted);
+                }
+                if rate >= fee_per_byte {
+                    return Err(MempoolError::MempoolFull);
+                }
+                if leaving.contains(id) {
+                    continue;
+                }
+                let group = Self::with_descendants(transactions, spent_outpoints, vec![*id]);
+                if group.iter().any(|member| ancestors.contains(member)) {
+                    return Err(MempoolError::MempoolFull);
+                }
+                for member in group {
+                    if leaving.insert(member) {
+                        freed += transactions.get(&member).map_or(0, |ptx| ptx.size);
+                        evicted.push(member);
+                    }
+                }
+            }
+        }
+        if freed < needed {
+            return Err(MempoolError::MempoolFull);
+        }
+        Ok(evicted)
+    }
+
+    /// The admission checks that only need the outputs `tx` spends, resolved
+    /// beforehand into `input_view`. These include signature and input
+    /// verification, so they run without holding any mempool lock.
+    async fn validate_standalone(
+        tx: &Transaction,
+        tx_size: usize,
+        input_view: &HashMap<OutPoint, UTXO>,
+        dag: &QantoDAG,
+    ) -> Result<(), MempoolError> {
+        let window = dag.inclusion_window().await;
+        if tx.is_expired_at(window.next_height, window.epoch) {
+            return Err(MempoolError::Expired(tx.id));
+        }
+        // Admission also applies this node's edicts, on top of the policy the
+        // next block commits to.
+        let relay_policy = dag.saga.fee_policy().await;
+        relay_policy.check_relay(tx, tx_size)?;
+        relay_policy.check_transaction(tx)?;
+        dag.verify_signatures(std::slice::from_ref(tx)).await?;
+        let context = SpendContext {
+            height: window.next_height,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            fee_policy: dag.saga.fee_policy_at(window.epoch).await,
+        };
+        tx.verify_inputs(input_view, context).await?;
+        Ok(())
+    }
+
+    /// The admission checks against the pool itself, run under its locks.
+    /// The outputs `tx` spends must still resolve as they did in
+    /// `input_view`, since the pool may have changed while `tx` was being
+    /// verified. Returns the entries `tx` replaces and its pooled ancestors.
+    fn validate_against_pool(
+        tx: &Transaction,
+        tx_size: usize,
+        utxos: &HashMap<OutPoint, UTXO>,
+        input_view: &HashMap<OutPoint, UTXO>,
+        transactions: &HashMap<TxId, PrioritizedTransaction>,
+        spent_outpoints: &HashMap<OutPoint, TxId>,
+    ) -> Result<(Replacement, Vec<TxId>), MempoolError> {
+        let replacement = Self::check_conflicts(tx, tx_size, transactions, spent_outpoints)?;
+        let current_view = Self::input_view(tx, utxos, transactions);
+        if current_view.len() != input_view.len()
+            || current_view.keys().any(|id| !input_view.contains_key(id))
+        {
+            return Err(MempoolError::InputsChanged(tx.id));
+        }
+
+        let ancestors = pooled_ancestors(transactions, tx);
+        if let Some(evicted_ancestor) = ancestors
+            .iter()
+            .find(|id| replacement.evicted.contains(*id))
+        {
+            return Err(MempoolError::ReplacesAncestor(*evicted_ancestor));
+        }
+        Self::check_package_limits(tx_size, &ancestors, transactions, spent_outpoints)?;
+        Ok((replacement, ancestors))
+    }
+
+    /// Rejects known and conflicting transactions early and resolves the
+    /// outputs `tx` spends, holding the pool's read locks only briefly.
+    async fn prepare_entry(
+        &self,
+        tx: &Transaction,
+        tx_size: usize,
+        utxos: &HashMap<OutPoint, UTXO>,
+    ) -> Result<HashMap<OutPoint, UTXO>, MempoolError> {
+        let transactions = self.transactions.read().await;
+        let spent_outpoints = self.spent_outpoints.read().await;
+        Self::check_conflicts(tx, tx_size, &transactions, &spent_outpoints)?;
+        Ok(Self::input_view(tx, utxos, &transactions))
+    }
+
+    /// Runs the mempool admission checks for `tx` without inserting it. Used by
+    /// the API to reject invalid or conflicting transactions before broadcasting.
+    pub async fn check_transaction(
+        &self,
+        tx: &Transaction,
+        utxos: &HashMap<OutPoint, UTXO>,
+        dag: &QantoDAG,
+    ) -> Result<(), MempoolError> {
+        let tx_size = codec::encoded_len(tx).unwrap_or(0);
+        let input_view = self.prepare_entry(tx, tx_size, utxos).await?;
+        Self::validate_standalone(tx, tx_size, &input_view, dag).await?;
+        let transactions = self.transactions.read().await;
+        let spent_outpoints = self.spent_outpoints.read().await;
+        Self::validate_against_pool(
+            tx,
+            tx_size,
+            utxos,
+            &input_view,
+            &transactions,
+            &spent_outpoints,
+        )
+        .map(|_| ())
     }
 
     #[instrument(skip(self, tx, utxos, dag))]
     pub async fn add_transaction(
         &self,
         tx: Transaction,
-        utxos: &HashMap<String, UTXO>,
+        utxos: &HashMap<OutPoint, UTXO>,
         dag: &QantoDAG,
     ) -> Result<(), MempoolError> {
-        tx.verify(dag, utxos).await?;
-
         if rand::random::<u8>() < 10 {
             // Prune roughly 4% of the time to keep the mempool clean.
             self.prune_old_transactions().await;
         }
+        let received_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| MempoolError::TimestampError)?
+            .as_secs();
+        self.insert_transaction(tx, utxos, dag, received_at).await
+    }
 
-        let tx_size = serde_json::to_vec(&tx).unwrap_or_default().len();
+    async fn insert_transaction(
+        &self,
+        tx: Transaction,
+        utxos: &HashMap<OutPoint, UTXO>,
+        dag: &QantoDAG,
+        received_at: u64,
+    ) -> Result<(), MempoolError> {
+        let tx_size = codec::encoded_len(&tx).unwrap_or(0);
         if tx_size == 0 {
             return Ok(());
         }
 
-        let fee_per_byte = (tx.fee * 100).checked_div(tx_size as u64).unwrap_or(0);
+        let fee_per_byte = fee_rate(&tx, tx_size);
+        let input_view = self.prepare_entry(&tx, tx_size, utxos).await?;
+        Self::validate_standalone(&tx, tx_size, &input_view, dag).await?;
 
         let mut transactions = self.transactions.write().await;
         let mut priority_queue = self.priority_queue.write().await;
         let mut current_size = self.current_size_bytes.write().await;
+        let mut spent_outpoints = self.spent_outpoints.write().await;
 
-        // --- Eviction logic to make space for higher-fee transactions ---
-        while *current_size + tx_size > self.max_size_bytes {
-            if let Some((&lowest_fee, ids)) = priority_queue.iter_mut().next() {
-                if lowest_fee >= fee_per_byte {
-                    return Err(MempoolError::MempoolFull);
-                }
-                if let Some(id_to_evict) = ids.pop() {
-                    if let Some(evicted_tx) = transactions.remove(&id_to_evict) {
-                        let evicted_size =
-                            serde_json::to_vec(&evicted_tx.tx).unwrap_or_default().len();
-                        *current_size = current_size.saturating_sub(evicted_size);
-                        warn!(id=%id_to_evict, "Mempool full. Evicting transaction to make space.");
-                    }
-                }
-                if ids.is_empty() {
-                    priority_queue.remove(&lowest_fee);
-                }
-            } else {
-                return Err(MempoolError::MempoolFull);
+        let (replacement, ancestors) = Self::validate_against_pool(
+            &tx,
+            tx_size,
+            utxos,
+            &input_view,
+            &transactions,
+            &spent_outpoints,
+        )?;
+        let replaced_size: usize = replacement
+            .evicted
+            .iter()
+            .filter_map(|id| transactions.get(id))
+            .map(|ptx| ptx.size)
+            .sum();
+        let needed = (current_size.saturating_sub(replaced_size) + tx_size)
+            .saturating_sub(self.max_size_bytes);
+        let evicted = Self::plan_eviction(
+            needed,
+            fee_per_byte,
+            &ancestors,
+            &replacement.evicted,
+            &transactions,
+            &priority_queue,
+            &spent_outpoints,
+        )?;
+
+        // Admission is certain from here on, so the pool is only changed now.
+        for id in &replacement.evicted {
+            if Self::remove_entry(
+                &mut transactions,
+                &mut priority_queue,
+                &mut current_size,
+                &mut spent_outpoints,
+                id,
+            )
+            .is_some()
+            {
+                info!(replaced=%id, by=%tx.id, "Replaced mempool transaction by fee.");
+            }
+        }
+        for id in &evicted {
+            if Self::remove_entry(
+                &mut transactions,
+                &mut priority_queue,
+                &mut current_size,
+                &mut spent_outpoints,
+                id,
+            )
+            .is_some()
+            {
+                warn!(id=%id, "Mempool full. Evicting transaction to make space.");
             }
         }
 
-        let tx_id = tx.id.clone();
+        let tx_id = tx.id;
+        for outpoint in input_outpoints(&tx) {
+            spent_outpoints.insert(outpoint, tx_id);
+        }
         let prioritized_tx = PrioritizedTransaction {
             tx,
             fee_per_byte,
-            timestamp: SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
+            size: tx_size,
+            timestamp: received_at,
         };
 
-        if transactions.insert(tx_id.clone(), prioritized_tx).is_none() {
+        if transactions.insert(tx_id, prioritized_tx).is_none() {
             *current_size += tx_size;
             // **FIX (E0382):** The info log is placed *before* `tx_id` is moved into the priority queue.
             info!(id=%tx_id, "Added transaction to mempool. Current size: {} bytes", *current_size);
@@ -184,26 +685,134 @@ impl Mempool {
         Ok(())
     }
 
-    pub async fn get_transactions(&self) -> HashMap<String, Transaction> {
+    /// Writes every pooled transaction to the journal in `store`, parents
+    /// before children, replacing the previous journal.
+    pub async fn save_journal(&self, store: Arc<dyn StateStore>) -> Result<usize, MempoolError> {
+        let entries: Vec<JournalEntry> = {
+            let transactions = self.transactions.read().await;
+            let mut written: HashSet<&TxId> = HashSet::new();
+            let mut entries = Vec::with_capacity(transactions.len());
+            for (id, ptx) in transactions.iter() {
+                let ancestors = pooled_ancestors(&transactions, &ptx.tx);
+                for member in ancestors.iter().chain(std::iter::once(id)) {
+                    if let Some((member_id, member_ptx)) = transactions.get_key_value(member) {
+                        if written.insert(member_id) {
+                            entries.push(JournalEntry {
+                                tx: member_ptx.tx.clone(),
+                                received_at: member_ptx.timestamp,
+                            });
+                        }
+                    }
+                }
+            }
+            entries
+        };
+
+        let bytes = serde_json::to_vec(&entries)?;
+        tokio::task::spawn_blocking(move || store.put(MEMPOOL_JOURNAL_KEY, &bytes)).await??;
+        Ok(entries.len())
+    }
+
+    /// Replays the journal in `store` through the normal admission checks.
+    /// Entries older than `max_age` or no longer valid against `utxos` are
+    /// dropped. Returns the number of restored transactions.
+    #[instrument(skip(self, store, utxos, dag))]
+    pub async fn load_journal(
+        &self,
+        store: Arc<dyn StateStore>,
+        utxos: &HashMap<OutPoint, UTXO>,
+        dag: &QantoDAG,
+    ) -> Result<usize, MempoolError> {
+        let Some(bytes) =
+            tokio::task::spawn_blocking(move || store.get(MEMPOOL_JOURNAL_KEY)).await??
+        else {
+            return Ok(0);
+        };
+        let entries: Vec<JournalEntry> = serde_json::from_slice(&bytes)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| MempoolError::TimestampError)?
+            .as_secs();
+
+        let total = entries.len();
+        let mut restored = 0;
+        for entry in entries {
+            if now.saturating_sub(entry.received_at) > self.max_age.as_secs() {
+                continue;
+            }
+            let tx_id = entry.tx.id;
+            match self
+                .insert_transaction(entry.tx, utxos, dag, entry.received_at)
+                .await
+            {
+                Ok(()) => restored += 1,
+                Err(e) => debug!(id=%tx_id, "Dropping journaled transaction: {e}"),
+            }
+        }
+        info!("Restored {restored} of {total} journaled mempool transactions.");
+        Ok(restored)
+    }
+
+    /// Returns the pending `(fee_rate, tx_count)` buckets used for fee estimation.
+    pub async fn fee_rate_buckets(&self) -> Vec<(u64, usize)> {
+        let priority_queue = self.priority_queue.read().await;
+        priority_queue
+            .iter()
+            .map(|(rate, ids)| (*rate, ids.len()))
+            .collect()
+    }
+
+    pub async fn get_transactions(&self) -> HashMap<TxId, Transaction> {
         let transactions = self.transactions.read().await;
         transactions
             .iter()
-            .map(|(id, p_tx)| (id.clone(), p_tx.tx.clone()))
+            .map(|(id, p_tx)| (*id, p_tx.tx.clone()))
             .collect()
     }
 
+    /// Selects up to `max_txs` transactions for a block, ordered by
+    /// ancestor-package fee rate. Unconfirmed parents are always placed before
+    /// the children that spend them.
     pub async fn select_transactions(&self, max_txs: usize) -> Vec<Transaction> {
         let transactions = self.transactions.read().await;
-        let priority_queue = self.priority_queue.read().await;
-        let mut selected = Vec::with_capacity(max_txs);
 
-        // Iterate from highest fee to lowest
-        for ids in priority_queue.values().rev() {
-            for id in ids {
-                if selected.len() >= max_txs {
-                    return selected;
-                }
-                if let Some(ptx) = transactions.get(id) {
+        let mut packages: Vec<(u64, &TxId, Vec<TxId>)> = transactions
+            .iter()
+            .map(|(id, ptx)| {
+                let ancestors = pooled_ancestors(&transactions, &ptx.tx);
+                let (fee, size) = ancestors
+                    .iter()
+                    .filter_map(|a| transactions.get(a))
+                    .chain(std::iter::once(ptx))
+                    .fold((0u64, 0u64), |(fee, size), p| {
+                        (fee + p.tx.fee, size + p.size as u64)
+                    });
+                let package_rate = (fee * 100).checked_div(size).unwrap_or(0);
+                (package_rate, id, ancestors)
+            })
+            .collect();
+        packages.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+
+        let mut included: HashSet<TxId> = HashSet::new();
+        let mut selected = Vec::with_capacity(max_txs.min(transactions.len()));
+        for (_, id, ancestors) in &packages {
+            if selected.len() >= max_txs {
+                break;
+            }
+            if included.contains(*id) {
+                continue;
+            }
+            let package: Vec<&TxId> = ancestors
+                .iter()
+                .filter(|a| !included.contains(*a))
+                .chain(std::iter::once(*id))
+                .collect();
+            if selected.len() + package.len() > max_txs {
+                continue;
+            }
+            for member in package {
+                if let Some(ptx) = transactions.get(member) {
+                    included.insert(*member);
                     selected.push(ptx.tx.clone());
                 }
             }
@@ -220,22 +829,541 @@ impl Mempool {
         let mut transactions = self.transactions.write().await;
         let mut priority_queue = self.priority_queue.write().await;
         let mut current_size = self.current_size_bytes.write().await;
+        let mut spent_outpoints = self.spent_outpoints.write().await;
 
+        // Pooled spends of outpoints consumed by the confirmed transactions are
+        // now double-spends; drop them together with their descendants.
+        let mut conflicting = Vec::new();
         for tx in txs_to_remove {
-            if let Some(removed_ptx) = transactions.remove(&tx.id) {
-                let tx_size = serde_json::to_vec(&removed_ptx.tx)
-                    .unwrap_or_default()
-                    .len();
-                *current_size = current_size.saturating_sub(tx_size);
-
-                if let Some(ids) = priority_queue.get_mut(&removed_ptx.fee_per_byte) {
-                    ids.retain(|id| id != &tx.id);
-                    if ids.is_empty() {
-                        priority_queue.remove(&removed_ptx.fee_per_byte);
+            for outpoint in input_outpoints(tx) {
+                if let Some(existing) = spent_outpoints.get(&outpoint) {
+                    if existing != &tx.id {
+                        conflicting.push(*existing);
                     }
                 }
             }
         }
+
+        for tx in txs_to_remove {
+            Self::remove_entry(
+                &mut transactions,
+                &mut priority_queue,
+                &mut current_size,
+                &mut spent_outpoints,
+                &tx.id,
+            );
+        }
+        if !conflicting.is_empty() {
+            let to_drop = Self::with_descendants(&transactions, &spent_outpoints, conflicting);
+            let mut dropped = 0;
+            for id in to_drop {
+                if Self::remove_entry(
+                    &mut transactions,
+                    &mut priority_queue,
+                    &mut current_size,
+                    &mut spent_outpoints,
+                    &id,
+                )
+                .is_some()
+                {
+                    dropped += 1;
+                }
+            }
+            warn!("Dropped {dropped} mempool transactions conflicting with confirmed spends.");
+        }
         info!("Removed {} transactions from mempool.", txs_to_remove.len());
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fee_policy::{FeePolicy, DEV_ADDRESS};
+    use crate::omega::{self, identity::set_threat_level, identity::ThreatLevel, OmegaState};
+    use crate::qantodag::{
+        HomomorphicEncrypted, QantoDagConfig, StorageMode, DEFAULT_COINBASE_MATURITY,
+    };
+    use crate::saga::PalletSaga;
+    use crate::storage::MemoryStore;
+    use crate::transaction::{Output, TransactionConfig};
+    use crate::types::Address;
+    use crate::wallet::Wallet;
+    use pqcrypto_traits::sign::{PublicKey, SecretKey};
+    use serial_test::serial;
+
+    const AMOUNT: u64 = 100;
+    const FEE: u64 = 1_000;
+
+    fn funding_utxo(wallet: &Wallet, byte: u8) -> (OutPoint, UTXO) {
+        let tx_id = TxId::new([byte; 32]);
+        let utxo = UTXO {
+            address: wallet.address(),
+            amount: AMOUNT + FeePolicy::default().dev_fee(AMOUNT) + FEE,
+            tx_id,
+            output_index: 0,
+            explorer_link: String::new(),
+            htlc: None,
+            coinbase: None,
+        };
+        (OutPoint::new(tx_id, 0), utxo)
+    }
+
+    async fn spend(wallet: &Wallet, funding: &OutPoint) -> Transaction {
+        let (secret_key, public_key) = wallet.get_keypair().unwrap();
+        let receiver = Address::new([0x01; 32]);
+        let output = |address, amount| Output {
+            address,
+            amount,
+            homomorphic_encrypted: HomomorphicEncrypted::new(0, &[]),
+            htlc: None,
+        };
+        Transaction::new(TransactionConfig {
+            sender: wallet.address(),
+            receiver,
+            amount: AMOUNT,
+            fee: FEE,
+            inputs: vec![Input {
+                tx_id: funding.tx_id,
+                output_index: funding.index,
+                htlc_preimage: None,
+            }],
+            outputs: vec![
+                output(receiver, AMOUNT),
+                output(DEV_ADDRESS, FeePolicy::default().dev_fee(AMOUNT)),
+            ],
+            metadata: None,
+            signing_key_bytes: secret_key.as_bytes(),
+            public_key_bytes: public_key.as_bytes(),
+            tx_timestamps: Arc::new(RwLock::new(HashMap::new())),
+            valid_until: None,
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Signs a transaction from `wallet` spending `inputs`, each given with
+    /// its value, that returns all but `fee` to the wallet in one output.
+    async fn spend_to_self(wallet: &Wallet, inputs: &[(OutPoint, u64)], fee: u64) -> Transaction {
+        let (secret_key, public_key) = wallet.get_keypair().unwrap();
+        let change = inputs.iter().map(|(_, value)| value).sum::<u64>() - fee;
+        Transaction::new(TransactionConfig {
+            sender: wallet.address(),
+            receiver: wallet.address(),
+            amount: change,
+            fee,
+            inputs: inputs
+                .iter()
+                .map(|(outpoint, _)| Input {
+                    tx_id: outpoint.tx_id,
+                    output_index: outpoint.index,
+                    htlc_preimage: None,
+                })
+                .collect(),
+            outputs: vec![Output {
+                address: wallet.address(),
+                amount: change,
+                homomorphic_encrypted: HomomorphicEncrypted::new(0, &[]),
+                htlc: None,
+            }],
+            metadata: None,
+            signing_key_bytes: secret_key.as_bytes(),
+            public_key_bytes: public_key.as_bytes(),
+            tx_timestamps: Arc::new(RwLock::new(HashMap::new())),
+            valid_until: None,
+        })
+        .await
+        .unwrap()
+    }
+
+    /// The change output of a transaction built by `spend_to_self`.
+    fn change_of(tx: &Transaction) -> (OutPoint, u64) {
+        (OutPoint::new(tx.id, 0), tx.outputs[0].amount)
+    }
+
+    /// A confirmed output of `value` owned by `wallet`.
+    fn owned_utxo(wallet: &Wallet, byte: u8, value: u64) -> (OutPoint, UTXO) {
+        let (outpoint, mut utxo) = funding_utxo(wallet, byte);
+        utxo.amount = value;
+        (outpoint, utxo)
+    }
+
+    async fn test_dag(wallet: &Wallet) -> Arc<QantoDAG> {
+        {
+            let mut state = omega::OMEGA_STATE.lock().await;
+            *state = OmegaState::new();
+            set_threat_level(ThreatLevel::Nominal);
+        }
+        let (secret_key, public_key) = wallet.get_keypair().unwrap();
+        QantoDAG::new(
+            QantoDagConfig {
+                initial_validator: wallet.address(),
+                target_block_time: 60000,
+                num_chains: 1,
+                qr_signing_key: &secret_key,
+                qr_public_key: &public_key,
+                storage_mode: StorageMode::default(),
+                tx_index: false,
+                coinbase_maturity: DEFAULT_COINBASE_MATURITY,
+            },
+            Arc::new(PalletSaga::new(
+                #[cfg(feature = "infinite-strata")]
+                None,
+            )),
+            Arc::new(MemoryStore::new()),
+        )
+        .unwrap()
+    }
+
+    fn pooled_ids(pooled: HashMap<TxId, Transaction>) -> HashSet<TxId> {
+        pooled.into_keys().collect()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_journal_round_trip() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+
+        let mut utxos: HashMap<OutPoint, UTXO> =
+            (1..=3).map(|byte| funding_utxo(&wallet, byte)).collect();
+        let outpoints: Vec<OutPoint> = (1..=3).map(|byte| funding_utxo(&wallet, byte).0).collect();
+        let kept = spend(&wallet, &outpoints[0]).await;
+        let stale = spend(&wallet, &outpoints[1]).await;
+        let conflicted = spend(&wallet, &outpoints[2]).await;
+
+        let max_age_secs = 3_600;
+        let mempool = Mempool::new(max_age_secs, 10_000_000, 1_000);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        for (tx, received_at) in [
+            (kept.clone(), now),
+            (stale, now - 2 * max_age_secs),
+            (conflicted, now),
+        ] {
+            mempool
+                .insert_transaction(tx, &utxos, &dag, received_at)
+                .await
+                .unwrap();
+        }
+        let store: Arc<dyn StateStore> = Arc::new(MemoryStore::new());
+        assert_eq!(mempool.save_journal(store.clone()).await.unwrap(), 3);
+
+        // The output spent by `conflicted` was confirmed in another spend
+        // while the node was down.
+        utxos.remove(&outpoints[2]);
+        let restored = Mempool::new(max_age_secs, 10_000_000, 1_000);
+        assert_eq!(restored.load_journal(store, &utxos, &dag).await.unwrap(), 1);
+        let pooled = restored.get_transactions().await;
+        assert_eq!(pooled.keys().collect::<Vec<_>>(), vec![&kept.id]);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_conflict_without_higher_fee_rate_is_rejected() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+        let (funding, utxo) = owned_utxo(&wallet, 1, 10_000);
+        let value = utxo.amount;
+        let utxos = HashMap::from([(funding, utxo)]);
+        let mempool = Mempool::new(3_600, 10_000_000, 1_000);
+
+        let original = spend_to_self(&wallet, &[(funding, value)], 1_000).await;
+        mempool
+            .add_transaction(original.clone(), &utxos, &dag)
+            .await
+            .unwrap();
+        let cheaper = spend_to_self(&wallet, &[(funding, value)], 900).await;
+        assert!(matches!(
+            mempool.add_transaction(cheaper, &utxos, &dag).await,
+            Err(MempoolError::ReplacementFeeRateTooLow { .. })
+        ));
+        assert_eq!(
+            pooled_ids(mempool.get_transactions().await),
+            HashSet::from([original.id])
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_replacement_by_fee_is_accepted() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+        let (funding, utxo) = owned_utxo(&wallet, 1, 10_000);
+        let value = utxo.amount;
+        let utxos = HashMap::from([(funding, utxo)]);
+        let mempool = Mempool::new(3_600, 10_000_000, 1_000);
+
+        let original = spend_to_self(&wallet, &[(funding, value)], 1_000).await;
+        mempool
+            .add_transaction(original, &utxos, &dag)
+            .await
+            .unwrap();
+        let size_before = mempool.size().await;
+        let replacement = spend_to_self(&wallet, &[(funding, value)], 2_000).await;
+        mempool
+            .add_transaction(replacement.clone(), &utxos, &dag)
+            .await
+            .unwrap();
+        assert_eq!(
+            pooled_ids(mempool.get_transactions().await),
+            HashSet::from([replacement.id])
+        );
+        assert_eq!(mempool.size().await, size_before);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_replacement_must_outpay_evicted_descendants() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+        let (funding, utxo) = owned_utxo(&wallet, 1, 10_000);
+        let value = utxo.amount;
+        let utxos = HashMap::from([(funding, utxo)]);
+        let mempool = Mempool::new(3_600, 10_000_000, 1_000);
+
+        let parent = spend_to_self(&wallet, &[(funding, value)], 1_000).await;
+        let child = spend_to_self(&wallet, &[change_of(&parent)], 1_500).await;
+        mempool
+            .add_transaction(parent.clone(), &utxos, &dag)
+            .await
+            .unwrap();
+        mempool
+            .add_transaction(child.clone(), &utxos, &dag)
+            .await
+            .unwrap();
+
+        // A higher fee rate than the parent alone, but less than the parent
+        // and child pay together.
+        let replacement = spend_to_self(&wallet, &[(funding, value)], 2_000).await;
+        match mempool.add_transaction(replacement, &utxos, &dag).await {
+            Err(MempoolError::ReplacementFeeTooLow { offered, required }) => {
+                assert_eq!((offered, required), (2_000, 2_500));
+            }
+            other => panic!("expected ReplacementFeeTooLow, got {other:?}"),
+        }
+        assert_eq!(
+            pooled_ids(mempool.get_transactions().await),
+            HashSet::from([parent.id, child.id])
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_replaced_and_confirmed_conflicts_take_descendants() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+        let (funding, utxo) = owned_utxo(&wallet, 1, 10_000);
+        let value = utxo.amount;
+        let utxos = HashMap::from([(funding, utxo)]);
+        let mempool = Mempool::new(3_600, 10_000_000, 1_000);
+
+        let parent = spend_to_self(&wallet, &[(funding, value)], 1_000).await;
+        let child = spend_to_self(&wallet, &[change_of(&parent)], 1_000).await;
+        let grandchild = spend_to_self(&wallet, &[change_of(&child)], 1_000).await;
+        for tx in [&parent, &child, &grandchild] {
+            mempool
+                .add_transaction(tx.clone(), &utxos, &dag)
+                .await
+                .unwrap();
+        }
+        let replacement = spend_to_self(&wallet, &[(funding, value)], 4_000).await;
+        mempool
+            .add_transaction(replacement.clone(), &utxos, &dag)
+            .await
+            .unwrap();
+        assert_eq!(
+            pooled_ids(mempool.get_transactions().await),
+            HashSet::from([replacement.id])
+        );
+
+        // A block confirming another spend of the same output drops the
+        // replacement and what was built on it.
+        let replacement_child = spend_to_self(&wallet, &[change_of(&replacement)], 1_000).await;
+        mempool
+            .add_transaction(replacement_child, &utxos, &dag)
+            .await
+            .unwrap();
+        let confirmed = spend_to_self(&wallet, &[(funding, value)], 5_000).await;
+        mempool.remove_transactions(&[confirmed]).await;
+        assert!(mempool.get_transactions().await.is_empty());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_descendants_follow_their_roots() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+        let funded: Vec<(OutPoint, UTXO)> = (1..=2)
+            .map(|byte| owned_utxo(&wallet, byte, 10_000))
+            .collect();
+        let utxos: HashMap<OutPoint, UTXO> = funded.iter().cloned().collect();
+        let mempool = Mempool::new(3_600, 10_000_000, 1_000);
+
+        let mut roots = Vec::new();
+        let mut children = Vec::new();
+        for (outpoint, utxo) in &funded {
+            let root = spend_to_self(&wallet, &[(*outpoint, utxo.amount)], 1_000).await;
+            let child = spend_to_self(&wallet, &[change_of(&root)], 1_000).await;
+            roots.push(root.id);
+            children.push(child.id);
+            mempool.add_transaction(root, &utxos, &dag).await.unwrap();
+            mempool.add_transaction(child, &utxos, &dag).await.unwrap();
+        }
+
+        let transactions = mempool.transactions.read().await;
+        let spent_outpoints = mempool.spent_outpoints.read().await;
+        let ordered = Mempool::with_descendants(&transactions, &spent_outpoints, roots.clone());
+        assert_eq!(
+            ordered[..2].iter().collect::<HashSet<_>>(),
+            roots.iter().collect()
+        );
+        assert_eq!(
+            ordered[2..].iter().collect::<HashSet<_>>(),
+            children.iter().collect()
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_child_pays_for_parent() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+        let funded: Vec<(OutPoint, UTXO)> = (1..=2)
+            .map(|byte| owned_utxo(&wallet, byte, 10_000))
+            .collect();
+        let utxos: HashMap<OutPoint, UTXO> = funded.iter().cloned().collect();
+        let mempool = Mempool::new(3_600, 10_000_000, 1_000);
+
+        let (parent_funding, parent_utxo) = &funded[0];
+        let parent = spend_to_self(&wallet, &[(*parent_funding, parent_utxo.amount)], 200).await;
+        let child = spend_to_self(&wallet, &[change_of(&parent)], 5_000).await;
+        let (other_funding, other_utxo) = &funded[1];
+        let unrelated = spend_to_self(&wallet, &[(*other_funding, other_utxo.amount)], 1_000).await;
+        for tx in [&parent, &child, &unrelated] {
+            mempool
+                .add_transaction(tx.clone(), &utxos, &dag)
+                .await
+                .unwrap();
+        }
+
+        // The parent pays less than the unrelated transaction, but its child
+        // lifts the pair above it, and the parent is placed first.
+        let selected: Vec<TxId> = mempool
+            .select_transactions(2)
+            .await
+            .iter()
+            .map(|tx| tx.id)
+            .collect();
+        assert_eq!(selected, vec![parent.id, child.id]);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_package_limit_rejects_long_chains() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+        let (funding, utxo) = owned_utxo(&wallet, 1, 1_000_000);
+        let mut previous = (funding, utxo.amount);
+        let utxos = HashMap::from([(funding, utxo)]);
+        let mempool = Mempool::new(3_600, 10_000_000, 1_000);
+
+        let mut chain = Vec::new();
+        let rejection = loop {
+            assert!(
+                chain.len() <= MAX_PACKAGE_COUNT,
+                "the chain outgrew the package limits"
+            );
+            let tx = spend_to_self(&wallet, &[previous], 1_000).await;
+            previous = change_of(&tx);
+            let tx_id = tx.id;
+            match mempool.add_transaction(tx, &utxos, &dag).await {
+                Ok(()) => chain.push(tx_id),
+                Err(e) => break e,
+            }
+        };
+        assert!(matches!(rejection, MempoolError::PackageLimit(_)));
+        assert_eq!(chain.len(), MAX_PACKAGE_COUNT);
+        assert_eq!(mempool.size().await, chain.len());
+
+        // A package within the count limit is still bounded in size.
+        let transactions = mempool.transactions.read().await;
+        assert!(matches!(
+            Mempool::check_package_limits(
+                MAX_PACKAGE_SIZE_BYTES,
+                &chain[..1],
+                &transactions,
+                &HashMap::new(),
+            ),
+            Err(MempoolError::PackageLimit(_))
+        ));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_replacement_of_own_ancestor_is_rejected() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+        let (funding, utxo) = owned_utxo(&wallet, 1, 10_000);
+        let value = utxo.amount;
+        let utxos = HashMap::from([(funding, utxo)]);
+        let mempool = Mempool::new(3_600, 10_000_000, 1_000);
+
+        let parent = spend_to_self(&wallet, &[(funding, value)], 1_000).await;
+        mempool
+            .add_transaction(parent.clone(), &utxos, &dag)
+            .await
+            .unwrap();
+        // Spends the parent's output while double-spending the parent's input.
+        let replacement =
+            spend_to_self(&wallet, &[(funding, value), change_of(&parent)], 5_000).await;
+        match mempool.add_transaction(replacement, &utxos, &dag).await {
+            Err(MempoolError::ReplacesAncestor(id)) => assert_eq!(id, parent.id),
+            other => panic!("expected ReplacesAncestor, got {other:?}"),
+        }
+        assert_eq!(
+            pooled_ids(mempool.get_transactions().await),
+            HashSet::from([parent.id])
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_inputs_changed_during_verification_are_rejected() {
+        let wallet = Wallet::new().unwrap();
+        let dag = test_dag(&wallet).await;
+        let (funding, utxo) = owned_utxo(&wallet, 1, 10_000);
+        let value = utxo.amount;
+        let utxos = HashMap::from([(funding, utxo)]);
+        let mempool = Mempool::new(3_600, 10_000_000, 1_000);
+
+        let parent = spend_to_self(&wallet, &[(funding, value)], 1_000).await;
+        mempool
+            .add_transaction(parent.clone(), &utxos, &dag)
+            .await
+            .unwrap();
+        let child = spend_to_self(&wallet, &[change_of(&parent)], 1_000).await;
+        let child_size = codec::encoded_len(&child).unwrap();
+        let input_view = mempool
+            .prepare_entry(&child, child_size, &utxos)
+            .await
+            .unwrap();
+
+        // The parent leaves the pool while the child is being verified.
+        mempool
+            .remove_transactions(std::slice::from_ref(&parent))
+            .await;
+        let transactions = mempool.transactions.read().await;
+        let spent_outpoints = mempool.spent_outpoints.read().await;
+        let result = Mempool::validate_against_pool(
+            &child,
+            child_size,
+            &utxos,
+            &input_view,
+            &transactions,
+            &spent_outpoints,
+        );
+        assert!(matches!(result, Err(MempoolError::InputsChanged(id)) if id == child.id));
+    }
+}
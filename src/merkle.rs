@@ -0,0 +1,248 @@
+//! --- Qanto Transaction Merkle Tree ---
+//! v1.0.0 - Inclusion Proofs
+//! This module builds the Merkle tree committed to by `QantoBlock::merkle_root`
+//! and proves that a transaction is part of a block without the rest of it.
+//!
+//! - **Domain separation:** leaves hash as `Keccak256(0x00 || tx_id)` and inner
+//!   nodes as `Keccak256(0x01 || left || right)`, so an inner node can never be
+//!   passed off as a transaction.
+//! - **Odd nodes are promoted, not duplicated:** duplicating the last node of an
+//!   odd level let `[a, b, c]` and `[a, b, c, c]` share a root (CVE-2012-2459).
+//!   A node without a sibling moves up a level unchanged instead.
+//! - **Proofs** carry the leaf index and leaf count, which fix the shape of the
+//!   tree, so a verifier knows at which levels a node was promoted.
+//!
+//! Block IDs and signatures commit to the Merkle root, so blocks below
+//! `DOMAIN_SEPARATION_HEIGHT` keep the legacy tree they were built with: bare
+//! Keccak256 leaves and nodes, with the last node of an odd level duplicated.
+//! `MerkleScheme::at_height` picks the tree for a block; proofs are built and
+//! verified under the same scheme.
+
+use crate::types::TxId;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+/// First block height, on every chain, that commits to the domain-separated tree.
+pub const DOMAIN_SEPARATION_HEIGHT: u64 = 1_000_000;
+
+/// The tree layout a block's Merkle root commits to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MerkleScheme {
+    /// Bare Keccak256 hashes; an odd level duplicates its last node.
+    Legacy,
+    /// Prefixed leaves and nodes; an odd level promotes its last node.
+    DomainSeparated,
+}
+
+impl MerkleScheme {
+    /// The scheme of a block at `height`.
+    pub fn at_height(height: u64) -> Self {
+        if height >= DOMAIN_SEPARATION_HEIGHT {
+            Self::DomainSeparated
+        } else {
+            Self::Legacy
+        }
+    }
+
+    fn leaf_hash(self, tx_id: &TxId) -> Hash {
+        let mut hasher = Keccak256::new();
+        if self == Self::DomainSeparated {
+            hasher.update([LEAF_PREFIX]);
+        }
+        hasher.update(tx_id.hex_bytes());
+        hasher.finalize().into()
+    }
+
+    fn node_hash(self, left: &Hash, right: &Hash) -> Hash {
+        let mut hasher = Keccak256::new();
+        if self == Self::DomainSeparated {
+            hasher.update([NODE_PREFIX]);
+        }
+        hasher.update(left);
+        hasher.update(right);
+        hasher.finalize().into()
+    }
+
+    /// Every level of the tree, from the leaves up to the single-node root level.
+    fn levels(self, tx_ids: &[TxId]) -> Vec<Vec<Hash>> {
+        let leaves = tx_ids.par_iter().map(|tx_id| self.leaf_hash(tx_id));
+        let mut levels = vec![leaves.collect::<Vec<_>>()];
+        while let Some(level) = levels.last().filter(|level| level.len() > 1) {
+            let next = level
+                .par_chunks(2)
+                .map(|pair| match (pair, self) {
+                    ([left, right], _) => self.node_hash(left, right),
+                    ([last], Self::Legacy) => self.node_hash(last, last),
+                    ([promoted], Self::DomainSeparated) => *promoted,
+                    _ => unreachable!("chunks hold one or two nodes"),
+                })
+                .collect();
+            levels.push(next);
+        }
+        levels
+    }
+}
+
+/// Computes the hex-encoded Merkle root of `tx_ids` under `scheme`. An empty
+/// block commits to the hash of the empty string.
+pub fn merkle_root(tx_ids: &[TxId], scheme: MerkleScheme) -> String {
+    match scheme.levels(tx_ids).last().and_then(|level| level.first()) {
+        Some(root) => hex::encode(root),
+        None => hex::encode(Keccak256::digest([])),
+    }
+}
+
+/// Builds the inclusion proof for the transaction at `index` under `scheme`.
+pub fn merkle_proof(tx_ids: &[TxId], index: usize, scheme: MerkleScheme) -> Option<MerkleProof> {
+    let tx_id = *tx_ids.get(index)?;
+    let mut siblings = Vec::new();
+    let mut position = index;
+    for level in scheme.levels(tx_ids).iter().filter(|level| level.len() > 1) {
+        match (level.get(position ^ 1), scheme) {
+            (Some(sibling), _) => siblings.push(hex::encode(sibling)),
+            (None, MerkleScheme::Legacy) => siblings.push(hex::encode(level[position])),
+            (None, MerkleScheme::DomainSeparated) => {}
+        }
+        position /= 2;
+    }
+    Some(MerkleProof {
+        tx_id,
+        index: index as u32,
+        leaf_count: tx_ids.len() as u32,
+        siblings,
+    })
+}
+
+/// Proof that a transaction is committed to by a block's Merkle root.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub tx_id: TxId,
+    /// Position of the transaction in the block.
+    pub index: u32,
+    /// Number of transactions in the block.
+    pub leaf_count: u32,
+    /// Hex-encoded sibling hashes, from the leaf level upwards. Levels where
+    /// the node was promoted contribute no sibling; under the legacy scheme a
+    /// duplicated node is its own sibling.
+    pub siblings: Vec<String>,
+}
+
+impl MerkleProof {
+    /// Checks the proof against a hex-encoded Merkle root built under
+    /// `scheme`. Needs nothing but the root and the block height, so light
+    /// clients can verify inclusion from a block header.
+    pub fn verify(&self, merkle_root: &str, scheme: MerkleScheme) -> bool {
+        if self.index >= self.leaf_count {
+            return false;
+        }
+        let mut siblings = self.siblings.iter();
+        let mut hash = scheme.leaf_hash(&self.tx_id);
+        let mut position = self.index;
+        let mut width = self.leaf_count;
+        while width > 1 {
+            let has_sibling =
+                scheme == MerkleScheme::Legacy || position % 2 == 1 || position + 1 < width;
+            if has_sibling {
+                let mut sibling = Hash::default();
+                match siblings.next() {
+                    Some(hex) if hex::decode_to_slice(hex, &mut sibling).is_ok() => {}
+                    _ => return false,
+                }
+                hash = if position % 2 == 1 {
+                    scheme.node_hash(&sibling, &hash)
+                } else {
+                    scheme.node_hash(&hash, &sibling)
+                };
+            }
+            position /= 2;
+            width = width.div_ceil(2);
+        }
+        siblings.next().is_none() && hex::encode(hash).eq_ignore_ascii_case(merkle_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MerkleScheme::{DomainSeparated, Legacy};
+
+    fn ids(count: u8) -> Vec<TxId> {
+        (0..count).map(|i| TxId::new([i; 32])).collect()
+    }
+
+    #[test]
+    fn test_every_proof_verifies() {
+        for scheme in [Legacy, DomainSeparated] {
+            for count in 1..=9 {
+                let tx_ids = ids(count);
+                let root = merkle_root(&tx_ids, scheme);
+                for index in 0..tx_ids.len() {
+                    let proof = merkle_proof(&tx_ids, index, scheme).unwrap();
+                    assert!(proof.verify(&root, scheme), "leaf {index} of {count}");
+                }
+            }
+            assert!(merkle_proof(&ids(3), 3, scheme).is_none());
+            assert_eq!(merkle_root(&[], scheme), hex::encode(Keccak256::digest([])));
+        }
+    }
+
+    #[test]
+    fn test_tampered_proofs_fail() {
+        let tx_ids = ids(5);
+        let root = merkle_root(&tx_ids, DomainSeparated);
+        let proof = merkle_proof(&tx_ids, 2, DomainSeparated).unwrap();
+
+        let wrong_tx = MerkleProof {
+            tx_id: TxId::new([9; 32]),
+            ..proof.clone()
+        };
+        assert!(!wrong_tx.verify(&root, DomainSeparated));
+        let wrong_index = MerkleProof {
+            index: 3,
+            ..proof.clone()
+        };
+        assert!(!wrong_index.verify(&root, DomainSeparated));
+        let mut extra_sibling = proof.clone();
+        extra_sibling.siblings.push(hex::encode([0u8; 32]));
+        assert!(!extra_sibling.verify(&root, DomainSeparated));
+        assert!(!proof.verify(&merkle_root(&ids(4), DomainSeparated), DomainSeparated));
+        assert!(!proof.verify(&root, Legacy));
+    }
+
+    #[test]
+    fn test_duplicated_last_leaf_changes_root() {
+        let tx_ids = ids(3);
+        let mut padded = tx_ids.clone();
+        padded.push(tx_ids[2]);
+        assert_ne!(
+            merkle_root(&tx_ids, DomainSeparated),
+            merkle_root(&padded, DomainSeparated)
+        );
+    }
+
+    #[test]
+    fn test_legacy_roots_are_unchanged_below_activation() {
+        // The root blocks were built with before domain separation.
+        let tx_ids = ids(3);
+        let leaves: Vec<Vec<u8>> = tx_ids
+            .iter()
+            .map(|id| Keccak256::digest(id.hex_bytes()).to_vec())
+            .collect();
+        let pair = |l: &[u8], r: &[u8]| Keccak256::digest([l, r].concat()).to_vec();
+        let expected = pair(&pair(&leaves[0], &leaves[1]), &pair(&leaves[2], &leaves[2]));
+
+        let scheme = MerkleScheme::at_height(DOMAIN_SEPARATION_HEIGHT - 1);
+        assert_eq!(scheme, Legacy);
+        assert_eq!(merkle_root(&tx_ids, scheme), hex::encode(expected));
+        assert_eq!(
+            MerkleScheme::at_height(DOMAIN_SEPARATION_HEIGHT),
+            DomainSeparated
+        );
+    }
+}
@@ -1,11 +1,11 @@
 use crate::qantodag::{QantoBlock, QantoDAG};
+use crate::types::Address;
 use anyhow::Result;
 use hex;
 use rand::Rng;
 use rayon::prelude::*;
-use regex::Regex;
 use std::ops::Div;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use thiserror::Error;
@@ -21,8 +21,6 @@ pub enum MiningError {
     DAG(#[from] crate::qantodag::QantoDAGError),
     #[error("Emission calculation error: {0}")]
     EmissionError(String),
-    #[error("Invalid address: {0}")]
-    InvalidAddress(String),
     #[error("Wallet error: {0}")]
     Wallet(#[from] crate::wallet::WalletError),
     #[error("Transaction error: {0}")]
@@ -51,7 +49,7 @@ impl From<rayon::ThreadPoolBuildError> for MiningError {
 
 #[derive(Debug)]
 pub struct MinerConfig {
-    pub address: String,
+    pub address: Address,
     pub dag: Arc<QantoDAG>,
     pub difficulty_hex: String,
     pub target_block_time: u64,
@@ -62,13 +60,14 @@ pub struct MinerConfig {
 }
 #[derive(Clone, Debug)]
 pub struct Miner {
-    _address: String,
+    _address: Address,
     _dag: Arc<QantoDAG>,
     _difficulty: u64,
     target_block_time: u64,
     _use_gpu: bool,
     _zk_enabled: bool,
-    threads: usize,
+    /// Shared by clones, so a reload reaches every copy.
+    threads: Arc<AtomicUsize>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
@@ -170,14 +169,6 @@ impl U256 {
 impl Miner {
     #[instrument]
     pub fn new(config: MinerConfig) -> Result<Self> {
-        let address_regex = Regex::new(r"^[0-9a-fA-F]{64}$")?;
-        if !address_regex.is_match(&config.address) {
-            return Err(MiningError::InvalidAddress(format!(
-                "Invalid miner address format: {}",
-                config.address
-            ))
-            .into());
-        }
         let difficulty = u64::from_str_radix(config.difficulty_hex.trim_start_matches("0x"), 16)?;
         let effective_use_gpu = config.use_gpu && cfg!(feature = "gpu");
         if config.use_gpu && !effective_use_gpu {
@@ -195,10 +186,16 @@ impl Miner {
             target_block_time: config.target_block_time,
             _use_gpu: effective_use_gpu,
             _zk_enabled: effective_zk_enabled,
-            threads: config.threads.max(1),
+            threads: Arc::new(AtomicUsize::new(config.threads.max(1))),
         })
     }
 
+    /// Sets the number of CPU mining threads, taking effect from the next
+    /// block template.
+    pub fn set_threads(&self, threads: usize) {
+        self.threads.store(threads.max(1), Ordering::Relaxed);
+    }
+
     /// Solves the Proof-of-Work for a given block template by finding a valid nonce.
     /// This is the primary mining function called by the node's mining loop.
     /// It modifies the block in-place with the found nonce and effort.
@@ -242,7 +239,7 @@ impl Miner {
         timeout_duration: Duration,
     ) -> Result<Option<(u64, u64)>, MiningError> {
         let thread_pool = rayon::ThreadPoolBuilder::new()
-            .num_threads(self.threads)
+            .num_threads(self.threads.load(Ordering::Relaxed))
             .build()?;
         let found_signal = Arc::new(AtomicBool::new(false));
         let hashes_tried = Arc::new(AtomicU64::new(0));
@@ -8,36 +8,48 @@
 //! - REFACTOR: Removed references to the obsolete `config.difficulty` during
 //!   DAG initialization, as difficulty is now managed dynamically.
 
-use crate::config::{Config, ConfigError};
-use crate::mempool::Mempool;
+use crate::config::{Config, ConfigError, ConfigSources, P2pConfig, RateLimitConfig};
+use crate::data_dir::{DataDir, DataDirError, DataDirLock};
+use crate::emission::SupplyAudit;
+use crate::fee_estimator::FeeEstimate;
+use crate::fee_policy::FeePolicy;
+use crate::mempool::{Mempool, MempoolError};
+use crate::merkle::{MerkleProof, MerkleScheme};
 use crate::miner::{Miner, MinerConfig, MiningError};
 use crate::omega::{self, reflect_on_action};
-use crate::p2p::{P2PCommand, P2PConfig, P2PError, P2PServer};
-use crate::qantodag::{QantoBlock, QantoDAG, QantoDAGError, QantoDagConfig, UTXO};
+use crate::p2p::{P2PCommand, P2PConfig, P2PError, P2PServer, StorageAdvert};
+use crate::qantodag::{
+    AddressUtxos, CrossChainSwap, InclusionWindow, QantoBlock, QantoDAG, QantoDAGError,
+    QantoDagConfig, UTXO,
+};
+use crate::reload;
 use crate::saga::{PalletSaga, SagaError};
+use crate::snapshot::{SnapshotRestore, SnapshotStore};
+use crate::storage::{BlockStore, RocksDbStore, StateStore, StoreError};
 use crate::transaction::Transaction;
+use crate::tx_index::{self, AddressTx, IndexError, TxLocation};
+use crate::types::{Address, BlockHash, OutPoint, TxId};
+use crate::utxo_set::{self, UtxoProof, UtxoSet};
 use crate::wallet::Wallet;
 use anyhow;
 use axum::{
     body::Body,
-    extract::{Path as AxumPath, State, State as MiddlewareState},
+    extract::{Path as AxumPath, Query, State, State as MiddlewareState},
     http::{Request as HttpRequest, StatusCode},
     middleware::{self, Next},
     response::IntoResponse,
     routing::{get, post},
     Json, Router,
 };
+use bech32::Hrp;
 use governor::clock::QuantaClock;
 use governor::state::{InMemoryState, NotKeyed};
-use governor::{Quota, RateLimiter};
+use governor::RateLimiter;
 use libp2p::identity;
 use libp2p::PeerId;
-use nonzero_ext::nonzero;
-use regex::Regex;
-use rocksdb::{Options, DB};
 use serde::{Deserialize, Serialize};
 use sp_core::H256;
-use std::collections::{HashMap, VecDeque};
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::path::Path;
 use std::sync::Arc;
@@ -45,7 +57,7 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use tokio::fs;
 use tokio::signal;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, watch, RwLock};
 use tokio::task::{JoinError, JoinSet};
 use tokio::time::timeout;
 use tracing::{debug, error, info, instrument, warn};
@@ -58,9 +70,13 @@ use crate::infinite_strata_node::{
 
 const MAX_UTXOS: usize = 1_000_000;
 const MAX_PROPOSALS: usize = 10_000;
-const ADDRESS_REGEX: &str = r"^[0-9a-fA-F]{64}$";
 const MAX_SYNC_AGE_SECONDS: u64 = 3600;
 const DEFAULT_MINING_INTERVAL_SECS: u64 = 5;
+const SNAPSHOT_CHECK_INTERVAL_SECS: u64 = 60;
+/// Where the database lived before it moved into the data directory.
+const LEGACY_DB_PATH: &str = "qantodag_db_evolved";
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+const MAX_HISTORY_LIMIT: usize = 1_000;
 
 #[derive(Error, Debug)]
 pub enum NodeError {
@@ -94,8 +110,10 @@ pub enum NodeError {
     QantoDAG(#[from] QantoDAGError),
     #[error("Sync error: {0}")]
     SyncError(String),
-    #[error("Database error: {0}")]
-    Database(#[from] rocksdb::Error),
+    #[error("Storage error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Data directory error: {0}")]
+    DataDir(#[from] DataDirError),
     #[error("Node initialization error: {0}")]
     NodeInitialization(String),
 }
@@ -106,6 +124,33 @@ impl From<NodeError> for String {
     }
 }
 
+/// A transaction's inclusion proof together with the block it is proven against.
+#[derive(Serialize, Debug)]
+struct TxInclusionProof {
+    block_id: BlockHash,
+    chain_id: u32,
+    height: u64,
+    merkle_root: String,
+    /// The tree layout of the block's Merkle root, fixed by its height.
+    scheme: MerkleScheme,
+    proof: MerkleProof,
+}
+
+/// A transaction found through the transaction index.
+#[derive(Serialize, Debug)]
+struct TxLookup {
+    #[serde(flatten)]
+    location: TxLocation,
+    transaction: Transaction,
+}
+
+/// Proof that an output is unspent, against the node's current UTXO state root.
+#[derive(Serialize, Debug)]
+struct UtxoStateProof {
+    state_root: String,
+    proof: UtxoProof,
+}
+
 #[derive(Serialize, Debug)]
 struct DagInfo {
     block_count: usize,
@@ -135,17 +180,21 @@ pub struct PeerCache {
 }
 
 pub struct Node {
-    _config_path: String,
-    config: Config,
+    config_sources: ConfigSources,
+    /// The running configuration. Reloads publish their changes through it.
+    config: watch::Sender<Config>,
     p2p_identity_keypair: identity::Keypair,
     pub dag: Arc<QantoDAG>,
     pub miner: Arc<Miner>,
     wallet: Arc<Wallet>,
     pub mempool: Arc<RwLock<Mempool>>,
-    pub utxos: Arc<RwLock<HashMap<String, UTXO>>>,
+    pub utxos: Arc<RwLock<UtxoSet>>,
     pub proposals: Arc<RwLock<Vec<QantoBlock>>>,
-    peer_cache_path: String,
+    data_dir: DataDir,
+    /// Held for the node's lifetime when it runs on an on-disk data directory.
+    _data_dir_lock: Option<DataDirLock>,
     pub saga_pallet: Arc<PalletSaga>,
+    snapshots: Option<Arc<SnapshotStore>>,
     #[cfg(feature = "infinite-strata")]
     isnm_service: Arc<InfiniteStrataNode>,
 }
@@ -155,53 +204,90 @@ type DirectApiRateLimiter = RateLimiter<NotKeyed, InMemoryState, QuantaClock>;
 impl Node {
     #[instrument(skip(config, wallet))]
     pub async fn new(
+        config: Config,
+        config_sources: ConfigSources,
+        wallet: Arc<Wallet>,
+        data_dir: DataDir,
+    ) -> Result<Self, NodeError> {
+        let lock = data_dir.lock()?;
+        info!("Using data directory {}", data_dir.root().display());
+        if !data_dir.db().exists() && Path::new(LEGACY_DB_PATH).exists() {
+            warn!(
+                "Found a database at '{LEGACY_DB_PATH}', which is no longer used. Move it to '{}' to keep its contents.",
+                data_dir.db().display()
+            );
+        }
+
+        info!("Opening node database...");
+        let store = Arc::new(RocksDbStore::open(data_dir.db())?);
+        let mut node = Self::with_store(config, config_sources, wallet, data_dir, store).await?;
+        node._data_dir_lock = Some(lock);
+        Ok(node)
+    }
+
+    /// Creates a node that persists into `store` instead of the on-disk
+    /// database. The rest of `data_dir` is used but not locked.
+    pub async fn with_store(
         mut config: Config,
-        config_path: String,
+        config_sources: ConfigSources,
         wallet: Arc<Wallet>,
-        p2p_identity_path: &str,
-        peer_cache_path: String,
+        data_dir: DataDir,
+        store: Arc<dyn StateStore>,
     ) -> Result<Self, NodeError> {
         config.validate()?;
 
-        let local_keypair = match fs::read(p2p_identity_path).await {
+        data_dir.create()?;
+        let p2p_identity_path = data_dir.p2p_identity();
+        let local_keypair = match fs::read(&p2p_identity_path).await {
             Ok(key_bytes) => {
-                info!("Loading P2P identity from file: {p2p_identity_path}");
+                info!(
+                    "Loading P2P identity from file: {}",
+                    p2p_identity_path.display()
+                );
                 identity::Keypair::from_protobuf_encoding(&key_bytes).map_err(|e| {
                     NodeError::P2PIdentity(format!("Failed to decode P2P identity key: {e}"))
                 })
             }
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
                 info!(
-                    "P2P identity key file not found at {p2p_identity_path}, generating a new one."
+                    "P2P identity key file not found at {}, generating a new one.",
+                    p2p_identity_path.display()
                 );
-                if let Some(p) = Path::new(p2p_identity_path).parent() {
-                    fs::create_dir_all(p).await?;
-                }
                 let new_key = identity::Keypair::generate_ed25519();
                 let new_key_bytes = new_key.to_protobuf_encoding().map_err(|e| {
                     NodeError::P2PIdentity(format!("Failed to encode P2P key: {e:?}"))
                 })?;
-                fs::write(p2p_identity_path, new_key_bytes).await?;
-                info!("New P2P identity key saved to {p2p_identity_path}");
+                fs::write(&p2p_identity_path, new_key_bytes).await?;
+                info!(
+                    "New P2P identity key saved to {}",
+                    p2p_identity_path.display()
+                );
                 Ok(new_key)
             }
             Err(e) => Err(NodeError::P2PIdentity(format!(
-                "Failed to read P2P identity key file '{p2p_identity_path}': {e}"
+                "Failed to read P2P identity key file '{}': {e}",
+                p2p_identity_path.display()
             ))),
         }?;
         let local_peer_id = PeerId::from(local_keypair.public());
         info!("Node Local P2P Peer ID: {local_peer_id}");
         let full_local_p2p_address = format!("{}/p2p/{}", config.p2p_address, local_peer_id);
         if config.local_full_p2p_address.as_deref() != Some(&full_local_p2p_address) {
-            info!(
-                "Updating config file '{config_path}' with local full P2P address: {full_local_p2p_address}"
-            );
+            if let Some(config_path) = &config_sources.file {
+                info!(
+                    "Updating config file '{config_path}' with local full P2P address: {full_local_p2p_address}"
+                );
+                Config::write_setting(
+                    config_path,
+                    "local_full_p2p_address",
+                    &full_local_p2p_address,
+                )?;
+            }
             config.local_full_p2p_address = Some(full_local_p2p_address.clone());
-            config.save(&config_path)?;
         }
 
-        let initial_validator = wallet.address().trim().to_lowercase();
-        if initial_validator != config.genesis_validator.trim().to_lowercase() {
+        let initial_validator = wallet.address();
+        if initial_validator != config.genesis_validator {
             return Err(NodeError::Config(ConfigError::Validation(
                 "Wallet address does not match genesis validator".to_string(),
             )));
@@ -229,48 +315,49 @@ impl Node {
                 Arc::new(PalletSaga::new())
             }
         };
+        #[cfg(feature = "ai")]
+        saga_pallet
+            .cognitive_engine
+            .write()
+            .await
+            .set_model_dir(data_dir.saga());
 
         info!("Initializing QantoDAG (loading database)...");
-        let db = {
-            let mut opts = Options::default();
-            opts.create_if_missing(true);
-            DB::open(&opts, "qantodag_db_evolved")?
-        };
-
         let dag_config = QantoDagConfig {
             initial_validator,
             target_block_time: config.target_block_time,
             num_chains: config.num_chains,
             qr_signing_key: &node_signing_key,
             qr_public_key: &node_public_key,
+            storage_mode: config.storage,
+            tx_index: config.index.enabled,
+            coinbase_maturity: config.coinbase_maturity,
         };
 
-        let dag_arc = QantoDAG::new(dag_config, saga_pallet.clone(), db)?;
+        let dag_arc = QantoDAG::new(dag_config, saga_pallet.clone(), store)?;
 
         info!("QantoDAG initialized.");
 
         let mempool = Arc::new(RwLock::new(Mempool::new(3600, 10_000_000, 10_000)));
-        let utxos = Arc::new(RwLock::new(HashMap::with_capacity(MAX_UTXOS)));
+        let utxos = Arc::new(RwLock::new(UtxoSet::with_capacity(MAX_UTXOS)));
         let proposals = Arc::new(RwLock::new(Vec::with_capacity(MAX_PROPOSALS)));
 
-        {
-            let mut utxos_lock = utxos.write().await;
-            for chain_id_val in 0..config.num_chains {
-                let genesis_id_convention =
-                    format!("genesis_placeholder_tx_id_for_chain_{chain_id_val}");
-                let utxo_id = format!("genesis_utxo_for_chain_{chain_id_val}");
-                utxos_lock.insert(
-                    utxo_id.clone(),
-                    UTXO {
-                        address: config.genesis_validator.clone(),
-                        amount: 100,
-                        tx_id: genesis_id_convention,
-                        output_index: 0,
-                        explorer_link: format!("https://qantoblockexplorer.org/utxo/{utxo_id}"),
-                    },
-                );
-            }
-        }
+        utxos.write().await.extend(utxo_set::genesis_utxos(
+            config.num_chains,
+            config.genesis_validator,
+        ));
+
+        let snapshots = if config.snapshot.enabled {
+            let store = SnapshotStore::open(
+                data_dir.snapshots(&config.snapshot.dir),
+                config.snapshot.interval_blocks,
+                config.snapshot.retain,
+            )
+            .map_err(|e| NodeError::NodeInitialization(format!("Snapshot store: {e}")))?;
+            Some(Arc::new(store))
+        } else {
+            None
+        };
 
         let miner_config = MinerConfig {
             address: wallet.address(),
@@ -286,8 +373,8 @@ impl Node {
         let miner = Arc::new(miner_instance);
 
         Ok(Self {
-            _config_path: config_path,
-            config: config.clone(),
+            config_sources,
+            config: watch::Sender::new(config),
             p2p_identity_keypair: local_keypair,
             dag: dag_arc,
             miner,
@@ -295,8 +382,10 @@ impl Node {
             mempool,
             utxos,
             proposals,
-            peer_cache_path,
+            data_dir,
+            _data_dir_lock: None,
             saga_pallet,
+            snapshots,
             #[cfg(feature = "infinite-strata")]
             isnm_service,
         })
@@ -304,7 +393,63 @@ impl Node {
 
     pub async fn start(&self) -> Result<(), NodeError> {
         let (tx_p2p_commands, mut rx_p2p_commands) = mpsc::channel::<P2PCommand>(100);
+        // Commands for the P2P server to send out. Without peers no server runs,
+        // the receiver is dropped and sends fail fast.
+        let (tx_p2p_outbound, mut rx_p2p_outbound) = mpsc::channel::<P2PCommand>(100);
         let mut join_set: JoinSet<Result<(), NodeError>> = JoinSet::new();
+        let config = self.config.borrow().clone();
+
+        let config_watch =
+            reload::watch_for_changes(self.config_sources.clone(), self.config.clone());
+        join_set.spawn(async move {
+            config_watch.await;
+            Ok(())
+        });
+
+        let api_rate_limit = RateLimitConfig::quota(config.rate_limits.api_per_second);
+        let (api_limiter_tx, api_limiter_rx) =
+            watch::channel(Arc::new(RateLimiter::direct(api_rate_limit)));
+        // Applies reloaded settings to the services that read them directly;
+        // the P2P server follows `self.config` itself.
+        let live_settings_task = {
+            let miner_clone = self.miner.clone();
+            let mut updates = self.config.subscribe();
+            let mut api_rate = config.rate_limits.api_per_second;
+            async move {
+                while updates.changed().await.is_ok() {
+                    let config = updates.borrow_and_update().clone();
+                    miner_clone.set_threads(config.mining_threads);
+                    if config.rate_limits.api_per_second != api_rate {
+                        api_rate = config.rate_limits.api_per_second;
+                        api_limiter_tx.send_replace(Arc::new(RateLimiter::direct(
+                            RateLimitConfig::quota(api_rate),
+                        )));
+                    }
+                }
+                Ok(())
+            }
+        };
+        join_set.spawn(live_settings_task);
+
+        let persist_mempool = config.mempool.persist;
+        if persist_mempool {
+            let utxos_reader = self.utxos.read().await;
+            if let Err(e) = self
+                .mempool
+                .read()
+                .await
+                .load_journal(self.dag.store.clone(), &utxos_reader, &self.dag)
+                .await
+            {
+                warn!("Failed to restore mempool journal: {}", e);
+            }
+
+            join_set.spawn(Self::flush_mempool_journal(
+                self.mempool.clone(),
+                self.dag.store.clone(),
+                Duration::from_secs(config.mempool.flush_interval_secs),
+            ));
+        }
 
         #[cfg(feature = "infinite-strata")]
         {
@@ -328,25 +473,77 @@ impl Node {
             });
         }
 
+        if let Some(store) = self.snapshots.clone() {
+            let dag_clone = self.dag.clone();
+            let utxos_clone = self.utxos.clone();
+            join_set.spawn(async move {
+                let mut interval =
+                    tokio::time::interval(Duration::from_secs(SNAPSHOT_CHECK_INTERVAL_SECS));
+                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                loop {
+                    interval.tick().await;
+                    if let Err(e) = store.take(&dag_clone, &utxos_clone).await {
+                        warn!("Failed to take state snapshot: {}", e);
+                    }
+                }
+                #[allow(unreachable_code)]
+                Ok(())
+            });
+        }
+
         let command_processor_task = {
             let dag_clone = self.dag.clone();
             let mempool_clone = self.mempool.clone();
             let utxos_clone = self.utxos.clone();
-            let p2p_tx_clone = tx_p2p_commands.clone();
+            let p2p_tx_clone = tx_p2p_outbound.clone();
             let saga_clone = self.saga_pallet.clone();
+            let snapshots_clone = self.snapshots.clone();
+            let config_updates = self.config.subscribe();
 
             async move {
+                let mut restore: Option<SnapshotRestore> = None;
                 while let Some(command) = rx_p2p_commands.recv().await {
                     match command {
                         P2PCommand::BroadcastBlock(block) => {
                             info!("\n{}", block);
+                            let height_hint = block.height.saturating_sub(1);
                             let add_result = dag_clone.add_block(block, &utxos_clone).await;
 
-                            if matches!(add_result, Ok(true)) {
-                                debug!("Running periodic maintenance after adding new block.");
-                                dag_clone.run_periodic_maintenance().await;
-                            } else if let Err(e) = add_result {
-                                warn!("Block failed validation or processing: {}", e);
+                            match add_result {
+                                Ok(true) => {
+                                    debug!("Running periodic maintenance after adding new block.");
+                                    dag_clone.run_periodic_maintenance().await;
+                                    mempool_clone
+                                        .read()
+                                        .await
+                                        .evict_expired(dag_clone.inclusion_window().await)
+                                        .await;
+                                    match dag_clone.prune_block_bodies(&utxos_clone).await {
+                                        Ok(0) => {}
+                                        Ok(_) => {
+                                            let advert = StorageAdvert::of(&dag_clone).await;
+                                            let cmd = P2PCommand::AdvertiseStorage(advert);
+                                            if let Err(e) = p2p_tx_clone.send(cmd).await {
+                                                warn!("Failed to send storage advert: {}", e);
+                                            }
+                                        }
+                                        Err(e) => warn!("Failed to prune block bodies: {}", e),
+                                    }
+                                }
+                                Ok(false) => {}
+                                Err(QantoDAGError::MissingParent(parent_id)) => {
+                                    debug!("Fetching missing parent block {}", parent_id);
+                                    let cmd = P2PCommand::FetchBlock {
+                                        block_id: parent_id,
+                                        height_hint,
+                                    };
+                                    if let Err(e) = p2p_tx_clone.send(cmd).await {
+                                        warn!("Failed to request missing parent: {}", e);
+                                    }
+                                }
+                                Err(e) => {
+                                    warn!("Block failed validation or processing: {}", e);
+                                }
                             }
                         }
                         P2PCommand::BroadcastTransaction(tx) => {
@@ -360,45 +557,86 @@ impl Node {
                                 warn!("Failed to add transaction to mempool: {}", e);
                             }
                         }
-                        P2PCommand::SyncResponse { blocks, utxos } => {
-                            info!(
-                                "Received state sync response with {} blocks and {} UTXOs.",
-                                blocks.len(),
-                                utxos.len()
-                            );
-                            {
-                                let mut utxos_writer = utxos_clone.write().await;
-                                let initial_utxo_count = utxos_writer.len();
-                                utxos_writer.extend(utxos);
-                                info!(
-                                    "UTXO set updated with {} new entries from sync.",
-                                    utxos_writer.len() - initial_utxo_count
-                                );
+                        P2PCommand::RequestState => {
+                            if let Some(manifest) = match &snapshots_clone {
+                                Some(store) => store.latest_manifest().await,
+                                None => None,
+                            } {
+                                let cmd = P2PCommand::SnapshotManifest(Box::new(manifest));
+                                if let Err(e) = p2p_tx_clone.send(cmd).await {
+                                    warn!("Failed to send snapshot manifest: {}", e);
+                                }
                             }
-                            if let Ok(sorted_blocks) =
-                                Self::topological_sort_blocks(blocks, &dag_clone).await
-                            {
-                                let mut added_count = 0;
-                                let mut failed_count = 0;
-                                for b in sorted_blocks {
-                                    match dag_clone.add_block(b, &utxos_clone).await {
-                                        Ok(true) => added_count += 1,
-                                        Ok(false) => { /* block already exists */ }
-                                        Err(e) => {
-                                            warn!("Failed to add block from sync response: {}", e);
-                                            failed_count += 1;
-                                        }
+                        }
+                        P2PCommand::SnapshotManifest(manifest) => {
+                            if restore.is_some() || !dag_clone.is_at_genesis().await {
+                                continue;
+                            }
+                            let trusted = config_updates.borrow().snapshot.trusted.clone();
+                            if trusted.is_empty() {
+                                debug!("No trusted checkpoints configured; ignoring snapshot manifest.");
+                                continue;
+                            }
+                            match SnapshotRestore::new(*manifest, &trusted) {
+                                Ok(new_restore) => {
+                                    info!(
+                                        "Restoring from snapshot at height {}.",
+                                        new_restore.checkpoint().height
+                                    );
+                                    Self::request_next_chunk(&new_restore, &p2p_tx_clone).await;
+                                    restore = Some(new_restore);
+                                }
+                                Err(e) => warn!("Rejected snapshot manifest: {}", e),
+                            }
+                        }
+                        P2PCommand::RequestSnapshotChunk { checkpoint, index } => {
+                            let Some(store) = &snapshots_clone else {
+                                continue;
+                            };
+                            match store.chunk(checkpoint, index).await {
+                                Ok(data) => {
+                                    let cmd = P2PCommand::SnapshotChunk {
+                                        checkpoint,
+                                        index,
+                                        data,
+                                    };
+                                    if let Err(e) = p2p_tx_clone.send(cmd).await {
+                                        warn!("Failed to send snapshot chunk: {}", e);
                                     }
                                 }
-                                info!(
-                                    "Block sync complete. Added: {}, Failed: {}.",
-                                    added_count, failed_count
-                                );
-                                dag_clone.run_periodic_maintenance().await;
-                            } else {
-                                error!(
-                                    "Failed to topologically sort blocks from sync response. Discarding batch."
-                                );
+                                Err(e) => debug!("Cannot serve snapshot chunk {}: {}", index, e),
+                            }
+                        }
+                        P2PCommand::SnapshotChunk {
+                            checkpoint,
+                            index,
+                            data,
+                        } => {
+                            let Some(in_progress) =
+                                restore.as_mut().filter(|r| r.checkpoint().id == checkpoint)
+                            else {
+                                continue;
+                            };
+                            if let Err(e) = in_progress.add_chunk(index, &data) {
+                                warn!("Discarding snapshot chunk {}: {}", index, e);
+                            }
+                            if in_progress.next_missing().is_some() {
+                                Self::request_next_chunk(in_progress, &p2p_tx_clone).await;
+                                continue;
+                            }
+                            let Some(finished) = restore.take() else {
+                                continue;
+                            };
+                            match finished.finish() {
+                                Ok((checkpoint_block, meta, utxo_set)) => {
+                                    *utxos_clone.write().await = utxo_set;
+                                    if let Err(e) =
+                                        dag_clone.install_checkpoint(checkpoint_block, meta).await
+                                    {
+                                        warn!("Failed to install snapshot checkpoint: {}", e);
+                                    }
+                                }
+                                Err(e) => warn!("Snapshot restore failed: {}", e),
                             }
                         }
                         P2PCommand::RequestBlock { block_id, peer_id } => {
@@ -406,6 +644,10 @@ impl Node {
                                 "Received request for block {} from peer {}",
                                 block_id, peer_id
                             );
+                            if dag_clone.is_pruned(&block_id).await {
+                                debug!("Not serving block {}: its body was pruned.", block_id);
+                                continue;
+                            }
                             let blocks_reader = dag_clone.blocks.read().await;
                             if let Some(block) = blocks_reader.get(&block_id) {
                                 info!("Found block {}, sending to peer {}", block_id, peer_id);
@@ -440,77 +682,93 @@ impl Node {
         };
         join_set.spawn(command_processor_task);
 
-        if !self.config.peers.is_empty() {
+        if !config.peers.is_empty() {
             info!("Peers detected in config, initializing P2P server task...");
             let p2p_dag_clone = self.dag.clone();
             let p2p_mempool_clone = self.mempool.clone();
             let p2p_utxos_clone = self.utxos.clone();
             let p2p_proposals_clone = self.proposals.clone();
             let p2p_command_sender_clone = tx_p2p_commands.clone();
+            let p2p_outbound_clone = tx_p2p_outbound.clone();
             let p2p_identity_keypair_clone = self.p2p_identity_keypair.clone();
-            let p2p_listen_address_config_clone = self.config.p2p_address.clone();
-            let p2p_initial_peers_config_clone = self.config.peers.clone();
-            let p2p_settings_clone = self.config.p2p.clone();
+            let p2p_listen_address_config_clone = config.p2p_address.clone();
+            let p2p_initial_peers_config_clone = config.peers.clone();
+            let mut p2p_settings_updates = self.config.subscribe();
             let (node_signing_key, node_public_key) = self.wallet.get_keypair()?;
-            let peer_cache_path_clone = self.peer_cache_path.clone();
-            let network_id_clone = self.config.network_id.clone();
+            let peer_cache_path_clone = self.data_dir.peer_cache().to_string_lossy().into_owned();
+            let network_id_clone = config.network_id.clone();
             let p2p_task_fut = async move {
-                let mut attempts = 0;
-                let mut p2p_server = loop {
-                    let p2p_config = P2PConfig {
-                        topic_prefix: &network_id_clone,
-                        listen_addresses: vec![p2p_listen_address_config_clone.clone()],
-                        initial_peers: p2p_initial_peers_config_clone.clone(),
-                        dag: p2p_dag_clone.clone(),
-                        mempool: p2p_mempool_clone.clone(),
-                        utxos: p2p_utxos_clone.clone(),
-                        proposals: p2p_proposals_clone.clone(),
-                        local_keypair: p2p_identity_keypair_clone.clone(),
-                        p2p_settings: p2p_settings_clone.clone(),
-                        node_qr_sk: &node_signing_key,
-                        node_qr_pk: &node_public_key,
-                        peer_cache_path: peer_cache_path_clone.clone(),
+                let mut requested_state = false;
+                // Gossipsub settings are fixed once the swarm is built, so the
+                // server is rebuilt whenever a reload changes them.
+                loop {
+                    let (p2p_settings, rate_limits) = {
+                        let config = p2p_settings_updates.borrow_and_update();
+                        (config.p2p.clone(), config.rate_limits.clone())
                     };
-                    info!(
-                        "Attempting to initialize P2P server (attempt {})...",
-                        attempts + 1
-                    );
-                    match timeout(
-                        Duration::from_secs(15),
-                        P2PServer::new(p2p_config, p2p_command_sender_clone.clone()),
-                    )
-                    .await
-                    {
-                        Ok(Ok(server)) => {
-                            info!("P2P server initialized successfully.");
-                            break server;
-                        }
-                        Ok(Err(e)) => {
-                            warn!("P2P server failed to initialize: {e}.");
+                    let mut attempts = 0;
+                    let mut p2p_server = loop {
+                        let p2p_config = P2PConfig {
+                            topic_prefix: &network_id_clone,
+                            listen_addresses: vec![p2p_listen_address_config_clone.clone()],
+                            initial_peers: p2p_initial_peers_config_clone.clone(),
+                            dag: p2p_dag_clone.clone(),
+                            mempool: p2p_mempool_clone.clone(),
+                            utxos: p2p_utxos_clone.clone(),
+                            proposals: p2p_proposals_clone.clone(),
+                            local_keypair: p2p_identity_keypair_clone.clone(),
+                            p2p_settings: p2p_settings.clone(),
+                            rate_limits: rate_limits.clone(),
+                            node_qr_sk: &node_signing_key,
+                            node_qr_pk: &node_public_key,
+                            peer_cache_path: peer_cache_path_clone.clone(),
+                        };
+                        info!(
+                            "Attempting to initialize P2P server (attempt {})...",
+                            attempts + 1
+                        );
+                        match timeout(
+                            Duration::from_secs(15),
+                            P2PServer::new(p2p_config, p2p_command_sender_clone.clone()),
+                        )
+                        .await
+                        {
+                            Ok(Ok(server)) => {
+                                info!("P2P server initialized successfully.");
+                                break server;
+                            }
+                            Ok(Err(e)) => {
+                                warn!("P2P server failed to initialize: {e}.");
+                            }
+                            Err(_) => {
+                                warn!("P2P server initialization timed out.");
+                            }
                         }
-                        Err(_) => {
-                            warn!("P2P server initialization timed out.");
+                        attempts += 1;
+                        let backoff_duration = Duration::from_secs(2u64.pow(attempts.min(6)));
+                        warn!("Retrying P2P initialization in {:?}", backoff_duration);
+                        tokio::time::sleep(backoff_duration).await;
+                    };
+                    if !requested_state && !p2p_initial_peers_config_clone.is_empty() {
+                        requested_state = true;
+                        if let Err(e) = p2p_outbound_clone.send(P2PCommand::RequestState).await {
+                            error!("Failed to send initial RequestState P2P command: {e}");
                         }
                     }
-                    attempts += 1;
-                    let backoff_duration = Duration::from_secs(2u64.pow(attempts.min(6)));
-                    warn!("Retrying P2P initialization in {:?}", backoff_duration);
-                    tokio::time::sleep(backoff_duration).await;
-                };
-                if !p2p_initial_peers_config_clone.is_empty() {
-                    if let Err(e) = p2p_command_sender_clone
-                        .send(P2PCommand::RequestState)
-                        .await
-                    {
-                        error!("Failed to send initial RequestState P2P command: {e}");
+                    tokio::select! {
+                        result = p2p_server.run(&mut rx_p2p_outbound) => {
+                            return result.map_err(NodeError::P2PSpecific);
+                        }
+                        _ = Self::p2p_settings_changed(&mut p2p_settings_updates, &p2p_settings, &rate_limits) => {
+                            info!("P2P settings changed, restarting the P2P server.");
+                        }
                     }
                 }
-                let (_p2p_tx, p2p_rx) = mpsc::channel::<P2PCommand>(100);
-                p2p_server.run(p2p_rx).await.map_err(NodeError::P2PSpecific)
             };
             join_set.spawn(p2p_task_fut);
         } else {
             info!("No peers found. Running in single-node mode. Spawning solo miner...");
+            drop(rx_p2p_outbound);
             let miner_dag_clone = self.dag.clone();
             let miner_wallet_clone = self.wallet.clone();
             let miner_mempool_clone = self.mempool.clone();
@@ -535,26 +793,36 @@ impl Node {
                 dag: self.dag.clone(),
                 mempool: self.mempool.clone(),
                 utxos: self.utxos.clone(),
-                api_address: self.config.api_address.clone(),
+                api_address: config.api_address.clone(),
+                address_hrp: config.address_hrp(),
                 p2p_command_sender: tx_p2p_commands.clone(),
                 saga: self.saga_pallet.clone(),
             };
             async move {
-                let rate_limiter: Arc<DirectApiRateLimiter> =
-                    Arc::new(RateLimiter::direct(Quota::per_second(nonzero!(50u32))));
                 let app = Router::new()
                     .route("/info", get(info_handler))
                     .route("/balance/:address", get(get_balance))
                     .route("/utxos/:address", get(get_utxos))
+                    .route("/utxo/:id", get(get_utxo))
+                    .route("/utxo/:id/proof", get(get_utxo_proof))
+                    .route("/swap/:id", get(get_swap))
                     .route("/transaction", post(submit_transaction))
+                    .route("/tx/:id", get(get_transaction))
+                    .route("/tx/:id/proof", get(get_tx_proof))
+                    .route("/address/:address/history", get(get_address_history))
+                    .route("/utxo/:id/spender", get(get_spender))
                     .route("/block/:id", get(get_block))
                     .route("/dag", get(get_dag))
                     .route("/health", get(health_check))
                     .route("/mempool", get(mempool_handler))
+                    .route("/fee-estimate", get(fee_estimate_handler))
+                    .route("/fee-policy", get(fee_policy_handler))
+                    .route("/supply", get(supply_handler))
+                    .route("/inclusion-window", get(inclusion_window_handler))
                     .route("/publish-readiness", get(publish_readiness_handler))
                     .route("/saga/ask", post(ask_saga))
                     .layer(middleware::from_fn_with_state(
-                        rate_limiter,
+                        api_limiter_rx,
                         rate_limit_layer,
                     ))
                     .with_state(app_state.clone());
@@ -590,85 +858,87 @@ impl Node {
             },
         }
         join_set.shutdown().await;
+        if persist_mempool {
+            match self
+                .mempool
+                .read()
+                .await
+                .save_journal(self.dag.store.clone())
+                .await
+            {
+                Ok(count) => info!("Saved {count} pending transactions to mempool journal."),
+                Err(e) => error!("Failed to save mempool journal on shutdown: {}", e),
+            }
+        }
         info!("Node shutdown complete.");
         Ok(())
     }
 
-    async fn topological_sort_blocks(
-        blocks: Vec<QantoBlock>,
-        dag: &QantoDAG,
-    ) -> Result<Vec<QantoBlock>, NodeError> {
-        if blocks.is_empty() {
-            return Ok(vec![]);
-        }
-        let block_map: HashMap<String, QantoBlock> =
-            blocks.into_iter().map(|b| (b.id.clone(), b)).collect();
-        let mut in_degree: HashMap<String, usize> = HashMap::new();
-        let mut children_map: HashMap<String, Vec<String>> = HashMap::new();
-        let local_blocks = dag.blocks.read().await;
-        for (id, block) in &block_map {
-            let mut degree = 0;
-            for parent_id in &block.parents {
-                if block_map.contains_key(parent_id) {
-                    degree += 1;
-                    children_map
-                        .entry(parent_id.clone())
-                        .or_default()
-                        .push(id.clone());
-                } else if !local_blocks.contains_key(parent_id) {
-                    warn!(
-                        "Sync Error: Block {} has a missing parent {} that is not in the local DAG or the sync batch.",
-                        id, parent_id
-                    );
-                    return Err(NodeError::SyncError(format!(
-                        "Missing parent {parent_id} for block {id} in sync batch"
-                    )));
-                }
+    /// Resolves once a reload changes the P2P settings or the gossip rates
+    /// away from `p2p` and `rate_limits`. Other rate limits, such as the API's,
+    /// are applied without touching the P2P server.
+    async fn p2p_settings_changed(
+        updates: &mut watch::Receiver<Config>,
+        p2p: &P2pConfig,
+        rate_limits: &RateLimitConfig,
+    ) {
+        while updates.changed().await.is_ok() {
+            let config = updates.borrow();
+            if config.p2p != *p2p || config.rate_limits.gossip_rates() != rate_limits.gossip_rates()
+            {
+                return;
             }
-            in_degree.insert(id.clone(), degree);
         }
-        let mut queue: VecDeque<String> = in_degree
-            .iter()
-            .filter(|(_, &degree)| degree == 0)
-            .map(|(id, _)| id.clone())
-            .collect();
-        let mut sorted_blocks = Vec::with_capacity(block_map.len());
-        while let Some(id) = queue.pop_front() {
-            if let Some(children) = children_map.get(&id) {
-                for child_id in children {
-                    if let Some(degree) = in_degree.get_mut(child_id) {
-                        *degree -= 1;
-                        if *degree == 0 {
-                            queue.push_back(child_id.clone());
-                        }
-                    }
-                }
-            }
-            if let Some(block) = block_map.get(&id) {
-                sorted_blocks.push(block.clone());
+        std::future::pending().await
+    }
+
+    /// Returns a receiver of the running configuration, updated on every
+    /// reload that changes a setting.
+    pub fn config_updates(&self) -> watch::Receiver<Config> {
+        self.config.subscribe()
+    }
+
+    /// Saves the mempool journal every `every` until the task is aborted at
+    /// shutdown, after which the journal is saved one last time.
+    async fn flush_mempool_journal(
+        mempool: Arc<RwLock<Mempool>>,
+        store: Arc<dyn StateStore>,
+        every: Duration,
+    ) -> Result<(), NodeError> {
+        let mut interval = tokio::time::interval(every);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let mempool_reader = mempool.read().await;
+            match mempool_reader.save_journal(store.clone()).await {
+                Ok(count) => debug!("Flushed {count} transactions to mempool journal."),
+                Err(e) => warn!("Failed to flush mempool journal: {}", e),
             }
         }
-        if sorted_blocks.len() != block_map.len() {
-            error!(
-                "Cycle detected in block sync batch or missing parent. Sorted {} of {} blocks.",
-                sorted_blocks.len(),
-                block_map.len()
-            );
-            Err(NodeError::SyncError(
-                "Cycle detected or missing parent in block sync batch.".to_string(),
-            ))
-        } else {
-            Ok(sorted_blocks)
+    }
+
+    /// Asks peers for the next snapshot chunk still missing from `restore`.
+    async fn request_next_chunk(restore: &SnapshotRestore, p2p_tx: &mpsc::Sender<P2PCommand>) {
+        if let Some(index) = restore.next_missing() {
+            let cmd = P2PCommand::RequestSnapshotChunk {
+                checkpoint: restore.checkpoint().id,
+                index,
+            };
+            if let Err(e) = p2p_tx.send(cmd).await {
+                warn!("Failed to request snapshot chunk {}: {}", index, e);
+            }
         }
     }
 }
 
 async fn rate_limit_layer(
-    MiddlewareState(limiter): MiddlewareState<Arc<DirectApiRateLimiter>>,
+    MiddlewareState(limiter): MiddlewareState<watch::Receiver<Arc<DirectApiRateLimiter>>>,
     req: HttpRequest<Body>,
     next: Next,
 ) -> Result<axum::response::Response, StatusCode> {
-    if limiter.check().is_err() {
+    let allowed = limiter.borrow().check().is_ok();
+    if !allowed {
         warn!("API rate limit exceeded for: {:?}", req.uri());
         Err(StatusCode::TOO_MANY_REQUESTS)
     } else {
@@ -680,8 +950,9 @@ async fn rate_limit_layer(
 struct AppState {
     dag: Arc<QantoDAG>,
     mempool: Arc<RwLock<Mempool>>,
-    utxos: Arc<RwLock<HashMap<String, UTXO>>>,
+    utxos: Arc<RwLock<UtxoSet>>,
     api_address: String,
+    address_hrp: Hrp,
     p2p_command_sender: mpsc::Sender<P2PCommand>,
     saga: Arc<PalletSaga>,
 }
@@ -690,6 +961,16 @@ struct AppState {
 struct SagaQuery {
     query: String,
 }
+
+#[derive(Deserialize)]
+struct FeeEstimateQuery {
+    target: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    limit: Option<usize>,
+}
 #[derive(Serialize)]
 struct ApiError {
     code: u16,
@@ -779,11 +1060,35 @@ async fn info_handler(
 
 async fn mempool_handler(
     State(state): State<AppState>,
-) -> Result<Json<HashMap<String, Transaction>>, StatusCode> {
+) -> Result<Json<HashMap<TxId, Transaction>>, StatusCode> {
     let mempool_read_guard = state.mempool.read().await;
     Ok(Json(mempool_read_guard.get_transactions().await))
 }
 
+async fn fee_estimate_handler(
+    State(state): State<AppState>,
+    Query(params): Query<FeeEstimateQuery>,
+) -> Result<Json<FeeEstimate>, StatusCode> {
+    let buckets = state.mempool.read().await.fee_rate_buckets().await;
+    let min_relay_fee_rate = state.saga.fee_policy().await.min_relay_fee_rate;
+    let estimator = state.dag.fee_estimator.read().await;
+    let mut estimate = estimator.estimate(params.target.unwrap_or(1), &buckets);
+    estimate.fee_rate = estimate.fee_rate.max(min_relay_fee_rate);
+    Ok(Json(estimate))
+}
+
+async fn fee_policy_handler(State(state): State<AppState>) -> Json<FeePolicy> {
+    Json(state.saga.fee_policy().await)
+}
+
+async fn supply_handler(State(state): State<AppState>) -> Json<SupplyAudit> {
+    Json(state.dag.supply_audit().await)
+}
+
+async fn inclusion_window_handler(State(state): State<AppState>) -> Json<InclusionWindow> {
+    Json(state.dag.inclusion_window().await)
+}
+
 async fn publish_readiness_handler(
     State(state): State<AppState>,
 ) -> Result<Json<PublishReadiness>, StatusCode> {
@@ -825,18 +1130,22 @@ async fn publish_readiness_handler(
     }))
 }
 
+fn parse_address(address: &str, hrp: Hrp, context: &str) -> Result<Address, ApiError> {
+    Address::parse_for_network(address, hrp).map_err(|e| {
+        warn!("Invalid address format for {context}: {address}");
+        ApiError {
+            code: 400,
+            message: "Invalid address format".to_string(),
+            details: Some(format!("{e}")),
+        }
+    })
+}
+
 async fn get_balance(
     State(state): State<AppState>,
     AxumPath(address): AxumPath<String>,
 ) -> Result<Json<u64>, ApiError> {
-    if !Regex::new(ADDRESS_REGEX).unwrap().is_match(&address) {
-        warn!("Invalid address format for balance check: {address}");
-        return Err(ApiError {
-            code: 400,
-            message: "Invalid address format".to_string(),
-            details: None,
-        });
-    }
+    let address = parse_address(&address, state.address_hrp, "balance check")?;
     let utxos_read_guard = state.utxos.read().await;
     let balance = utxos_read_guard
         .values()
@@ -849,39 +1158,74 @@ async fn get_balance(
 async fn get_utxos(
     State(state): State<AppState>,
     AxumPath(address): AxumPath<String>,
-) -> Result<Json<HashMap<String, UTXO>>, ApiError> {
-    if !Regex::new(ADDRESS_REGEX).unwrap().is_match(&address) {
-        warn!("Invalid address format for UTXO fetch: {address}");
-        return Err(ApiError {
-            code: 400,
-            message: "Invalid address format".to_string(),
-            details: None,
-        });
-    }
-    let utxos_read_guard = state.utxos.read().await;
-    let filtered_utxos_map = utxos_read_guard
+) -> Result<Json<AddressUtxos>, ApiError> {
+    let address = parse_address(&address, state.address_hrp, "UTXO fetch")?;
+    let filtered_utxos: Vec<(OutPoint, UTXO)> = state
+        .utxos
+        .read()
+        .await
         .iter()
         .filter(|(_, utxo_item)| utxo_item.address == address)
-        .map(|(key_str, value_utxo)| (key_str.clone(), value_utxo.clone()))
+        .map(|(outpoint, value_utxo)| (*outpoint, value_utxo.clone()))
         .collect();
-    Ok(Json(filtered_utxos_map))
+    Ok(Json(state.dag.partition_utxos(filtered_utxos).await))
+}
+
+async fn get_utxo(
+    State(state): State<AppState>,
+    AxumPath(utxo_id): AxumPath<String>,
+) -> Result<Json<UTXO>, StatusCode> {
+    let utxo_id: OutPoint = utxo_id.parse().map_err(|_| {
+        warn!("Invalid UTXO ID: {utxo_id}");
+        StatusCode::BAD_REQUEST
+    })?;
+    let utxos_read_guard = state.utxos.read().await;
+    let utxo = utxos_read_guard
+        .get(&utxo_id)
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(utxo))
+}
+
+async fn get_utxo_proof(
+    State(state): State<AppState>,
+    AxumPath(utxo_id): AxumPath<String>,
+) -> Result<Json<UtxoStateProof>, StatusCode> {
+    let utxo_id: OutPoint = utxo_id.parse().map_err(|_| {
+        warn!("Invalid UTXO ID: {utxo_id}");
+        StatusCode::BAD_REQUEST
+    })?;
+    let utxos_read_guard = state.utxos.read().await;
+    let proof = utxos_read_guard
+        .prove(&utxo_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(UtxoStateProof {
+        state_root: utxos_read_guard.state_root(),
+        proof,
+    }))
+}
+
+async fn get_swap(
+    State(state): State<AppState>,
+    AxumPath(swap_id): AxumPath<String>,
+) -> Result<Json<CrossChainSwap>, StatusCode> {
+    if swap_id.len() > 160 || swap_id.is_empty() {
+        warn!("Invalid swap ID length: {swap_id}");
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let swaps_read_guard = state.dag.cross_chain_swaps.read().await;
+    let swap = swaps_read_guard
+        .get(&swap_id)
+        .cloned()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(swap))
 }
 
 async fn submit_transaction(
     State(state): State<AppState>,
     Json(tx_data): Json<Transaction>,
-) -> Result<Json<String>, ApiError> {
-    let tx_hash_bytes = match hex::decode(&tx_data.id) {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return Err(ApiError {
-                code: 400,
-                message: "Invalid transaction ID format".to_string(),
-                details: None,
-            })
-        }
-    };
-    let tx_hash = H256::from_slice(&tx_hash_bytes);
+) -> Result<Json<TxId>, ApiError> {
+    let tx_hash = H256::from(*tx_data.id.as_bytes());
     if !reflect_on_action(tx_hash).await {
         error!("ΛΣ-ΩMEGA rejected transaction {}", tx_data.id);
         return Err(ApiError {
@@ -891,18 +1235,36 @@ async fn submit_transaction(
         });
     }
     let utxos_read_guard = state.utxos.read().await;
-    if let Err(e) = tx_data.verify(&state.dag, &utxos_read_guard).await {
-        warn!(
-            "Transaction {} failed verification via API: {}",
-            tx_data.id, e
-        );
-        return Err(ApiError {
-            code: 400,
-            message: "Transaction verification failed".to_string(),
-            details: Some(e.to_string()),
-        });
+    let check_result = state
+        .mempool
+        .read()
+        .await
+        .check_transaction(&tx_data, &utxos_read_guard, &state.dag)
+        .await;
+    drop(utxos_read_guard);
+    match check_result {
+        Ok(()) => {}
+        Err(MempoolError::Tx(e)) => {
+            warn!(
+                "Transaction {} failed verification via API: {}",
+                tx_data.id, e
+            );
+            return Err(ApiError {
+                code: 400,
+                message: "Transaction verification failed".to_string(),
+                details: Some(e.to_string()),
+            });
+        }
+        Err(e) => {
+            warn!("Transaction {} rejected by mempool: {}", tx_data.id, e);
+            return Err(ApiError {
+                code: 409,
+                message: "Transaction rejected by mempool".to_string(),
+                details: Some(e.to_string()),
+            });
+        }
     }
-    let tx_id = tx_data.id.clone();
+    let tx_id = tx_data.id;
     if let Err(e) = state
         .p2p_command_sender
         .send(P2PCommand::BroadcastTransaction(tx_data))
@@ -925,19 +1287,195 @@ async fn submit_transaction(
 async fn get_block(
     State(state): State<AppState>,
     AxumPath(id_str): AxumPath<String>,
-) -> Result<Json<QantoBlock>, StatusCode> {
-    if id_str.len() > 128 || id_str.is_empty() {
-        warn!("Invalid block ID length: {id_str}");
-        return Err(StatusCode::BAD_REQUEST);
+) -> Result<Json<QantoBlock>, ApiError> {
+    let block_id: BlockHash = id_str.parse().map_err(|e| {
+        warn!("Invalid block ID: {id_str}");
+        ApiError {
+            code: 400,
+            message: "Invalid block ID".to_string(),
+            details: Some(format!("{e}")),
+        }
+    })?;
+    if state.dag.is_pruned(&block_id).await {
+        return Err(ApiError {
+            code: 410,
+            message: "Block body pruned".to_string(),
+            details: Some(format!(
+                "Block {block_id} is older than the bodies this pruned node keeps; query an archive node."
+            )),
+        });
     }
     let blocks_read_guard = state.dag.blocks.read().await;
     let block_data = blocks_read_guard
-        .get(&id_str)
+        .get(&block_id)
         .cloned()
-        .ok_or(StatusCode::NOT_FOUND)?;
+        .ok_or_else(|| ApiError {
+            code: 404,
+            message: "Block not found".to_string(),
+            details: None,
+        })?;
     Ok(Json(block_data))
 }
 
+/// Runs a query against the history indexes off the async runtime.
+async fn query_index<T: Send + 'static>(
+    state: &AppState,
+    query: impl FnOnce(&dyn StateStore) -> Result<T, IndexError> + Send + 'static,
+) -> Result<T, ApiError> {
+    if !state.dag.tx_index {
+        return Err(ApiError {
+            code: 501,
+            message: "History indexes disabled".to_string(),
+            details: Some(
+                "Enable [index] in the node configuration and run `qanto reindex`.".to_string(),
+            ),
+        });
+    }
+    let store = state.dag.store.clone();
+    let internal_error = |details: String| ApiError {
+        code: 500,
+        message: "Index query failed".to_string(),
+        details: Some(details),
+    };
+    tokio::task::spawn_blocking(move || query(store.as_ref()))
+        .await
+        .map_err(|e| internal_error(e.to_string()))?
+        .map_err(|e| internal_error(e.to_string()))
+}
+
+async fn get_transaction(
+    State(state): State<AppState>,
+    AxumPath(id_str): AxumPath<String>,
+) -> Result<Json<TxLookup>, ApiError> {
+    let tx_id: TxId = id_str.parse().map_err(|e| ApiError {
+        code: 400,
+        message: "Invalid transaction ID".to_string(),
+        details: Some(format!("{e}")),
+    })?;
+    let (location, transaction) = query_index(&state, move |store| {
+        tx_index::lookup_transaction(store, &tx_id)
+    })
+    .await?
+    .ok_or_else(|| ApiError {
+        code: 404,
+        message: "Transaction not found".to_string(),
+        details: None,
+    })?;
+    let transaction = transaction.ok_or_else(|| ApiError {
+        code: 410,
+        message: "Block body pruned".to_string(),
+        details: Some(format!(
+            "Transaction {tx_id} is in block {}, whose body this pruned node no longer keeps; query an archive node.",
+            location.block_id
+        )),
+    })?;
+    Ok(Json(TxLookup {
+        location,
+        transaction,
+    }))
+}
+
+async fn get_address_history(
+    State(state): State<AppState>,
+    AxumPath(address): AxumPath<String>,
+    Query(params): Query<HistoryQuery>,
+) -> Result<Json<Vec<AddressTx>>, ApiError> {
+    let address = parse_address(&address, state.address_hrp, "history lookup")?;
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .min(MAX_HISTORY_LIMIT);
+    let history = query_index(&state, move |store| {
+        tx_index::address_history(store, &address, limit)
+    })
+    .await?;
+    Ok(Json(history))
+}
+
+async fn get_spender(
+    State(state): State<AppState>,
+    AxumPath(utxo_id): AxumPath<String>,
+) -> Result<Json<TxId>, ApiError> {
+    let outpoint: OutPoint = utxo_id.parse().map_err(|e| ApiError {
+        code: 400,
+        message: "Invalid UTXO ID".to_string(),
+        details: Some(format!("{e}")),
+    })?;
+    let spender = query_index(&state, move |store| tx_index::spender(store, &outpoint))
+        .await?
+        .ok_or_else(|| ApiError {
+            code: 404,
+            message: "Output not spent".to_string(),
+            details: None,
+        })?;
+    Ok(Json(spender))
+}
+
+/// Proves a transaction's inclusion. With the transaction index enabled the
+/// block is looked up directly, including blocks no longer held in memory;
+/// otherwise the in-memory blocks are scanned.
+async fn get_tx_proof(
+    State(state): State<AppState>,
+    AxumPath(id_str): AxumPath<String>,
+) -> Result<Json<TxInclusionProof>, ApiError> {
+    let tx_id: TxId = id_str.parse().map_err(|e| ApiError {
+        code: 400,
+        message: "Invalid transaction ID".to_string(),
+        details: Some(format!("{e}")),
+    })?;
+    let not_found = || ApiError {
+        code: 404,
+        message: "Transaction not found".to_string(),
+        details: None,
+    };
+    let block = if state.dag.tx_index {
+        let location = query_index(&state, move |store| tx_index::tx_location(store, &tx_id))
+            .await?
+            .ok_or_else(not_found)?;
+        let cached = state
+            .dag
+            .blocks
+            .read()
+            .await
+            .get(&location.block_id)
+            .cloned();
+        match cached {
+            Some(block) => Some(block),
+            None => {
+                query_index(&state, move |store| Ok(store.block(&location.block_id)?))
+                    .await?
+            }
+        }
+        .ok_or_else(|| ApiError {
+            code: 410,
+            message: "Block body pruned".to_string(),
+            details: Some(format!(
+                "Transaction {tx_id} is in block {}, whose body this pruned node no longer keeps; query an archive node.",
+                location.block_id
+            )),
+        })?
+    } else {
+        state
+            .dag
+            .blocks
+            .read()
+            .await
+            .values()
+            .find(|block| block.transactions.iter().any(|tx| tx.id == tx_id))
+            .cloned()
+            .ok_or_else(not_found)?
+    };
+    let proof = block.merkle_proof(&tx_id).ok_or_else(not_found)?;
+    Ok(Json(TxInclusionProof {
+        block_id: block.id,
+        chain_id: block.chain_id,
+        height: block.height,
+        merkle_root: block.merkle_root.clone(),
+        scheme: MerkleScheme::at_height(block.height),
+        proof,
+    }))
+}
+
 async fn get_dag(State(state): State<AppState>) -> Result<Json<DagInfo>, StatusCode> {
     let blocks_read_guard = state.dag.blocks.read().await;
     let tips_read_guard = state.dag.tips.read().await;
@@ -976,36 +1514,34 @@ async fn health_check() -> Result<Json<serde_json::Value>, StatusCode> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{LoggingConfig, P2pConfig};
+    use crate::config::{
+        IndexConfig, LoggingConfig, MempoolConfig, P2pConfig, RateLimitConfig, SnapshotConfig,
+    };
+    use crate::qantodag::{StorageMode, DEFAULT_COINBASE_MATURITY};
+    use crate::storage::MemoryStore;
     use crate::wallet::Wallet;
     use rand::Rng; // Import Rng trait
-    use serial_test::serial;
     use std::fs as std_fs;
 
     #[tokio::test]
-    #[serial]
     async fn test_node_creation_and_config_save() {
-        let db_path = "qantodag_db_test_node_creation";
-        if std::path::Path::new(db_path).exists() {
-            std_fs::remove_dir_all(db_path).unwrap();
-        }
         let _ = tracing_subscriber::fmt::try_init();
         let wallet = Wallet::new().expect("Failed to create new wallet for test");
         let wallet_arc = Arc::new(wallet);
         let genesis_validator_addr = wallet_arc.address();
         let rand_id: u32 = rand::thread_rng().gen(); // Use thread_rng()
         let temp_config_path = format!("./temp_test_config_{rand_id}.toml");
-        let temp_identity_path = format!("./temp_p2p_identity_{rand_id}.key");
-        let temp_peer_cache_path = format!("./temp_peer_cache_{rand_id}.json");
+        let temp_data_dir = tempfile::tempdir().expect("Failed to create temp data dir");
         let test_config = Config {
             p2p_address: "/ip4/127.0.0.1/tcp/0".to_string(),
             local_full_p2p_address: None,
             api_address: "127.0.0.1:0".to_string(),
             peers: vec![],
-            genesis_validator: genesis_validator_addr.clone(),
+            genesis_validator: genesis_validator_addr,
             target_block_time: 60,
             difficulty: 1, // This is now a placeholder in config
             max_amount: 10_000_000_000,
+            coinbase_maturity: DEFAULT_COINBASE_MATURITY,
             use_gpu: false,
             zk_enabled: false,
             mining_threads: 1,
@@ -1015,25 +1551,33 @@ mod tests {
                 level: "debug".to_string(),
             },
             p2p: P2pConfig::default(),
+            mempool: MempoolConfig::default(),
+            snapshot: SnapshotConfig {
+                enabled: false,
+                ..SnapshotConfig::default()
+            },
+            storage: StorageMode::default(),
+            index: IndexConfig::default(),
+            rate_limits: RateLimitConfig::default(),
             network_id: "testnet".to_string(),
+            data_dir: temp_data_dir.path().to_string_lossy().into_owned(),
         };
         test_config
             .save(&temp_config_path)
             .expect("Failed to save initial temp config for test");
 
-        let node_instance_result = Node::new(
+        let node_instance_result = Node::with_store(
             test_config,
-            temp_config_path.clone(),
+            ConfigSources {
+                file: Some(temp_config_path.clone()),
+                ..ConfigSources::default()
+            },
             wallet_arc.clone(),
-            &temp_identity_path,
-            temp_peer_cache_path.clone(),
+            DataDir::new(temp_data_dir.path()),
+            Arc::new(MemoryStore::new()),
         )
         .await;
 
-        if std::path::Path::new(db_path).exists() {
-            std_fs::remove_dir_all(db_path).unwrap();
-        }
-
         assert!(
             node_instance_result.is_ok(),
             "Node::new failed: {:?}",
@@ -1041,7 +1585,20 @@ mod tests {
         );
 
         let _ = std_fs::remove_file(&temp_config_path);
-        let _ = std_fs::remove_file(&temp_identity_path);
-        let _ = std_fs::remove_file(&temp_peer_cache_path);
+    }
+
+    #[tokio::test]
+    async fn test_p2p_restart_ignores_api_rate_limit() {
+        let config = Config::default();
+        let (sender, mut updates) = watch::channel(config.clone());
+        let wait = Duration::from_millis(50);
+
+        sender.send_modify(|config| config.rate_limits.api_per_second += 1);
+        let changed = Node::p2p_settings_changed(&mut updates, &config.p2p, &config.rate_limits);
+        assert!(tokio::time::timeout(wait, changed).await.is_err());
+
+        sender.send_modify(|config| config.rate_limits.gossip_state_per_second += 1);
+        let changed = Node::p2p_settings_changed(&mut updates, &config.p2p, &config.rate_limits);
+        assert!(tokio::time::timeout(wait, changed).await.is_ok());
     }
 }
@@ -9,15 +9,19 @@
 //! - LINTING: Corrected `tracing` macro usage to skip non-debuggable keys,
 //!   resolving the `E0277` trait bound errors.
 
-use crate::config::P2pConfig;
+use crate::codec::{self, CodecError};
+use crate::config::{P2pConfig, RateLimitConfig};
 use crate::mempool::Mempool;
 use crate::node::PeerCache;
 // Corrected: Replaced `LatticeSignature` with `QuantumResistantSignature`.
-use crate::qantodag::{QantoBlock, QantoDAG, QuantumResistantSignature, UTXO};
+use crate::qantodag::{QantoBlock, QantoDAG, QuantumResistantSignature, StorageMode};
 use crate::saga::CarbonOffsetCredential;
+use crate::snapshot::SnapshotManifest;
 use crate::transaction::Transaction;
+use crate::types::BlockHash;
+use crate::utxo_set::UtxoSet;
 use futures::stream::StreamExt;
-use governor::{clock::DefaultClock, state::keyed::DashMapStateStore, Quota, RateLimiter};
+use governor::{clock::DefaultClock, state::keyed::DashMapStateStore, RateLimiter};
 use hmac::{Hmac, Mac};
 use libp2p::{
     gossipsub::{self, IdentTopic, MessageAuthenticity, ValidationMode},
@@ -29,13 +33,11 @@ use libp2p::{
     swarm::{NetworkBehaviour, SwarmEvent},
     yamux, Multiaddr, PeerId, Swarm, SwarmBuilder,
 };
-use nonzero_ext::nonzero;
 use prometheus::{register_int_counter, IntCounter};
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
 use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
-use std::env;
 use std::error::Error as StdError;
 use std::fs;
 use std::hash::Hash;
@@ -51,6 +53,7 @@ use tracing::{error, info, instrument, warn};
 const MAX_MESSAGE_SIZE: usize = 2_000_000;
 const MIN_PEERS_FOR_MESH: usize = 1;
 const DEFAULT_HMAC_SECRET: &str = "qanto_secret_key_for_p2p";
+const STORAGE_ADVERT_INTERVAL_SECS: u64 = 300;
 
 lazy_static::lazy_static! {
     static ref MESSAGES_SENT: IntCounter = register_int_counter!("p2p_messages_sent_total", "Total messages sent").unwrap();
@@ -72,6 +75,8 @@ pub enum P2PError {
     Multiaddr(#[from] libp2p::multiaddr::Error),
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+    #[error("Encoding error: {0}")]
+    Codec(#[from] CodecError),
     #[error("HMAC error")]
     Hmac,
     #[error("Invalid HMAC key length")]
@@ -134,13 +139,53 @@ pub struct NetworkMessage {
     signature: QuantumResistantSignature,
 }
 
+/// Which block bodies a peer can serve.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageAdvert {
+    pub mode: StorageMode,
+    /// Bodies at or below this height may have been pruned.
+    pub pruned_height: u64,
+}
+
+impl StorageAdvert {
+    pub async fn of(dag: &QantoDAG) -> Self {
+        Self {
+            mode: dag.storage_mode,
+            pruned_height: *dag.pruned_height.read().await,
+        }
+    }
+
+    /// Whether the peer still keeps block bodies at `height`.
+    pub fn serves(&self, height: u64) -> bool {
+        match self.mode {
+            StorageMode::Archive => true,
+            StorageMode::Pruned { .. } => height > self.pruned_height,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum NetworkMessageData {
     Block(QantoBlock),
     Transaction(Transaction),
-    State(HashMap<String, QantoBlock>, HashMap<String, UTXO>),
     StateRequest,
+    SnapshotManifest(Box<SnapshotManifest>),
+    SnapshotChunkRequest {
+        checkpoint: BlockHash,
+        index: u32,
+    },
+    SnapshotChunk {
+        checkpoint: BlockHash,
+        index: u32,
+        data: Vec<u8>,
+    },
     CarbonOffsetCredential(CarbonOffsetCredential),
+    StorageAdvert(StorageAdvert),
+    /// Asks `target` (any peer if `None`) for a block body.
+    BlockRequest {
+        block_id: BlockHash,
+        target: Option<Vec<u8>>,
+    },
 }
 
 impl NetworkMessage {
@@ -148,12 +193,12 @@ impl NetworkMessage {
     #[instrument(skip(signing_key, public_key))]
     fn new(
         data: NetworkMessageData,
+        hmac_secret: &str,
         signing_key: &pqcrypto_dilithium::dilithium5::SecretKey,
         public_key: &pqcrypto_dilithium::dilithium5::PublicKey,
     ) -> Result<Self, P2PError> {
-        let hmac_secret = Self::get_hmac_secret();
-        let serialized_data = serde_json::to_vec(&data)?;
-        let hmac = Self::compute_hmac(&serialized_data, &hmac_secret)?;
+        let serialized_data = codec::encode(&data)?;
+        let hmac = Self::compute_hmac(&serialized_data, hmac_secret)?;
 
         let signature = QuantumResistantSignature::sign(signing_key, public_key, &serialized_data)
             .map_err(|e| P2PError::QuantumSignature(e.to_string()))?;
@@ -165,15 +210,6 @@ impl NetworkMessage {
         })
     }
 
-    fn get_hmac_secret() -> String {
-        dotenvy::dotenv().ok();
-        let secret = env::var("HMAC_SECRET").unwrap_or_else(|_| DEFAULT_HMAC_SECRET.to_string());
-        if secret == DEFAULT_HMAC_SECRET {
-            warn!("SECURITY: Using default HMAC secret. This is not secure for production. Please set the HMAC_SECRET environment variable.");
-        }
-        secret
-    }
-
     fn compute_hmac(data: &[u8], secret: &str) -> Result<Vec<u8>, P2PError> {
         let mut hmac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
             .map_err(|_| P2PError::HmacKeyLength)?;
@@ -186,15 +222,28 @@ impl NetworkMessage {
 pub enum P2PCommand {
     BroadcastBlock(QantoBlock),
     BroadcastTransaction(Transaction),
+    /// Asks peers for their latest snapshot manifest.
     RequestState,
-    BroadcastState(HashMap<String, QantoBlock>, HashMap<String, UTXO>),
+    SnapshotManifest(Box<SnapshotManifest>),
+    RequestSnapshotChunk {
+        checkpoint: BlockHash,
+        index: u32,
+    },
+    SnapshotChunk {
+        checkpoint: BlockHash,
+        index: u32,
+        data: Vec<u8>,
+    },
     BroadcastCarbonCredential(CarbonOffsetCredential),
-    SyncResponse {
-        blocks: Vec<QantoBlock>,
-        utxos: HashMap<String, UTXO>,
+    /// Updates and re-announces which block bodies this node serves.
+    AdvertiseStorage(StorageAdvert),
+    /// Asks a peer that still keeps bodies at `height_hint` for a block.
+    FetchBlock {
+        block_id: BlockHash,
+        height_hint: u64,
     },
     RequestBlock {
-        block_id: String,
+        block_id: BlockHash,
         peer_id: PeerId,
     },
     SendBlockToOnePeer {
@@ -213,10 +262,11 @@ pub struct P2PConfig<'a> {
     pub initial_peers: Vec<String>,
     pub dag: Arc<QantoDAG>,
     pub mempool: Arc<RwLock<Mempool>>,
-    pub utxos: Arc<RwLock<HashMap<String, UTXO>>>,
+    pub utxos: Arc<RwLock<UtxoSet>>,
     pub proposals: Arc<RwLock<Vec<QantoBlock>>>,
     pub local_keypair: identity::Keypair,
     pub p2p_settings: P2pConfig,
+    pub rate_limits: RateLimitConfig,
     pub node_qr_sk: &'a pqcrypto_dilithium::dilithium5::SecretKey,
     pub node_qr_pk: &'a pqcrypto_dilithium::dilithium5::PublicKey,
     pub peer_cache_path: String,
@@ -225,11 +275,15 @@ pub struct P2PConfig<'a> {
 pub struct P2PServer {
     swarm: Swarm<NodeBehaviour>,
     topics: Vec<IdentTopic>,
+    hmac_secret: String,
+    rate_limits: RateLimitConfig,
     node_qr_sk: pqcrypto_dilithium::dilithium5::SecretKey,
     node_qr_pk: pqcrypto_dilithium::dilithium5::PublicKey,
     initial_peers_config: Vec<String>,
     peer_cache_path: String,
     p2p_command_sender: mpsc::Sender<P2PCommand>,
+    storage_advert: StorageAdvert,
+    peer_storage: Arc<RwLock<HashMap<PeerId, StorageAdvert>>>,
 }
 
 #[derive(Clone)]
@@ -249,6 +303,7 @@ impl P2PServer {
         let local_peer_id = PeerId::from(config.local_keypair.public());
         info!("P2PServer using Local P2P Peer ID: {}", local_peer_id);
 
+        let storage_advert = StorageAdvert::of(&config.dag).await;
         let store = MemoryStore::new(local_peer_id);
         let mut kademlia_behaviour = KadBehaviour::new(local_peer_id, store);
 
@@ -303,14 +358,23 @@ impl P2PServer {
             Self::dial_initial_peers(&mut swarm, &config.initial_peers).await;
         }
 
+        let hmac_secret = config.p2p_settings.hmac_secret.clone().unwrap_or_else(|| {
+            warn!("SECURITY: Using default HMAC secret. This is not secure for production. Please set QANTO_P2P__HMAC_SECRET or p2p.hmac_secret.");
+            DEFAULT_HMAC_SECRET.to_string()
+        });
+
         Ok(Self {
             swarm,
             topics,
+            hmac_secret,
+            rate_limits: config.rate_limits,
             node_qr_sk: *config.node_qr_sk,
             node_qr_pk: *config.node_qr_pk,
             initial_peers_config: config.initial_peers,
             peer_cache_path: config.peer_cache_path,
             p2p_command_sender,
+            storage_advert,
+            peer_storage: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
@@ -391,16 +455,26 @@ impl P2PServer {
     }
 
     #[instrument(skip(self, rx))]
-    pub async fn run(&mut self, mut rx: mpsc::Receiver<P2PCommand>) -> Result<(), P2PError> {
+    pub async fn run(&mut self, rx: &mut mpsc::Receiver<P2PCommand>) -> Result<(), P2PError> {
         let mut mesh_check_ticker = interval(Duration::from_secs(60));
         let mut peer_cache_ticker = interval(Duration::from_secs(300));
+        let mut storage_advert_ticker = interval(Duration::from_secs(STORAGE_ADVERT_INTERVAL_SECS));
         let blacklist = Arc::new(RwLock::new(HashSet::new()));
 
+        let limits = &self.rate_limits;
         let rate_limiters = GossipRateLimiters {
-            block: Arc::new(RateLimiter::keyed(Quota::per_second(nonzero!(10u32)))),
-            tx: Arc::new(RateLimiter::keyed(Quota::per_second(nonzero!(50u32)))),
-            state: Arc::new(RateLimiter::keyed(Quota::per_second(nonzero!(5u32)))),
-            credential: Arc::new(RateLimiter::keyed(Quota::per_second(nonzero!(20u32)))),
+            block: Arc::new(RateLimiter::keyed(RateLimitConfig::quota(
+                limits.gossip_blocks_per_second,
+            ))),
+            tx: Arc::new(RateLimiter::keyed(RateLimitConfig::quota(
+                limits.gossip_transactions_per_second,
+            ))),
+            state: Arc::new(RateLimiter::keyed(RateLimitConfig::quota(
+                limits.gossip_state_per_second,
+            ))),
+            credential: Arc::new(RateLimiter::keyed(RateLimitConfig::quota(
+                limits.gossip_credentials_per_second,
+            ))),
         };
 
         loop {
@@ -411,6 +485,8 @@ impl P2PServer {
                             let blacklist = blacklist.clone();
                             let p2p_sender = self.p2p_command_sender.clone();
                             let rate_limiters = rate_limiters.clone();
+                            let peer_storage = self.peer_storage.clone();
+                            let local_peer_id = *self.swarm.local_peer_id();
                             async move {
                                 Self::static_process_gossip_message(
                                     message,
@@ -418,6 +494,8 @@ impl P2PServer {
                                     blacklist,
                                     p2p_sender,
                                     rate_limiters,
+                                    peer_storage,
+                                    local_peer_id,
                                 )
                                 .await;
                             }
@@ -437,6 +515,12 @@ impl P2PServer {
                         warn!("Failed to save peer cache: {e}");
                     }
                 }
+                _ = storage_advert_ticker.tick() => {
+                    let advert = NetworkMessageData::StorageAdvert(self.storage_advert);
+                    if let Err(e) = self.broadcast_message(advert, 2, "storage advert").await {
+                        warn!("Failed to advertise storage mode: {e}");
+                    }
+                }
             }
         }
     }
@@ -500,10 +584,36 @@ impl P2PServer {
                 self.broadcast_message(NetworkMessageData::StateRequest, 2, "state request")
                     .await
             }
-            P2PCommand::BroadcastState(blocks, utxos) => {
-                self.broadcast_message(NetworkMessageData::State(blocks, utxos), 2, "state data")
+            P2PCommand::SnapshotManifest(manifest) => {
+                let log_info =
+                    format!("snapshot manifest at height {}", manifest.checkpoint.height);
+                self.broadcast_message(NetworkMessageData::SnapshotManifest(manifest), 2, &log_info)
                     .await
             }
+            P2PCommand::RequestSnapshotChunk { checkpoint, index } => {
+                self.broadcast_message(
+                    NetworkMessageData::SnapshotChunkRequest { checkpoint, index },
+                    2,
+                    &format!("request for snapshot chunk {index}"),
+                )
+                .await
+            }
+            P2PCommand::SnapshotChunk {
+                checkpoint,
+                index,
+                data,
+            } => {
+                self.broadcast_message(
+                    NetworkMessageData::SnapshotChunk {
+                        checkpoint,
+                        index,
+                        data,
+                    },
+                    2,
+                    &format!("snapshot chunk {index}"),
+                )
+                .await
+            }
             P2PCommand::BroadcastCarbonCredential(cred) => {
                 self.broadcast_message(
                     NetworkMessageData::CarbonOffsetCredential(cred.clone()),
@@ -512,7 +622,46 @@ impl P2PServer {
                 )
                 .await
             }
-            _ => Ok(()),
+            P2PCommand::AdvertiseStorage(advert) => {
+                self.storage_advert = advert;
+                self.broadcast_message(
+                    NetworkMessageData::StorageAdvert(advert),
+                    2,
+                    "storage advert",
+                )
+                .await
+            }
+            P2PCommand::FetchBlock {
+                block_id,
+                height_hint,
+            } => {
+                // Pruned peers drop old bodies, so only ask one known to keep them.
+                let target = {
+                    let peer_storage = self.peer_storage.read().await;
+                    peer_storage
+                        .iter()
+                        .filter(|(_, advert)| advert.serves(height_hint))
+                        .min_by_key(|(_, advert)| advert.mode != StorageMode::Archive)
+                        .map(|(peer_id, _)| peer_id.to_bytes())
+                };
+                self.broadcast_message(
+                    NetworkMessageData::BlockRequest { block_id, target },
+                    2,
+                    &format!("request for block {block_id}"),
+                )
+                .await
+            }
+            P2PCommand::SendBlockToOnePeer { peer_id, block } => {
+                // Gossip has no direct delivery; the requester picks the block up
+                // from the blocks topic.
+                self.broadcast_message(
+                    NetworkMessageData::Block(*block),
+                    0,
+                    &format!("requested block for peer {peer_id}"),
+                )
+                .await
+            }
+            P2PCommand::RequestBlock { .. } => Ok(()),
         }
     }
 
@@ -522,6 +671,8 @@ impl P2PServer {
         blacklist: Arc<RwLock<HashSet<PeerId>>>,
         p2p_command_sender: mpsc::Sender<P2PCommand>,
         rate_limiters: GossipRateLimiters,
+        peer_storage: Arc<RwLock<HashMap<PeerId, StorageAdvert>>>,
+        local_peer_id: PeerId,
     ) {
         if blacklist.read().await.contains(&source) {
             return;
@@ -550,18 +701,45 @@ impl P2PServer {
             return;
         }
 
-        if let Ok(msg_payload) = serde_json::from_slice::<NetworkMessage>(&message.data) {
+        if let Ok(msg_payload) =
+            codec::decode_with_limit::<NetworkMessage>(&message.data, MAX_MESSAGE_SIZE)
+        {
+            let origin = message.source.unwrap_or(source);
             let cmd = match msg_payload.data {
                 NetworkMessageData::Block(block) => P2PCommand::BroadcastBlock(block),
                 NetworkMessageData::Transaction(tx) => P2PCommand::BroadcastTransaction(tx),
-                NetworkMessageData::State(blocks, utxos) => P2PCommand::SyncResponse {
-                    blocks: blocks.into_values().collect(),
-                    utxos,
-                },
                 NetworkMessageData::StateRequest => P2PCommand::RequestState,
+                NetworkMessageData::SnapshotManifest(manifest) => {
+                    P2PCommand::SnapshotManifest(manifest)
+                }
+                NetworkMessageData::SnapshotChunkRequest { checkpoint, index } => {
+                    P2PCommand::RequestSnapshotChunk { checkpoint, index }
+                }
+                NetworkMessageData::SnapshotChunk {
+                    checkpoint,
+                    index,
+                    data,
+                } => P2PCommand::SnapshotChunk {
+                    checkpoint,
+                    index,
+                    data,
+                },
                 NetworkMessageData::CarbonOffsetCredential(cred) => {
                     P2PCommand::BroadcastCarbonCredential(cred)
                 }
+                NetworkMessageData::StorageAdvert(advert) => {
+                    peer_storage.write().await.insert(origin, advert);
+                    return;
+                }
+                NetworkMessageData::BlockRequest { block_id, target } => {
+                    if target.is_some_and(|target| target != local_peer_id.to_bytes()) {
+                        return;
+                    }
+                    P2PCommand::RequestBlock {
+                        block_id,
+                        peer_id: origin,
+                    }
+                }
             };
             if p2p_command_sender.send(cmd).await.is_err() {
                 error!("Failed to forward message to command processor");
@@ -597,8 +775,16 @@ impl P2PServer {
         log_info: &str,
     ) -> Result<(), P2PError> {
         let topic = &self.topics[topic_index];
-        let net_msg = NetworkMessage::new(data, &self.node_qr_sk, &self.node_qr_pk)?;
-        let msg_bytes = serde_json::to_vec(&net_msg)?;
+        let net_msg =
+            NetworkMessage::new(data, &self.hmac_secret, &self.node_qr_sk, &self.node_qr_pk)?;
+        let msg_bytes = codec::encode(&net_msg)?;
+        if msg_bytes.len() > MAX_MESSAGE_SIZE {
+            return Err(CodecError::TooLarge {
+                size: msg_bytes.len(),
+                limit: MAX_MESSAGE_SIZE,
+            }
+            .into());
+        }
 
         self.swarm
             .behaviour_mut()
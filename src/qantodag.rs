@@ -6,13 +6,24 @@
 //!   in the error enum, but the compiler was incorrectly flagging it as unused.
 //!   The import is kept as it is necessary for the build.
 
-use crate::emission::Emission;
+use crate::codec::{self, CodecError};
+use crate::emission::{Emission, SupplyAudit};
+use crate::fee_estimator::FeeEstimator;
+use crate::fee_policy::{FeePolicy, FeePolicyError};
+use crate::htlc::{self, HashTimeLock, SWAP_DB_PREFIX};
 use crate::mempool::Mempool;
+use crate::merkle::{self, MerkleProof, MerkleScheme};
 use crate::miner::Miner;
 use crate::saga::{
     CarbonOffsetCredential, GovernanceProposal, PalletSaga, ProposalStatus, ProposalType,
 };
-use crate::transaction::{Output, Transaction};
+use crate::schema::{self, SchemaError};
+use crate::snapshot::SnapshotMeta;
+use crate::storage::{StateStore, StoreBatch, StoreError};
+use crate::transaction::{self, Output, SpendContext, Transaction};
+use crate::tx_index;
+use crate::types::{Address, BlockHash, OutPoint, TxId};
+use crate::utxo_set::{self, UtxoChange, UtxoSet, UtxoSetError};
 use crate::wallet::Wallet;
 use hex;
 use lru::LruCache;
@@ -22,10 +33,10 @@ use primitive_types::U256;
 use prometheus::{register_int_counter, IntCounter};
 use rand::Rng;
 use rayon::prelude::*;
-use rocksdb::DB;
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
 use std::fmt;
 use std::num::NonZeroUsize;
 use std::sync::{Arc, Weak};
@@ -38,22 +49,36 @@ use uuid::Uuid;
 
 pub const MAX_BLOCK_SIZE: usize = 20_000_000;
 pub const MAX_TRANSACTIONS_PER_BLOCK: usize = 25_000;
-pub const DEV_ADDRESS: &str = "74fd2aae70ae8e0930b87a3dcb3b77f5b71d956659849f067360d3486604db41";
-pub const DEV_FEE_RATE: f64 = 0.0304;
+pub use crate::fee_policy::{DEV_ADDRESS, DEV_FEE_RATE};
 
-const FINALIZATION_DEPTH: u64 = 8;
+/// Blocks a chain must grow past a coinbase before a block may spend its
+/// outputs, on networks whose genesis configuration does not set it.
+pub const DEFAULT_COINBASE_MATURITY: u64 = 100;
+
+fn default_coinbase_maturity() -> u64 {
+    DEFAULT_COINBASE_MATURITY
+}
+
+/// Length of the first-parent path from a tip that finalizes its blocks.
+pub const FINALIZATION_DEPTH: u64 = 8;
 const SHARD_THRESHOLD: u32 = 2;
 const TEMPORAL_CONSENSUS_WINDOW: u64 = 600;
 const MAX_BLOCKS_PER_MINUTE: u64 = 32 * 60; // Adjusted for high throughput
 const MIN_VALIDATOR_STAKE: u64 = 50;
 const SLASHING_PENALTY: u64 = 30;
 const CACHE_SIZE: usize = 1_000;
+const SIGNATURE_CACHE_SIZE: usize = 100_000;
 const ANOMALY_DETECTION_BASELINE_BLOCKS: usize = 100;
 const ANOMALY_Z_SCORE_THRESHOLD: f64 = 3.5;
 const ASERT_IDEAL_BLOCK_TIME: i64 = 2; // Target block time in seconds (for ~32 BPS, this is aggressive)
 const ASERT_HALFLIFE: i64 = 172800; // Halflife for adjustment (2 days), controls responsiveness.
 const INITIAL_DIFFICULTY: u64 = 1;
 
+/// Key prefix of the `BlockHeader` kept for a block whose body was pruned.
+pub const PRUNED_DB_PREFIX: &str = "pruned:";
+/// Key prefix of the consensus fee policy recorded for an epoch.
+pub const FEE_POLICY_DB_PREFIX: &str = "feepolicy:";
+
 lazy_static::lazy_static! {
     static ref BLOCKS_PROCESSED: IntCounter = register_int_counter!("blocks_processed_total", "Total blocks processed").unwrap();
     static ref TRANSACTIONS_PROCESSED: IntCounter = register_int_counter!("transactions_processed_total", "Total transactions processed").unwrap();
@@ -62,11 +87,77 @@ lazy_static::lazy_static! {
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct UTXO {
-    pub address: String,
+    pub address: Address,
     pub amount: u64,
-    pub tx_id: String,
+    pub tx_id: TxId,
     pub output_index: u32,
     pub explorer_link: String,
+    #[serde(default)]
+    pub htlc: Option<HashTimeLock>,
+    /// Set when the output was minted by a block's coinbase transaction.
+    #[serde(default)]
+    pub coinbase: Option<CoinbaseOrigin>,
+}
+
+/// The block that minted a coinbase output, used to check its maturity.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CoinbaseOrigin {
+    pub block_id: BlockHash,
+    pub chain_id: u32,
+    pub height: u64,
+    /// The coinbase maturity the minting block committed to.
+    pub maturity: u64,
+}
+
+impl CoinbaseOrigin {
+    /// Blocks still to come before a block at `spend_height` may spend the
+    /// outputs; zero once they are mature.
+    pub fn blocks_remaining(&self, spend_height: u64) -> u64 {
+        (self.height + self.maturity).saturating_sub(spend_height)
+    }
+
+    /// Whether a block at `spend_height` may spend the outputs.
+    pub fn is_mature_at(&self, spend_height: u64) -> bool {
+        self.blocks_remaining(spend_height) == 0
+    }
+}
+
+/// Which block bodies a node keeps once they are finalized.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum StorageMode {
+    /// Keep every block body and its undo data.
+    #[default]
+    Archive,
+    /// Keep headers, but drop the bodies and undo data of blocks finalized
+    /// more than `keep_epochs` epochs ago.
+    Pruned { keep_epochs: u64 },
+}
+
+/// The lowest height and the epoch at which a new block could still be added.
+/// A transaction expired for this window can no longer be mined on any chain.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InclusionWindow {
+    pub next_height: u64,
+    pub epoch: u64,
+}
+
+/// A coinbase output that cannot be spent yet.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ImmatureUtxo {
+    #[serde(flatten)]
+    pub utxo: UTXO,
+    /// Blocks left until the output can be spent in the next block.
+    pub blocks_remaining: u64,
+}
+
+/// The outputs owned by an address, split by whether they can be spent now.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct AddressUtxos {
+    pub spendable: HashMap<OutPoint, UTXO>,
+    pub immature: HashMap<OutPoint, ImmatureUtxo>,
+    pub spendable_balance: u64,
+    pub immature_balance: u64,
 }
 
 #[derive(Error, Debug)]
@@ -77,16 +168,26 @@ pub enum QantoDAGError {
     InvalidTransaction(#[from] crate::transaction::TransactionError),
     #[error("Invalid parent: {0}")]
     InvalidParent(String),
+    #[error("Parent block {0} not found")]
+    MissingParent(BlockHash),
     #[error("System time error: {0}")]
     Time(#[from] SystemTimeError),
     #[error("Cross-chain reference error: {0}")]
     CrossChainReferenceError(String),
     #[error("Reward mismatch: expected {0}, got {1}")]
     RewardMismatch(u64, u64),
+    #[error("Block mints {minted}, but the scheduled reward is {scheduled}")]
+    MintMismatch { minted: u64, scheduled: u64 },
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
+    #[error("Encoding error: {0}")]
+    Codec(#[from] CodecError),
+    #[error("UTXO set error: {0}")]
+    UtxoSet(#[from] UtxoSetError),
     #[error("Merkle root mismatch")]
     MerkleRootMismatch,
+    #[error("UTXO state root mismatch: block commits to {committed}, expected {computed}")]
+    StateRootMismatch { committed: String, computed: String },
     #[error("ZKP verification failed: {0}")]
     ZKPVerification(String),
     #[error("Governance proposal failed: {0}")]
@@ -113,14 +214,18 @@ pub enum QantoDAGError {
     SagaError(#[from] anyhow::Error),
     #[error("QantoDAG self-reference not initialized. This indicates a critical bug in the node startup sequence.")]
     SelfReferenceNotInitialized,
-    #[error("RocksDB error: {0}")]
-    RocksDB(#[from] rocksdb::Error),
+    #[error("Storage error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Database schema error: {0}")]
+    Schema(#[from] SchemaError),
     #[error("Miner error: {0}")]
     MinerError(String),
     #[error("Hex decoding error: {0}")]
     HexError(#[from] hex::FromHexError),
     #[error("Wallet error: {0}")]
     WalletError(String),
+    #[error("Fee policy violated: {0}")]
+    FeePolicy(#[from] FeePolicyError),
 }
 
 impl From<crate::wallet::WalletError> for QantoDAGError {
@@ -130,41 +235,39 @@ impl From<crate::wallet::WalletError> for QantoDAGError {
 }
 
 pub struct SigningData<'a> {
-    pub parents: &'a [String],
+    pub parents: &'a [BlockHash],
+    pub cross_chain_references: &'a [(u32, BlockHash)],
     pub transactions: &'a [Transaction],
     pub timestamp: u64,
     pub difficulty: u64,
-    pub validator: &'a str,
-    pub miner: &'a str,
+    pub validator: &'a Address,
+    pub miner: &'a Address,
     pub chain_id: u32,
     pub merkle_root: &'a str,
     pub height: u64,
+    pub fee_policy: &'a FeePolicy,
+    pub coinbase_maturity: u64,
+    pub utxo_root: &'a str,
 }
 
 pub struct QantoBlockCreationData<'a> {
     pub chain_id: u32,
-    pub parents: Vec<String>,
+    pub parents: Vec<BlockHash>,
+    /// Tips of other chains the block builds on, besides its parents.
+    pub cross_chain_references: Vec<(u32, BlockHash)>,
     pub transactions: Vec<Transaction>,
     pub difficulty: u64,
-    pub validator: String,
-    pub miner: String,
+    pub validator: Address,
+    pub miner: Address,
     pub qr_signing_key: &'a dilithium5::SecretKey,
     pub qr_public_key: &'a dilithium5::PublicKey,
     pub timestamp: u64,
     pub current_epoch: u64,
     pub height: u64,
-}
-
-#[derive(Debug)]
-pub struct CrossChainSwapParams {
-    pub source_chain: u32,
-    pub target_chain: u32,
-    pub source_block_id: String,
-    pub amount: u64,
-    pub initiator: String,
-    pub responder: String,
-    pub timelock_duration: u64,
-    pub secret_hash: String,
+    pub fee_policy: FeePolicy,
+    pub coinbase_maturity: u64,
+    /// UTXO state root of the block's past after its transactions are applied.
+    pub utxo_root: String,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -237,8 +340,8 @@ pub struct CrossChainSwap {
     pub source_chain: u32,
     pub target_chain: u32,
     pub amount: u64,
-    pub initiator: String,
-    pub responder: String,
+    pub initiator: Address,
+    pub responder: Address,
     pub timelock: u64,
     pub state: SwapState,
     pub secret_hash: String,
@@ -256,6 +359,7 @@ pub enum SwapState {
 pub struct SmartContract {
     pub contract_id: String,
     pub code: String,
+    #[serde(serialize_with = "codec::sorted_map")]
     pub storage: HashMap<String, String>,
     pub owner: String,
     pub gas_balance: u64,
@@ -305,19 +409,20 @@ impl SmartContract {
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct QantoBlock {
     pub chain_id: u32,
-    pub id: String,
-    pub parents: Vec<String>,
+    pub id: BlockHash,
+    pub parents: Vec<BlockHash>,
     pub transactions: Vec<Transaction>,
     pub difficulty: u64,
-    pub validator: String,
-    pub miner: String,
+    pub validator: Address,
+    pub miner: Address,
     pub nonce: u64,
     pub timestamp: u64,
     pub height: u64,
     pub reward: u64,
     pub effort: u64,
-    pub cross_chain_references: Vec<(u32, String)>,
+    pub cross_chain_references: Vec<(u32, BlockHash)>,
     pub cross_chain_swaps: Vec<CrossChainSwap>,
+    #[serde(with = "codec::compact_hex")]
     pub merkle_root: String,
     pub qr_signature: QuantumResistantSignature,
     pub homomorphic_encrypted: Vec<HomomorphicEncrypted>,
@@ -325,6 +430,15 @@ pub struct QantoBlock {
     #[serde(default)]
     pub carbon_credentials: Vec<CarbonOffsetCredential>,
     pub epoch: u64,
+    /// Fee policy the block was built under, committed in the signed data.
+    #[serde(default)]
+    pub fee_policy: FeePolicy,
+    /// The network's coinbase maturity, committed in the signed data.
+    #[serde(default = "default_coinbase_maturity")]
+    pub coinbase_maturity: u64,
+    /// Root of the UTXO set after this block, committed in the signed data.
+    #[serde(default, with = "codec::compact_hex")]
+    pub utxo_root: String,
 }
 
 impl fmt::Display for QantoBlock {
@@ -346,7 +460,11 @@ impl fmt::Display for QantoBlock {
             if self.parents.is_empty() {
                 "(Genesis Block)".to_string()
             } else {
-                self.parents.join(", ")
+                self.parents
+                    .iter()
+                    .map(BlockHash::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
             }
         )?;
         writeln!(f, "║ 🧾 Transactions:   {}", self.transactions.len())?;
@@ -359,6 +477,7 @@ impl fmt::Display for QantoBlock {
             writeln!(f, "║ 🌍 Carbon Offset:  {total_offset:.4} tonnes CO₂e")?;
         }
         writeln!(f, "║ 🌳 Merkle Root:    {}", self.merkle_root)?;
+        writeln!(f, "║ 🗃️  UTXO Root:      {}", self.utxo_root)?;
         writeln!(f, "╟─ Mining Details ─{}╢", "─".repeat(70))?;
         writeln!(f, "║ ⛏️  Miner:           {}", self.miner)?;
         writeln!(f, "║ ✨ Nonce:          {}", self.nonce)?;
@@ -374,10 +493,11 @@ impl QantoBlock {
     #[instrument(skip(data))]
     pub fn new(data: QantoBlockCreationData) -> Result<Self, QantoDAGError> {
         let nonce = 0;
-        let merkle_root = Self::compute_merkle_root(&data.transactions)?;
+        let merkle_root = Self::compute_merkle_root(&data.transactions, data.height)?;
 
         let signing_data = SigningData {
             parents: &data.parents,
+            cross_chain_references: &data.cross_chain_references,
             transactions: &data.transactions,
             timestamp: data.timestamp,
             difficulty: data.difficulty,
@@ -386,10 +506,13 @@ impl QantoBlock {
             chain_id: data.chain_id,
             merkle_root: &merkle_root,
             height: data.height,
+            fee_policy: &data.fee_policy,
+            coinbase_maturity: data.coinbase_maturity,
+            utxo_root: &data.utxo_root,
         };
 
         let pre_signature_data_for_id = Self::serialize_for_signing(&signing_data)?;
-        let id = hex::encode(Keccak256::digest(&pre_signature_data_for_id));
+        let id = BlockHash::new(Keccak256::digest(&pre_signature_data_for_id).into());
 
         let qr_signature = QuantumResistantSignature::sign(
             data.qr_signing_key,
@@ -416,7 +539,7 @@ impl QantoBlock {
             height: data.height,
             reward: 0,
             effort: 0,
-            cross_chain_references: vec![],
+            cross_chain_references: data.cross_chain_references,
             merkle_root,
             qr_signature,
             cross_chain_swaps: vec![],
@@ -424,6 +547,9 @@ impl QantoBlock {
             smart_contracts: vec![],
             carbon_credentials: vec![],
             epoch: data.current_epoch,
+            fee_policy: data.fee_policy,
+            coinbase_maturity: data.coinbase_maturity,
+            utxo_root: data.utxo_root,
         })
     }
 
@@ -432,60 +558,96 @@ impl QantoBlock {
         hasher.update(data.chain_id.to_le_bytes());
         hasher.update(data.merkle_root.as_bytes());
         for parent in data.parents {
-            hasher.update(parent.as_bytes());
+            hasher.update(parent.hex_bytes());
+        }
+        for (chain_id, reference) in data.cross_chain_references {
+            hasher.update(chain_id.to_le_bytes());
+            hasher.update(reference.hex_bytes());
         }
         for tx in data.transactions {
-            hasher.update(tx.id.as_bytes());
+            hasher.update(tx.id.hex_bytes());
         }
         hasher.update(data.timestamp.to_be_bytes());
         hasher.update(data.difficulty.to_be_bytes());
         hasher.update(data.height.to_be_bytes());
-        hasher.update(data.validator.as_bytes());
-        hasher.update(data.miner.as_bytes());
+        hasher.update(data.validator.hex_bytes());
+        hasher.update(data.miner.hex_bytes());
+        hasher.update(data.fee_policy.commitment());
+        hasher.update(data.coinbase_maturity.to_be_bytes());
+        hasher.update(data.utxo_root.as_bytes());
         Ok(hasher.finalize().to_vec())
     }
 
+    /// The Merkle root of `transactions` in a block at `height`, under the
+    /// tree layout in force at that height.
     #[instrument]
-    pub fn compute_merkle_root(transactions: &[Transaction]) -> Result<String, QantoDAGError> {
-        if transactions.is_empty() {
-            return Ok(hex::encode(Keccak256::digest([])));
-        }
-        let mut leaves: Vec<Vec<u8>> = transactions
-            .par_iter()
-            .map(|tx| Keccak256::digest(tx.id.as_bytes()).to_vec())
-            .collect();
+    pub fn compute_merkle_root(
+        transactions: &[Transaction],
+        height: u64,
+    ) -> Result<String, QantoDAGError> {
+        let tx_ids: Vec<TxId> = transactions.iter().map(|tx| tx.id).collect();
+        Ok(merkle::merkle_root(
+            &tx_ids,
+            MerkleScheme::at_height(height),
+        ))
+    }
 
-        while leaves.len() > 1 {
-            if leaves.len() % 2 != 0 {
-                leaves.push(leaves.last().unwrap().clone());
-            }
-            leaves = leaves
-                .par_chunks(2)
-                .map(|chunk| {
-                    let mut hasher = Keccak256::new();
-                    hasher.update(&chunk[0]);
-                    hasher.update(&chunk[1]);
-                    hasher.finalize().to_vec()
-                })
-                .collect();
+    /// Proves that the transaction `tx_id` is committed to by `merkle_root`.
+    pub fn merkle_proof(&self, tx_id: &TxId) -> Option<MerkleProof> {
+        let tx_ids: Vec<TxId> = self.transactions.iter().map(|tx| tx.id).collect();
+        let index = tx_ids.iter().position(|id| id == tx_id)?;
+        merkle::merkle_proof(&tx_ids, index, MerkleScheme::at_height(self.height))
+    }
+
+    /// The context in which the block's transactions are checked.
+    pub fn spend_context(&self) -> SpendContext {
+        SpendContext {
+            height: self.height,
+            timestamp: self.timestamp,
+            fee_policy: self.fee_policy,
         }
-        Ok(hex::encode(leaves.first().ok_or_else(|| {
-            QantoDAGError::InvalidBlock("Merkle root computation failed".to_string())
-        })?))
+    }
+
+    /// The part of the reward newly issued by the coinbase, beyond the fees
+    /// of the block's transactions.
+    pub fn minted(&self) -> u64 {
+        let fees = self.transactions.iter().map(|tx| tx.fee).sum::<u64>();
+        self.reward.saturating_sub(fees)
+    }
+
+    /// The blocks this one builds on: its parents and cross-chain references.
+    /// Its state root covers exactly the past they lead back to.
+    pub fn links(&self) -> Vec<BlockHash> {
+        self.parents
+            .iter()
+            .copied()
+            .chain(self.cross_chain_references.iter().map(|(_, id)| *id))
+            .collect()
+    }
+
+    /// The changes the block makes to the UTXO set, in transaction order.
+    pub fn utxo_changes(&self) -> Vec<UtxoChange> {
+        utxo_changes(
+            &self.transactions,
+            CoinbaseOrigin {
+                block_id: self.id,
+                chain_id: self.chain_id,
+                height: self.height,
+                maturity: self.coinbase_maturity,
+            },
+        )
     }
 
     #[instrument]
     pub fn hash(&self) -> String {
-        let mut hasher = Keccak256::new();
-        hasher.update(self.id.as_bytes());
-        hasher.update(self.timestamp.to_be_bytes());
-        hasher.update(self.nonce.to_le_bytes());
-        hex::encode(hasher.finalize())
+        pow_hash(&self.id, self.timestamp, self.nonce)
     }
 
-    pub fn verify_signature(&self) -> Result<bool, QantoDAGError> {
+    /// The data the block's ID and signature are derived from.
+    fn signing_bytes(&self) -> Result<Vec<u8>, QantoDAGError> {
         let signing_data = SigningData {
             parents: &self.parents,
+            cross_chain_references: &self.cross_chain_references,
             transactions: &self.transactions,
             timestamp: self.timestamp,
             difficulty: self.difficulty,
@@ -494,20 +656,250 @@ impl QantoBlock {
             chain_id: self.chain_id,
             merkle_root: &self.merkle_root,
             height: self.height,
+            fee_policy: &self.fee_policy,
+            coinbase_maturity: self.coinbase_maturity,
+            utxo_root: &self.utxo_root,
         };
-        let data_to_verify = QantoBlock::serialize_for_signing(&signing_data)?;
+        QantoBlock::serialize_for_signing(&signing_data)
+    }
+
+    /// The ID the block's contents hash to, which a stored block must match.
+    pub fn computed_id(&self) -> Result<BlockHash, QantoDAGError> {
+        Ok(BlockHash::new(
+            Keccak256::digest(self.signing_bytes()?).into(),
+        ))
+    }
+
+    pub fn verify_signature(&self) -> Result<bool, QantoDAGError> {
+        Ok(self.qr_signature.verify(&self.signing_bytes()?))
+    }
+
+    /// Drops the body, keeping the header fields and their commitments.
+    /// The signature of a pruned block can no longer be re-verified.
+    pub fn prune_body(&mut self) {
+        self.transactions = Vec::new();
+        self.cross_chain_swaps = Vec::new();
+        self.homomorphic_encrypted = Vec::new();
+        self.smart_contracts = Vec::new();
+        self.carbon_credentials = Vec::new();
+    }
+}
+
+/// The proof-of-work hash of a block, which must meet its difficulty target.
+fn pow_hash(id: &BlockHash, timestamp: u64, nonce: u64) -> String {
+    let mut hasher = Keccak256::new();
+    hasher.update(id.hex_bytes());
+    hasher.update(timestamp.to_be_bytes());
+    hasher.update(nonce.to_le_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// The header fields of a block, stored under `PRUNED_DB_PREFIX` once its
+/// body is pruned. The signature commits to the body, so it is not kept.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BlockHeader {
+    pub chain_id: u32,
+    pub id: BlockHash,
+    pub parents: Vec<BlockHash>,
+    pub difficulty: u64,
+    pub validator: Address,
+    pub miner: Address,
+    pub nonce: u64,
+    pub timestamp: u64,
+    pub height: u64,
+    pub reward: u64,
+    pub effort: u64,
+    pub cross_chain_references: Vec<(u32, BlockHash)>,
+    #[serde(with = "codec::compact_hex")]
+    pub merkle_root: String,
+    pub epoch: u64,
+    pub fee_policy: FeePolicy,
+    pub coinbase_maturity: u64,
+    #[serde(with = "codec::compact_hex")]
+    pub utxo_root: String,
+}
+
+impl BlockHeader {
+    pub fn hash(&self) -> String {
+        pow_hash(&self.id, self.timestamp, self.nonce)
+    }
+}
+
+impl From<&QantoBlock> for BlockHeader {
+    fn from(block: &QantoBlock) -> Self {
+        Self {
+            chain_id: block.chain_id,
+            id: block.id,
+            parents: block.parents.clone(),
+            difficulty: block.difficulty,
+            validator: block.validator,
+            miner: block.miner,
+            nonce: block.nonce,
+            timestamp: block.timestamp,
+            height: block.height,
+            reward: block.reward,
+            effort: block.effort,
+            cross_chain_references: block.cross_chain_references.clone(),
+            merkle_root: block.merkle_root.clone(),
+            epoch: block.epoch,
+            fee_policy: block.fee_policy,
+            coinbase_maturity: block.coinbase_maturity,
+            utxo_root: block.utxo_root.clone(),
+        }
+    }
+}
 
-        Ok(self.qr_signature.verify(&data_to_verify))
+/// What `link_order` needs of the items it orders.
+pub(crate) trait Linked {
+    fn id(&self) -> BlockHash;
+    fn timestamp(&self) -> u64;
+    fn height(&self) -> u64;
+    /// The parents and cross-chain references.
+    fn links(&self) -> Vec<BlockHash>;
+}
+
+impl Linked for QantoBlock {
+    fn id(&self) -> BlockHash {
+        self.id
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    fn height(&self) -> u64 {
+        self.height
+    }
+
+    fn links(&self) -> Vec<BlockHash> {
+        QantoBlock::links(self)
+    }
+}
+
+impl Linked for BlockHeader {
+    fn id(&self) -> BlockHash {
+        self.id
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    fn height(&self) -> u64 {
+        self.height
+    }
+
+    fn links(&self) -> Vec<BlockHash> {
+        self.parents
+            .iter()
+            .copied()
+            .chain(self.cross_chain_references.iter().map(|(_, id)| *id))
+            .collect()
     }
 }
 
+/// The UTXO set changes of `transactions`, in order. Coinbase outputs record
+/// `origin` as the block that minted them.
+pub(crate) fn utxo_changes(
+    transactions: &[Transaction],
+    origin: CoinbaseOrigin,
+) -> Vec<UtxoChange> {
+    let mut changes = Vec::new();
+    for tx in transactions {
+        let coinbase_origin = tx.is_coinbase().then(|| origin.clone());
+        changes.extend(
+            tx.inputs
+                .iter()
+                .map(|input| UtxoChange::Spend(input.outpoint())),
+        );
+        for (index, output) in tx.outputs.iter().enumerate() {
+            let utxo_id = OutPoint::new(tx.id, index as u32);
+            changes.push(UtxoChange::Create(
+                utxo_id,
+                Box::new(UTXO {
+                    address: output.address,
+                    amount: output.amount,
+                    tx_id: tx.id,
+                    output_index: index as u32,
+                    explorer_link: format!("https://qantoblockexplorer.org/utxo/{utxo_id}"),
+                    htlc: output.htlc.clone(),
+                    coinbase: coinbase_origin.clone(),
+                }),
+            ));
+        }
+    }
+    changes
+}
+
+/// `items` reordered so every block follows the blocks it links to, which is
+/// the order state roots build on. Among the blocks free to go next, the
+/// earliest `(timestamp, height, id)` goes first. Links to blocks outside
+/// `items` are ignored, and blocks caught in a cycle go last in that order.
+pub(crate) fn link_order<T, B: Linked>(items: Vec<T>, block: impl Fn(&T) -> &B) -> Vec<T> {
+    let positions: HashMap<BlockHash, usize> = items
+        .iter()
+        .enumerate()
+        .map(|(position, item)| (block(item).id(), position))
+        .collect();
+    let rank = |position: usize| {
+        let block = block(&items[position]);
+        Reverse((block.timestamp(), block.height(), block.id(), position))
+    };
+    let mut pending = vec![0usize; items.len()];
+    let mut children = vec![Vec::new(); items.len()];
+    for (position, item) in items.iter().enumerate() {
+        let mut links = block(item).links();
+        links.sort_unstable();
+        links.dedup();
+        for link in links {
+            match positions.get(&link) {
+                Some(&linked) if linked != position => {
+                    pending[position] += 1;
+                    children[linked].push(position);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut ready: BinaryHeap<_> = (0..items.len())
+        .filter(|&position| pending[position] == 0)
+        .map(rank)
+        .collect();
+    let mut order = Vec::with_capacity(items.len());
+    while let Some(Reverse((.., position))) = ready.pop() {
+        order.push(position);
+        for &child in &children[position] {
+            pending[child] -= 1;
+            if pending[child] == 0 {
+                ready.push(rank(child));
+            }
+        }
+    }
+    let mut cyclic: Vec<usize> = (0..items.len())
+        .filter(|&position| pending[position] > 0)
+        .collect();
+    cyclic.sort_by_key(|&position| Reverse(rank(position)));
+    order.extend(cyclic);
+
+    let mut slots: Vec<Option<T>> = items.into_iter().map(Some).collect();
+    order
+        .into_iter()
+        .filter_map(|position| slots[position].take())
+        .collect()
+}
+
 /// Configuration for creating a new QantoDAG instance.
 pub struct QantoDagConfig<'a> {
-    pub initial_validator: String,
+    pub initial_validator: Address,
     pub target_block_time: u64,
     pub num_chains: u32,
     pub qr_signing_key: &'a dilithium5::SecretKey,
     pub qr_public_key: &'a dilithium5::PublicKey,
+    pub storage_mode: StorageMode,
+    pub tx_index: bool,
+    /// The network's coinbase maturity, which every block commits to.
+    pub coinbase_maturity: u64,
 }
 
 #[derive(Clone, Debug)]
@@ -519,34 +911,47 @@ pub struct DaaAnchor {
 
 #[derive(Debug)]
 pub struct QantoDAG {
-    pub blocks: Arc<RwLock<HashMap<String, QantoBlock>>>,
-    pub tips: Arc<RwLock<HashMap<u32, HashSet<String>>>>,
-    pub validators: Arc<RwLock<HashMap<String, u64>>>,
+    pub blocks: Arc<RwLock<HashMap<BlockHash, QantoBlock>>>,
+    pub tips: Arc<RwLock<HashMap<u32, HashSet<BlockHash>>>>,
+    pub validators: Arc<RwLock<HashMap<Address, u64>>>,
     pub target_block_time: u64,
+    pub storage_mode: StorageMode,
+    /// Blocks whose bodies were pruned; only their headers remain.
+    pub pruned_blocks: Arc<RwLock<HashSet<BlockHash>>>,
+    /// Highest height of any pruned block; bodies at or below it may be gone.
+    pub pruned_height: Arc<RwLock<u64>>,
+    /// Maintain the transaction, address and spent-output indexes.
+    pub tx_index: bool,
+    /// Blocks a chain must grow past a coinbase before its outputs can be
+    /// spent; a genesis parameter every block commits to.
+    pub coinbase_maturity: u64,
     pub difficulties: Arc<RwLock<HashMap<u32, u64>>>,
     pub difficulty_anchors: Arc<RwLock<HashMap<u32, DaaAnchor>>>,
     pub emission: Arc<RwLock<Emission>>,
     pub num_chains: Arc<RwLock<u32>>,
-    pub finalized_blocks: Arc<RwLock<HashSet<String>>>,
+    pub finalized_blocks: Arc<RwLock<HashSet<BlockHash>>>,
     pub chain_loads: Arc<RwLock<HashMap<u32, u64>>>,
     pub difficulty_history: Arc<RwLock<Vec<(u64, u64)>>>,
-    pub block_creation_timestamps: Arc<RwLock<HashMap<String, u64>>>,
+    pub block_creation_timestamps: Arc<RwLock<HashMap<BlockHash, u64>>>,
     pub anomaly_history: Arc<RwLock<HashMap<String, u64>>>,
     pub cross_chain_swaps: Arc<RwLock<HashMap<String, CrossChainSwap>>>,
+    pub fee_estimator: Arc<RwLock<FeeEstimator>>,
     pub smart_contracts: Arc<RwLock<HashMap<String, SmartContract>>>,
-    pub cache: Arc<RwLock<LruCache<String, QantoBlock>>>,
-    pub db: Arc<DB>,
+    pub cache: Arc<RwLock<LruCache<BlockHash, QantoBlock>>>,
+    /// Signature digests of transactions whose signatures already verified.
+    pub verified_signatures: Arc<RwLock<LruCache<[u8; 32], ()>>>,
+    pub store: Arc<dyn StateStore>,
     pub saga: Arc<PalletSaga>,
     pub self_arc: Weak<QantoDAG>,
     pub current_epoch: Arc<RwLock<u64>>,
 }
 
 impl QantoDAG {
-    #[instrument(skip(config, saga, db))]
+    #[instrument(skip(config, saga, store))]
     pub fn new(
         config: QantoDagConfig,
         saga: Arc<PalletSaga>,
-        db: DB,
+        store: Arc<dyn StateStore>,
     ) -> Result<Arc<Self>, QantoDAGError> {
         let mut blocks_map = HashMap::new();
         let mut tips_map = HashMap::new();
@@ -558,21 +963,25 @@ impl QantoDAG {
             let genesis_creation_data = QantoBlockCreationData {
                 chain_id: chain_id_val,
                 parents: vec![],
+                cross_chain_references: vec![],
                 transactions: vec![],
                 difficulty: INITIAL_DIFFICULTY,
-                validator: config.initial_validator.clone(),
-                miner: config.initial_validator.clone(),
+                validator: config.initial_validator,
+                miner: config.initial_validator,
                 qr_signing_key: config.qr_signing_key,
                 qr_public_key: config.qr_public_key,
                 timestamp: genesis_timestamp,
                 current_epoch: 0,
                 height: 0,
+                fee_policy: FeePolicy::default(),
+                coinbase_maturity: config.coinbase_maturity,
+                utxo_root: utxo_set::EMPTY_ROOT.to_string(),
             };
             let mut genesis_block = QantoBlock::new(genesis_creation_data)?;
             genesis_block.reward = 0;
-            let genesis_id = genesis_block.id.clone();
+            let genesis_id = genesis_block.id;
 
-            blocks_map.insert(genesis_id.clone(), genesis_block);
+            blocks_map.insert(genesis_id, genesis_block);
             tips_map
                 .entry(chain_id_val)
                 .or_insert_with(HashSet::new)
@@ -580,36 +989,60 @@ impl QantoDAG {
             difficulties_map.insert(chain_id_val, INITIAL_DIFFICULTY);
         }
         validators_map.insert(
-            config.initial_validator.clone(),
+            config.initial_validator,
             MIN_VALIDATOR_STAKE * config.num_chains as u64 * 2,
         );
 
+        schema::migrate(store.as_ref(), true)?;
+        let persisted_swaps = Self::load_persisted_swaps(store.as_ref())?;
+        let persisted_fee_policies = Self::load_persisted_fee_policies(store.as_ref())?;
+        let current_epoch = persisted_fee_policies
+            .keys()
+            .next_back()
+            .copied()
+            .unwrap_or(0);
+        if !persisted_fee_policies.is_empty() {
+            saga.economy
+                .fee_policies
+                .try_write()
+                .map_err(|_| {
+                    QantoDAGError::DatabaseError("SAGA fee policies are locked".to_string())
+                })?
+                .extend(persisted_fee_policies);
+        }
+
         let dag = Self {
             blocks: Arc::new(RwLock::new(blocks_map)),
             tips: Arc::new(RwLock::new(tips_map)),
             validators: Arc::new(RwLock::new(validators_map)),
             target_block_time: config.target_block_time,
+            storage_mode: config.storage_mode,
+            pruned_blocks: Arc::new(RwLock::new(HashSet::new())),
+            pruned_height: Arc::new(RwLock::new(0)),
+            tx_index: config.tx_index,
+            coinbase_maturity: config.coinbase_maturity,
             difficulties: Arc::new(RwLock::new(difficulties_map)),
             difficulty_anchors: Arc::new(RwLock::new(HashMap::new())),
-            emission: Arc::new(RwLock::new(Emission::default_with_timestamp(
-                genesis_timestamp,
-                config.num_chains,
-            ))),
+            emission: Arc::new(RwLock::new(Emission::for_chains(config.num_chains))),
             num_chains: Arc::new(RwLock::new(config.num_chains.max(1))),
             finalized_blocks: Arc::new(RwLock::new(HashSet::new())),
             chain_loads: Arc::new(RwLock::new(HashMap::new())),
             difficulty_history: Arc::new(RwLock::new(Vec::new())),
             block_creation_timestamps: Arc::new(RwLock::new(HashMap::new())),
             anomaly_history: Arc::new(RwLock::new(HashMap::new())),
-            cross_chain_swaps: Arc::new(RwLock::new(HashMap::new())),
+            cross_chain_swaps: Arc::new(RwLock::new(persisted_swaps)),
+            fee_estimator: Arc::new(RwLock::new(FeeEstimator::new())),
             smart_contracts: Arc::new(RwLock::new(HashMap::new())),
             cache: Arc::new(RwLock::new(LruCache::new(
                 NonZeroUsize::new(CACHE_SIZE.max(1)).unwrap(),
             ))),
-            db: Arc::new(db),
+            verified_signatures: Arc::new(RwLock::new(LruCache::new(
+                NonZeroUsize::new(SIGNATURE_CACHE_SIZE).unwrap(),
+            ))),
+            store,
             saga,
             self_arc: Weak::new(),
-            current_epoch: Arc::new(RwLock::new(0)),
+            current_epoch: Arc::new(RwLock::new(current_epoch)),
         };
 
         let arc_dag = Arc::new(dag);
@@ -629,7 +1062,7 @@ impl QantoDAG {
         self: Arc<Self>,
         wallet: Arc<Wallet>,
         mempool: Arc<RwLock<Mempool>>,
-        utxos: Arc<RwLock<HashMap<String, UTXO>>>,
+        utxos: Arc<RwLock<UtxoSet>>,
         miner: Arc<Miner>,
         mining_interval_secs: u64,
     ) {
@@ -705,11 +1138,13 @@ impl QantoDAG {
             match self.add_block(mined_block.clone(), &utxos).await {
                 Ok(true) => {
                     info!("SOLO MINER: Successfully added new block to the QantoDAG.");
-                    mempool
-                        .read()
-                        .await
+                    let mempool_guard = mempool.read().await;
+                    mempool_guard
                         .remove_transactions(&mined_block.transactions)
                         .await;
+                    mempool_guard
+                        .evict_expired(self.inclusion_window().await)
+                        .await;
                 }
                 Ok(false) => {
                     warn!("SOLO MINER: Mined block was rejected by the DAG (already exists?).")
@@ -733,7 +1168,7 @@ impl QantoDAG {
     pub async fn add_block(
         &self,
         block: QantoBlock,
-        utxos_arc: &Arc<RwLock<HashMap<String, UTXO>>>,
+        utxos_arc: &Arc<RwLock<UtxoSet>>,
     ) -> Result<bool, QantoDAGError> {
         if self.blocks.read().await.contains_key(&block.id) {
             warn!("Attempted to add block {} which already exists.", block.id);
@@ -760,9 +1195,31 @@ impl QantoDAG {
             return Ok(false);
         }
 
+        // The state root covers the block's past, so blocks the set holds
+        // outside it are staged out; checked under the write lock so the undo
+        // history cannot move in between.
+        let utxo_changes = block.utxo_changes();
+        let computed_root = utxos_write_guard.state_root_after(&block.links(), &utxo_changes)?;
+        if computed_root != block.utxo_root {
+            return Err(QantoDAGError::StateRootMismatch {
+                committed: block.utxo_root.clone(),
+                computed: computed_root,
+            });
+        }
+
         let anomaly_score = self
             .detect_anomaly_internal(&blocks_write_guard, &block)
             .await?;
+
+        // Recorded before anything else changes, so a block past the supply
+        // cap is rejected without side effects, and under the UTXO lock, so a
+        // snapshot sees the issued supply and the set at the same point.
+        self.emission
+            .write()
+            .await
+            .update_supply(block.height, block.minted())
+            .map_err(QantoDAGError::EmissionError)?;
+
         if anomaly_score > 0.9 {
             if let Some(stake) = validators_guard.get_mut(&block.validator) {
                 let penalty = (*stake * SLASHING_PENALTY) / 100;
@@ -774,25 +1231,12 @@ impl QantoDAG {
             }
         }
 
-        for tx in &block.transactions {
-            for input in &tx.inputs {
-                let utxo_id = format!("{}_{}", input.tx_id, input.output_index);
-                utxos_write_guard.remove(&utxo_id);
-            }
-            for (index, output) in tx.outputs.iter().enumerate() {
-                let utxo_id = format!("{}_{}", tx.id, index);
-                utxos_write_guard.insert(
-                    utxo_id.clone(),
-                    UTXO {
-                        address: output.address.clone(),
-                        amount: output.amount,
-                        tx_id: tx.id.clone(),
-                        output_index: index as u32,
-                        explorer_link: format!("https://qantoblockexplorer.org/utxo/{utxo_id}"),
-                    },
-                );
-            }
-        }
+        let spent_htlcs: Vec<(OutPoint, UTXO)> = utxos_write_guard
+            .apply_block(block.id, block.links(), utxo_changes)
+            .into_iter()
+            .filter(|(_, spent)| spent.htlc.is_some())
+            .collect();
+        let swap_updates = htlc::watch_block(&block, &spent_htlcs);
 
         let current_tips = tips_guard
             .entry(block.chain_id)
@@ -800,26 +1244,29 @@ impl QantoDAG {
         for parent_id in &block.parents {
             current_tips.remove(parent_id);
         }
-        current_tips.insert(block.id.clone());
+        current_tips.insert(block.id);
+
+        self.fee_estimator.write().await.record_block(&block);
 
         let block_for_db = block.clone();
-        blocks_write_guard.insert(block.id.clone(), block);
+        blocks_write_guard.insert(block.id, block);
 
         drop(validators_guard);
         drop(tips_guard);
         drop(blocks_write_guard);
         drop(utxos_write_guard);
 
-        let db_clone = self.db.clone();
-        let id_bytes = block_for_db.id.as_bytes().to_vec();
-        let block_bytes = serde_json::to_vec(&block_for_db)?;
-
-        task::spawn_blocking(move || db_clone.put(id_bytes, block_bytes)).await??;
+        let mut batch = StoreBatch::default();
+        batch.put_block(&block_for_db)?;
+        if self.tx_index {
+            tx_index::index_block(&mut batch, &block_for_db)?;
+        }
+        let store = self.store.clone();
+        task::spawn_blocking(move || store.write(batch)).await??;
 
-        let mut emission = self.emission.write().await;
-        emission
-            .update_supply(block_for_db.reward)
-            .map_err(QantoDAGError::EmissionError)?;
+        if !swap_updates.is_empty() {
+            self.apply_swap_updates(swap_updates).await?;
+        }
 
         BLOCKS_PROCESSED.inc();
         TRANSACTIONS_PROCESSED.inc_by(block_for_db.transactions.len() as u64);
@@ -832,7 +1279,7 @@ impl QantoDAG {
     }
 
     #[instrument]
-    pub async fn get_tips(&self, chain_id: u32) -> Option<Vec<String>> {
+    pub async fn get_tips(&self, chain_id: u32) -> Option<Vec<BlockHash>> {
         self.tips
             .read()
             .await
@@ -840,72 +1287,167 @@ impl QantoDAG {
             .map(|tips_set| tips_set.iter().cloned().collect())
     }
 
-    #[instrument]
-    pub async fn add_validator(&self, address: String, stake: u64) {
-        let mut validators_guard = self.validators.write().await;
-        validators_guard.insert(address, stake.max(MIN_VALIDATOR_STAKE));
+    /// The supply issued by the blocks this node has validated, compared with
+    /// the emission schedule.
+    pub async fn supply_audit(&self) -> SupplyAudit {
+        self.emission.read().await.audit()
     }
 
-    #[instrument]
-    pub async fn initiate_cross_chain_swap(
+    /// Height of the highest tip on `chain_id`.
+    pub async fn chain_height(&self, chain_id: u32) -> u64 {
+        let blocks_guard = self.blocks.read().await;
+        let tips_guard = self.tips.read().await;
+        tips_guard.get(&chain_id).map_or(0, |tips| {
+            tips.iter()
+                .filter_map(|id| blocks_guard.get(id))
+                .map(|block| block.height)
+                .max()
+                .unwrap_or(0)
+        })
+    }
+
+    /// The next height of the shortest chain, paired with the current epoch.
+    pub async fn inclusion_window(&self) -> InclusionWindow {
+        let num_chains_val = *self.num_chains.read().await;
+        let mut next_height = u64::MAX;
+        for chain_id_val in 0..num_chains_val {
+            next_height = next_height.min(self.chain_height(chain_id_val).await + 1);
+        }
+        InclusionWindow {
+            next_height: if next_height == u64::MAX {
+                1
+            } else {
+                next_height
+            },
+            epoch: *self.current_epoch.read().await,
+        }
+    }
+
+    /// Splits `utxos` into outputs the next block may spend, as judged for
+    /// mempool admission, and immature coinbase outputs.
+    pub async fn partition_utxos(
         &self,
-        params: CrossChainSwapParams,
-    ) -> Result<String, QantoDAGError> {
-        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-        let swap_id = hex::encode(Keccak256::digest(
-            format!(
-                "swap_{}_{}_{}_{}",
-                params.initiator, params.responder, params.amount, now
-            )
-            .as_bytes(),
-        ));
-        let swap = CrossChainSwap {
-            swap_id: swap_id.clone(),
-            source_chain: params.source_chain,
-            target_chain: params.target_chain,
-            amount: params.amount,
-            initiator: params.initiator,
-            responder: params.responder,
-            timelock: now + params.timelock_duration,
-            state: SwapState::Initiated,
-            secret_hash: params.secret_hash,
-            secret: None,
-        };
-        self.cross_chain_swaps
-            .write()
-            .await
-            .insert(swap_id.clone(), swap);
-        Ok(swap_id)
+        utxos: impl IntoIterator<Item = (OutPoint, UTXO)>,
+    ) -> AddressUtxos {
+        let next_height = self.inclusion_window().await.next_height;
+        let mut result = AddressUtxos::default();
+        for (utxo_id, utxo) in utxos {
+            match utxo.coinbase.clone() {
+                Some(origin) if !origin.is_mature_at(next_height) => {
+                    result.immature_balance += utxo.amount;
+                    result.immature.insert(
+                        utxo_id,
+                        ImmatureUtxo {
+                            utxo,
+                            blocks_remaining: origin.blocks_remaining(next_height),
+                        },
+                    );
+                }
+                _ => {
+                    result.spendable_balance += utxo.amount;
+                    result.spendable.insert(utxo_id, utxo);
+                }
+            }
+        }
+        result
     }
 
     #[instrument]
-    pub async fn redeem_cross_chain_swap(
-        &self,
-        swap_id: &str,
-        secret: &str,
-    ) -> Result<(), QantoDAGError> {
-        let mut swaps = self.cross_chain_swaps.write().await;
-        let swap = swaps.get_mut(swap_id).ok_or_else(|| {
-            QantoDAGError::CrossChainSwapError(format!("Swap ID {swap_id} not found"))
-        })?;
+    pub async fn add_validator(&self, address: Address, stake: u64) {
+        let mut validators_guard = self.validators.write().await;
+        validators_guard.insert(address, stake.max(MIN_VALIDATOR_STAKE));
+    }
 
-        let hash_of_provided_secret = hex::encode(Keccak256::digest(secret.as_bytes()));
-        if hash_of_provided_secret != swap.secret_hash {
-            return Err(QantoDAGError::CrossChainSwapError(
-                "Invalid secret provided for swap.".to_string(),
-            ));
+    /// Restores swap records written by the swap watcher so HTLC swaps
+    /// survive node restarts.
+    fn load_persisted_swaps(
+        store: &dyn StateStore,
+    ) -> Result<HashMap<String, CrossChainSwap>, QantoDAGError> {
+        let mut swaps = HashMap::new();
+        for item in store.scan_prefix(SWAP_DB_PREFIX.as_bytes()) {
+            let (_, value) = item?;
+            let swap: CrossChainSwap = serde_json::from_slice(&value)?;
+            swaps.insert(swap.swap_id.clone(), swap);
         }
+        if !swaps.is_empty() {
+            info!("Restored {} cross-chain swaps from database.", swaps.len());
+        }
+        Ok(swaps)
+    }
 
-        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-        if now > swap.timelock {
-            return Err(QantoDAGError::CrossChainSwapError(
-                "Swap timelock has expired.".to_string(),
-            ));
+    /// Restores the fee policies recorded at past epoch transitions, which
+    /// blocks of those epochs commit to, so they survive node restarts.
+    fn load_persisted_fee_policies(
+        store: &dyn StateStore,
+    ) -> Result<BTreeMap<u64, FeePolicy>, QantoDAGError> {
+        let mut policies = BTreeMap::new();
+        for item in store.scan_prefix(FEE_POLICY_DB_PREFIX.as_bytes()) {
+            let (key, value) = item?;
+            let epoch = std::str::from_utf8(&key[FEE_POLICY_DB_PREFIX.len()..])
+                .ok()
+                .and_then(|epoch| epoch.parse::<u64>().ok())
+                .ok_or_else(|| {
+                    QantoDAGError::DatabaseError(format!(
+                        "Malformed fee policy key {}",
+                        String::from_utf8_lossy(&key)
+                    ))
+                })?;
+            policies.insert(epoch, serde_json::from_slice(&value)?);
         }
+        if !policies.is_empty() {
+            info!("Restored {} fee policies from database.", policies.len());
+        }
+        Ok(policies)
+    }
 
-        swap.state = SwapState::Redeemed;
-        swap.secret = Some(secret.to_string());
-        info!("Cross-chain swap {} redeemed successfully.", swap_id);
+    /// Persists the fee policies recorded from `from_epoch` on.
+    async fn persist_fee_policies(&self, from_epoch: u64) -> Result<(), QantoDAGError> {
+        let mut batch = StoreBatch::default();
+        for (epoch, policy) in self
+            .saga
+            .economy
+            .fee_policies
+            .read()
+            .await
+            .range(from_epoch..)
+        {
+            batch.put(
+                format!("{FEE_POLICY_DB_PREFIX}{epoch:020}").as_bytes(),
+                serde_json::to_vec(policy)?,
+            );
+        }
+        let store = self.store.clone();
+        task::spawn_blocking(move || store.write(batch)).await??;
+        Ok(())
+    }
+
+    /// Records swap state transitions observed on-chain and persists them.
+    async fn apply_swap_updates(&self, updates: Vec<CrossChainSwap>) -> Result<(), QantoDAGError> {
+        let mut batch = StoreBatch::default();
+        {
+            let mut swaps = self.cross_chain_swaps.write().await;
+            for update in updates {
+                match &update.state {
+                    SwapState::Redeemed => info!(
+                        swap_id = %update.swap_id,
+                        "Swap watcher: HTLC redeemed, secret revealed on-chain."
+                    ),
+                    SwapState::Refunded => {
+                        info!(swap_id = %update.swap_id, "Swap watcher: HTLC refunded.")
+                    }
+                    SwapState::Initiated => {
+                        debug!(swap_id = %update.swap_id, "Swap watcher: HTLC locked.")
+                    }
+                }
+                batch.put(
+                    format!("{SWAP_DB_PREFIX}{}", update.swap_id).as_bytes(),
+                    serde_json::to_vec(&update)?,
+                );
+                swaps.insert(update.swap_id.clone(), update);
+            }
+        }
+        let store = self.store.clone();
+        task::spawn_blocking(move || store.write(batch)).await??;
         Ok(())
     }
 
@@ -931,14 +1473,14 @@ impl QantoDAG {
         Ok(contract_id)
     }
 
-    #[instrument(skip(self, qr_signing_key, qr_public_key, mempool_arc, _utxos_arc))]
+    #[instrument(skip(self, qr_signing_key, qr_public_key, mempool_arc, utxos_arc))]
     pub async fn create_candidate_block(
         &self,
         qr_signing_key: &dilithium5::SecretKey,
         qr_public_key: &dilithium5::PublicKey,
-        validator_address: &str,
+        validator_address: &Address,
         mempool_arc: &Arc<RwLock<Mempool>>,
-        _utxos_arc: &Arc<RwLock<HashMap<String, UTXO>>>,
+        utxos_arc: &Arc<RwLock<UtxoSet>>,
         chain_id_val: u32,
     ) -> Result<QantoBlock, QantoDAGError> {
         {
@@ -977,7 +1519,7 @@ impl QantoDAG {
                 .select_transactions(MAX_TRANSACTIONS_PER_BLOCK)
                 .await
         };
-        let parent_tips: Vec<String> = self.get_tips(chain_id_val).await.unwrap_or_default();
+        let parent_tips: Vec<BlockHash> = self.get_tips(chain_id_val).await.unwrap_or_default();
 
         let (height, new_timestamp) = {
             let blocks_guard = self.blocks.read().await;
@@ -996,6 +1538,22 @@ impl QantoDAG {
         };
 
         let epoch = *self.current_epoch.read().await;
+        let fee_policy = self.saga.fee_policy_at(epoch).await;
+
+        // Leave out transactions that expire before this height or epoch, along
+        // with any selected children spending their outputs.
+        let mut expired_ids: HashSet<TxId> = HashSet::new();
+        let selected_transactions: Vec<Transaction> = selected_transactions
+            .into_iter()
+            .filter(|tx| {
+                let expired = tx.is_expired_at(height, epoch)
+                    || tx.inputs.iter().any(|i| expired_ids.contains(&i.tx_id));
+                if expired {
+                    expired_ids.insert(tx.id);
+                }
+                !expired
+            })
+            .collect();
 
         let current_difficulty = self
             .difficulties
@@ -1005,18 +1563,24 @@ impl QantoDAG {
             .cloned()
             .unwrap_or(INITIAL_DIFFICULTY);
 
+        // The reward is the minted part plus the fees of the transactions it is
+        // computed over, so those must be the ones the block will carry.
         let temp_block_for_reward_calc = QantoBlock::new(QantoBlockCreationData {
             chain_id: chain_id_val,
             parents: parent_tips.clone(),
-            transactions: vec![],
+            cross_chain_references: vec![],
+            transactions: selected_transactions.clone(),
             difficulty: current_difficulty,
-            validator: validator_address.to_string(),
-            miner: validator_address.to_string(),
+            validator: *validator_address,
+            miner: *validator_address,
             qr_signing_key,
             qr_public_key,
             timestamp: new_timestamp,
             current_epoch: epoch,
             height,
+            fee_policy,
+            coinbase_maturity: self.coinbase_maturity,
+            utxo_root: utxo_set::EMPTY_ROOT.to_string(),
         })?;
 
         let self_arc_strong = self
@@ -1028,24 +1592,26 @@ impl QantoDAG {
             .calculate_dynamic_reward(&temp_block_for_reward_calc, &self_arc_strong)
             .await?;
 
-        let dev_fee = (reward as f64 * DEV_FEE_RATE).round() as u64;
+        let dev_fee = fee_policy.dev_fee(reward);
         let miner_reward = reward.saturating_sub(dev_fee);
 
         let coinbase_outputs = vec![
             Output {
-                address: validator_address.to_string(),
+                address: *validator_address,
                 amount: miner_reward,
                 homomorphic_encrypted: HomomorphicEncrypted::new(0, &[]),
+                htlc: None,
             },
             Output {
-                address: DEV_ADDRESS.to_string(),
+                address: DEV_ADDRESS,
                 amount: dev_fee,
                 homomorphic_encrypted: HomomorphicEncrypted::new(0, &[]),
+                htlc: None,
             },
         ];
 
         let reward_tx = Transaction::new_coinbase(
-            validator_address.to_string(),
+            *validator_address,
             reward,
             qr_signing_key.as_bytes(),
             qr_public_key.as_bytes(),
@@ -1055,38 +1621,61 @@ impl QantoDAG {
         let mut transactions_for_block = vec![reward_tx];
         transactions_for_block.extend(selected_transactions);
 
-        let mut cross_chain_references = vec![];
-        let num_chains_val = *self.num_chains.read().await;
-        if num_chains_val > 1 {
-            let prev_chain = (chain_id_val + num_chains_val - 1) % num_chains_val;
-            let tips_guard = self.tips.read().await;
-            if let Some(prev_tips_set) = tips_guard.get(&prev_chain) {
-                if let Some(tip_val) = prev_tips_set.iter().next() {
-                    cross_chain_references.push((prev_chain, tip_val.clone()));
-                }
-            }
-        }
+        // Referencing every other chain's tips puts all the blocks the inputs
+        // were checked against into the block's past, which its root covers.
+        let mut cross_chain_references: Vec<(u32, BlockHash)> = self
+            .tips
+            .read()
+            .await
+            .iter()
+            .filter(|(chain, _)| **chain != chain_id_val)
+            .flat_map(|(chain, tips)| tips.iter().map(|tip| (*chain, *tip)))
+            .collect();
+        cross_chain_references.sort_unstable();
+        let links: Vec<BlockHash> = parent_tips
+            .iter()
+            .copied()
+            .chain(cross_chain_references.iter().map(|(_, id)| *id))
+            .collect();
+
+        // The minting block's ID is not part of the state root, so the coinbase
+        // outputs can be staged before the ID exists.
+        let utxo_root = utxos_arc.read().await.state_root_after(
+            &links,
+            &utxo_changes(
+                &transactions_for_block,
+                CoinbaseOrigin {
+                    block_id: BlockHash::ZERO,
+                    chain_id: chain_id_val,
+                    height,
+                    maturity: self.coinbase_maturity,
+                },
+            ),
+        )?;
 
         let mut block = QantoBlock::new(QantoBlockCreationData {
             chain_id: chain_id_val,
             parents: parent_tips,
+            cross_chain_references,
             transactions: transactions_for_block,
             difficulty: current_difficulty,
-            validator: validator_address.to_string(),
-            miner: validator_address.to_string(),
+            validator: *validator_address,
+            miner: *validator_address,
             qr_signing_key,
             qr_public_key,
             timestamp: new_timestamp,
             current_epoch: epoch,
             height,
+            fee_policy,
+            coinbase_maturity: self.coinbase_maturity,
+            utxo_root,
         })?;
-        block.cross_chain_references = cross_chain_references;
         block.reward = reward;
 
         self.block_creation_timestamps
             .write()
             .await
-            .insert(block.id.clone(), new_timestamp);
+            .insert(block.id, new_timestamp);
         *self
             .chain_loads
             .write()
@@ -1101,11 +1690,11 @@ impl QantoDAG {
     pub async fn is_valid_block(
         &self,
         block: &QantoBlock,
-        utxos_arc: &Arc<RwLock<HashMap<String, UTXO>>>,
+        utxos_arc: &Arc<RwLock<UtxoSet>>,
     ) -> Result<bool, QantoDAGError> {
-        if block.id.is_empty() {
+        if block.id.is_zero() {
             return Err(QantoDAGError::InvalidBlock(
-                "Block ID cannot be empty".to_string(),
+                "Block ID cannot be zero".to_string(),
             ));
         }
         if block.transactions.is_empty() {
@@ -1114,7 +1703,7 @@ impl QantoDAG {
             ));
         }
 
-        let serialized_size = serde_json::to_vec(&block)?.len();
+        let serialized_size = codec::encoded_len(block)?;
         if block.transactions.len() > MAX_TRANSACTIONS_PER_BLOCK || serialized_size > MAX_BLOCK_SIZE
         {
             return Err(QantoDAGError::InvalidBlock(format!(
@@ -1124,7 +1713,8 @@ impl QantoDAG {
             )));
         }
 
-        let expected_merkle_root = QantoBlock::compute_merkle_root(&block.transactions)?;
+        let expected_merkle_root =
+            QantoBlock::compute_merkle_root(&block.transactions, block.height)?;
         if block.merkle_root != expected_merkle_root {
             return Err(QantoDAGError::MerkleRootMismatch);
         }
@@ -1154,9 +1744,9 @@ impl QantoDAG {
             let blocks_guard = self.blocks.read().await;
             let mut max_parent_height = 0;
             for parent_id in &block.parents {
-                let parent_block = blocks_guard.get(parent_id).ok_or_else(|| {
-                    QantoDAGError::InvalidParent(format!("Parent block {parent_id} not found"))
-                })?;
+                let parent_block = blocks_guard
+                    .get(parent_id)
+                    .ok_or(QantoDAGError::MissingParent(*parent_id))?;
                 if parent_block.chain_id != block.chain_id {
                     return Err(QantoDAGError::InvalidParent(format!(
                         "Parent {} on chain {} but block {} on chain {}",
@@ -1181,11 +1771,20 @@ impl QantoDAG {
                 )));
             }
 
-            for (_ref_chain_id, ref_block_id) in &block.cross_chain_references {
-                if !blocks_guard.contains_key(ref_block_id) {
-                    return Err(QantoDAGError::CrossChainReferenceError(format!(
-                        "Reference block {ref_block_id} not found"
-                    )));
+            for (ref_chain_id, ref_block_id) in &block.cross_chain_references {
+                match blocks_guard.get(ref_block_id) {
+                    None => {
+                        return Err(QantoDAGError::CrossChainReferenceError(format!(
+                            "Reference block {ref_block_id} not found"
+                        )));
+                    }
+                    Some(referenced) if referenced.chain_id != *ref_chain_id => {
+                        return Err(QantoDAGError::CrossChainReferenceError(format!(
+                            "Reference block {ref_block_id} is on chain {}, not {ref_chain_id}",
+                            referenced.chain_id
+                        )));
+                    }
+                    Some(_) => {}
                 }
             }
         }
@@ -1198,17 +1797,24 @@ impl QantoDAG {
         }
         let total_coinbase_output: u64 = coinbase_tx.outputs.iter().map(|o| o.amount).sum();
 
-        let self_arc_strong = self
-            .self_arc
-            .upgrade()
-            .ok_or(QantoDAGError::SelfReferenceNotInitialized)?;
-        let expected_reward = self
-            .saga
-            .calculate_dynamic_reward(block, &self_arc_strong)
-            .await?;
-
-        if block.reward != expected_reward {
-            return Err(QantoDAGError::RewardMismatch(expected_reward, block.reward));
+        // A block mints exactly the scheduled reward for its height, so what
+        // it issues depends on the chain alone, and the schedule never
+        // allows more than the supply cap.
+        {
+            let emission = self.emission.read().await;
+            let scheduled = emission.reward_at(block.height);
+            let minted = block.minted();
+            if minted != scheduled {
+                return Err(QantoDAGError::MintMismatch { minted, scheduled });
+            }
+            let scheduled_supply = emission.scheduled_supply_at(block.height);
+            if scheduled_supply > emission.total_supply() {
+                return Err(QantoDAGError::EmissionError(format!(
+                    "supply scheduled by height {} is {scheduled_supply}, above the total supply of {}",
+                    block.height,
+                    emission.total_supply()
+                )));
+            }
         }
 
         if total_coinbase_output != block.reward {
@@ -1218,9 +1824,60 @@ impl QantoDAG {
             ));
         }
 
+        if block.coinbase_maturity != self.coinbase_maturity {
+            return Err(QantoDAGError::InvalidBlock(format!(
+                "Block commits to coinbase maturity {}, expected {}",
+                block.coinbase_maturity, self.coinbase_maturity
+            )));
+        }
+
+        let expected_fee_policy = self.saga.fee_policy_at(block.epoch).await;
+        if block.fee_policy != expected_fee_policy {
+            return Err(QantoDAGError::InvalidBlock(format!(
+                "Block commits to fee policy {:?}, expected {:?}",
+                block.fee_policy, expected_fee_policy
+            )));
+        }
+        block.fee_policy.check_coinbase(coinbase_tx, block.reward)?;
+
+        // Signatures are stateless, so they are checked in parallel before any
+        // UTXO lock is taken. Transactions may spend outputs created earlier in
+        // the same block, so each one is then checked against what the block's
+        // past leaves behind, the view its state root covers, plus the block's
+        // own outputs, and no outpoint may be spent twice. Outputs of branches
+        // outside the past are not spendable, whether or not this node has
+        // seen them.
+        self.verify_signatures(&block.transactions[1..]).await?;
         let utxos_guard = utxos_arc.read().await;
+        let past = utxos_guard.past(&block.links())?;
+        let mut block_outputs: HashMap<OutPoint, UTXO> = HashMap::new();
+        let mut block_spent: HashSet<OutPoint> = HashSet::new();
         for tx in block.transactions.iter().skip(1) {
-            tx.verify(self, &utxos_guard).await?;
+            if tx.is_expired_at(block.height, block.epoch) {
+                return Err(QantoDAGError::InvalidBlock(format!(
+                    "Transaction {} expired before height {} (epoch {})",
+                    tx.id, block.height, block.epoch
+                )));
+            }
+            let mut input_view = HashMap::with_capacity(tx.inputs.len());
+            for input in &tx.inputs {
+                let utxo_id = input.outpoint();
+                if !block_spent.insert(utxo_id) {
+                    return Err(QantoDAGError::InvalidBlock(format!(
+                        "Outpoint {utxo_id} is spent more than once in block"
+                    )));
+                }
+                if let Some(utxo) = block_outputs
+                    .remove(&utxo_id)
+                    .or_else(|| past.get(&utxo_id).cloned())
+                {
+                    input_view.insert(utxo_id, utxo);
+                }
+            }
+            tx.verify_inputs(&input_view, block.spend_context()).await?;
+            for index in 0..tx.outputs.len() as u32 {
+                block_outputs.insert(OutPoint::new(tx.id, index), tx.generate_utxo(index));
+            }
         }
 
         let blocks_guard = self.blocks.read().await;
@@ -1238,7 +1895,7 @@ impl QantoDAG {
 
     async fn detect_anomaly_internal(
         &self,
-        blocks_guard: &HashMap<String, QantoBlock>,
+        blocks_guard: &HashMap<BlockHash, QantoBlock>,
         block: &QantoBlock,
     ) -> Result<f64, QantoDAGError> {
         if blocks_guard.len() < ANOMALY_DETECTION_BASELINE_BLOCKS {
@@ -1301,13 +1958,46 @@ impl QantoDAG {
         Ok(anomaly_score)
     }
 
+    /// Checks the signatures of `txs` on the rayon pool, skipping any that were
+    /// already verified (for example on mempool admission).
+    pub async fn verify_signatures(
+        &self,
+        txs: &[Transaction],
+    ) -> Result<(), transaction::TransactionError> {
+        let digests = run_cpu_bound(|| {
+            txs.par_iter()
+                .map(Transaction::signature_digest)
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        let pending: Vec<(&Transaction, [u8; 32])> = {
+            let cache = self.verified_signatures.read().await;
+            txs.iter()
+                .zip(digests)
+                .filter(|(_, digest)| !cache.contains(digest))
+                .collect()
+        };
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let pending_txs: Vec<&Transaction> = pending.iter().map(|(tx, _)| *tx).collect();
+        run_cpu_bound(|| transaction::verify_signatures_parallel(&pending_txs))?;
+
+        let mut cache = self.verified_signatures.write().await;
+        for (_, digest) in pending {
+            cache.put(digest, ());
+        }
+        Ok(())
+    }
+
     #[instrument]
     pub async fn validate_transaction(
         &self,
         tx: &Transaction,
-        utxos_map: &HashMap<String, UTXO>,
+        utxos_map: &HashMap<OutPoint, UTXO>,
+        context: SpendContext,
     ) -> bool {
-        tx.verify(self, utxos_map).await.is_ok()
+        tx.verify(utxos_map, context).await.is_ok()
     }
 
     #[instrument(skip(self))]
@@ -1381,7 +2071,7 @@ impl QantoDAG {
             if let Some(chain_tips) = tips_guard.get(&chain_id_val) {
                 for tip_id in chain_tips {
                     let mut path_to_finalize = Vec::new();
-                    let mut current_id = tip_id.clone();
+                    let mut current_id = *tip_id;
 
                     for _depth in 0..FINALIZATION_DEPTH {
                         if finalized_guard.contains(&current_id) {
@@ -1389,12 +2079,12 @@ impl QantoDAG {
                         }
 
                         if let Some(current_block) = blocks_guard.get(&current_id) {
-                            path_to_finalize.push(current_id.clone());
+                            path_to_finalize.push(current_id);
                             if current_block.parents.is_empty() {
                                 break;
                             }
                             // Simple finalization follows the first parent. More complex schemes could be used.
-                            current_id = current_block.parents[0].clone();
+                            current_id = current_block.parents[0];
                         } else {
                             break;
                         }
@@ -1402,7 +2092,7 @@ impl QantoDAG {
 
                     if path_to_finalize.len() >= FINALIZATION_DEPTH as usize {
                         for id_to_finalize in path_to_finalize {
-                            if finalized_guard.insert(id_to_finalize.clone()) {
+                            if finalized_guard.insert(id_to_finalize) {
                                 log::debug!("Finalized block: {id_to_finalize}");
                             }
                         }
@@ -1438,7 +2128,7 @@ impl QantoDAG {
             return Ok(());
         }
 
-        let initial_validator_placeholder = DEV_ADDRESS.to_string();
+        let initial_validator_placeholder = DEV_ADDRESS;
         let (placeholder_pk, placeholder_sk) = dilithium5::keypair();
 
         let mut tips_guard = self.tips.write().await;
@@ -1472,20 +2162,24 @@ impl QantoDAG {
             let mut genesis_block = QantoBlock::new(QantoBlockCreationData {
                 chain_id: new_chain_id,
                 parents: vec![],
+                cross_chain_references: vec![],
                 transactions: vec![],
                 difficulty: parent_difficulty,
-                validator: initial_validator_placeholder.clone(),
-                miner: initial_validator_placeholder.clone(),
+                validator: initial_validator_placeholder,
+                miner: initial_validator_placeholder,
                 qr_signing_key: &placeholder_sk,
                 qr_public_key: &placeholder_pk,
                 timestamp: new_genesis_timestamp,
                 current_epoch: epoch,
                 height: 0,
+                fee_policy: FeePolicy::default(),
+                coinbase_maturity: self.coinbase_maturity,
+                utxo_root: utxo_set::EMPTY_ROOT.to_string(),
             })?;
             genesis_block.reward = 0;
-            let new_genesis_id = genesis_block.id.clone();
+            let new_genesis_id = genesis_block.id;
 
-            blocks_guard.insert(new_genesis_id.clone(), genesis_block);
+            blocks_guard.insert(new_genesis_id, genesis_block);
             let mut new_tips = HashSet::new();
             new_tips.insert(new_genesis_id);
             tips_guard.insert(new_chain_id, new_tips);
@@ -1500,7 +2194,7 @@ impl QantoDAG {
     #[instrument(skip(self, proposer_address, rule_name, new_value))]
     pub async fn propose_governance(
         &self,
-        proposer_address: String,
+        proposer_address: Address,
         rule_name: String,
         new_value: f64,
         _creation_epoch: u64,
@@ -1520,7 +2214,7 @@ impl QantoDAG {
         let proposal_id_val = format!("saga-proposal-{}", Uuid::new_v4());
         let proposal_obj = GovernanceProposal {
             id: proposal_id_val.clone(),
-            proposer: proposer_address,
+            proposer: proposer_address.to_string(),
             proposal_type: ProposalType::UpdateRule(rule_name, new_value),
             votes_for: 0.0,
             votes_against: 0.0,
@@ -1544,7 +2238,7 @@ impl QantoDAG {
     #[instrument]
     pub async fn vote_governance(
         &self,
-        voter: String,
+        voter: Address,
         proposal_id: String,
         vote_for: bool,
     ) -> Result<(), QantoDAGError> {
@@ -1586,7 +2280,7 @@ impl QantoDAG {
     pub async fn aggregate_blocks(
         &self,
         blocks_vec: Vec<QantoBlock>,
-        utxos_arc: &Arc<RwLock<HashMap<String, UTXO>>>,
+        utxos_arc: &Arc<RwLock<UtxoSet>>,
     ) -> Result<Option<QantoBlock>, QantoDAGError> {
         if blocks_vec.is_empty() {
             return Ok(None);
@@ -1604,61 +2298,154 @@ impl QantoDAG {
     }
 
     #[instrument]
-    pub async fn select_validator(&self) -> Option<String> {
+    pub async fn select_validator(&self) -> Option<Address> {
         let validators_guard = self.validators.read().await;
         if validators_guard.is_empty() {
             return None;
         }
         let total_stake_val: u64 = validators_guard.values().sum();
         if total_stake_val == 0 {
-            let validator_keys: Vec<String> = validators_guard.keys().cloned().collect();
+            let validator_keys: Vec<Address> = validators_guard.keys().copied().collect();
             if validator_keys.is_empty() {
                 return None;
             }
             let index = rand::thread_rng().gen_range(0..validator_keys.len());
-            return Some(validator_keys[index].clone());
+            return Some(validator_keys[index]);
         }
         let mut rand_num = rand::thread_rng().gen_range(0..total_stake_val);
         for (validator_addr, stake_val) in validators_guard.iter() {
             if rand_num < *stake_val {
-                return Some(validator_addr.clone());
+                return Some(*validator_addr);
             }
             rand_num -= *stake_val;
         }
-        validators_guard.keys().next().cloned()
+        validators_guard.keys().next().copied()
     }
 
-    #[instrument]
-    pub async fn get_state_snapshot(
-        &self,
-        chain_id_val: u32,
-    ) -> (HashMap<String, QantoBlock>, HashMap<String, UTXO>) {
+    /// Whether no block past genesis has been added yet.
+    pub async fn is_at_genesis(&self) -> bool {
+        self.blocks
+            .read()
+            .await
+            .values()
+            .all(|block| block.height == 0)
+    }
+
+    /// The highest finalized block past genesis, which snapshots are taken at.
+    pub async fn snapshot_checkpoint(&self) -> Option<QantoBlock> {
         let blocks_guard = self.blocks.read().await;
-        let mut chain_blocks_map = HashMap::new();
-        let mut utxos_map_for_chain = HashMap::new();
-        for (id_val, block_val) in blocks_guard.iter() {
-            if block_val.chain_id == chain_id_val {
-                chain_blocks_map.insert(id_val.clone(), block_val.clone());
-                for tx_val in &block_val.transactions {
-                    for (index_val, output_val) in tx_val.outputs.iter().enumerate() {
-                        let utxo_id_val = format!("{}_{}", tx_val.id, index_val);
-                        utxos_map_for_chain.insert(
-                            utxo_id_val.clone(),
-                            UTXO {
-                                address: output_val.address.clone(),
-                                amount: output_val.amount,
-                                tx_id: tx_val.id.clone(),
-                                output_index: index_val as u32,
-                                explorer_link: format!(
-                                    "https://qantoblockexplorer.org/utxo/{utxo_id_val}"
-                                ),
-                            },
-                        );
-                    }
-                }
+        let finalized_guard = self.finalized_blocks.read().await;
+        finalized_guard
+            .iter()
+            .filter_map(|id| blocks_guard.get(id))
+            .filter(|block| block.height > 0)
+            .max_by_key(|block| (block.height, block.id))
+            .cloned()
+    }
+
+    /// Starts the ledger from a verified snapshot checkpoint: the checkpoint
+    /// becomes the finalized tip of its chain, the emission tally resumes from
+    /// the snapshot's supply, and its validator set, epoch, SAGA rules and
+    /// fee policy history replace the local ones.
+    pub async fn install_checkpoint(
+        &self,
+        checkpoint: QantoBlock,
+        meta: SnapshotMeta,
+    ) -> Result<(), QantoDAGError> {
+        let mut blocks_guard = self.blocks.write().await;
+        let mut tips_guard = self.tips.write().await;
+        tips_guard.insert(checkpoint.chain_id, HashSet::from([checkpoint.id]));
+        self.finalized_blocks.write().await.insert(checkpoint.id);
+        self.difficulties
+            .write()
+            .await
+            .insert(checkpoint.chain_id, checkpoint.difficulty);
+        self.emission
+            .write()
+            .await
+            .seed(meta.issued_supply, meta.scheduled_supply);
+        *self.validators.write().await = meta.validators.into_iter().collect();
+        *self.current_epoch.write().await = meta.epoch;
+        self.saga
+            .economy
+            .epoch_rules
+            .write()
+            .await
+            .extend(meta.epoch_rules);
+        *self.saga.economy.fee_policies.write().await = meta.fee_policies;
+        info!(
+            "Installed snapshot checkpoint {} at height {} on chain {}.",
+            checkpoint.id, checkpoint.height, checkpoint.chain_id
+        );
+        blocks_guard.insert(checkpoint.id, checkpoint);
+        drop(blocks_guard);
+        drop(tips_guard);
+        self.persist_fee_policies(0).await
+    }
+
+    /// Whether the body of `block_id` was pruned by this node.
+    pub async fn is_pruned(&self, block_id: &BlockHash) -> bool {
+        self.pruned_blocks.read().await.contains(block_id)
+    }
+
+    /// In pruned mode, drops the bodies and undo data of blocks finalized
+    /// more than `keep_epochs` epochs ago. Returns how many were pruned.
+    #[instrument(skip(self, utxos_arc))]
+    pub async fn prune_block_bodies(
+        &self,
+        utxos_arc: &Arc<RwLock<UtxoSet>>,
+    ) -> Result<usize, QantoDAGError> {
+        let StorageMode::Pruned { keep_epochs } = self.storage_mode else {
+            return Ok(0);
+        };
+        let horizon = self.current_epoch.read().await.saturating_sub(keep_epochs);
+
+        let mut blocks_guard = self.blocks.write().await;
+        let finalized_guard = self.finalized_blocks.read().await;
+        let mut pruned_guard = self.pruned_blocks.write().await;
+        let mut batch = StoreBatch::default();
+        let mut pruned_height = 0;
+        let mut count = 0;
+        for id in finalized_guard.iter() {
+            if pruned_guard.contains(id) {
+                continue;
             }
+            let Some(block) = blocks_guard.get_mut(id) else {
+                continue;
+            };
+            if block.height == 0 || block.epoch >= horizon {
+                continue;
+            }
+            block.prune_body();
+            batch.delete(id.to_string());
+            batch.put(
+                format!("{PRUNED_DB_PREFIX}{id}"),
+                codec::encode(&BlockHeader::from(&*block))?,
+            );
+            pruned_height = pruned_height.max(block.height);
+            pruned_guard.insert(*id);
+            count += 1;
+        }
+        if count == 0 {
+            return Ok(0);
         }
-        (chain_blocks_map, utxos_map_for_chain)
+
+        utxos_arc
+            .write()
+            .await
+            .discard_undo(|block_id| pruned_guard.contains(block_id));
+        {
+            let mut height_guard = self.pruned_height.write().await;
+            *height_guard = (*height_guard).max(pruned_height);
+        }
+        drop(pruned_guard);
+        drop(finalized_guard);
+        drop(blocks_guard);
+
+        let store = self.store.clone();
+        task::spawn_blocking(move || store.write(batch)).await??;
+        info!("Pruned the bodies of {count} blocks finalized before epoch {horizon}.");
+        Ok(count)
     }
 
     pub async fn run_periodic_maintenance(&self) {
@@ -1680,11 +2467,23 @@ impl QantoDAG {
 
         let _rules = self.saga.economy.epoch_rules.read().await;
         self.saga.process_epoch_evolution(current_epoch, self).await;
+        if let Err(e) = self.persist_fee_policies(current_epoch).await {
+            warn!("Failed to persist the fee policy of epoch {current_epoch}: {e}");
+        }
 
         debug!("Periodic DAG maintenance complete for epoch {current_epoch}.");
     }
 }
 
+/// Runs CPU-bound `work` (typically a rayon job) without stalling the other
+/// tasks scheduled on this runtime worker.
+fn run_cpu_bound<R>(work: impl FnOnce() -> R) -> R {
+    match tokio::runtime::Handle::try_current().map(|handle| handle.runtime_flavor()) {
+        Ok(tokio::runtime::RuntimeFlavor::MultiThread) => task::block_in_place(work),
+        _ => work(),
+    }
+}
+
 impl Clone for QantoDAG {
     fn clone(&self) -> Self {
         Self {
@@ -1692,6 +2491,11 @@ impl Clone for QantoDAG {
             tips: self.tips.clone(),
             validators: self.validators.clone(),
             target_block_time: self.target_block_time,
+            storage_mode: self.storage_mode,
+            pruned_blocks: self.pruned_blocks.clone(),
+            pruned_height: self.pruned_height.clone(),
+            tx_index: self.tx_index,
+            coinbase_maturity: self.coinbase_maturity,
             difficulties: self.difficulties.clone(),
             difficulty_anchors: self.difficulty_anchors.clone(),
             emission: self.emission.clone(),
@@ -1702,12 +2506,150 @@ impl Clone for QantoDAG {
             block_creation_timestamps: self.block_creation_timestamps.clone(),
             anomaly_history: self.anomaly_history.clone(),
             cross_chain_swaps: self.cross_chain_swaps.clone(),
+            fee_estimator: self.fee_estimator.clone(),
             smart_contracts: self.smart_contracts.clone(),
             cache: self.cache.clone(),
-            db: self.db.clone(),
+            verified_signatures: self.verified_signatures.clone(),
+            store: self.store.clone(),
             saga: self.saga.clone(),
             self_arc: self.self_arc.clone(),
             current_epoch: self.current_epoch.clone(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coinbase_maturity_boundary() {
+        let origin = CoinbaseOrigin {
+            block_id: BlockHash::ZERO,
+            chain_id: 0,
+            height: 250,
+            maturity: 10,
+        };
+        assert_eq!(origin.blocks_remaining(origin.height), 10);
+        assert_eq!(origin.blocks_remaining(origin.height + 9), 1);
+        assert!(!origin.is_mature_at(origin.height + 9));
+        assert_eq!(origin.blocks_remaining(origin.height + 10), 0);
+        assert!(origin.is_mature_at(origin.height + 10));
+        assert!(origin.is_mature_at(origin.height + 11));
+    }
+
+    #[test]
+    fn test_block_commits_to_coinbase_maturity() {
+        let (public_key, secret_key) = dilithium5::keypair();
+        let mut block = QantoBlock::new(QantoBlockCreationData {
+            chain_id: 0,
+            parents: vec![BlockHash::new([1; 32])],
+            cross_chain_references: vec![],
+            transactions: vec![],
+            difficulty: 1,
+            validator: Address::new([1; 32]),
+            miner: Address::new([1; 32]),
+            qr_signing_key: &secret_key,
+            qr_public_key: &public_key,
+            timestamp: 100,
+            current_epoch: 0,
+            height: 1,
+            fee_policy: FeePolicy::default(),
+            coinbase_maturity: 10,
+            utxo_root: String::new(),
+        })
+        .unwrap();
+        assert!(block.verify_signature().unwrap());
+        assert_eq!(block.computed_id().unwrap(), block.id);
+
+        block.coinbase_maturity = DEFAULT_COINBASE_MATURITY;
+        assert!(!block.verify_signature().unwrap());
+        assert_ne!(block.computed_id().unwrap(), block.id);
+    }
+
+    #[test]
+    fn test_link_order_puts_references_first() {
+        let (public_key, secret_key) = dilithium5::keypair();
+        let block = |chain_id, timestamp, references: Vec<(u32, BlockHash)>| {
+            QantoBlock::new(QantoBlockCreationData {
+                chain_id,
+                parents: vec![BlockHash::new([chain_id as u8; 32])],
+                cross_chain_references: references,
+                transactions: vec![],
+                difficulty: 1,
+                validator: Address::new([1; 32]),
+                miner: Address::new([1; 32]),
+                qr_signing_key: &secret_key,
+                qr_public_key: &public_key,
+                timestamp,
+                current_epoch: 0,
+                height: 1,
+                fee_policy: FeePolicy::default(),
+                coinbase_maturity: DEFAULT_COINBASE_MATURITY,
+                utxo_root: String::new(),
+            })
+            .unwrap()
+        };
+        // A chain's clock may run ahead of a chain that references it.
+        let referenced = block(1, 200, vec![]);
+        let referencing = block(0, 100, vec![(1, referenced.id)]);
+        let unrelated = block(2, 150, vec![]);
+        let ids = |blocks: &[QantoBlock]| blocks.iter().map(|b| b.id).collect::<Vec<_>>();
+
+        let ordered = link_order(
+            vec![referencing.clone(), unrelated.clone(), referenced.clone()],
+            |block| block,
+        );
+        assert_eq!(
+            ids(&ordered),
+            vec![unrelated.id, referenced.id, referencing.id]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_fee_policies_survive_restart() {
+        let (public_key, secret_key) = dilithium5::keypair();
+        let store: Arc<dyn StateStore> = Arc::new(crate::storage::MemoryStore::new());
+        let dag = |store: Arc<dyn StateStore>| {
+            QantoDAG::new(
+                QantoDagConfig {
+                    initial_validator: Address::new([1; 32]),
+                    target_block_time: 60000,
+                    num_chains: 1,
+                    qr_signing_key: &secret_key,
+                    qr_public_key: &public_key,
+                    storage_mode: StorageMode::default(),
+                    tx_index: false,
+                    coinbase_maturity: DEFAULT_COINBASE_MATURITY,
+                },
+                Arc::new(PalletSaga::new(
+                    #[cfg(feature = "infinite-strata")]
+                    None,
+                )),
+                store,
+            )
+            .unwrap()
+        };
+        let governed = FeePolicy {
+            consensus_min_fee: 7,
+            ..FeePolicy::default()
+        };
+
+        let running = dag(store.clone());
+        running
+            .saga
+            .economy
+            .fee_policies
+            .write()
+            .await
+            .insert(3, governed);
+        running.persist_fee_policies(3).await.unwrap();
+        drop(running);
+
+        let restarted = dag(store);
+        assert_ne!(restarted.saga.fee_policy_at(2).await, governed);
+        assert_eq!(restarted.saga.fee_policy_at(3).await, governed);
+        assert_eq!(restarted.saga.fee_policy_at(9).await, governed);
+        assert_eq!(*restarted.current_epoch.read().await, 3);
+    }
+}
@@ -0,0 +1,263 @@
+//! --- Qanto Configuration Reload ---
+//! v1.0.0 - Live Settings
+//! A running node re-reads its configuration when it receives SIGHUP or its
+//! configuration file changes, assembling it from the same layers it started
+//! with. Only the settings in `RELOADABLE` are applied; a change to any
+//! other setting is rejected, and reported again on every reload until the
+//! node is restarted. Each reload is logged under `AUDIT_TARGET` together
+//! with every setting it applied or rejected.
+
+use crate::config::{Config, ConfigError, ConfigSources};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, SystemTime};
+use tokio::sync::watch;
+use toml::{Table, Value};
+use tracing::{info, warn};
+
+/// Log target of configuration reload events.
+pub const AUDIT_TARGET: &str = "qanto::audit";
+
+/// Settings applied without a restart. An entry ending in `.` covers a
+/// whole section.
+pub const RELOADABLE: &[&str] = &[
+    "logging.level",
+    "mining_threads",
+    "p2p.heartbeat_interval",
+    "p2p.mesh_n_low",
+    "p2p.mesh_n",
+    "p2p.mesh_n_high",
+    "p2p.mesh_outbound_min",
+    "rate_limits.",
+];
+
+/// How often the configuration file is checked for changes.
+const POLL_INTERVAL_SECS: u64 = 5;
+
+/// A setting whose value differs between two configurations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SettingChange {
+    pub key: String,
+    pub old: String,
+    pub new: String,
+}
+
+impl fmt::Display for SettingChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.key, self.old, self.new)
+    }
+}
+
+/// The outcome of comparing the running configuration with a new one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadReport {
+    pub applied: Vec<SettingChange>,
+    /// Changes that only take effect after a restart.
+    pub rejected: Vec<SettingChange>,
+}
+
+fn is_reloadable(key: &str) -> bool {
+    RELOADABLE
+        .iter()
+        .any(|setting| match setting.strip_suffix('.') {
+            Some(section) => key
+                .strip_prefix(section)
+                .is_some_and(|rest| rest.starts_with('.')),
+            None => key == *setting,
+        })
+}
+
+fn flatten(prefix: &str, table: Table, settings: &mut BTreeMap<String, Value>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+        match value {
+            Value::Table(inner) => flatten(&key, inner, settings),
+            value => {
+                settings.insert(key, value);
+            }
+        }
+    }
+}
+
+fn settings(config: &Config) -> BTreeMap<String, Value> {
+    let mut settings = BTreeMap::new();
+    if let Ok(table) = Table::try_from(config) {
+        flatten("", table, &mut settings);
+    }
+    settings
+}
+
+/// Lists the settings that differ between `current` and `new`, split into
+/// those a reload applies and those it rejects.
+pub fn diff(current: &Config, new: &Config) -> ReloadReport {
+    let (old, new_settings) = (settings(current), settings(new));
+    let mut keys: Vec<&String> = old.keys().chain(new_settings.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    let show =
+        |value: Option<&Value>| value.map_or_else(|| "<unset>".to_string(), Value::to_string);
+    let mut report = ReloadReport::default();
+    for key in keys {
+        let (before, after) = (old.get(key), new_settings.get(key));
+        if before == after {
+            continue;
+        }
+        let change = SettingChange {
+            key: key.clone(),
+            old: show(before),
+            new: show(after),
+        };
+        if is_reloadable(key) {
+            report.applied.push(change);
+        } else {
+            report.rejected.push(change);
+        }
+    }
+    // Never serialized, so compared on its own and never shown.
+    if current.p2p.hmac_secret != new.p2p.hmac_secret {
+        report.rejected.push(SettingChange {
+            key: "p2p.hmac_secret".to_string(),
+            old: "<redacted>".to_string(),
+            new: "<redacted>".to_string(),
+        });
+    }
+    report
+}
+
+/// Copies the reloadable settings of `new` into `config`.
+fn apply(config: &mut Config, new: &Config) {
+    config.logging.level = new.logging.level.clone();
+    config.mining_threads = new.mining_threads;
+    config.p2p.heartbeat_interval = new.p2p.heartbeat_interval;
+    config.p2p.mesh_n_low = new.p2p.mesh_n_low;
+    config.p2p.mesh_n = new.p2p.mesh_n;
+    config.p2p.mesh_n_high = new.p2p.mesh_n_high;
+    config.p2p.mesh_outbound_min = new.p2p.mesh_outbound_min;
+    config.rate_limits = new.rate_limits.clone();
+}
+
+/// Loads the configuration from `sources` and publishes its reloadable
+/// settings to `live`. An invalid configuration changes nothing.
+pub fn reload(
+    sources: &ConfigSources,
+    live: &watch::Sender<Config>,
+) -> Result<ReloadReport, ConfigError> {
+    let new = Config::load_layered(sources).inspect_err(|e| {
+        warn!(target: AUDIT_TARGET, "Configuration reload failed, nothing was changed: {e}");
+    })?;
+    let report = diff(&live.borrow(), &new);
+    if !report.applied.is_empty() {
+        live.send_modify(|config| apply(config, &new));
+    }
+
+    if report.applied.is_empty() && report.rejected.is_empty() {
+        info!(target: AUDIT_TARGET, "Configuration reloaded, no settings changed.");
+    }
+    for change in &report.applied {
+        info!(target: AUDIT_TARGET, "Configuration reload applied {change}");
+    }
+    for change in &report.rejected {
+        warn!(
+            target: AUDIT_TARGET,
+            "Configuration reload rejected {change}: this setting requires a restart"
+        );
+    }
+    Ok(report)
+}
+
+/// Reloads the configuration into `live` on SIGHUP and whenever the
+/// configuration file is modified.
+pub async fn watch_for_changes(sources: ConfigSources, live: watch::Sender<Config>) {
+    let modified = |path: &str| -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    };
+    let mut last_modified = sources.file.as_deref().and_then(modified);
+    let mut poll = tokio::time::interval(Duration::from_secs(POLL_INTERVAL_SECS));
+    poll.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    #[cfg(unix)]
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => Some(signal),
+        Err(e) => {
+            warn!("Cannot listen for SIGHUP, configuration reloads on file changes only: {e}");
+            None
+        }
+    };
+
+    loop {
+        #[cfg(unix)]
+        let hangup_received = async {
+            match hangup.as_mut() {
+                Some(signal) => signal.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let hangup_received = std::future::pending::<Option<()>>();
+
+        tokio::select! {
+            _ = hangup_received => {
+                info!(target: AUDIT_TARGET, "Received SIGHUP, reloading configuration.");
+            }
+            _ = poll.tick() => {
+                let current = sources.file.as_deref().and_then(modified);
+                if current == last_modified {
+                    continue;
+                }
+                last_modified = current;
+                info!(target: AUDIT_TARGET, "Configuration file changed, reloading.");
+            }
+        }
+        // Failures are logged by `reload` and leave the running settings as they were.
+        let _ = reload(&sources, &live);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_reload_applies_safe_settings_only() {
+        let file = NamedTempFile::new().unwrap();
+        let path = file.path().to_str().unwrap().to_string();
+        let sources = ConfigSources {
+            file: Some(path.clone()),
+            ..ConfigSources::default()
+        };
+        Config::default().save(&path).unwrap();
+        let (live, mut updates) = watch::channel(Config::load_layered(&sources).unwrap());
+
+        let mut edited = Config::default();
+        edited.logging.level = "debug".to_string();
+        edited.p2p.mesh_n = 12;
+        edited.rate_limits.api_per_second = 5;
+        edited.api_address = "127.0.0.1:9999".to_string();
+        edited.save(&path).unwrap();
+
+        let report = reload(&sources, &live).unwrap();
+        let keys =
+            |changes: &[SettingChange]| changes.iter().map(|c| c.key.clone()).collect::<Vec<_>>();
+        assert_eq!(
+            keys(&report.applied),
+            vec!["logging.level", "p2p.mesh_n", "rate_limits.api_per_second"]
+        );
+        assert_eq!(keys(&report.rejected), vec!["api_address"]);
+        assert!(updates.has_changed().unwrap());
+        let config = updates.borrow_and_update().clone();
+        assert_eq!(config.p2p.mesh_n, 12);
+        assert_eq!(config.rate_limits.api_per_second, 5);
+        assert_eq!(config.api_address, Config::default().api_address);
+
+        // An invalid file leaves the running settings alone.
+        std::fs::write(&path, "mining_threads = 0").unwrap();
+        assert!(reload(&sources, &live).is_err());
+        assert!(!updates.has_changed().unwrap());
+    }
+}
@@ -9,14 +9,16 @@
 // [!!] REFACTOR NOTE: This file is becoming large. Future work should break SAGA's components
 // (e.g., ai, governance, security) into a dedicated module folder (`src/saga/`).
 
+use crate::fee_policy::{self, FeePolicy};
 #[cfg(feature = "infinite-strata")]
 use crate::infinite_strata_node::InfiniteStrataNode;
 use crate::omega;
 use crate::qantodag::{QantoBlock, QantoDAG, MAX_TRANSACTIONS_PER_BLOCK};
 use crate::transaction::Transaction;
+use crate::types::Address;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
@@ -307,8 +309,8 @@ impl CarbonImpactPredictor {
         const ECU_PER_CONTRACT_OPCODE: f64 = 0.5;
         const ECU_CONTRACT_DEPLOYMENT_BASE: f64 = 500.0;
 
-        let tx_bytes = match serde_json::to_vec(tx) {
-            Ok(v) => v.len() as f64,
+        let tx_bytes = match crate::codec::encoded_len(tx) {
+            Ok(len) => len as f64,
             Err(e) => {
                 warn!(tx_id = %tx.id, error = %e, "Failed to serialize transaction for carbon calculation. Using default size.");
                 256.0 // Use a default, average size on failure
@@ -377,6 +379,8 @@ pub struct CognitiveAnalyticsEngine {
     pub carbon_impact_model: CarbonImpactPredictor,
     #[cfg(feature = "ai")]
     training_data: VecDeque<ModelTrainingData>,
+    #[cfg(feature = "ai")]
+    model_dir: PathBuf,
 }
 
 impl Default for CognitiveAnalyticsEngine {
@@ -424,6 +428,8 @@ impl CognitiveAnalyticsEngine {
             carbon_impact_model: CarbonImpactPredictor {},
             #[cfg(feature = "ai")]
             training_data: VecDeque::with_capacity(TRAINING_DATA_CAPACITY),
+            #[cfg(feature = "ai")]
+            model_dir: PathBuf::from(MODEL_SAVE_PATH),
         };
 
         #[cfg(feature = "ai")]
@@ -636,7 +642,7 @@ impl CognitiveAnalyticsEngine {
         (-deviation.powi(2)).exp()
     }
 
-    async fn check_historical_performance(&self, miner_address: &str, dag: &QantoDAG) -> f64 {
+    async fn check_historical_performance(&self, miner_address: &Address, dag: &QantoDAG) -> f64 {
         let blocks_reader = dag.blocks.read().await;
         let total_blocks = blocks_reader.len().max(1) as f64;
         let node_blocks = blocks_reader
@@ -899,22 +905,22 @@ impl CognitiveAnalyticsEngine {
 
     #[cfg(feature = "ai")]
     pub fn save_models_to_disk(&self) -> Result<(), SagaError> {
-        std::fs::create_dir_all(MODEL_SAVE_PATH)
+        std::fs::create_dir_all(&self.model_dir)
             .map_err(|e| SagaError::ModelFileError(e.to_string()))?;
 
-        let path = PathBuf::from(MODEL_SAVE_PATH).join(BEHAVIOR_MODEL_FILENAME);
+        let path = self.model_dir.join(BEHAVIOR_MODEL_FILENAME);
         self.behavior_model_vs
             .save(&path)
             .map_err(|e| SagaError::ModelFileError(e.to_string()))?;
         info!("SAGA: BehaviorNet model saved to {path:?}");
 
-        let path = PathBuf::from(MODEL_SAVE_PATH).join(CONGESTION_MODEL_FILENAME);
+        let path = self.model_dir.join(CONGESTION_MODEL_FILENAME);
         self.congestion_model_vs
             .save(&path)
             .map_err(|e| SagaError::ModelFileError(e.to_string()))?;
         info!("SAGA: CongestionPredictorLSTM model saved to {path:?}");
 
-        let path = PathBuf::from(MODEL_SAVE_PATH).join(CREDENTIAL_MODEL_FILENAME);
+        let path = self.model_dir.join(CREDENTIAL_MODEL_FILENAME);
         self.credential_verifier_model_vs
             .save(&path)
             .map_err(|e| SagaError::ModelFileError(e.to_string()))?;
@@ -923,9 +929,21 @@ impl CognitiveAnalyticsEngine {
         Ok(())
     }
 
+    /// Keeps the models in `dir` from now on, loading any saved there.
+    #[cfg(feature = "ai")]
+    pub fn set_model_dir(&mut self, dir: PathBuf) {
+        self.model_dir = dir;
+        if let Err(e) = self.load_models_from_disk() {
+            warn!(
+                "Could not load SAGA AI models from {:?}: {e}",
+                self.model_dir
+            );
+        }
+    }
+
     #[cfg(feature = "ai")]
     pub fn load_models_from_disk(&mut self) -> Result<(), SagaError> {
-        let path = PathBuf::from(MODEL_SAVE_PATH).join(BEHAVIOR_MODEL_FILENAME);
+        let path = self.model_dir.join(BEHAVIOR_MODEL_FILENAME);
         if path.exists() {
             self.behavior_model_vs
                 .load(&path)
@@ -933,7 +951,7 @@ impl CognitiveAnalyticsEngine {
             info!("SAGA: Loaded BehaviorNet model from {path:?}");
         }
 
-        let path = PathBuf::from(MODEL_SAVE_PATH).join(CONGESTION_MODEL_FILENAME);
+        let path = self.model_dir.join(CONGESTION_MODEL_FILENAME);
         if path.exists() {
             self.congestion_model_vs
                 .load(&path)
@@ -941,7 +959,7 @@ impl CognitiveAnalyticsEngine {
             info!("SAGA: Loaded CongestionPredictorLSTM model from {path:?}");
         }
 
-        let path = PathBuf::from(MODEL_SAVE_PATH).join(CREDENTIAL_MODEL_FILENAME);
+        let path = self.model_dir.join(CREDENTIAL_MODEL_FILENAME);
         if path.exists() {
             self.credential_verifier_model_vs
                 .load(&path)
@@ -1534,7 +1552,7 @@ impl SecurityMonitor {
             .values()
             .filter(|b| b.epoch >= current_epoch_val.saturating_sub(epoch_lookback))
             .fold(HashMap::new(), |mut acc, b| {
-                *acc.entry(b.miner.clone()).or_insert(0) += 1;
+                *acc.entry(b.miner.to_string()).or_insert(0) += 1;
                 acc
             });
 
@@ -1596,7 +1614,7 @@ impl SecurityMonitor {
         let mut creds_by_miner = HashMap::<String, Vec<CarbonOffsetCredential>>::new();
         for block in recent_blocks {
             creds_by_miner
-                .entry(block.miner.clone())
+                .entry(block.miner.to_string())
                 .or_default()
                 .extend(block.carbon_credentials.clone());
         }
@@ -1670,7 +1688,7 @@ impl SecurityMonitor {
                 .unwrap_or(block.timestamp);
 
             let (count, suspicious_count) = suspicious_timestamps
-                .entry(block.miner.clone())
+                .entry(block.miner.to_string())
                 .or_insert((0, 0));
             *count += 1;
 
@@ -1740,7 +1758,7 @@ impl SecurityMonitor {
         let tx_map: HashMap<_, _> = blocks
             .values()
             .flat_map(|b| &b.transactions)
-            .map(|tx| (tx.id.clone(), tx))
+            .map(|tx| (tx.id, tx))
             .collect();
 
         for block in recent_blocks {
@@ -1759,11 +1777,13 @@ impl SecurityMonitor {
                             for output in &tx.outputs {
                                 let output_addr = &output.address;
                                 tx_graph
-                                    .entry(input_addr.clone())
+                                    .entry(input_addr.to_string())
                                     .or_default()
-                                    .push(output_addr.clone());
-                                *address_tx_counts.entry(input_addr.clone()).or_insert(0) += 1;
-                                *address_tx_counts.entry(output_addr.clone()).or_insert(0) += 1;
+                                    .push(output_addr.to_string());
+                                *address_tx_counts.entry(input_addr.to_string()).or_insert(0) += 1;
+                                *address_tx_counts
+                                    .entry(output_addr.to_string())
+                                    .or_insert(0) += 1;
                             }
                         }
                     }
@@ -1844,10 +1864,10 @@ impl SecurityMonitor {
             let parent_miners: Vec<String> = block
                 .parents
                 .iter()
-                .filter_map(|p_id| blocks.get(p_id).map(|p| p.miner.clone()))
+                .filter_map(|p_id| blocks.get(p_id).map(|p| p.miner.to_string()))
                 .collect();
             miner_parent_map
-                .entry(block.miner.clone())
+                .entry(block.miner.to_string())
                 .or_default()
                 .extend(parent_miners);
         }
@@ -1994,12 +2014,12 @@ impl SecurityMonitor {
                     let tx1_inputs: std::collections::HashSet<_> = tx1
                         .inputs
                         .iter()
-                        .map(|i| (i.tx_id.clone(), i.output_index))
+                        .map(|i| (i.tx_id, i.output_index))
                         .collect();
                     let tx2_inputs: std::collections::HashSet<_> = tx2
                         .inputs
                         .iter()
-                        .map(|i| (i.tx_id.clone(), i.output_index))
+                        .map(|i| (i.tx_id, i.output_index))
                         .collect();
 
                     if !tx1_inputs.is_disjoint(&tx2_inputs) {
@@ -2137,6 +2157,8 @@ pub struct EconomicState {
     pub proactive_insights: Arc<RwLock<Vec<SagaInsight>>>,
     pub congestion_history: Arc<RwLock<VecDeque<f64>>>,
     pub environmental_metrics: Arc<RwLock<EnvironmentalMetrics>>,
+    /// Consensus fee policy by the epoch it took effect in.
+    pub fee_policies: Arc<RwLock<BTreeMap<u64, FeePolicy>>>,
 }
 
 #[derive(Debug)]
@@ -2283,7 +2305,8 @@ impl PalletSaga {
             "base_reward".to_string(),
             EpochRule {
                 value: 50.0,
-                description: "Base QNTO reward per block before modifiers.".to_string(),
+                description: "QNTO reward per block before modifiers at the initial emission rate."
+                    .to_string(),
             },
         );
         rules.insert(
@@ -2294,13 +2317,28 @@ impl PalletSaga {
             },
         );
         rules.insert(
-            "base_tx_fee_min".to_string(),
+            fee_policy::RULE_CONSENSUS_MIN_FEE.to_string(),
             EpochRule {
                 value: 1.0,
                 description: "A minimum flat fee for all transactions, regardless of amount."
                     .to_string(),
             },
         );
+        rules.insert(
+            fee_policy::RULE_MIN_RELAY_FEE_RATE.to_string(),
+            EpochRule {
+                value: 1.0,
+                description: "Minimum fee per 100 bytes for mempool admission.".to_string(),
+            },
+        );
+        rules.insert(
+            fee_policy::RULE_DEV_FEE_RATE.to_string(),
+            EpochRule {
+                value: fee_policy::DEV_FEE_RATE,
+                description: "Share of each transfer and block reward paid to the developer fund."
+                    .to_string(),
+            },
+        );
 
         // --- Governance & Karma ---
         rules.insert(
@@ -2408,6 +2446,8 @@ impl PalletSaga {
             .trusted_project_registry
             .insert("low-quality-p-001".to_string(), 0.5);
 
+        let genesis_fee_policy = FeePolicy::from_epoch_rules(&rules, 1.0);
+
         Self {
             reputation: ReputationState {
                 credit_scores: Arc::new(RwLock::new(HashMap::new())),
@@ -2425,6 +2465,7 @@ impl PalletSaga {
                 proactive_insights: Arc::new(RwLock::new(Vec::new())),
                 congestion_history: Arc::new(RwLock::new(VecDeque::with_capacity(10))),
                 environmental_metrics: Arc::new(RwLock::new(env_metrics)),
+                fee_policies: Arc::new(RwLock::new(BTreeMap::from([(0, genesis_fee_policy)]))),
             },
             cognitive_engine: Arc::new(RwLock::new(CognitiveAnalyticsEngine::new())),
             economic_model: Arc::new(PredictiveEconomicModel::new()),
@@ -2436,12 +2477,10 @@ impl PalletSaga {
         }
     }
 
-    /// Calculates the transaction fee based on a simple minimum.
-    /// The tiered fee structure has been removed in favor of a simpler, more predictable model
-    /// that is easier for SAGA to reason about autonomously.
-    pub async fn calculate_dynamic_fee(&self, _amount: u64) -> u64 {
+    /// Returns the fee policy this node relays under: the current epoch rules,
+    /// scaled by an active economic edict.
+    pub async fn fee_policy(&self) -> FeePolicy {
         let rules = self.economy.epoch_rules.read().await;
-        let min_fee = rules.get("base_tx_fee_min").map_or(1.0, |r| r.value) as u64;
 
         // The fee can be further modified by active edicts, for example during spam attacks.
         let edict_multiplier = if let Some(edict) = &*self.economy.active_edict.read().await {
@@ -2454,7 +2493,32 @@ impl PalletSaga {
             1.0
         };
 
-        (min_fee as f64 * edict_multiplier) as u64
+        FeePolicy::from_epoch_rules(&rules, edict_multiplier)
+    }
+
+    /// Returns the consensus fee policy a block of `epoch` commits to: the
+    /// policy recorded for the latest epoch up to `epoch`. It is derived from
+    /// the epoch rules alone, since edicts are local to this node.
+    pub async fn fee_policy_at(&self, epoch: u64) -> FeePolicy {
+        let policies = self.economy.fee_policies.read().await;
+        policies
+            .range(..=epoch)
+            .next_back()
+            .map_or_else(FeePolicy::default, |(_, policy)| *policy)
+    }
+
+    async fn record_fee_policy(&self, epoch: u64) {
+        let policy = FeePolicy::from_epoch_rules(&*self.economy.epoch_rules.read().await, 1.0);
+        self.economy
+            .fee_policies
+            .write()
+            .await
+            .insert(epoch, policy);
+    }
+
+    /// Calculates the minimum transaction fee under the current fee policy.
+    pub async fn calculate_dynamic_fee(&self, _amount: u64) -> u64 {
+        self.fee_policy().await.consensus_min_fee
     }
 
     #[inline]
@@ -2637,73 +2701,18 @@ impl PalletSaga {
         Ok(())
     }
 
-    async fn get_isnm_reward_multiplier(&self) -> f64 {
-        #[cfg(feature = "infinite-strata")]
-        {
-            if let Some(service) = &self.isnm_service {
-                let (multiplier, _redistributed_reward) = service.get_rewards().await;
-                return multiplier;
-            }
-        }
-        1.0
-    }
-
+    /// The reward a block must claim: exactly the scheduled reward for its
+    /// height plus the fees of its transactions. SAGA's modifiers read
+    /// node-local state, so they cannot adjust what a block mints without
+    /// validators disagreeing on it.
     pub async fn calculate_dynamic_reward(
         &self,
         block: &QantoBlock,
         dag_arc: &Arc<QantoDAG>,
     ) -> Result<u64> {
-        let rules = self.economy.epoch_rules.read().await;
-        let base_reward = rules.get("base_reward").map_or(50.0, |r| r.value);
-        let threat_modifier = rules
-            .get("omega_threat_reward_modifier")
-            .map_or(-0.25, |r| r.value);
-        let scs = self
-            .reputation
-            .credit_scores
-            .read()
-            .await
-            .get(&block.miner)
-            .map_or(0.5, |s| s.score);
-
-        let threat_level = omega::identity::get_threat_level().await;
-        let omega_penalty = match threat_level {
-            omega::identity::ThreatLevel::Nominal => 1.0,
-            omega::identity::ThreatLevel::Guarded => 1.0 + threat_modifier,
-            omega::identity::ThreatLevel::Elevated => 1.0 + (threat_modifier * 2.0),
-        }
-        .max(0.0);
-
-        let metrics = self.economy.environmental_metrics.read().await;
-        let market_premium = self
-            .economic_model
-            .predictive_market_premium(dag_arc, &metrics)
-            .await;
-
-        let edict_multiplier = if let Some(edict) = &*self.economy.active_edict.read().await {
-            if let EdictAction::Economic {
-                reward_multiplier, ..
-            } = edict.action
-            {
-                reward_multiplier
-            } else {
-                1.0
-            }
-        } else {
-            1.0
-        };
-
-        let isnm_multiplier = self.get_isnm_reward_multiplier().await;
+        let scheduled_reward = dag_arc.emission.read().await.reward_at(block.height);
         let total_fees = block.transactions.iter().map(|tx| tx.fee).sum::<u64>();
-
-        let final_reward = (base_reward
-            * scs
-            * omega_penalty
-            * market_premium
-            * edict_multiplier
-            * isnm_multiplier) as u64
-            + total_fees;
-        Ok(final_reward)
+        Ok(scheduled_reward + total_fees)
     }
 
     #[instrument(skip(self, dag))]
@@ -2717,6 +2726,7 @@ impl PalletSaga {
 
         // THINK: Process information, update internal models, and decide on actions.
         self.tally_proposals(current_epoch).await;
+        self.record_fee_policy(current_epoch).await;
         self.process_karma_decay(current_epoch).await;
         self.update_council(current_epoch).await;
 
@@ -2850,11 +2860,12 @@ impl PalletSaga {
     #[instrument(skip(self, trust_breakdown, dag_arc))]
     async fn update_credit_score(
         &self,
-        miner_address: &str,
+        miner_address: &Address,
         trust_breakdown: &TrustScoreBreakdown,
         dag_arc: &Arc<QantoDAG>,
     ) -> Result<()> {
         let rules = self.economy.epoch_rules.read().await;
+        let miner_key = miner_address.to_string();
 
         let trust_weight = rules.get("scs_trust_weight").map_or(0.55, |r| r.value);
         let karma_weight = rules.get("scs_karma_weight").map_or(0.2, |r| r.value);
@@ -2876,7 +2887,7 @@ impl PalletSaga {
             .karma_ledgers
             .read()
             .await
-            .get(miner_address)
+            .get(&miner_key)
             .map_or(0.0, |kl| (kl.total_karma as f64 / karma_divisor).min(1.0));
         let stake_score = dag_arc
             .validators
@@ -2897,7 +2908,7 @@ impl PalletSaga {
             + (env_score * env_weight);
 
         let mut scores = self.reputation.credit_scores.write().await;
-        let mut scs_entry = scores.entry(miner_address.to_string()).or_default().clone();
+        let mut scs_entry = scores.entry(miner_key.clone()).or_default().clone();
 
         scs_entry.score =
             (scs_entry.score * (1.0 - smoothing_factor)) + (new_raw_score * smoothing_factor);
@@ -2920,8 +2931,8 @@ impl PalletSaga {
             .factors
             .insert("stake_score".to_string(), stake_score);
 
-        scores.insert(miner_address.to_string(), scs_entry.clone());
-        info!(miner = miner_address, scs = scs_entry.score, "SCS Updated");
+        scores.insert(miner_key, scs_entry.clone());
+        info!(miner = %miner_address, scs = scs_entry.score, "SCS Updated");
         Ok(())
     }
 
@@ -3354,7 +3365,7 @@ impl PalletSaga {
     async fn propose_economic_parameter_tuning(&self, current_epoch: u64) -> bool {
         let network_state = *self.economy.network_state.read().await;
         if let NetworkState::UnderAttack(AttackType::Spam) = network_state {
-            let fee_rule = "base_tx_fee_min".to_string();
+            let fee_rule = fee_policy::RULE_CONSENSUS_MIN_FEE.to_string();
             let rules = self.economy.epoch_rules.read().await;
             let current_base_fee = rules.get(&fee_rule).map_or(1.0, |r| r.value);
 
@@ -0,0 +1,174 @@
+//! --- Qanto Database Schema ---
+//! v1.0.0 - Versioned Layouts
+//! The node database records the version of the layout it was written with.
+//! On startup older layouts are upgraded one migration at a time, each step
+//! recording its version once it completes so an interrupted upgrade resumes
+//! where it stopped. A database written by a newer version is refused rather
+//! than misread.
+//!
+//! | Version | Layout                                                          |
+//! |---------|-----------------------------------------------------------------|
+//! | 0       | Unversioned; blocks may be stored as JSON.                      |
+//! | 1       | Blocks in the versioned binary codec.                           |
+//! | 2       | Transaction, address-history and spent-output indexes present.  |
+
+use crate::codec;
+use crate::storage::{BlockStore, StateStore, StoreError};
+use crate::tx_index::{self, IndexError};
+use thiserror::Error;
+use tracing::info;
+
+/// Store key of the schema version record.
+pub const SCHEMA_VERSION_KEY: &[u8] = b"meta:schema_version";
+/// The layout this build writes.
+pub const CURRENT_SCHEMA_VERSION: u32 = 2;
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("Storage error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Index error: {0}")]
+    Index(#[from] IndexError),
+    #[error("Database schema version {found} is newer than the supported version {supported}; upgrade the node")]
+    TooNew { found: u32, supported: u32 },
+    #[error("Corrupt schema version record")]
+    CorruptVersion,
+}
+
+/// One upgrade step, from `to_version - 1` to `to_version`.
+pub struct Migration {
+    pub to_version: u32,
+    pub description: &'static str,
+    /// Returns how many records the step touches. Nothing is written unless
+    /// `apply` is set.
+    run: fn(&dyn StateStore, bool) -> Result<usize, SchemaError>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        to_version: 1,
+        description: "Re-encode JSON blocks in the binary codec",
+        run: |store, apply| Ok(codec::migrate_legacy_blocks(store, apply)?),
+    },
+    Migration {
+        to_version: 2,
+        description: "Build the transaction, address-history and spent-output indexes",
+        run: |store, apply| {
+            if apply {
+                Ok(tx_index::reindex(store)?.blocks)
+            } else {
+                Ok(store.blocks().count())
+            }
+        },
+    },
+];
+
+/// A migration step and the number of records it touched or would touch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStep {
+    pub to_version: u32,
+    pub description: &'static str,
+    pub records: usize,
+}
+
+/// The version recorded in `store`. A store without a record is a fresh
+/// database if empty, and an unversioned one (version 0) otherwise.
+pub fn schema_version(store: &dyn StateStore) -> Result<Option<u32>, SchemaError> {
+    match store.get(SCHEMA_VERSION_KEY)? {
+        Some(bytes) => {
+            let bytes: [u8; 4] = bytes
+                .as_slice()
+                .try_into()
+                .map_err(|_| SchemaError::CorruptVersion)?;
+            Ok(Some(u32::from_be_bytes(bytes)))
+        }
+        None if store.scan_prefix(b"").next().is_none() => Ok(None),
+        None => Ok(Some(0)),
+    }
+}
+
+fn set_schema_version(store: &dyn StateStore, version: u32) -> Result<(), SchemaError> {
+    Ok(store.put(SCHEMA_VERSION_KEY, &version.to_be_bytes())?)
+}
+
+/// Brings `store` up to `CURRENT_SCHEMA_VERSION`. With `apply` unset nothing
+/// is written and the steps that would run are reported.
+pub fn migrate(store: &dyn StateStore, apply: bool) -> Result<Vec<MigrationStep>, SchemaError> {
+    let Some(version) = schema_version(store)? else {
+        if apply {
+            set_schema_version(store, CURRENT_SCHEMA_VERSION)?;
+        }
+        return Ok(Vec::new());
+    };
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(SchemaError::TooNew {
+            found: version,
+            supported: CURRENT_SCHEMA_VERSION,
+        });
+    }
+
+    let mut steps = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.to_version > version) {
+        let records = (migration.run)(store, apply)?;
+        if apply {
+            set_schema_version(store, migration.to_version)?;
+            info!(
+                "Migrated database to schema v{}: {} ({records} records).",
+                migration.to_version, migration.description
+            );
+        }
+        steps.push(MigrationStep {
+            to_version: migration.to_version,
+            description: migration.description,
+            records,
+        });
+    }
+    Ok(steps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStore;
+
+    #[test]
+    fn test_fresh_store_is_stamped_current() {
+        let store = MemoryStore::new();
+        assert!(migrate(&store, true).unwrap().is_empty());
+        assert_eq!(
+            schema_version(&store).unwrap(),
+            Some(CURRENT_SCHEMA_VERSION)
+        );
+    }
+
+    #[test]
+    fn test_unversioned_store_migrates_step_by_step() {
+        let store = MemoryStore::new();
+        store.put(b"swap:legacy", b"{}").unwrap();
+        assert_eq!(schema_version(&store).unwrap(), Some(0));
+
+        let planned = migrate(&store, false).unwrap();
+        assert_eq!(
+            planned.iter().map(|s| s.to_version).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(schema_version(&store).unwrap(), Some(0));
+
+        assert_eq!(migrate(&store, true).unwrap(), planned);
+        assert_eq!(
+            schema_version(&store).unwrap(),
+            Some(CURRENT_SCHEMA_VERSION)
+        );
+        assert!(migrate(&store, true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_newer_schema_is_refused() {
+        let store = MemoryStore::new();
+        set_schema_version(&store, CURRENT_SCHEMA_VERSION + 1).unwrap();
+        assert!(matches!(
+            migrate(&store, true),
+            Err(SchemaError::TooNew { .. })
+        ));
+    }
+}
@@ -0,0 +1,743 @@
+//! --- Qanto State Snapshots ---
+//! v1.0.0 - Verifiable Fast Sync
+//! This module takes snapshots of the ledger state at finalized checkpoints so
+//! a new node can start from a recent block instead of replaying from genesis.
+//!
+//! - **Contents:** the UTXO set as of the checkpoint block, the supply its past
+//!   issued, the validator set and the SAGA epoch rules. The checkpoint block itself travels in the
+//!   manifest, so its signature, proof-of-work and `utxo_root` can be checked.
+//! - **File format:** the magic `QSNP`, then the length-prefixed manifest, then
+//!   the length-prefixed UTXO chunks. Lengths are little-endian `u32`s, and the
+//!   manifest and chunks use the canonical codec.
+//! - **Trust:** a signed, mined block proves nothing about whether it belongs
+//!   to the chain, and the validator set and SAGA rules are not committed on
+//!   chain at all. A node restores only a checkpoint configured in
+//!   `snapshot.trusted`, whose entry pins both the block ID and the hash of the
+//!   snapshot's other ledger state, so no single peer decides either. Nodes
+//!   log both values for every snapshot they take.
+//! - **Verification:** every chunk is checked against its hash in the manifest
+//!   as it arrives, and the rebuilt UTXO set must hash to the checkpoint's
+//!   state root.
+
+use crate::codec::{self, CodecError};
+use crate::fee_policy::FeePolicy;
+use crate::miner::Miner;
+use crate::qantodag::{QantoBlock, QantoDAG, QantoDAGError, UTXO};
+use crate::saga::EpochRule;
+use crate::types::{Address, BlockHash, OutPoint};
+use crate::utxo_set::UtxoSet;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tokio::sync::RwLock;
+use tokio::task;
+use tracing::{debug, info, warn};
+
+pub const SNAPSHOT_FORMAT_VERSION: u16 = 1;
+/// Number of UTXOs per chunk; one chunk fits comfortably in a gossip message.
+pub const UTXOS_PER_CHUNK: usize = 4_096;
+const SNAPSHOT_MAGIC: &[u8; 4] = b"QSNP";
+const SNAPSHOT_EXTENSION: &str = "qsnap";
+
+#[derive(Error, Debug)]
+pub enum SnapshotError {
+    #[error("Snapshot I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Snapshot encoding error: {0}")]
+    Codec(#[from] CodecError),
+    #[error("Not a snapshot file")]
+    BadMagic,
+    #[error("Unsupported snapshot format version {0}")]
+    UnsupportedVersion(u16),
+    #[error("Invalid checkpoint: {0}")]
+    InvalidCheckpoint(String),
+    #[error("Checkpoint {0} is not a trusted checkpoint")]
+    UntrustedCheckpoint(BlockHash),
+    #[error(
+        "Ledger state of checkpoint {checkpoint} hashes to {computed}, trusted value is {trusted}"
+    )]
+    MetaMismatch {
+        checkpoint: BlockHash,
+        trusted: String,
+        computed: String,
+    },
+    #[error("Chunk {index} is out of range; the snapshot has {count} chunks")]
+    ChunkOutOfRange { index: u32, count: usize },
+    #[error("Chunk {0} does not match its hash in the manifest")]
+    ChunkHashMismatch(u32),
+    #[error("Snapshot is missing chunk {0}")]
+    MissingChunk(u32),
+    #[error("Snapshot holds {found} UTXOs, manifest declares {expected}")]
+    UtxoCountMismatch { expected: u64, found: u64 },
+    #[error("UTXO state root mismatch: checkpoint commits to {committed}, snapshot hashes to {computed}")]
+    StateRootMismatch { committed: String, computed: String },
+    #[error("No snapshot for checkpoint {0}")]
+    UnknownCheckpoint(BlockHash),
+    #[error("QantoDAG error: {0}")]
+    QantoDAG(#[from] QantoDAGError),
+    #[error("Task join error: {0}")]
+    Join(#[from] task::JoinError),
+}
+
+/// Ledger state outside the UTXO set.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SnapshotMeta {
+    pub epoch: u64,
+    /// Supply issued and scheduled by the checkpoint's past, genesis outputs
+    /// included, which the restored node's emission tally starts from.
+    pub issued_supply: u64,
+    pub scheduled_supply: u64,
+    pub validators: BTreeMap<Address, u64>,
+    pub epoch_rules: BTreeMap<String, EpochRule>,
+    /// Consensus fee policy by the epoch it took effect in, which blocks
+    /// after the checkpoint are validated against.
+    pub fee_policies: BTreeMap<u64, FeePolicy>,
+}
+
+impl SnapshotMeta {
+    /// Hex-encoded Keccak256 hash of the encoded state, which a trusted
+    /// checkpoint pins.
+    pub fn commitment(&self) -> Result<String, SnapshotError> {
+        Ok(chunk_hash(&codec::encode(self)?))
+    }
+}
+
+/// A checkpoint a syncing node may restore from, as published by a source the
+/// operator trusts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedCheckpoint {
+    pub block_id: BlockHash,
+    /// The `SnapshotMeta::commitment` of the snapshot taken at the block.
+    pub meta_hash: String,
+}
+
+/// Describes a snapshot and commits to each of its chunks.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SnapshotManifest {
+    pub version: u16,
+    pub checkpoint: QantoBlock,
+    pub utxo_count: u64,
+    /// Hex-encoded Keccak256 hash of each encoded chunk.
+    pub chunk_hashes: Vec<String>,
+    pub meta: SnapshotMeta,
+}
+
+impl SnapshotManifest {
+    /// Checks the manifest's shape, that the checkpoint and its ledger state
+    /// are the ones `trusted` pins, and that the checkpoint is a correctly
+    /// signed and mined block.
+    pub fn verify(&self, trusted: &[TrustedCheckpoint]) -> Result<(), SnapshotError> {
+        if self.version != SNAPSHOT_FORMAT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(self.version));
+        }
+        let checkpoint = &self.checkpoint;
+        let pinned = trusted
+            .iter()
+            .find(|pinned| pinned.block_id == checkpoint.id)
+            .ok_or(SnapshotError::UntrustedCheckpoint(checkpoint.id))?;
+        if checkpoint.computed_id()? != checkpoint.id {
+            return Err(SnapshotError::InvalidCheckpoint(format!(
+                "block {} does not hash to its ID",
+                checkpoint.id
+            )));
+        }
+        let computed = self.meta.commitment()?;
+        if !computed.eq_ignore_ascii_case(&pinned.meta_hash) {
+            return Err(SnapshotError::MetaMismatch {
+                checkpoint: checkpoint.id,
+                trusted: pinned.meta_hash.clone(),
+                computed,
+            });
+        }
+        if checkpoint.height == 0 {
+            return Err(SnapshotError::InvalidCheckpoint(
+                "genesis blocks are not checkpoints".to_string(),
+            ));
+        }
+        if !checkpoint.verify_signature()? {
+            return Err(SnapshotError::InvalidCheckpoint(format!(
+                "block {} has an invalid signature",
+                checkpoint.id
+            )));
+        }
+        let target = Miner::calculate_target_from_difficulty(checkpoint.difficulty);
+        let pow_hash = hex::decode(checkpoint.hash()).map_err(QantoDAGError::from)?;
+        if !Miner::hash_meets_target(&pow_hash, &target) {
+            return Err(SnapshotError::InvalidCheckpoint(format!(
+                "block {} does not satisfy its proof-of-work",
+                checkpoint.id
+            )));
+        }
+        let expected_chunks = (self.utxo_count as usize).div_ceil(UTXOS_PER_CHUNK);
+        if self.chunk_hashes.len() != expected_chunks {
+            return Err(SnapshotError::InvalidCheckpoint(format!(
+                "{} UTXOs need {expected_chunks} chunks, manifest lists {}",
+                self.utxo_count,
+                self.chunk_hashes.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn chunk_hash(chunk: &[u8]) -> String {
+    hex::encode(Keccak256::digest(chunk))
+}
+
+/// A snapshot held in memory: its manifest and encoded chunks.
+pub struct Snapshot {
+    pub manifest: SnapshotManifest,
+    pub chunks: Vec<Vec<u8>>,
+}
+
+impl Snapshot {
+    /// Snapshots `utxos`, the set as of `checkpoint`, after checking it against
+    /// the checkpoint's state root.
+    pub fn build(
+        checkpoint: QantoBlock,
+        utxos: Vec<(OutPoint, UTXO)>,
+        meta: SnapshotMeta,
+    ) -> Result<Self, SnapshotError> {
+        let mut utxos = utxos;
+        utxos.sort_unstable_by_key(|(outpoint, _)| *outpoint);
+        let set: UtxoSet = utxos.iter().cloned().collect();
+        let computed = set.state_root();
+        if computed != checkpoint.utxo_root {
+            return Err(SnapshotError::StateRootMismatch {
+                committed: checkpoint.utxo_root.clone(),
+                computed,
+            });
+        }
+        let chunks = utxos
+            .chunks(UTXOS_PER_CHUNK)
+            .map(|chunk| codec::encode(&chunk))
+            .collect::<Result<Vec<_>, _>>()?;
+        let manifest = SnapshotManifest {
+            version: SNAPSHOT_FORMAT_VERSION,
+            checkpoint,
+            utxo_count: utxos.len() as u64,
+            chunk_hashes: chunks.iter().map(|chunk| chunk_hash(chunk)).collect(),
+            meta,
+        };
+        Ok(Self { manifest, chunks })
+    }
+
+    /// Writes the snapshot to `path`, replacing any file there only once the
+    /// new one is complete.
+    pub fn write_to(&self, path: &Path) -> Result<(), SnapshotError> {
+        let partial = path.with_extension("partial");
+        {
+            let mut writer = BufWriter::new(File::create(&partial)?);
+            writer.write_all(SNAPSHOT_MAGIC)?;
+            write_section(&mut writer, &codec::encode(&self.manifest)?)?;
+            for chunk in &self.chunks {
+                write_section(&mut writer, chunk)?;
+            }
+            writer
+                .into_inner()
+                .map_err(|e| e.into_error())?
+                .sync_all()?;
+        }
+        fs::rename(partial, path)?;
+        Ok(())
+    }
+
+    /// Reads a whole snapshot back, checking every chunk against the manifest.
+    pub fn read_from(path: &Path) -> Result<Self, SnapshotError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let manifest = read_manifest_from(&mut reader)?;
+        let mut chunks = Vec::with_capacity(manifest.chunk_hashes.len());
+        for (index, expected) in manifest.chunk_hashes.iter().enumerate() {
+            let chunk = read_section(&mut reader)?;
+            if &chunk_hash(&chunk) != expected {
+                return Err(SnapshotError::ChunkHashMismatch(index as u32));
+            }
+            chunks.push(chunk);
+        }
+        Ok(Self { manifest, chunks })
+    }
+}
+
+fn write_section(writer: &mut impl Write, bytes: &[u8]) -> Result<(), SnapshotError> {
+    let len = u32::try_from(bytes.len()).map_err(|_| CodecError::TooLarge {
+        size: bytes.len(),
+        limit: u32::MAX as usize,
+    })?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+fn read_section_len(reader: &mut impl Read) -> Result<usize, SnapshotError> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > codec::MAX_ENCODED_SIZE {
+        return Err(CodecError::TooLarge {
+            size: len,
+            limit: codec::MAX_ENCODED_SIZE,
+        }
+        .into());
+    }
+    Ok(len)
+}
+
+fn read_section(reader: &mut impl Read) -> Result<Vec<u8>, SnapshotError> {
+    let mut bytes = vec![0u8; read_section_len(reader)?];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_manifest_from(reader: &mut impl Read) -> Result<SnapshotManifest, SnapshotError> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != SNAPSHOT_MAGIC {
+        return Err(SnapshotError::BadMagic);
+    }
+    let manifest: SnapshotManifest = codec::decode(&read_section(reader)?)?;
+    if manifest.version != SNAPSHOT_FORMAT_VERSION {
+        return Err(SnapshotError::UnsupportedVersion(manifest.version));
+    }
+    Ok(manifest)
+}
+
+/// Reads only the manifest of the snapshot file at `path`.
+pub fn read_manifest(path: &Path) -> Result<SnapshotManifest, SnapshotError> {
+    read_manifest_from(&mut BufReader::new(File::open(path)?))
+}
+
+/// Reads chunk `index` of the snapshot file at `path`, skipping the others.
+pub fn read_chunk(path: &Path, index: u32) -> Result<Vec<u8>, SnapshotError> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let manifest = read_manifest_from(&mut reader)?;
+    let count = manifest.chunk_hashes.len();
+    if index as usize >= count {
+        return Err(SnapshotError::ChunkOutOfRange { index, count });
+    }
+    for _ in 0..index {
+        let len = read_section_len(&mut reader)?;
+        reader.seek(SeekFrom::Current(len as i64))?;
+    }
+    let chunk = read_section(&mut reader)?;
+    if chunk_hash(&chunk) != manifest.chunk_hashes[index as usize] {
+        return Err(SnapshotError::ChunkHashMismatch(index));
+    }
+    Ok(chunk)
+}
+
+/// Assembles a snapshot received from peers, one verified chunk at a time.
+pub struct SnapshotRestore {
+    manifest: SnapshotManifest,
+    chunks: Vec<Option<Vec<(OutPoint, UTXO)>>>,
+}
+
+impl SnapshotRestore {
+    /// Starts a restore of `manifest` if it passes `verify` against `trusted`.
+    pub fn new(
+        manifest: SnapshotManifest,
+        trusted: &[TrustedCheckpoint],
+    ) -> Result<Self, SnapshotError> {
+        manifest.verify(trusted)?;
+        let chunks = vec![None; manifest.chunk_hashes.len()];
+        Ok(Self { manifest, chunks })
+    }
+
+    pub fn checkpoint(&self) -> &QantoBlock {
+        &self.manifest.checkpoint
+    }
+
+    /// The lowest chunk index still to be received.
+    pub fn next_missing(&self) -> Option<u32> {
+        self.chunks
+            .iter()
+            .position(Option::is_none)
+            .map(|index| index as u32)
+    }
+
+    pub fn add_chunk(&mut self, index: u32, data: &[u8]) -> Result<(), SnapshotError> {
+        let count = self.chunks.len();
+        let expected = self
+            .manifest
+            .chunk_hashes
+            .get(index as usize)
+            .ok_or(SnapshotError::ChunkOutOfRange { index, count })?;
+        if &chunk_hash(data) != expected {
+            return Err(SnapshotError::ChunkHashMismatch(index));
+        }
+        self.chunks[index as usize] = Some(codec::decode(data)?);
+        Ok(())
+    }
+
+    /// Rebuilds the UTXO set and checks it against the checkpoint's state root.
+    pub fn finish(self) -> Result<(QantoBlock, SnapshotMeta, UtxoSet), SnapshotError> {
+        let mut set = UtxoSet::with_capacity(self.manifest.utxo_count as usize);
+        for (index, chunk) in self.chunks.into_iter().enumerate() {
+            set.extend(chunk.ok_or(SnapshotError::MissingChunk(index as u32))?);
+        }
+        if set.len() as u64 != self.manifest.utxo_count {
+            return Err(SnapshotError::UtxoCountMismatch {
+                expected: self.manifest.utxo_count,
+                found: set.len() as u64,
+            });
+        }
+        let computed = set.state_root();
+        if computed != self.manifest.checkpoint.utxo_root {
+            return Err(SnapshotError::StateRootMismatch {
+                committed: self.manifest.checkpoint.utxo_root,
+                computed,
+            });
+        }
+        set.settle_at(self.manifest.checkpoint.id);
+        Ok((self.manifest.checkpoint, self.manifest.meta, set))
+    }
+}
+
+/// The snapshots this node has taken, kept in one directory.
+pub struct SnapshotStore {
+    dir: PathBuf,
+    /// Minimum number of blocks between two checkpoints.
+    interval: u64,
+    retain: usize,
+    latest: RwLock<Option<(PathBuf, SnapshotManifest)>>,
+}
+
+impl SnapshotStore {
+    /// Opens the store in `dir`, picking up the newest snapshot already there.
+    pub fn open(
+        dir: impl Into<PathBuf>,
+        interval: u64,
+        retain: usize,
+    ) -> Result<Self, SnapshotError> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let latest = Self::snapshot_files(&dir)?
+            .into_iter()
+            .rev()
+            .find_map(|path| match read_manifest(&path) {
+                Ok(manifest) => Some((path, manifest)),
+                Err(e) => {
+                    warn!("Ignoring unreadable snapshot {}: {}", path.display(), e);
+                    None
+                }
+            });
+        if let Some((path, manifest)) = &latest {
+            info!(
+                "Found snapshot at height {} in {}",
+                manifest.checkpoint.height,
+                path.display()
+            );
+        }
+        Ok(Self {
+            dir,
+            interval,
+            retain: retain.max(1),
+            latest: RwLock::new(latest),
+        })
+    }
+
+    /// Snapshot files in `dir`, oldest first. File names start with the
+    /// zero-padded checkpoint height, so name order is height order.
+    fn snapshot_files(dir: &Path) -> Result<Vec<PathBuf>, SnapshotError> {
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == SNAPSHOT_EXTENSION)
+            })
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    pub async fn latest_manifest(&self) -> Option<SnapshotManifest> {
+        self.latest
+            .read()
+            .await
+            .as_ref()
+            .map(|(_, manifest)| manifest.clone())
+    }
+
+    /// Reads chunk `index` of the snapshot taken at `checkpoint`.
+    pub async fn chunk(&self, checkpoint: BlockHash, index: u32) -> Result<Vec<u8>, SnapshotError> {
+        let path = match &*self.latest.read().await {
+            Some((path, manifest)) if manifest.checkpoint.id == checkpoint => path.clone(),
+            _ => return Err(SnapshotError::UnknownCheckpoint(checkpoint)),
+        };
+        task::spawn_blocking(move || read_chunk(&path, index)).await?
+    }
+
+    /// Snapshots the newest finalized checkpoint if it is at least `interval`
+    /// blocks past the previous one. Returns the new manifest, if any.
+    pub async fn take(
+        &self,
+        dag: &QantoDAG,
+        utxos: &RwLock<UtxoSet>,
+    ) -> Result<Option<SnapshotManifest>, SnapshotError> {
+        let Some(checkpoint) = dag.snapshot_checkpoint().await else {
+            return Ok(None);
+        };
+        if let Some((_, latest)) = &*self.latest.read().await {
+            if checkpoint.height < latest.checkpoint.height + self.interval {
+                return Ok(None);
+            }
+        }
+        // The emission tally counts every block applied to the set, so the
+        // blocks outside the checkpoint's past are taken back out of it. Locks
+        // are taken in `add_block`'s order, which updates both together.
+        let (checkpoint_utxos, supply) = {
+            let blocks_guard = dag.blocks.read().await;
+            let utxos_guard = utxos.read().await;
+            let (Some(checkpoint_utxos), Some(later)) = (
+                utxos_guard.utxos_at(&checkpoint.id),
+                utxos_guard.blocks_after(&checkpoint.id),
+            ) else {
+                debug!(
+                    "Checkpoint {} is no longer in the UTXO undo history; skipping snapshot.",
+                    checkpoint.id
+                );
+                return Ok(None);
+            };
+            let emission = dag.emission.read().await;
+            let mut supply = emission.audit();
+            for block in later.iter().filter_map(|id| blocks_guard.get(id)) {
+                supply.issued = supply.issued.saturating_sub(block.minted());
+                supply.scheduled = supply
+                    .scheduled
+                    .saturating_sub(emission.reward_at(block.height));
+            }
+            (checkpoint_utxos, supply)
+        };
+        let meta = SnapshotMeta {
+            epoch: checkpoint.epoch,
+            issued_supply: supply.issued,
+            scheduled_supply: supply.scheduled,
+            validators: dag.validators.read().await.clone().into_iter().collect(),
+            epoch_rules: dag
+                .saga
+                .economy
+                .epoch_rules
+                .read()
+                .await
+                .clone()
+                .into_iter()
+                .collect(),
+            fee_policies: dag.saga.economy.fee_policies.read().await.clone(),
+        };
+        let path = self.dir.join(format!(
+            "{:012}-{}.{SNAPSHOT_EXTENSION}",
+            checkpoint.height, checkpoint.id
+        ));
+        let (path, manifest) = task::spawn_blocking(move || {
+            let snapshot =
+                Snapshot::build(checkpoint, checkpoint_utxos.into_iter().collect(), meta)?;
+            snapshot.write_to(&path)?;
+            Ok::<_, SnapshotError>((path, snapshot.manifest))
+        })
+        .await??;
+        info!(
+            "Took snapshot at height {} ({} UTXOs in {} chunks): {}",
+            manifest.checkpoint.height,
+            manifest.utxo_count,
+            manifest.chunk_hashes.len(),
+            path.display()
+        );
+        info!(
+            "Snapshot trust anchor: block_id = \"{}\", meta_hash = \"{}\"",
+            manifest.checkpoint.id,
+            manifest.meta.commitment()?
+        );
+        *self.latest.write().await = Some((path, manifest.clone()));
+        self.prune()?;
+        Ok(Some(manifest))
+    }
+
+    /// Deletes all but the newest `retain` snapshots.
+    fn prune(&self) -> Result<(), SnapshotError> {
+        let files = Self::snapshot_files(&self.dir)?;
+        let excess = files.len().saturating_sub(self.retain);
+        for path in &files[..excess] {
+            fs::remove_file(path)?;
+            debug!("Removed old snapshot {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::qantodag::{QantoBlockCreationData, DEFAULT_COINBASE_MATURITY};
+    use crate::types::TxId;
+    use pqcrypto_dilithium::dilithium5;
+
+    fn utxos(count: u32) -> Vec<(OutPoint, UTXO)> {
+        (0..count)
+            .map(|i| {
+                let tx_id = TxId::new(Keccak256::digest(i.to_be_bytes()).into());
+                let outpoint = OutPoint::new(tx_id, i % 3);
+                let utxo = UTXO {
+                    address: Address::new([7; 32]),
+                    amount: i as u64,
+                    tx_id,
+                    output_index: i % 3,
+                    explorer_link: String::new(),
+                    htlc: None,
+                    coinbase: None,
+                };
+                (outpoint, utxo)
+            })
+            .collect()
+    }
+
+    fn checkpoint(utxo_root: String) -> QantoBlock {
+        let (public_key, secret_key) = dilithium5::keypair();
+        QantoBlock::new(QantoBlockCreationData {
+            chain_id: 0,
+            parents: vec![BlockHash::new([1; 32])],
+            cross_chain_references: vec![],
+            transactions: vec![],
+            difficulty: 0,
+            validator: Address::new([7; 32]),
+            miner: Address::new([7; 32]),
+            qr_signing_key: &secret_key,
+            qr_public_key: &public_key,
+            timestamp: 1,
+            current_epoch: 3,
+            height: 42,
+            fee_policy: FeePolicy::default(),
+            coinbase_maturity: DEFAULT_COINBASE_MATURITY,
+            utxo_root,
+        })
+        .unwrap()
+    }
+
+    fn meta() -> SnapshotMeta {
+        SnapshotMeta {
+            epoch: 3,
+            issued_supply: 1_000,
+            scheduled_supply: 1_200,
+            validators: BTreeMap::from([(Address::new([7; 32]), 100)]),
+            epoch_rules: BTreeMap::new(),
+            fee_policies: BTreeMap::from([(0, FeePolicy::default())]),
+        }
+    }
+
+    fn trust(manifest: &SnapshotManifest) -> Vec<TrustedCheckpoint> {
+        vec![TrustedCheckpoint {
+            block_id: manifest.checkpoint.id,
+            meta_hash: manifest.meta.commitment().unwrap(),
+        }]
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let utxos = utxos(UTXOS_PER_CHUNK as u32 + 10);
+        let set: UtxoSet = utxos.iter().cloned().collect();
+        let snapshot = Snapshot::build(checkpoint(set.state_root()), utxos, meta()).unwrap();
+        assert_eq!(snapshot.chunks.len(), 2);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.qsnap");
+        snapshot.write_to(&path).unwrap();
+        let manifest = read_manifest(&path).unwrap();
+        let read_back = Snapshot::read_from(&path).unwrap();
+        assert_eq!(read_back.chunks, snapshot.chunks);
+
+        let trusted = trust(&manifest);
+        let mut restore = SnapshotRestore::new(manifest, &trusted).unwrap();
+        while let Some(index) = restore.next_missing() {
+            restore
+                .add_chunk(index, &read_chunk(&path, index).unwrap())
+                .unwrap();
+        }
+        let (checkpoint, meta, restored) = restore.finish().unwrap();
+        assert_eq!(checkpoint.id, snapshot.manifest.checkpoint.id);
+        assert_eq!(meta.validators.len(), 1);
+        assert_eq!(meta.issued_supply, 1_000);
+        assert_eq!(restored.state_root(), set.state_root());
+    }
+
+    #[test]
+    fn test_snapshot_rejects_tampering() {
+        let utxos = utxos(100);
+        let set: UtxoSet = utxos.iter().cloned().collect();
+        let snapshot =
+            Snapshot::build(checkpoint(set.state_root()), utxos.clone(), meta()).unwrap();
+
+        let trusted = trust(&snapshot.manifest);
+        let mut restore = SnapshotRestore::new(snapshot.manifest.clone(), &trusted).unwrap();
+        let mut tampered = utxos.clone();
+        tampered[0].1.amount += 1;
+        let tampered_chunk = codec::encode(&tampered).unwrap();
+        assert!(matches!(
+            restore.add_chunk(0, &tampered_chunk),
+            Err(SnapshotError::ChunkHashMismatch(0))
+        ));
+        assert!(matches!(
+            restore.add_chunk(1, &snapshot.chunks[0]),
+            Err(SnapshotError::ChunkOutOfRange { .. })
+        ));
+
+        // A snapshot whose UTXOs do not match the checkpoint's root is refused,
+        // both when it is built and when it is restored.
+        assert!(matches!(
+            Snapshot::build(checkpoint(set.state_root()), tampered.clone(), meta()),
+            Err(SnapshotError::StateRootMismatch { .. })
+        ));
+        let forged_chunk = codec::encode(&tampered).unwrap();
+        let mut forged = snapshot.manifest.clone();
+        forged.chunk_hashes = vec![chunk_hash(&forged_chunk)];
+        let mut restore = SnapshotRestore::new(forged, &trusted).unwrap();
+        restore.add_chunk(0, &forged_chunk).unwrap();
+        assert!(matches!(
+            restore.finish(),
+            Err(SnapshotError::StateRootMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_requires_trusted_checkpoint() {
+        let utxos = utxos(10);
+        let set: UtxoSet = utxos.iter().cloned().collect();
+        let snapshot = Snapshot::build(checkpoint(set.state_root()), utxos, meta()).unwrap();
+        let trusted = trust(&snapshot.manifest);
+
+        // A well-formed checkpoint no one pinned is refused.
+        let elsewhere = vec![TrustedCheckpoint {
+            block_id: BlockHash::new([5; 32]),
+            meta_hash: trusted[0].meta_hash.clone(),
+        }];
+        assert!(matches!(
+            snapshot.manifest.verify(&elsewhere),
+            Err(SnapshotError::UntrustedCheckpoint(_))
+        ));
+        assert!(matches!(
+            snapshot.manifest.verify(&[]),
+            Err(SnapshotError::UntrustedCheckpoint(_))
+        ));
+
+        // The pinned checkpoint with a validator set of the peer's choosing.
+        let mut forged = snapshot.manifest.clone();
+        forged
+            .meta
+            .validators
+            .insert(Address::new([9; 32]), 1_000_000);
+        assert!(matches!(
+            forged.verify(&trusted),
+            Err(SnapshotError::MetaMismatch { .. })
+        ));
+
+        // A checkpoint altered after it was signed no longer hashes to its ID.
+        let mut altered = snapshot.manifest.clone();
+        altered.checkpoint.timestamp += 1;
+        assert!(matches!(
+            altered.verify(&trusted),
+            Err(SnapshotError::InvalidCheckpoint(_))
+        ));
+        assert!(snapshot.manifest.verify(&trusted).is_ok());
+    }
+}
@@ -0,0 +1,247 @@
+//! --- Qanto Storage Backends ---
+//! v1.0.0 - Pluggable Stores
+//! The DAG, its indexes and the mempool journal persist through the
+//! `StateStore` trait instead of a concrete RocksDB handle.
+//!
+//! - **`RocksDbStore`** is the on-disk backend a node runs with.
+//! - **`MemoryStore`** keeps everything in a sorted map, so tests and
+//!   simulations run without touching the filesystem and without colliding
+//!   over shared database directories.
+//!
+//! `BlockStore` layers typed block access over any `StateStore`. Blocks are
+//! stored under their hex ID; other records use a `prefix:` key namespace.
+
+use crate::codec::{self, CodecError};
+use crate::qantodag::QantoBlock;
+use crate::types::BlockHash;
+use rocksdb::{Direction, IteratorMode, Options, WriteBatch, DB};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::{PoisonError, RwLock};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("RocksDB error: {0}")]
+    RocksDB(#[from] rocksdb::Error),
+    #[error("Encoding error: {0}")]
+    Codec(#[from] CodecError),
+}
+
+/// Key-value pairs yielded by a prefix scan, in key order.
+pub type StoreIter<'a> = Box<dyn Iterator<Item = Result<(Vec<u8>, Vec<u8>), StoreError>> + 'a>;
+
+enum BatchOp {
+    Put(Vec<u8>, Vec<u8>),
+    Delete(Vec<u8>),
+}
+
+/// Writes applied atomically by `StateStore::write`.
+#[derive(Default)]
+pub struct StoreBatch {
+    ops: Vec<BatchOp>,
+}
+
+impl StoreBatch {
+    pub fn put(&mut self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) {
+        self.ops
+            .push(BatchOp::Put(key.as_ref().to_vec(), value.as_ref().to_vec()));
+    }
+
+    pub fn delete(&mut self, key: impl AsRef<[u8]>) {
+        self.ops.push(BatchOp::Delete(key.as_ref().to_vec()));
+    }
+
+    /// Stores `block` under its ID.
+    pub fn put_block(&mut self, block: &QantoBlock) -> Result<(), CodecError> {
+        self.put(block.id.to_string(), codec::encode(block)?);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+/// A persistent, ordered key-value store.
+pub trait StateStore: Send + Sync {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StoreError>;
+
+    /// Applies every operation of `batch`, or none of them.
+    fn write(&self, batch: StoreBatch) -> Result<(), StoreError>;
+
+    /// All entries whose key starts with `prefix`; an empty prefix scans
+    /// the whole store.
+    fn scan_prefix(&self, prefix: &[u8]) -> StoreIter<'_>;
+
+    fn put(&self, key: &[u8], value: &[u8]) -> Result<(), StoreError> {
+        let mut batch = StoreBatch::default();
+        batch.put(key, value);
+        self.write(batch)
+    }
+}
+
+impl fmt::Debug for dyn StateStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StateStore")
+    }
+}
+
+/// Typed block access over a `StateStore`.
+pub trait BlockStore: StateStore {
+    fn block(&self, id: &BlockHash) -> Result<Option<QantoBlock>, StoreError> {
+        match self.get(id.to_string().as_bytes())? {
+            Some(bytes) => Ok(Some(codec::decode(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Every stored block, in ID order.
+    fn blocks(&self) -> Box<dyn Iterator<Item = Result<QantoBlock, StoreError>> + '_> {
+        Box::new(self.scan_prefix(b"").filter_map(|item| match item {
+            Ok((key, value)) => is_block_key(&key).then(|| Ok(codec::decode(&value)?)),
+            Err(e) => Some(Err(e)),
+        }))
+    }
+}
+
+impl<S: StateStore + ?Sized> BlockStore for S {}
+
+/// Whether `key` is a block ID rather than a `prefix:` record.
+pub fn is_block_key(key: &[u8]) -> bool {
+    std::str::from_utf8(key).is_ok_and(|key| key.parse::<BlockHash>().is_ok())
+}
+
+/// The on-disk RocksDB backend.
+pub struct RocksDbStore {
+    db: DB,
+}
+
+impl RocksDbStore {
+    /// Opens the database at `path`, creating it if missing.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        let mut opts = Options::default();
+        opts.create_if_missing(true);
+        Ok(Self {
+            db: DB::open(&opts, path)?,
+        })
+    }
+}
+
+impl StateStore for RocksDbStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StoreError> {
+        Ok(self.db.get(key)?)
+    }
+
+    fn write(&self, batch: StoreBatch) -> Result<(), StoreError> {
+        let mut write_batch = WriteBatch::default();
+        for op in batch.ops {
+            match op {
+                BatchOp::Put(key, value) => write_batch.put(key, value),
+                BatchOp::Delete(key) => write_batch.delete(key),
+            }
+        }
+        Ok(self.db.write(write_batch)?)
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> StoreIter<'_> {
+        let owned_prefix = prefix.to_vec();
+        Box::new(
+            self.db
+                .iterator(IteratorMode::From(prefix, Direction::Forward))
+                .map(|item| {
+                    item.map(|(key, value)| (key.into_vec(), value.into_vec()))
+                        .map_err(StoreError::from)
+                })
+                .take_while(move |item| {
+                    item.as_ref()
+                        .map_or(true, |(key, _)| key.starts_with(&owned_prefix))
+                }),
+        )
+    }
+}
+
+/// An in-memory backend for tests and simulations.
+#[derive(Default)]
+pub struct MemoryStore {
+    entries: RwLock<BTreeMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl StateStore for MemoryStore {
+    fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, StoreError> {
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+        Ok(entries.get(key).cloned())
+    }
+
+    fn write(&self, batch: StoreBatch) -> Result<(), StoreError> {
+        let mut entries = self.entries.write().unwrap_or_else(PoisonError::into_inner);
+        for op in batch.ops {
+            match op {
+                BatchOp::Put(key, value) => {
+                    entries.insert(key, value);
+                }
+                BatchOp::Delete(key) => {
+                    entries.remove(&key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn scan_prefix(&self, prefix: &[u8]) -> StoreIter<'_> {
+        let entries = self.entries.read().unwrap_or_else(PoisonError::into_inner);
+        let matching: Vec<_> = entries
+            .range(prefix.to_vec()..)
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .map(|(key, value)| Ok((key.clone(), value.clone())))
+            .collect();
+        Box::new(matching.into_iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(store: &dyn StateStore) {
+        let mut batch = StoreBatch::default();
+        batch.put(b"swap:a", b"1");
+        batch.put(b"swap:b", b"2");
+        batch.put(b"swaq", b"3");
+        batch.put(b"txidx:a", b"4");
+        store.write(batch).unwrap();
+        assert_eq!(store.get(b"swap:b").unwrap(), Some(b"2".to_vec()));
+
+        let keys: Vec<Vec<u8>> = store
+            .scan_prefix(b"swap:")
+            .map(|item| item.unwrap().0)
+            .collect();
+        assert_eq!(keys, vec![b"swap:a".to_vec(), b"swap:b".to_vec()]);
+        assert_eq!(store.scan_prefix(b"").count(), 4);
+
+        let mut batch = StoreBatch::default();
+        batch.delete(b"swap:a");
+        store.write(batch).unwrap();
+        assert_eq!(store.get(b"swap:a").unwrap(), None);
+        assert_eq!(store.scan_prefix(b"swap:").count(), 1);
+        assert_eq!(store.blocks().count(), 0);
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        exercise(&MemoryStore::new());
+        let dir = tempfile::tempdir().unwrap();
+        exercise(&RocksDbStore::open(dir.path()).unwrap());
+    }
+}
@@ -5,13 +5,18 @@
 //! This version adds the required PartialEq and Eq traits to the Transaction
 //! struct, resolving compilation errors in the mempool.
 
+use crate::codec;
+pub use crate::fee_policy::calculate_dynamic_fee;
+use crate::fee_policy::FeePolicy;
+use crate::htlc::HashTimeLock;
 use crate::omega;
-use crate::qantodag::{HomomorphicEncrypted, QantoDAG, QuantumResistantSignature, UTXO};
-use hex;
+use crate::qantodag::{HomomorphicEncrypted, QuantumResistantSignature, UTXO};
+use crate::types::{Address, OutPoint, TxId};
 use pqcrypto_dilithium::dilithium5;
 use pqcrypto_traits::sign::{PublicKey, SecretKey};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use sha3::{Digest, Keccak512};
+use sha3::{Digest, Keccak256, Keccak512};
 use sp_core::H256;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -25,18 +30,11 @@ const MAX_TRANSACTIONS_PER_MINUTE: u64 = 1000;
 const MAX_METADATA_PAIRS: usize = 16;
 const MAX_METADATA_KEY_LEN: usize = 64;
 const MAX_METADATA_VALUE_LEN: usize = 256;
-const FEE_TIER1_THRESHOLD: u64 = 1_000_000;
-const FEE_TIER2_THRESHOLD: u64 = 100_000_000;
-const FEE_RATE_TIER1: f64 = 0.01;
-const FEE_RATE_TIER2: f64 = 0.02;
-const FEE_RATE_TIER3: f64 = 0.03;
 
 #[derive(Error, Debug)]
 pub enum TransactionError {
     #[error("ΛΣ-ΩMEGA Protocol rejected the action as unstable")]
     OmegaRejection,
-    #[error("Invalid address format")]
-    InvalidAddress,
     #[error("Quantum-resistant signature verification failed")]
     QuantumSignatureVerification,
     #[error("Insufficient funds")]
@@ -59,24 +57,71 @@ pub enum TransactionError {
     InvalidMetadata(String),
     #[error("Post-quantum crypto error: {0}")]
     PqCrypto(String),
+    #[error("HTLC condition not met: {0}")]
+    Htlc(#[from] crate::htlc::HtlcError),
+    #[error("Fee policy violated: {0}")]
+    FeePolicy(#[from] crate::fee_policy::FeePolicyError),
+    #[error("Coinbase output {0} is not yet mature")]
+    ImmatureCoinbase(OutPoint),
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq)]
 pub struct Input {
-    pub tx_id: String,
+    pub tx_id: TxId,
     pub output_index: u32,
+    /// Swap secret revealed when redeeming an HTLC output.
+    #[serde(default)]
+    pub htlc_preimage: Option<String>,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)] // Added Eq for comparison
 pub struct Output {
-    pub address: String,
+    pub address: Address,
     pub amount: u64,
     pub homomorphic_encrypted: HomomorphicEncrypted,
+    /// Optional hash-time-lock restricting how this output may be spent.
+    #[serde(default)]
+    pub htlc: Option<HashTimeLock>,
+}
+
+impl Input {
+    /// The output this input spends.
+    pub fn outpoint(&self) -> OutPoint {
+        OutPoint::new(self.tx_id, self.output_index)
+    }
+}
+
+/// The last block height or epoch at which a transaction may be included.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TxExpiry {
+    Height(u64),
+    Epoch(u64),
+}
+
+impl TxExpiry {
+    /// Whether a block at `height` in `epoch` may no longer include the transaction.
+    pub fn is_expired(&self, height: u64, epoch: u64) -> bool {
+        match *self {
+            TxExpiry::Height(last_height) => height > last_height,
+            TxExpiry::Epoch(last_epoch) => epoch > last_epoch,
+        }
+    }
+}
+
+/// The block a transaction is checked for inclusion in. For mempool
+/// admission this is the next block the node expects to build on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpendContext {
+    pub height: u64,
+    pub timestamp: u64,
+    /// The fee policy the block commits to.
+    pub fee_policy: FeePolicy,
 }
 
 pub struct TransactionConfig<'a> {
-    pub sender: String,
-    pub receiver: String,
+    pub sender: Address,
+    pub receiver: Address,
     pub amount: u64,
     pub fee: u64,
     pub inputs: Vec<Input>,
@@ -84,60 +129,51 @@ pub struct TransactionConfig<'a> {
     pub metadata: Option<HashMap<String, String>>,
     pub signing_key_bytes: &'a [u8],
     pub public_key_bytes: &'a [u8],
-    pub tx_timestamps: Arc<RwLock<HashMap<String, u64>>>,
+    pub tx_timestamps: Arc<RwLock<HashMap<TxId, u64>>>,
+    pub valid_until: Option<TxExpiry>,
 }
 
 #[derive(Debug)]
 struct TransactionSigningPayload<'a> {
-    sender: &'a str,
-    receiver: &'a str,
+    sender: &'a Address,
+    receiver: &'a Address,
     amount: u64,
     fee: u64,
     inputs: &'a [Input],
     outputs: &'a [Output],
     metadata: &'a HashMap<String, String>,
     timestamp: u64,
+    valid_until: Option<TxExpiry>,
 }
 
 // FIX: Added PartialEq and Eq to allow transactions to be compared.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct Transaction {
-    pub id: String,
-    pub sender: String,
-    pub receiver: String,
+    pub id: TxId,
+    pub sender: Address,
+    pub receiver: Address,
     pub amount: u64,
     pub fee: u64,
     pub inputs: Vec<Input>,
     pub outputs: Vec<Output>,
     pub qr_signature: QuantumResistantSignature,
     pub timestamp: u64,
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default, serialize_with = "codec::sorted_map")]
     pub metadata: HashMap<String, String>,
-}
-
-pub fn calculate_dynamic_fee(amount: u64) -> u64 {
-    let rate = if amount < FEE_TIER1_THRESHOLD {
-        FEE_RATE_TIER1
-    } else if amount < FEE_TIER2_THRESHOLD {
-        FEE_RATE_TIER2
-    } else {
-        FEE_RATE_TIER3
-    };
-    (amount as f64 * rate).round() as u64
+    /// Blocks past this height or epoch must not include the transaction.
+    #[serde(default)]
+    pub valid_until: Option<TxExpiry>,
 }
 
 impl Transaction {
     #[instrument(skip(config))]
     pub async fn new(config: TransactionConfig<'_>) -> Result<Self, TransactionError> {
         Self::validate_structure_pre_creation(
-            &config.sender,
-            &config.receiver,
             config.amount,
             &config.inputs,
             config.metadata.as_ref(),
         )?;
         Self::check_rate_limit(&config.tx_timestamps, MAX_TRANSACTIONS_PER_MINUTE).await?;
-        Self::validate_addresses(&config.sender, &config.receiver, &config.outputs)?;
         let timestamp = Self::get_current_timestamp()?;
         let metadata = config.metadata.unwrap_or_default();
         let signing_payload = TransactionSigningPayload {
@@ -149,6 +185,7 @@ impl Transaction {
             outputs: &config.outputs,
             metadata: &metadata,
             timestamp,
+            valid_until: config.valid_until,
         };
         let signature_data = Self::serialize_for_signing(&signing_payload)?;
         let action_hash =
@@ -165,7 +202,7 @@ impl Transaction {
             .map_err(|_| TransactionError::QuantumSignatureVerification)?;
 
         let mut tx = Self {
-            id: String::new(),
+            id: TxId::ZERO,
             sender: config.sender,
             receiver: config.receiver,
             amount: config.amount,
@@ -175,10 +212,11 @@ impl Transaction {
             qr_signature: signature_obj,
             timestamp,
             metadata,
+            valid_until: config.valid_until,
         };
         tx.id = tx.compute_hash();
         let mut timestamps_guard = config.tx_timestamps.write().await;
-        timestamps_guard.insert(tx.id.clone(), timestamp);
+        timestamps_guard.insert(tx.id, timestamp);
         if timestamps_guard.len() > (MAX_TRANSACTIONS_PER_MINUTE * 2) as usize {
             let current_time = Self::get_current_timestamp().unwrap_or(0);
             timestamps_guard
@@ -188,13 +226,13 @@ impl Transaction {
     }
 
     pub(crate) fn new_coinbase(
-        receiver: String,
+        receiver: Address,
         reward: u64,
         signing_key_bytes: &[u8],
         public_key_bytes: &[u8],
         outputs: Vec<Output>,
     ) -> Result<Self, TransactionError> {
-        let sender = "0000000000000000000000000000000000000000000000000000000000000000".to_string();
+        let sender = Address::ZERO;
         let timestamp = Self::get_current_timestamp()?;
         let metadata = HashMap::new();
         let signing_payload = TransactionSigningPayload {
@@ -206,6 +244,7 @@ impl Transaction {
             outputs: &outputs,
             metadata: &metadata,
             timestamp,
+            valid_until: None,
         };
         let signature_data = Self::serialize_for_signing(&signing_payload)?;
 
@@ -218,7 +257,7 @@ impl Transaction {
             .map_err(|_| TransactionError::QuantumSignatureVerification)?;
 
         let mut tx = Self {
-            id: String::new(),
+            id: TxId::ZERO,
             sender,
             receiver,
             amount: reward,
@@ -228,6 +267,7 @@ impl Transaction {
             qr_signature: signature_obj,
             timestamp,
             metadata,
+            valid_until: None,
         };
         tx.id = tx.compute_hash();
         Ok(tx)
@@ -237,27 +277,21 @@ impl Transaction {
         self.inputs.is_empty()
     }
 
+    /// Whether a block at `height` in `epoch` may no longer include this transaction.
+    pub fn is_expired_at(&self, height: u64, epoch: u64) -> bool {
+        self.valid_until
+            .is_some_and(|expiry| expiry.is_expired(height, epoch))
+    }
+
     pub fn get_metadata(&self) -> &HashMap<String, String> {
         &self.metadata
     }
 
     fn validate_structure_pre_creation(
-        sender: &str,
-        receiver: &str,
         amount: u64,
         inputs: &[Input],
         metadata: Option<&HashMap<String, String>>,
     ) -> Result<(), TransactionError> {
-        if sender.is_empty() {
-            return Err(TransactionError::InvalidStructure(
-                "Sender cannot be empty".to_string(),
-            ));
-        }
-        if receiver.is_empty() {
-            return Err(TransactionError::InvalidStructure(
-                "Receiver cannot be empty".to_string(),
-            ));
-        }
         if amount == 0 && !inputs.is_empty() {
             return Err(TransactionError::InvalidStructure(
                 "Amount cannot be zero for regular transactions".to_string(),
@@ -281,7 +315,7 @@ impl Transaction {
     }
 
     async fn check_rate_limit(
-        tx_timestamps: &Arc<RwLock<HashMap<String, u64>>>,
+        tx_timestamps: &Arc<RwLock<HashMap<TxId, u64>>>,
         max_txs: u64,
     ) -> Result<(), TransactionError> {
         let now = Self::get_current_timestamp()?;
@@ -296,25 +330,6 @@ impl Transaction {
         Ok(())
     }
 
-    fn validate_addresses(
-        sender: &str,
-        receiver: &str,
-        outputs: &[Output],
-    ) -> Result<(), TransactionError> {
-        if !Self::is_valid_address(sender)
-            || !Self::is_valid_address(receiver)
-            || outputs.iter().any(|o| !Self::is_valid_address(&o.address))
-        {
-            Err(TransactionError::InvalidAddress)
-        } else {
-            Ok(())
-        }
-    }
-
-    fn is_valid_address(address: &str) -> bool {
-        address.len() == 64 && hex::decode(address).is_ok()
-    }
-
     fn get_current_timestamp() -> Result<u64, TransactionError> {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -326,18 +341,18 @@ impl Transaction {
         payload: &TransactionSigningPayload,
     ) -> Result<Vec<u8>, TransactionError> {
         let mut hasher = Keccak512::new();
-        hasher.update(payload.sender.as_bytes());
-        hasher.update(payload.receiver.as_bytes());
+        hasher.update(payload.sender.hex_bytes());
+        hasher.update(payload.receiver.hex_bytes());
         hasher.update(payload.amount.to_be_bytes());
         hasher.update(payload.fee.to_be_bytes());
-        payload.inputs.iter().for_each(|i| {
-            hasher.update(i.tx_id.as_bytes());
-            hasher.update(i.output_index.to_be_bytes());
-        });
-        payload.outputs.iter().for_each(|o| {
-            hasher.update(o.address.as_bytes());
-            hasher.update(o.amount.to_be_bytes());
-        });
+        payload
+            .inputs
+            .iter()
+            .for_each(|i| Self::hash_input(&mut hasher, i));
+        payload
+            .outputs
+            .iter()
+            .for_each(|o| Self::hash_output(&mut hasher, o));
         let mut sorted_metadata: Vec<_> = payload.metadata.iter().collect();
         sorted_metadata.sort_by_key(|(k, _)| *k);
         sorted_metadata.iter().for_each(|(k, v)| {
@@ -345,23 +360,58 @@ impl Transaction {
             hasher.update(v.as_bytes());
         });
         hasher.update(payload.timestamp.to_be_bytes());
+        Self::hash_expiry(&mut hasher, payload.valid_until);
         Ok(hasher.finalize().to_vec())
     }
 
-    fn compute_hash(&self) -> String {
+    // HTLC fields are only hashed when present so that plain inputs and outputs
+    // keep the same signing payload and ID as before HTLC support.
+    fn hash_input(hasher: &mut Keccak512, input: &Input) {
+        hasher.update(input.tx_id.hex_bytes());
+        hasher.update(input.output_index.to_be_bytes());
+        if let Some(preimage) = &input.htlc_preimage {
+            hasher.update(preimage.as_bytes());
+        }
+    }
+
+    fn hash_output(hasher: &mut Keccak512, output: &Output) {
+        hasher.update(output.address.hex_bytes());
+        hasher.update(output.amount.to_be_bytes());
+        if let Some(lock) = &output.htlc {
+            hasher.update(lock.recipient.hex_bytes());
+            hasher.update(lock.refund_address.hex_bytes());
+            hasher.update(lock.secret_hash.as_bytes());
+            hasher.update(lock.timelock.to_be_bytes());
+        }
+    }
+
+    // Like HTLC fields, the expiry is only hashed when set.
+    fn hash_expiry(hasher: &mut Keccak512, valid_until: Option<TxExpiry>) {
+        match valid_until {
+            Some(TxExpiry::Height(height)) => {
+                hasher.update([0u8]);
+                hasher.update(height.to_be_bytes());
+            }
+            Some(TxExpiry::Epoch(epoch)) => {
+                hasher.update([1u8]);
+                hasher.update(epoch.to_be_bytes());
+            }
+            None => {}
+        }
+    }
+
+    fn compute_hash(&self) -> TxId {
         let mut hasher = Keccak512::new();
-        hasher.update(self.sender.as_bytes());
-        hasher.update(self.receiver.as_bytes());
+        hasher.update(self.sender.hex_bytes());
+        hasher.update(self.receiver.hex_bytes());
         hasher.update(self.amount.to_be_bytes());
         hasher.update(self.fee.to_be_bytes());
-        self.inputs.iter().for_each(|i| {
-            hasher.update(i.tx_id.as_bytes());
-            hasher.update(i.output_index.to_be_bytes());
-        });
-        self.outputs.iter().for_each(|o| {
-            hasher.update(o.address.as_bytes());
-            hasher.update(o.amount.to_be_bytes());
-        });
+        self.inputs
+            .iter()
+            .for_each(|i| Self::hash_input(&mut hasher, i));
+        self.outputs
+            .iter()
+            .for_each(|o| Self::hash_output(&mut hasher, o));
         let mut sorted_metadata: Vec<_> = self.metadata.iter().collect();
         sorted_metadata.sort_by_key(|(k, _)| *k);
         sorted_metadata.iter().for_each(|(k, v)| {
@@ -369,15 +419,15 @@ impl Transaction {
             hasher.update(v.as_bytes());
         });
         hasher.update(self.timestamp.to_be_bytes());
-        hex::encode(&hasher.finalize()[..32])
+        Self::hash_expiry(&mut hasher, self.valid_until);
+        let digest = hasher.finalize();
+        let mut id = [0u8; 32];
+        id.copy_from_slice(&digest[..32]);
+        TxId::new(id)
     }
 
-    #[instrument(skip(self, _dag, utxos))]
-    pub async fn verify(
-        &self,
-        _dag: &QantoDAG,
-        utxos: &HashMap<String, UTXO>,
-    ) -> Result<(), TransactionError> {
+    /// The digest covered by the transaction's signature.
+    pub fn signing_bytes(&self) -> Result<Vec<u8>, TransactionError> {
         let signing_payload = TransactionSigningPayload {
             sender: &self.sender,
             receiver: &self.receiver,
@@ -387,12 +437,53 @@ impl Transaction {
             outputs: &self.outputs,
             metadata: &self.metadata,
             timestamp: self.timestamp,
+            valid_until: self.valid_until,
         };
-        let data_to_verify = Self::serialize_for_signing(&signing_payload)?;
+        Self::serialize_for_signing(&signing_payload)
+    }
 
-        if !self.qr_signature.verify(&data_to_verify) {
+    /// Binds the signed payload to the signature and key that signed it, so a
+    /// successful signature check can be cached and looked up later.
+    pub fn signature_digest(&self) -> Result<[u8; 32], TransactionError> {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.signing_bytes()?);
+        hasher.update(&self.qr_signature.signer_public_key);
+        hasher.update(&self.qr_signature.signature);
+        Ok(hasher.finalize().into())
+    }
+
+    /// Stateless check of the quantum-resistant signature.
+    pub fn verify_signature(&self) -> Result<(), TransactionError> {
+        if !self.qr_signature.verify(&self.signing_bytes()?) {
             return Err(TransactionError::QuantumSignatureVerification);
         }
+        Ok(())
+    }
+
+    #[instrument(skip(self, utxos))]
+    pub async fn verify(
+        &self,
+        utxos: &HashMap<OutPoint, UTXO>,
+        context: SpendContext,
+    ) -> Result<(), TransactionError> {
+        self.verify_signature()?;
+        self.verify_inputs(utxos, context).await
+    }
+
+    /// Stateful checks against `utxos` and the current consensus rules for
+    /// inclusion in the block described by `context`. The signature must
+    /// already have been checked with `verify_signature`.
+    #[instrument(skip(self, utxos))]
+    pub async fn verify_inputs(
+        &self,
+        utxos: &HashMap<OutPoint, UTXO>,
+        context: SpendContext,
+    ) -> Result<(), TransactionError> {
+        for output in &self.outputs {
+            if let Some(lock) = &output.htlc {
+                lock.validate_output(&output.address)?;
+            }
+        }
 
         if self.is_coinbase() {
             if self.fee != 0 {
@@ -407,16 +498,39 @@ impl Transaction {
                 ));
             }
         } else {
+            context.fee_policy.check_transaction(self)?;
+
             let mut total_input_value = 0;
             for input in &self.inputs {
-                let utxo_id = format!("{}_{}", input.tx_id, input.output_index);
+                let utxo_id = input.outpoint();
                 let utxo = utxos.get(&utxo_id).ok_or_else(|| {
                     TransactionError::InvalidStructure(format!("UTXO {utxo_id} not found"))
                 })?;
-                if utxo.address != self.sender {
-                    return Err(TransactionError::InvalidStructure(format!(
-                        "Input UTXO {utxo_id} does not belong to sender"
-                    )));
+                if let Some(origin) = &utxo.coinbase {
+                    if !origin.is_mature_at(context.height) {
+                        return Err(TransactionError::ImmatureCoinbase(utxo_id));
+                    }
+                }
+                match &utxo.htlc {
+                    Some(lock) => {
+                        lock.authorize_spend(
+                            &self.sender,
+                            input.htlc_preimage.as_deref(),
+                            context.timestamp,
+                        )?;
+                    }
+                    None => {
+                        if input.htlc_preimage.is_some() {
+                            return Err(TransactionError::InvalidStructure(format!(
+                                "Input UTXO {utxo_id} is not hash-time-locked"
+                            )));
+                        }
+                        if utxo.address != self.sender {
+                            return Err(TransactionError::InvalidStructure(format!(
+                                "Input UTXO {utxo_id} does not belong to sender"
+                            )));
+                        }
+                    }
                 }
                 total_input_value += utxo.amount;
             }
@@ -432,17 +546,24 @@ impl Transaction {
     #[instrument]
     pub fn generate_utxo(&self, index: u32) -> UTXO {
         let output = &self.outputs[index as usize];
-        let utxo_id = format!("{}_{}", self.id, index);
+        let utxo_id = OutPoint::new(self.id, index);
         UTXO {
-            address: output.address.clone(),
+            address: output.address,
             amount: output.amount,
-            tx_id: self.id.clone(),
+            tx_id: self.id,
             output_index: index,
             explorer_link: format!("https://qantoblockexplorer.org/utxo/{utxo_id}"),
+            htlc: output.htlc.clone(),
+            coinbase: None,
         }
     }
 }
 
+/// Verifies the signatures of `txs` in parallel on the rayon pool.
+pub fn verify_signatures_parallel(txs: &[&Transaction]) -> Result<(), TransactionError> {
+    txs.par_iter().try_for_each(|tx| tx.verify_signature())
+}
+
 impl Zeroize for Transaction {
     fn zeroize(&mut self) {
         self.id.zeroize();
@@ -454,6 +575,7 @@ impl Zeroize for Transaction {
         self.outputs.clear();
         self.timestamp = 0;
         self.metadata.clear();
+        self.valid_until = None;
     }
 }
 
@@ -467,8 +589,9 @@ impl Drop for Transaction {
 mod tests {
     use super::*;
     use crate::omega::{self, identity::set_threat_level, identity::ThreatLevel, OmegaState};
-    use crate::qantodag::{QantoDAG, QantoDagConfig};
+    use crate::qantodag::{QantoDAG, QantoDagConfig, StorageMode, DEFAULT_COINBASE_MATURITY};
     use crate::saga::PalletSaga;
+    use crate::storage::MemoryStore;
     use crate::wallet::Wallet;
     use pqcrypto_traits::sign::{PublicKey, SecretKey};
     use serial_test::serial;
@@ -483,11 +606,6 @@ mod tests {
             set_threat_level(ThreatLevel::Nominal);
         }
 
-        let db_path = "qantodag_db_test";
-        if std::path::Path::new(db_path).exists() {
-            std::fs::remove_dir_all(db_path)?;
-        }
-
         let wallet = Arc::new(Wallet::new()?);
         let (qr_secret_key, qr_public_key) = wallet.get_keypair()?;
         let sender_address = wallet.address();
@@ -495,23 +613,25 @@ mod tests {
         let fee = 5;
         let dev_fee_on_transfer = (amount_to_receiver as f64 * 0.0304).round() as u64;
 
+        let genesis_tx_id = TxId::new([0x01; 32]);
+        let receiver = Address::new([0x01; 32]);
         let mut initial_utxos_map = HashMap::new();
         let input_utxo_amount = amount_to_receiver + fee + dev_fee_on_transfer + 10;
         let genesis_utxo_for_test = UTXO {
-            address: sender_address.clone(),
+            address: sender_address,
             amount: input_utxo_amount,
-            tx_id: "genesis_tx_id_for_test_0".to_string(),
+            tx_id: genesis_tx_id,
             output_index: 0,
             explorer_link: String::new(),
+            htlc: None,
+            coinbase: None,
         };
-        initial_utxos_map.insert(
-            "genesis_tx_id_for_test_0_0".to_string(),
-            genesis_utxo_for_test,
-        );
+        initial_utxos_map.insert(OutPoint::new(genesis_tx_id, 0), genesis_utxo_for_test);
 
         let inputs_for_tx = vec![Input {
-            tx_id: "genesis_tx_id_for_test_0".to_string(),
+            tx_id: genesis_tx_id,
             output_index: 0,
+            htlc_preimage: None,
         }];
 
         let change_amount = input_utxo_amount - amount_to_receiver - fee - dev_fee_on_transfer;
@@ -520,32 +640,34 @@ mod tests {
         let he_pub_key_material_slice: &[u8] = he_public_key_dalek.as_bytes();
 
         let mut outputs_for_tx = vec![Output {
-            address: "0000000000000000000000000000000000000000000000000000000000000001".to_string(),
+            address: receiver,
             amount: amount_to_receiver,
             homomorphic_encrypted: HomomorphicEncrypted::new(
                 amount_to_receiver,
                 he_pub_key_material_slice,
             ),
+            htlc: None,
         }];
         if dev_fee_on_transfer > 0 {
             outputs_for_tx.push(Output {
-                address: "74fd2aae70ae8e0930b87a3dcb3b77f5b71d956659849f067360d3486604db41"
-                    .to_string(), // DEV_ADDRESS
+                address: crate::fee_policy::DEV_ADDRESS,
                 amount: dev_fee_on_transfer,
                 homomorphic_encrypted: HomomorphicEncrypted::new(
                     dev_fee_on_transfer,
                     he_pub_key_material_slice,
                 ),
+                htlc: None,
             });
         }
         if change_amount > 0 {
             outputs_for_tx.push(Output {
-                address: sender_address.clone(),
+                address: sender_address,
                 amount: change_amount,
                 homomorphic_encrypted: HomomorphicEncrypted::new(
                     change_amount,
                     he_pub_key_material_slice,
                 ),
+                htlc: None,
             });
         }
 
@@ -555,9 +677,8 @@ mod tests {
         let tx_timestamps_map = Arc::new(RwLock::new(HashMap::new()));
 
         let tx_config = TransactionConfig {
-            sender: sender_address.clone(),
-            receiver: "0000000000000000000000000000000000000000000000000000000000000001"
-                .to_string(),
+            sender: sender_address,
+            receiver,
             amount: amount_to_receiver,
             fee,
             inputs: inputs_for_tx.clone(),
@@ -566,31 +687,19 @@ mod tests {
             signing_key_bytes: qr_secret_key.as_bytes(),
             public_key_bytes: qr_public_key.as_bytes(),
             tx_timestamps: tx_timestamps_map.clone(),
+            valid_until: None,
         };
 
         let tx = Transaction::new(tx_config).await?;
 
-        let saga_pallet = Arc::new(PalletSaga::new(
-            #[cfg(feature = "infinite-strata")]
-            None,
-        ));
-
-        let dag_config = QantoDagConfig {
-            initial_validator: sender_address.clone(),
-            target_block_time: 60000,
-            num_chains: 1,
-            qr_signing_key: &qr_secret_key,
-            qr_public_key: &qr_public_key,
-        };
-        let dag_arc = QantoDAG::new(
-            dag_config,
-            saga_pallet,
-            rocksdb::DB::open_default(db_path).unwrap(),
-        )?;
-
         let utxos_arc_for_test = Arc::new(RwLock::new(initial_utxos_map));
         let utxos_read_guard = utxos_arc_for_test.read().await;
-        tx.verify(&dag_arc, &utxos_read_guard)
+        let context = SpendContext {
+            height: 1,
+            timestamp: tx.timestamp,
+            fee_policy: FeePolicy::default(),
+        };
+        tx.verify(&utxos_read_guard, context)
             .await
             .map_err(|e| format!("TX verification error: {e:?}"))?;
 
@@ -600,4 +709,118 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_expiry_window() {
+        let by_height = TxExpiry::Height(10);
+        assert!(!by_height.is_expired(10, 99));
+        assert!(by_height.is_expired(11, 0));
+        let by_epoch = TxExpiry::Epoch(3);
+        assert!(!by_epoch.is_expired(1_000, 3));
+        assert!(by_epoch.is_expired(0, 4));
+    }
+
+    /// A transaction from `wallet` that spends one output back to itself.
+    async fn self_transfer(wallet: &Wallet, fee: u64) -> Transaction {
+        let (secret_key, public_key) = wallet.get_keypair().unwrap();
+        Transaction::new(TransactionConfig {
+            sender: wallet.address(),
+            receiver: wallet.address(),
+            amount: 100,
+            fee,
+            inputs: vec![Input {
+                tx_id: TxId::new([0x02; 32]),
+                output_index: 0,
+                htlc_preimage: None,
+            }],
+            outputs: vec![Output {
+                address: wallet.address(),
+                amount: 100,
+                homomorphic_encrypted: HomomorphicEncrypted::new(0, &[]),
+                htlc: None,
+            }],
+            metadata: None,
+            signing_key_bytes: secret_key.as_bytes(),
+            public_key_bytes: public_key.as_bytes(),
+            tx_timestamps: Arc::new(RwLock::new(HashMap::new())),
+            valid_until: None,
+        })
+        .await
+        .unwrap()
+    }
+
+    async fn signature_cache_dag(wallet: &Wallet) -> Arc<QantoDAG> {
+        {
+            let mut state = omega::OMEGA_STATE.lock().await;
+            *state = OmegaState::new();
+            set_threat_level(ThreatLevel::Nominal);
+        }
+        let (secret_key, public_key) = wallet.get_keypair().unwrap();
+        QantoDAG::new(
+            QantoDagConfig {
+                initial_validator: wallet.address(),
+                target_block_time: 60000,
+                num_chains: 1,
+                qr_signing_key: &secret_key,
+                qr_public_key: &public_key,
+                storage_mode: StorageMode::default(),
+                tx_index: false,
+                coinbase_maturity: DEFAULT_COINBASE_MATURITY,
+            },
+            Arc::new(PalletSaga::new(
+                #[cfg(feature = "infinite-strata")]
+                None,
+            )),
+            Arc::new(MemoryStore::new()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_cached_signature_is_not_verified_again() {
+        let wallet = Wallet::new().unwrap();
+        let dag = signature_cache_dag(&wallet).await;
+        let mut forged = self_transfer(&wallet, 10).await;
+        forged.amount += 1;
+        assert!(verify_signatures_parallel(&[&forged]).is_err());
+        assert!(dag
+            .verify_signatures(std::slice::from_ref(&forged))
+            .await
+            .is_err());
+
+        // A digest in the cache stands for a signature already checked, so a
+        // transaction that hashes to it is not verified again.
+        dag.verified_signatures
+            .write()
+            .await
+            .put(forged.signature_digest().unwrap(), ());
+        assert!(dag
+            .verify_signatures(std::slice::from_ref(&forged))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_mutated_transaction_misses_signature_cache() {
+        let wallet = Wallet::new().unwrap();
+        let dag = signature_cache_dag(&wallet).await;
+        let mut tx = self_transfer(&wallet, 10).await;
+        dag.verify_signatures(std::slice::from_ref(&tx))
+            .await
+            .unwrap();
+        assert!(dag
+            .verified_signatures
+            .read()
+            .await
+            .contains(&tx.signature_digest().unwrap()));
+
+        tx.fee += 1;
+        assert!(matches!(
+            dag.verify_signatures(std::slice::from_ref(&tx)).await,
+            Err(TransactionError::QuantumSignatureVerification)
+        ));
+        assert_eq!(dag.verified_signatures.read().await.len(), 1);
+    }
 }
@@ -0,0 +1,340 @@
+//! --- Qanto Transaction Indexes ---
+//! v1.0.0 - History Queries
+//! Optional secondary indexes kept next to the blocks in the node database,
+//! so transactions, address histories and spends can be looked up without
+//! scanning the ledger.
+//!
+//! - **`txidx:<tx_id>`** locates a transaction: its block, height and
+//!   position in the block.
+//! - **`addridx:<address>:<height>:<tx_id>`** lists every transaction that
+//!   sends from, pays to or has an output for an address, in height order.
+//! - **`spentidx:<tx_id>_<index>`** names the transaction that spent an output.
+//!
+//! Entries are written in the same batch as the block itself and removed with
+//! `unindex_block` when a block is rolled back. `reindex` rebuilds them from
+//! the stored blocks of an existing database.
+
+use crate::codec::{self, CodecError};
+use crate::qantodag::QantoBlock;
+use crate::storage::{BlockStore, StateStore, StoreBatch, StoreError};
+use crate::transaction::Transaction;
+use crate::types::{Address, BlockHash, OutPoint, TxId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use thiserror::Error;
+
+pub const TX_INDEX_PREFIX: &str = "txidx:";
+pub const ADDRESS_INDEX_PREFIX: &str = "addridx:";
+pub const SPENT_INDEX_PREFIX: &str = "spentidx:";
+
+const REINDEX_BATCH_SIZE: usize = 10_000;
+
+#[derive(Error, Debug)]
+pub enum IndexError {
+    #[error("Storage error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Encoding error: {0}")]
+    Codec(#[from] CodecError),
+    #[error("Corrupt index entry under key '{0}'")]
+    CorruptEntry(String),
+}
+
+/// Where a transaction was included.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxLocation {
+    pub block_id: BlockHash,
+    pub height: u64,
+    pub position: u32,
+}
+
+/// One transaction in an address's history.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressTx {
+    pub tx_id: TxId,
+    pub height: u64,
+}
+
+/// Counts reported by `reindex`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReindexStats {
+    pub blocks: usize,
+    pub transactions: usize,
+}
+
+fn tx_key(tx_id: &TxId) -> String {
+    format!("{TX_INDEX_PREFIX}{tx_id}")
+}
+
+fn address_prefix(address: &Address) -> String {
+    format!("{ADDRESS_INDEX_PREFIX}{address}:")
+}
+
+fn address_key(address: &Address, height: u64, tx_id: &TxId) -> String {
+    format!("{}{height:020}:{tx_id}", address_prefix(address))
+}
+
+fn spent_key(outpoint: &OutPoint) -> String {
+    format!("{SPENT_INDEX_PREFIX}{outpoint}")
+}
+
+/// Every key `block` adds to the indexes, with its value.
+fn block_entries(block: &QantoBlock) -> Result<Vec<(String, Vec<u8>)>, CodecError> {
+    let mut entries = Vec::new();
+    for (position, tx) in block.transactions.iter().enumerate() {
+        let location = TxLocation {
+            block_id: block.id,
+            height: block.height,
+            position: position as u32,
+        };
+        entries.push((tx_key(&tx.id), codec::encode(&location)?));
+
+        let addresses: BTreeSet<Address> = [tx.sender, tx.receiver]
+            .into_iter()
+            .chain(tx.outputs.iter().map(|output| output.address))
+            .collect();
+        for address in &addresses {
+            entries.push((address_key(address, block.height, &tx.id), Vec::new()));
+        }
+
+        for input in &tx.inputs {
+            let outpoint = OutPoint::new(input.tx_id, input.output_index);
+            entries.push((spent_key(&outpoint), tx.id.as_bytes().to_vec()));
+        }
+    }
+    Ok(entries)
+}
+
+/// Adds the index entries of `block` to `batch`.
+pub fn index_block(batch: &mut StoreBatch, block: &QantoBlock) -> Result<(), CodecError> {
+    for (key, value) in block_entries(block)? {
+        batch.put(key.as_bytes(), value);
+    }
+    Ok(())
+}
+
+/// Removes the index entries of a rolled-back `block` in `batch`.
+pub fn unindex_block(batch: &mut StoreBatch, block: &QantoBlock) -> Result<(), CodecError> {
+    for (key, _) in block_entries(block)? {
+        batch.delete(key.as_bytes());
+    }
+    Ok(())
+}
+
+/// Where the transaction `tx_id` was included, if it is indexed.
+pub fn tx_location(store: &dyn StateStore, tx_id: &TxId) -> Result<Option<TxLocation>, IndexError> {
+    match store.get(tx_key(tx_id).as_bytes())? {
+        Some(value) => Ok(Some(codec::decode(&value)?)),
+        None => Ok(None),
+    }
+}
+
+/// Looks up a transaction and reads it from its stored block. The transaction
+/// is `None` when the block's body was pruned.
+pub fn lookup_transaction(
+    store: &dyn StateStore,
+    tx_id: &TxId,
+) -> Result<Option<(TxLocation, Option<Transaction>)>, IndexError> {
+    let Some(location) = tx_location(store, tx_id)? else {
+        return Ok(None);
+    };
+    let transaction = store.block(&location.block_id)?.and_then(|mut block| {
+        let position = location.position as usize;
+        (position < block.transactions.len()).then(|| block.transactions.swap_remove(position))
+    });
+    Ok(Some((location, transaction)))
+}
+
+/// The most recent `limit` transactions touching `address`, newest first.
+pub fn address_history(
+    store: &dyn StateStore,
+    address: &Address,
+    limit: usize,
+) -> Result<Vec<AddressTx>, IndexError> {
+    let prefix = address_prefix(address);
+    let mut history = Vec::new();
+    for item in store.scan_prefix(prefix.as_bytes()) {
+        let (key, _) = item?;
+        let key = String::from_utf8_lossy(&key).into_owned();
+        let entry = key[prefix.len()..]
+            .split_once(':')
+            .and_then(|(height, tx_id)| {
+                Some(AddressTx {
+                    tx_id: tx_id.parse().ok()?,
+                    height: height.parse().ok()?,
+                })
+            })
+            .ok_or_else(|| IndexError::CorruptEntry(key.clone()))?;
+        history.push(entry);
+    }
+    history.reverse();
+    history.truncate(limit);
+    Ok(history)
+}
+
+/// The transaction that spent `outpoint`, if the spend is indexed.
+pub fn spender(store: &dyn StateStore, outpoint: &OutPoint) -> Result<Option<TxId>, IndexError> {
+    let key = spent_key(outpoint);
+    match store.get(key.as_bytes())? {
+        Some(value) => {
+            let bytes: [u8; 32] = value
+                .as_slice()
+                .try_into()
+                .map_err(|_| IndexError::CorruptEntry(key))?;
+            Ok(Some(TxId::new(bytes)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Drops all index entries and rebuilds them from the blocks in `store`.
+/// Blocks whose bodies were pruned contribute no entries.
+pub fn reindex(store: &dyn StateStore) -> Result<ReindexStats, IndexError> {
+    let mut batch = StoreBatch::default();
+    for prefix in [TX_INDEX_PREFIX, ADDRESS_INDEX_PREFIX, SPENT_INDEX_PREFIX] {
+        for item in store.scan_prefix(prefix.as_bytes()) {
+            let (key, _) = item?;
+            batch.delete(&key);
+            if batch.len() >= REINDEX_BATCH_SIZE {
+                store.write(std::mem::take(&mut batch))?;
+            }
+        }
+    }
+    store.write(std::mem::take(&mut batch))?;
+
+    let mut stats = ReindexStats::default();
+    for block in store.blocks() {
+        let block = block?;
+        index_block(&mut batch, &block)?;
+        stats.blocks += 1;
+        stats.transactions += block.transactions.len();
+        if batch.len() >= REINDEX_BATCH_SIZE {
+            store.write(std::mem::take(&mut batch))?;
+        }
+    }
+    if !batch.is_empty() {
+        store.write(batch)?;
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fee_policy::FeePolicy;
+    use crate::qantodag::{
+        HomomorphicEncrypted, QantoBlockCreationData, DEFAULT_COINBASE_MATURITY,
+    };
+    use crate::storage::MemoryStore;
+    use crate::transaction::{Input, Output, Transaction};
+    use crate::types::BlockHash;
+    use pqcrypto_dilithium::dilithium5;
+    use std::collections::HashMap;
+
+    fn block(height: u64, transactions: Vec<(TxId, Vec<OutPoint>)>) -> QantoBlock {
+        let (public_key, secret_key) = dilithium5::keypair();
+        let mut block = QantoBlock::new(QantoBlockCreationData {
+            chain_id: 0,
+            parents: vec![BlockHash::new([height as u8; 32])],
+            cross_chain_references: vec![],
+            transactions: vec![],
+            difficulty: 0,
+            validator: Address::new([7; 32]),
+            miner: Address::new([7; 32]),
+            qr_signing_key: &secret_key,
+            qr_public_key: &public_key,
+            timestamp: height,
+            current_epoch: 0,
+            height,
+            fee_policy: FeePolicy::default(),
+            coinbase_maturity: DEFAULT_COINBASE_MATURITY,
+            utxo_root: String::new(),
+        })
+        .unwrap();
+        block.transactions = transactions
+            .into_iter()
+            .map(|(id, spends)| Transaction {
+                id,
+                sender: Address::new([1; 32]),
+                receiver: Address::new([2; 32]),
+                amount: 10,
+                fee: 0,
+                inputs: spends
+                    .into_iter()
+                    .map(|outpoint| Input {
+                        tx_id: outpoint.tx_id,
+                        output_index: outpoint.index,
+                        htlc_preimage: None,
+                    })
+                    .collect(),
+                outputs: vec![Output {
+                    address: Address::new([3; 32]),
+                    amount: 10,
+                    homomorphic_encrypted: HomomorphicEncrypted::new(10, &[]),
+                    htlc: None,
+                }],
+                qr_signature: block.qr_signature.clone(),
+                timestamp: height,
+                metadata: HashMap::new(),
+                valid_until: None,
+            })
+            .collect();
+        block
+    }
+
+    #[test]
+    fn test_index_lookup_and_rebuild() {
+        let store = MemoryStore::new();
+        let first_tx = TxId::new([10; 32]);
+        let second_tx = TxId::new([11; 32]);
+        let spent = OutPoint::new(first_tx, 0);
+        let first = block(1, vec![(first_tx, vec![])]);
+        let second = block(
+            2,
+            vec![(TxId::new([12; 32]), vec![]), (second_tx, vec![spent])],
+        );
+
+        let mut batch = StoreBatch::default();
+        for block in [&first, &second] {
+            batch.put_block(block).unwrap();
+            index_block(&mut batch, block).unwrap();
+        }
+        store.write(batch).unwrap();
+
+        let location = tx_location(&store, &second_tx).unwrap().unwrap();
+        assert_eq!((location.block_id, location.position), (second.id, 1));
+        assert_eq!(spender(&store, &spent).unwrap(), Some(second_tx));
+        let history = address_history(&store, &Address::new([3; 32]), 2).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].height, 2);
+        assert!(address_history(&store, &Address::new([9; 32]), 10)
+            .unwrap()
+            .is_empty());
+
+        let mut batch = StoreBatch::default();
+        unindex_block(&mut batch, &second).unwrap();
+        store.write(batch).unwrap();
+        assert_eq!(spender(&store, &spent).unwrap(), None);
+        assert_eq!(
+            address_history(&store, &Address::new([1; 32]), 10)
+                .unwrap()
+                .len(),
+            1
+        );
+
+        let stats = reindex(&store).unwrap();
+        assert_eq!(
+            stats,
+            ReindexStats {
+                blocks: 2,
+                transactions: 3
+            }
+        );
+        assert_eq!(spender(&store, &spent).unwrap(), Some(second_tx));
+        assert_eq!(
+            address_history(&store, &Address::new([1; 32]), 10)
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+}
@@ -0,0 +1,422 @@
+//! --- Qanto Core Types ---
+//! v1.1.0 - Checksummed Addresses
+//! Block hashes, transaction IDs and addresses are 32-byte values. Wrapping
+//! them in dedicated types means lookups hash 32 bytes instead of 64 ASCII
+//! characters, and a malformed identifier is rejected once, where it is parsed,
+//! rather than by ad-hoc checks scattered across the node.
+//!
+//! - **Human-readable formats** (the JSON API, config files, the mempool
+//!   journal) use lowercase hex, exactly as the `String` IDs did.
+//! - **Binary formats** write the 32 bytes as they are, with no length prefix.
+//! - **Hashing** commits to the hex form via `hex_bytes`, keeping every block
+//!   and transaction ID unchanged.
+//!
+//! Addresses shown to users are bech32m strings such as `tqnto1...`: the
+//! prefix names the network, a version byte leads the payload so new key
+//! types can be added later, and the checksum catches mistyped characters.
+//! They are parsed only where the node's network is known, through
+//! `Address::parse_for_network`; serialized data such as JSON carries the
+//! network-neutral hex form, and legacy hex is still accepted everywhere.
+
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::{Bech32m, Hrp};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+use zeroize::Zeroize;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ParseIdError {
+    #[error("Expected 64 hex characters, got {0}")]
+    InvalidLength(usize),
+    #[error("Invalid hex character in identifier")]
+    InvalidHex,
+    #[error("Outpoint must have the form <tx_id>_<index>")]
+    InvalidOutPoint,
+    #[error("Invalid bech32m address: {0}")]
+    InvalidBech32(String),
+    #[error("Address payload must be 33 bytes, got {0}")]
+    InvalidPayloadLength(usize),
+    #[error("Unsupported address version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Address is for network '{found}', expected '{expected}'")]
+    WrongNetwork { expected: String, found: String },
+}
+
+/// Version byte of addresses that are an Ed25519 verifying key.
+pub const ADDRESS_VERSION_ED25519: u8 = 0;
+/// Address prefix on mainnet.
+pub const MAINNET_HRP: Hrp = Hrp::parse_unchecked("qnto");
+/// Address prefix on every other network.
+pub const TESTNET_HRP: Hrp = Hrp::parse_unchecked("tqnto");
+
+/// The address prefix for a `Config.network_id`. Any network whose ID names
+/// it as a mainnet uses `qnto`; testnets and devnets share `tqnto`.
+pub fn network_hrp(network_id: &str) -> Hrp {
+    if network_id.contains("mainnet") {
+        MAINNET_HRP
+    } else {
+        TESTNET_HRP
+    }
+}
+
+const fn hex_value(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        b'A'..=b'F' => c - b'A' + 10,
+        _ => panic!("invalid hex character"),
+    }
+}
+
+/// Decodes a 64-character hex literal at compile time.
+const fn decode_hex_const(s: &str) -> [u8; 32] {
+    let digits = s.as_bytes();
+    assert!(digits.len() == 64, "expected 64 hex characters");
+    let mut bytes = [0u8; 32];
+    let mut i = 0;
+    while i < 32 {
+        bytes[i] = (hex_value(digits[2 * i]) << 4) | hex_value(digits[2 * i + 1]);
+        i += 1;
+    }
+    bytes
+}
+
+macro_rules! hash_type {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        pub struct $name([u8; 32]);
+
+        impl $name {
+            pub const ZERO: Self = Self([0; 32]);
+
+            pub const fn new(bytes: [u8; 32]) -> Self {
+                Self(bytes)
+            }
+
+            /// Parses a hex literal at compile time. Panics on malformed input.
+            pub const fn from_hex_const(hex: &str) -> Self {
+                Self(decode_hex_const(hex))
+            }
+
+            pub const fn as_bytes(&self) -> &[u8; 32] {
+                &self.0
+            }
+
+            pub fn is_zero(&self) -> bool {
+                self.0 == [0; 32]
+            }
+
+            /// The lowercase hex digits, as committed to by IDs and signatures.
+            pub fn hex_bytes(&self) -> [u8; 64] {
+                let mut out = [0u8; 64];
+                hex::encode_to_slice(self.0, &mut out).expect("output holds 64 digits");
+                out
+            }
+        }
+
+        impl From<[u8; 32]> for $name {
+            fn from(bytes: [u8; 32]) -> Self {
+                Self(bytes)
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let digits = self.hex_bytes();
+                f.write_str(std::str::from_utf8(&digits).expect("hex digits are ASCII"))
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({self})", stringify!($name))
+            }
+        }
+
+        impl $name {
+            /// Parses exactly 64 hex digits, in either case.
+            pub fn from_hex(s: &str) -> Result<Self, ParseIdError> {
+                if s.len() != 64 {
+                    return Err(ParseIdError::InvalidLength(s.len()));
+                }
+                let mut bytes = [0u8; 32];
+                hex::decode_to_slice(s, &mut bytes).map_err(|_| ParseIdError::InvalidHex)?;
+                Ok(Self(bytes))
+            }
+        }
+
+        impl Zeroize for $name {
+            fn zeroize(&mut self) {
+                self.0.zeroize();
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                if serializer.is_human_readable() {
+                    serializer.collect_str(self)
+                } else {
+                    self.0.serialize(serializer)
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                if deserializer.is_human_readable() {
+                    String::deserialize(deserializer)?
+                        .parse()
+                        .map_err(D::Error::custom)
+                } else {
+                    <[u8; 32]>::deserialize(deserializer).map(Self)
+                }
+            }
+        }
+    };
+}
+
+hash_type!(
+    /// Keccak256 hash identifying a block.
+    BlockHash
+);
+hash_type!(
+    /// Identifier of a transaction, the truncated Keccak512 of its contents.
+    TxId
+);
+hash_type!(
+    /// An account address: the owner's 32-byte public key.
+    Address
+);
+
+impl FromStr for BlockHash {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl FromStr for TxId {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+impl Address {
+    /// Encodes the address as bech32m under `hrp`, e.g. `tqnto1...`.
+    pub fn to_bech32(&self, hrp: Hrp) -> String {
+        let mut payload = [0u8; 33];
+        payload[0] = ADDRESS_VERSION_ED25519;
+        payload[1..].copy_from_slice(&self.0);
+        bech32::encode::<Bech32m>(hrp, &payload).expect("a 33-byte payload is within limits")
+    }
+
+    /// Parses an address for the network with prefix `hrp`. Bech32m addresses
+    /// of any other network are rejected; legacy hex carries no network and
+    /// is always accepted.
+    pub fn parse_for_network(s: &str, hrp: Hrp) -> Result<Self, ParseIdError> {
+        if s.len() == 64 {
+            return Self::from_hex(s);
+        }
+        let (found, address) = Self::decode_bech32(s)?;
+        if found != hrp {
+            return Err(ParseIdError::WrongNetwork {
+                expected: hrp.to_lowercase(),
+                found: found.to_lowercase(),
+            });
+        }
+        Ok(address)
+    }
+
+    fn decode_bech32(s: &str) -> Result<(Hrp, Self), ParseIdError> {
+        let checked = CheckedHrpstring::new::<Bech32m>(s)
+            .map_err(|e| ParseIdError::InvalidBech32(e.to_string()))?;
+        let payload: Vec<u8> = checked.byte_iter().collect();
+        match payload.split_first() {
+            Some((&ADDRESS_VERSION_ED25519, key)) => key
+                .try_into()
+                .map(|key| (checked.hrp(), Self(key)))
+                .map_err(|_| ParseIdError::InvalidPayloadLength(payload.len())),
+            Some((&version, _)) => Err(ParseIdError::UnsupportedVersion(version)),
+            None => Err(ParseIdError::InvalidPayloadLength(0)),
+        }
+    }
+}
+
+/// Accepts only the hex form, which carries no network. Bech32m input must
+/// go through `Address::parse_for_network`, so an address of another network
+/// cannot slip in through serde.
+impl FromStr for Address {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(s)
+    }
+}
+
+/// Reference to a transaction output; the key of the UTXO set.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OutPoint {
+    pub tx_id: TxId,
+    pub index: u32,
+}
+
+impl OutPoint {
+    pub const fn new(tx_id: TxId, index: u32) -> Self {
+        Self { tx_id, index }
+    }
+}
+
+impl fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.tx_id, self.index)
+    }
+}
+
+impl fmt::Debug for OutPoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OutPoint({self})")
+    }
+}
+
+impl FromStr for OutPoint {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (tx_id, index) = s.split_once('_').ok_or(ParseIdError::InvalidOutPoint)?;
+        Ok(Self {
+            tx_id: tx_id.parse()?,
+            index: index.parse().map_err(|_| ParseIdError::InvalidOutPoint)?,
+        })
+    }
+}
+
+// Written as `<tx_id>_<index>` in human-readable formats so outpoints can key
+// JSON objects, as the UTXO endpoints have always returned them.
+impl Serialize for OutPoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            (self.tx_id, self.index).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OutPoint {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            String::deserialize(deserializer)?
+                .parse()
+                .map_err(D::Error::custom)
+        } else {
+            let (tx_id, index) = <(TxId, u32)>::deserialize(deserializer)?;
+            Ok(Self { tx_id, index })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_hex_round_trip_and_validation() {
+        let hex = "74fd2aae70ae8e0930b87a3dcb3b77f5b71d956659849f067360d3486604db41";
+        let address: Address = hex.parse().unwrap();
+        assert_eq!(address.to_string(), hex);
+        assert_eq!(address, Address::from_hex_const(hex));
+        assert_eq!(hex.to_uppercase().parse::<Address>(), Ok(address));
+        assert_eq!("abc".parse::<TxId>(), Err(ParseIdError::InvalidLength(3)));
+        assert_eq!(
+            "zz".repeat(32).parse::<Address>(),
+            Err(ParseIdError::InvalidHex)
+        );
+    }
+
+    #[test]
+    fn test_bech32m_addresses() {
+        let address = Address::new([0x42; 32]);
+        let encoded = address.to_bech32(TESTNET_HRP);
+        assert!(encoded.starts_with("tqnto1"));
+        assert_eq!(
+            encoded.parse::<Address>(),
+            Err(ParseIdError::InvalidLength(encoded.len()))
+        );
+        assert!(serde_json::from_str::<Address>(&format!("\"{encoded}\"")).is_err());
+        assert_eq!(
+            Address::parse_for_network(&encoded, TESTNET_HRP),
+            Ok(address)
+        );
+        assert_eq!(
+            Address::parse_for_network(&encoded.to_uppercase(), TESTNET_HRP),
+            Ok(address)
+        );
+        assert_eq!(
+            Address::parse_for_network(&address.to_string(), MAINNET_HRP),
+            Ok(address)
+        );
+        assert!(matches!(
+            Address::parse_for_network(&encoded, MAINNET_HRP),
+            Err(ParseIdError::WrongNetwork { .. })
+        ));
+
+        // A single mistyped character breaks the checksum.
+        let mut typo = encoded.clone().into_bytes();
+        let last = typo.len() - 1;
+        typo[last] = if typo[last] == b'q' { b'p' } else { b'q' };
+        let typo = String::from_utf8(typo).unwrap();
+        assert!(matches!(
+            Address::parse_for_network(&typo, TESTNET_HRP),
+            Err(ParseIdError::InvalidBech32(_))
+        ));
+
+        let mut payload = [1u8; 33];
+        payload[1..].copy_from_slice(address.as_bytes());
+        let future = bech32::encode::<Bech32m>(TESTNET_HRP, &payload).unwrap();
+        assert_eq!(
+            Address::parse_for_network(&future, TESTNET_HRP),
+            Err(ParseIdError::UnsupportedVersion(1))
+        );
+        assert_eq!(network_hrp("qanto-mainnet"), MAINNET_HRP);
+        assert_eq!(network_hrp("qanto-testnet-phoenix"), TESTNET_HRP);
+    }
+
+    #[test]
+    fn test_outpoint_keys_json_objects() {
+        let outpoint = OutPoint::new(TxId::new([0xab; 32]), 7);
+        assert_eq!(outpoint.to_string(), format!("{}_7", "ab".repeat(32)));
+        assert_eq!(outpoint.to_string().parse::<OutPoint>(), Ok(outpoint));
+
+        let map: HashMap<OutPoint, u64> = [(outpoint, 5)].into_iter().collect();
+        let json = serde_json::to_string(&map).unwrap();
+        assert_eq!(json, format!("{{\"{outpoint}\":5}}"));
+        assert_eq!(
+            serde_json::from_str::<HashMap<OutPoint, u64>>(&json).unwrap(),
+            map
+        );
+    }
+
+    #[test]
+    fn test_binary_layout_is_the_raw_bytes() {
+        let id = BlockHash::new([0x5c; 32]);
+        let encoded = codec::encode(&id).unwrap();
+        assert_eq!(encoded[0], codec::CODEC_VERSION);
+        assert_eq!(&encoded[1..], &[0x5c; 32]);
+        assert_eq!(codec::decode::<BlockHash>(&encoded).unwrap(), id);
+    }
+}
@@ -0,0 +1,953 @@
+//! --- Qanto UTXO Set ---
+//! v1.0.0 - State Root Commitment
+//! This module holds the node's set of unspent outputs together with a sparse
+//! Merkle tree over it. Its root is committed to by `QantoBlock::utxo_root`,
+//! so the set a block leaves behind can be checked, and any single output can
+//! be proven unspent against a block header.
+//!
+//! - **Keys and leaves:** an outpoint is keyed by `Keccak256(0x02 || tx_id || index)`
+//!   and its leaf commits to the output's address, amount, HTLC terms and, for
+//!   coinbase outputs, the minting chain, height and maturity. Addresses are hashed as
+//!   their raw bytes. The minting block's ID and
+//!   the explorer link are left out: the ID is derived from the signed data that
+//!   carries the root, and the link is presentation only.
+//! - **Shape:** the top 16 levels form a dense tree over 65,536 buckets whose
+//!   node hashes are cached. Below that, each bucket is a compact sparse tree: a
+//!   subtree holding one leaf collapses to that leaf, and an empty subtree hashes
+//!   to zero. Leaves and inner nodes are domain separated, so neither can be
+//!   passed off as the other.
+//! - **Incremental:** inserts and removals mark their bucket dirty, and every
+//!   write rehashes the dirty buckets and their paths before it returns, so
+//!   roots and proofs are read without mutating the set.
+//! - **Relative to the block's past:** blocks on different chains arrive in
+//!   different orders at different nodes, so a block commits to the set its
+//!   own past leaves behind: the genesis outputs plus every output created and
+//!   not spent by the blocks its parents and cross-chain references lead back
+//!   to. Being a set difference, this does not depend on the order the blocks
+//!   were applied in. A node's set may hold blocks outside that past; the undo
+//!   history gives the `PastView` the block sees instead, which both its
+//!   inputs and its root are checked against.
+//! - **Settled blocks:** changes that leave the undo history, by age, pruning
+//!   or a snapshot restore, are folded into the set for good. The newest such
+//!   blocks are remembered, and a block whose past does not include them all
+//!   cannot be checked by this node and fails with `BeyondHistory`, rather
+//!   than being checked against a view no peer shares.
+//! - **Undo history:** the changes of recently applied blocks are kept, so the
+//!   set as of a recent block can be rebuilt, e.g. for a snapshot.
+
+use crate::qantodag::UTXO;
+use crate::types::{Address, BlockHash, OutPoint, TxId};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::ops::{Deref, RangeInclusive};
+use thiserror::Error;
+
+type Hash = [u8; 32];
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+const KEY_PREFIX: u8 = 0x02;
+
+/// Depth of the dense top of the tree; its bottom level holds the buckets.
+const TOP_DEPTH: usize = 16;
+const BUCKETS: usize = 1 << TOP_DEPTH;
+/// Hash of an empty subtree at any depth.
+const EMPTY: Hash = [0; 32];
+
+/// Number of applied blocks whose changes are kept for rolling back.
+const MAX_UNDO_BLOCKS: usize = 4_096;
+
+/// Hex-encoded root of an empty UTXO set.
+pub const EMPTY_ROOT: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Value of the genesis output of each chain.
+pub const GENESIS_AMOUNT: u64 = 100;
+
+fn utxo_key(outpoint: &OutPoint) -> Hash {
+    let mut hasher = Keccak256::new();
+    hasher.update([KEY_PREFIX]);
+    hasher.update(outpoint.tx_id.as_bytes());
+    hasher.update(outpoint.index.to_be_bytes());
+    hasher.finalize().into()
+}
+
+fn update_str(hasher: &mut Keccak256, value: &str) {
+    hasher.update((value.len() as u64).to_be_bytes());
+    hasher.update(value.as_bytes());
+}
+
+fn leaf_hash(key: &Hash, utxo: &UTXO) -> Hash {
+    let mut hasher = Keccak256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(key);
+    hasher.update(utxo.address.as_bytes());
+    hasher.update(utxo.amount.to_be_bytes());
+    match &utxo.htlc {
+        Some(htlc) => {
+            hasher.update([1]);
+            update_str(&mut hasher, &htlc.secret_hash);
+            hasher.update(htlc.timelock.to_be_bytes());
+            hasher.update(htlc.recipient.as_bytes());
+            hasher.update(htlc.refund_address.as_bytes());
+        }
+        None => hasher.update([0]),
+    }
+    match &utxo.coinbase {
+        Some(origin) => {
+            hasher.update([1]);
+            hasher.update(origin.chain_id.to_be_bytes());
+            hasher.update(origin.height.to_be_bytes());
+            hasher.update(origin.maturity.to_be_bytes());
+        }
+        None => hasher.update([0]),
+    }
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    if left == &EMPTY && right == &EMPTY {
+        return EMPTY;
+    }
+    let mut hasher = Keccak256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// Whether `key` branches right at `depth`.
+fn bit(key: &Hash, depth: usize) -> bool {
+    key[depth / 8] >> (7 - depth % 8) & 1 == 1
+}
+
+fn bucket_of(key: &Hash) -> usize {
+    u16::from_be_bytes([key[0], key[1]]) as usize
+}
+
+/// The keys that fall into `bucket`.
+fn bucket_range(bucket: usize) -> RangeInclusive<Hash> {
+    let prefix = (bucket as u16).to_be_bytes();
+    let mut start = EMPTY;
+    start[..2].copy_from_slice(&prefix);
+    let mut end = [0xff; 32];
+    end[..2].copy_from_slice(&prefix);
+    start..=end
+}
+
+/// Hash of the compact subtree at `depth` holding `leaves`, sorted by key.
+fn subtree_hash(leaves: &[(Hash, Hash)], depth: usize) -> Hash {
+    match leaves {
+        [] => EMPTY,
+        [(_, leaf)] => *leaf,
+        _ => {
+            let split = leaves.partition_point(|(key, _)| !bit(key, depth));
+            node_hash(
+                &subtree_hash(&leaves[..split], depth + 1),
+                &subtree_hash(&leaves[split..], depth + 1),
+            )
+        }
+    }
+}
+
+/// Sibling hashes from `depth` down to the leaf keyed by `key`, deepest first.
+fn subtree_siblings(leaves: &[(Hash, Hash)], key: &Hash, depth: usize, out: &mut Vec<Hash>) {
+    if leaves.len() <= 1 {
+        return;
+    }
+    let split = leaves.partition_point(|(k, _)| !bit(k, depth));
+    let (ours, theirs) = if bit(key, depth) {
+        (&leaves[split..], &leaves[..split])
+    } else {
+        (&leaves[..split], &leaves[split..])
+    };
+    subtree_siblings(ours, key, depth + 1, out);
+    out.push(subtree_hash(theirs, depth + 1));
+}
+
+/// The outputs every node seeds its set with: one per chain, paid to the
+/// genesis validator.
+pub fn genesis_utxos(num_chains: u32, genesis_validator: Address) -> Vec<(OutPoint, UTXO)> {
+    (0..num_chains)
+        .map(|chain_id| {
+            let tx_id = TxId::new(
+                Keccak256::digest(format!("genesis_placeholder_tx_id_for_chain_{chain_id}")).into(),
+            );
+            let outpoint = OutPoint::new(tx_id, 0);
+            let utxo = UTXO {
+                address: genesis_validator,
+                amount: GENESIS_AMOUNT,
+                tx_id,
+                output_index: 0,
+                explorer_link: format!("https://qantoblockexplorer.org/utxo/{outpoint}"),
+                htlc: None,
+                coinbase: None,
+            };
+            (outpoint, utxo)
+        })
+        .collect()
+}
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum UtxoSetError {
+    #[error("the block's past does not include {0}, which this node's UTXO set has settled")]
+    BeyondHistory(BlockHash),
+}
+
+/// A change to the UTXO set, applied in order.
+#[derive(Clone, Debug)]
+pub enum UtxoChange {
+    Spend(OutPoint),
+    Create(OutPoint, Box<UTXO>),
+}
+
+/// One change a block made: the output it created or spent.
+struct UndoOp {
+    outpoint: OutPoint,
+    utxo: UTXO,
+    spent: bool,
+    /// Whether the set held the outpoint just before the change.
+    was_held: bool,
+}
+
+/// The changes one block made to the set, in the order they were applied.
+struct BlockUndo {
+    block_id: BlockHash,
+    /// The block's parents and cross-chain references.
+    links: Vec<BlockHash>,
+    ops: Vec<UndoOp>,
+}
+
+/// Sparse Merkle tree over leaf hashes.
+#[derive(Default)]
+struct StateTree {
+    leaves: BTreeMap<Hash, Hash>,
+    /// Heap-ordered node hashes of the dense top: index 1 is the root and the
+    /// buckets start at `BUCKETS`. Empty until the root is first needed.
+    nodes: Vec<Hash>,
+    dirty: BTreeSet<usize>,
+}
+
+impl StateTree {
+    fn set(&mut self, key: Hash, leaf: Option<Hash>) {
+        match leaf {
+            Some(leaf) => self.leaves.insert(key, leaf),
+            None => self.leaves.remove(&key),
+        };
+        if !self.nodes.is_empty() {
+            self.dirty.insert(bucket_of(&key));
+        }
+    }
+
+    fn bucket(&self, bucket: usize) -> Vec<(Hash, Hash)> {
+        self.leaves
+            .range(bucket_range(bucket))
+            .map(|(key, leaf)| (*key, *leaf))
+            .collect()
+    }
+
+    /// Rehashes the dirty buckets and the paths above them.
+    fn flush(&mut self) {
+        if self.nodes.is_empty() {
+            self.nodes = vec![EMPTY; 2 * BUCKETS];
+            self.dirty = self.leaves.keys().map(bucket_of).collect();
+        }
+        let mut level = BTreeSet::new();
+        for bucket in std::mem::take(&mut self.dirty) {
+            self.nodes[BUCKETS + bucket] = subtree_hash(&self.bucket(bucket), TOP_DEPTH);
+            level.insert((BUCKETS + bucket) / 2);
+        }
+        while !level.is_empty() {
+            let mut next = BTreeSet::new();
+            for index in level {
+                self.nodes[index] = node_hash(&self.nodes[2 * index], &self.nodes[2 * index + 1]);
+                if index > 1 {
+                    next.insert(index / 2);
+                }
+            }
+            level = next;
+        }
+    }
+
+    /// Root of the flushed tree.
+    fn root(&self) -> Hash {
+        self.nodes.get(1).copied().unwrap_or(EMPTY)
+    }
+
+    /// Root of the flushed tree after applying `overrides`, leaving the tree
+    /// itself untouched.
+    fn root_with(&self, overrides: &BTreeMap<Hash, Option<Hash>>) -> Hash {
+        let mut staged: HashMap<usize, Hash> = HashMap::new();
+        let buckets: BTreeSet<usize> = overrides.keys().map(bucket_of).collect();
+        let mut level = BTreeSet::new();
+        for bucket in buckets {
+            let mut leaves: BTreeMap<Hash, Hash> = self.bucket(bucket).into_iter().collect();
+            for (key, leaf) in overrides.range(bucket_range(bucket)) {
+                match leaf {
+                    Some(leaf) => leaves.insert(*key, *leaf),
+                    None => leaves.remove(key),
+                };
+            }
+            let leaves: Vec<(Hash, Hash)> = leaves.into_iter().collect();
+            staged.insert(BUCKETS + bucket, subtree_hash(&leaves, TOP_DEPTH));
+            level.insert((BUCKETS + bucket) / 2);
+        }
+        let node = |staged: &HashMap<usize, Hash>, index: usize| {
+            staged
+                .get(&index)
+                .or(self.nodes.get(index))
+                .copied()
+                .unwrap_or(EMPTY)
+        };
+        while !level.is_empty() {
+            let mut next = BTreeSet::new();
+            for index in level {
+                let hash = node_hash(&node(&staged, 2 * index), &node(&staged, 2 * index + 1));
+                staged.insert(index, hash);
+                if index > 1 {
+                    next.insert(index / 2);
+                }
+            }
+            level = next;
+        }
+        node(&staged, 1)
+    }
+
+    /// Proof siblings of `key` in the flushed tree.
+    fn siblings(&self, key: &Hash) -> Vec<Hash> {
+        let bucket = bucket_of(key);
+        let mut siblings = Vec::new();
+        subtree_siblings(&self.bucket(bucket), key, TOP_DEPTH, &mut siblings);
+        let mut index = BUCKETS + bucket;
+        while index > 1 {
+            siblings.push(self.nodes.get(index ^ 1).copied().unwrap_or(EMPTY));
+            index /= 2;
+        }
+        siblings
+    }
+}
+
+/// The unspent outputs, kept in step with their state root. Reads go through
+/// `Deref` to the underlying map; writes must use the methods below so the
+/// tree sees them.
+#[derive(Default)]
+pub struct UtxoSet {
+    utxos: HashMap<OutPoint, UTXO>,
+    tree: StateTree,
+    undo: VecDeque<BlockUndo>,
+    /// The newest blocks whose changes left the undo history. Every block
+    /// checked against the set must have them in its past.
+    settled: HashSet<BlockHash>,
+}
+
+impl fmt::Debug for UtxoSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UtxoSet")
+            .field("len", &self.utxos.len())
+            .finish()
+    }
+}
+
+impl Deref for UtxoSet {
+    type Target = HashMap<OutPoint, UTXO>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.utxos
+    }
+}
+
+impl FromIterator<(OutPoint, UTXO)> for UtxoSet {
+    fn from_iter<I: IntoIterator<Item = (OutPoint, UTXO)>>(iter: I) -> Self {
+        let mut set = UtxoSet::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl From<HashMap<OutPoint, UTXO>> for UtxoSet {
+    fn from(utxos: HashMap<OutPoint, UTXO>) -> Self {
+        utxos.into_iter().collect()
+    }
+}
+
+impl UtxoSet {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            utxos: HashMap::with_capacity(capacity),
+            tree: StateTree::default(),
+            undo: VecDeque::new(),
+            settled: HashSet::new(),
+        }
+    }
+
+    fn stage_insert(&mut self, outpoint: OutPoint, utxo: UTXO) -> Option<UTXO> {
+        let key = utxo_key(&outpoint);
+        self.tree.set(key, Some(leaf_hash(&key, &utxo)));
+        self.utxos.insert(outpoint, utxo)
+    }
+
+    fn stage_remove(&mut self, outpoint: &OutPoint) -> Option<UTXO> {
+        let removed = self.utxos.remove(outpoint)?;
+        self.tree.set(utxo_key(outpoint), None);
+        Some(removed)
+    }
+
+    pub fn insert(&mut self, outpoint: OutPoint, utxo: UTXO) -> Option<UTXO> {
+        let replaced = self.stage_insert(outpoint, utxo);
+        self.tree.flush();
+        replaced
+    }
+
+    pub fn remove(&mut self, outpoint: &OutPoint) -> Option<UTXO> {
+        let removed = self.stage_remove(outpoint);
+        self.tree.flush();
+        removed
+    }
+
+    pub fn extend(&mut self, utxos: impl IntoIterator<Item = (OutPoint, UTXO)>) {
+        for (outpoint, utxo) in utxos {
+            self.stage_insert(outpoint, utxo);
+        }
+        self.tree.flush();
+    }
+
+    /// Applies the changes of block `block_id` in order, recording them in the
+    /// undo history with the block's `links`, its parents and cross-chain
+    /// references, and returns the outputs they spent.
+    pub fn apply_block(
+        &mut self,
+        block_id: BlockHash,
+        links: Vec<BlockHash>,
+        changes: Vec<UtxoChange>,
+    ) -> Vec<(OutPoint, UTXO)> {
+        let mut spent = Vec::new();
+        let mut ops = Vec::with_capacity(changes.len());
+        for change in changes {
+            match change {
+                UtxoChange::Spend(outpoint) => {
+                    let removed = self.stage_remove(&outpoint);
+                    let was_held = removed.is_some();
+                    // A block outside this one's past may have spent the
+                    // output already; it is still spent here.
+                    let Some(utxo) = removed.or_else(|| self.recorded(&outpoint)) else {
+                        continue;
+                    };
+                    spent.push((outpoint, utxo.clone()));
+                    ops.push(UndoOp {
+                        outpoint,
+                        utxo,
+                        spent: true,
+                        was_held,
+                    });
+                }
+                UtxoChange::Create(outpoint, utxo) => {
+                    let was_held = self.stage_insert(outpoint, (*utxo).clone()).is_some();
+                    ops.push(UndoOp {
+                        outpoint,
+                        utxo: *utxo,
+                        spent: false,
+                        was_held,
+                    });
+                }
+            }
+        }
+        self.tree.flush();
+        if self.undo.len() == MAX_UNDO_BLOCKS {
+            self.settle_oldest();
+        }
+        self.undo.push_back(BlockUndo {
+            block_id,
+            links,
+            ops,
+        });
+        spent
+    }
+
+    /// Folds the oldest entry of the undo history into the set for good. It
+    /// covers the settled blocks it links to, which were applied before it.
+    fn settle_oldest(&mut self) {
+        if let Some(undo) = self.undo.pop_front() {
+            for link in &undo.links {
+                self.settled.remove(link);
+            }
+            self.settled.insert(undo.block_id);
+        }
+    }
+
+    /// Marks the set as what `block_id` and its past leave behind, with no undo
+    /// history, as after restoring a snapshot taken at that block.
+    pub fn settle_at(&mut self, block_id: BlockHash) {
+        self.undo.clear();
+        self.settled = HashSet::from([block_id]);
+    }
+
+    /// Which entries of the undo history lie in the past that `links` lead
+    /// back to. Blocks are applied after everything they link to, so one pass
+    /// from the newest entry finds them all. Fails if the past leaves out a
+    /// settled block, whose changes cannot be taken back out.
+    fn past_in_history(&self, links: &[BlockHash]) -> Result<Vec<bool>, UtxoSetError> {
+        let mut wanted: HashSet<BlockHash> = links.iter().copied().collect();
+        let mut in_past = vec![false; self.undo.len()];
+        for (position, undo) in self.undo.iter().enumerate().rev() {
+            if wanted.remove(&undo.block_id) {
+                in_past[position] = true;
+                wanted.extend(undo.links.iter().copied());
+            }
+        }
+        match self.settled.iter().find(|id| !wanted.contains(*id)) {
+            Some(id) => Err(UtxoSetError::BeyondHistory(*id)),
+            None => Ok(in_past),
+        }
+    }
+
+    /// The output the undo history last recorded at `outpoint`.
+    fn recorded(&self, outpoint: &OutPoint) -> Option<UTXO> {
+        self.undo
+            .iter()
+            .rev()
+            .flat_map(|undo| undo.ops.iter().rev())
+            .find(|op| op.outpoint == *outpoint)
+            .map(|op| op.utxo.clone())
+    }
+
+    /// The entries where the set differs from what the blocks in `in_past`
+    /// leave behind. An outpoint the history touches is held if it was held
+    /// before the history began or one of them created it, and none of them
+    /// spent it.
+    fn past_overrides(&self, in_past: &[bool]) -> HashMap<OutPoint, Option<&UTXO>> {
+        // Per outpoint: held before the history, created and spent in the past.
+        let mut touched: HashMap<OutPoint, (bool, bool, bool, &UTXO)> = HashMap::new();
+        for (undo, in_past) in self.undo.iter().zip(in_past) {
+            for op in &undo.ops {
+                let entry =
+                    touched
+                        .entry(op.outpoint)
+                        .or_insert((op.was_held, false, false, &op.utxo));
+                if *in_past {
+                    if op.spent {
+                        entry.2 = true;
+                    } else {
+                        entry.1 = true;
+                        entry.3 = &op.utxo;
+                    }
+                }
+            }
+        }
+        touched
+            .into_iter()
+            .filter_map(|(outpoint, (held_before, created, spent, utxo))| {
+                let held = (held_before || created) && !spent;
+                (held != self.utxos.contains_key(&outpoint))
+                    .then_some((outpoint, held.then_some(utxo)))
+            })
+            .collect()
+    }
+
+    /// The set as a block linking to `links`, its parents and cross-chain
+    /// references, sees it.
+    pub fn past(&self, links: &[BlockHash]) -> Result<PastView<'_>, UtxoSetError> {
+        Ok(PastView {
+            set: self,
+            overrides: self.past_overrides(&self.past_in_history(links)?),
+        })
+    }
+
+    /// Which entries of the undo history are `block_id` or lie in its past, or
+    /// `None` once the block has left the history.
+    fn up_to(&self, block_id: &BlockHash) -> Option<Vec<bool>> {
+        let position = self
+            .undo
+            .iter()
+            .rposition(|undo| undo.block_id == *block_id)?;
+        let mut in_past = self.past_in_history(&self.undo[position].links).ok()?;
+        in_past[position] = true;
+        Some(in_past)
+    }
+
+    /// The blocks applied to the set that are neither `block_id` nor in its
+    /// past, or `None` once the block has left the undo history.
+    pub fn blocks_after(&self, block_id: &BlockHash) -> Option<Vec<BlockHash>> {
+        let in_past = self.up_to(block_id)?;
+        Some(
+            self.undo
+                .iter()
+                .zip(in_past)
+                .filter(|(_, in_past)| !in_past)
+                .map(|(undo, _)| undo.block_id)
+                .collect(),
+        )
+    }
+
+    /// The outputs as they stood right after `block_id` was applied, counting
+    /// only the block and its past, or `None` once the block has left the undo
+    /// history.
+    pub fn utxos_at(&self, block_id: &BlockHash) -> Option<HashMap<OutPoint, UTXO>> {
+        let in_past = self.up_to(block_id)?;
+        let mut utxos = self.utxos.clone();
+        for (outpoint, utxo) in self.past_overrides(&in_past) {
+            match utxo {
+                Some(utxo) => utxos.insert(outpoint, utxo.clone()),
+                None => utxos.remove(&outpoint),
+            };
+        }
+        Some(utxos)
+    }
+
+    /// Settles undo history from the oldest end for as long as `discard`
+    /// holds. Later entries are kept even if they match, as rewinding walks
+    /// the history in order.
+    pub fn discard_undo(&mut self, mut discard: impl FnMut(&BlockHash) -> bool) {
+        while self
+            .undo
+            .front()
+            .is_some_and(|undo| discard(&undo.block_id))
+        {
+            self.settle_oldest();
+        }
+    }
+
+    /// Hex-encoded root of the current set.
+    pub fn state_root(&self) -> String {
+        hex::encode(self.tree.root())
+    }
+
+    /// Hex-encoded root a block linking to `links` commits to: the set its
+    /// past leaves behind, after `changes`. Nothing is applied.
+    pub fn state_root_after(
+        &self,
+        links: &[BlockHash],
+        changes: &[UtxoChange],
+    ) -> Result<String, UtxoSetError> {
+        Ok(self.past(links)?.state_root_after(changes))
+    }
+
+    /// Proves that `outpoint` is unspent, or `None` if it is not in the set.
+    pub fn prove(&self, outpoint: &OutPoint) -> Option<UtxoProof> {
+        let utxo = self.utxos.get(outpoint)?.clone();
+        let siblings = self.tree.siblings(&utxo_key(outpoint));
+        Some(UtxoProof {
+            outpoint: *outpoint,
+            utxo,
+            siblings: siblings.iter().map(hex::encode).collect(),
+        })
+    }
+}
+
+/// The outputs the past of a block leaves behind, as seen through the node's
+/// set. Returned by `UtxoSet::past`.
+pub struct PastView<'a> {
+    set: &'a UtxoSet,
+    overrides: HashMap<OutPoint, Option<&'a UTXO>>,
+}
+
+impl<'a> PastView<'a> {
+    pub fn get(&self, outpoint: &OutPoint) -> Option<&'a UTXO> {
+        match self.overrides.get(outpoint) {
+            Some(utxo) => *utxo,
+            None => self.set.utxos.get(outpoint),
+        }
+    }
+
+    /// Hex-encoded root of the view after `changes`.
+    pub fn state_root_after(&self, changes: &[UtxoChange]) -> String {
+        let mut overrides: BTreeMap<Hash, Option<Hash>> = self
+            .overrides
+            .iter()
+            .map(|(outpoint, utxo)| {
+                let key = utxo_key(outpoint);
+                (key, utxo.map(|utxo| leaf_hash(&key, utxo)))
+            })
+            .collect();
+        for change in changes {
+            match change {
+                UtxoChange::Spend(outpoint) => {
+                    overrides.insert(utxo_key(outpoint), None);
+                }
+                UtxoChange::Create(outpoint, utxo) => {
+                    let key = utxo_key(outpoint);
+                    overrides.insert(key, Some(leaf_hash(&key, utxo)));
+                }
+            }
+        }
+        hex::encode(self.set.tree.root_with(&overrides))
+    }
+}
+
+/// Proof that an output is unspent under a UTXO state root.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UtxoProof {
+    pub outpoint: OutPoint,
+    pub utxo: UTXO,
+    /// Hex-encoded sibling hashes from the leaf up to the root.
+    pub siblings: Vec<String>,
+}
+
+impl UtxoProof {
+    /// Checks the proof against a hex-encoded state root.
+    pub fn verify(&self, state_root: &str) -> bool {
+        if self.siblings.len() < TOP_DEPTH || self.siblings.len() > 256 {
+            return false;
+        }
+        let key = utxo_key(&self.outpoint);
+        let mut hash = leaf_hash(&key, &self.utxo);
+        for (i, hex) in self.siblings.iter().enumerate() {
+            let mut sibling = Hash::default();
+            if hex::decode_to_slice(hex, &mut sibling).is_err() {
+                return false;
+            }
+            let depth = self.siblings.len() - 1 - i;
+            hash = if bit(&key, depth) {
+                node_hash(&sibling, &hash)
+            } else {
+                node_hash(&hash, &sibling)
+            };
+        }
+        hex::encode(hash).eq_ignore_ascii_case(state_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Address, TxId};
+
+    fn utxo(seed: u8, index: u32) -> (OutPoint, UTXO) {
+        let tx_id = TxId::new([seed; 32]);
+        let outpoint = OutPoint::new(tx_id, index);
+        let utxo = UTXO {
+            address: Address::new([seed; 32]),
+            amount: seed as u64 * 10 + index as u64,
+            tx_id,
+            output_index: index,
+            explorer_link: String::new(),
+            htlc: None,
+            coinbase: None,
+        };
+        (outpoint, utxo)
+    }
+
+    #[test]
+    fn test_root_depends_only_on_contents() {
+        let mut set = UtxoSet::default();
+        assert_eq!(set.state_root(), EMPTY_ROOT);
+
+        let items: Vec<_> = (0..50u8).map(|i| utxo(i, i as u32 % 3)).collect();
+        set.extend(items.iter().cloned());
+        let root = set.state_root();
+        let mut reversed: UtxoSet = items.iter().rev().cloned().collect();
+        assert_eq!(reversed.state_root(), root);
+
+        reversed.remove(&items[7].0);
+        assert_ne!(reversed.state_root(), root);
+        reversed.insert(items[7].0, items[7].1.clone());
+        assert_eq!(reversed.state_root(), root);
+
+        for (outpoint, _) in &items {
+            set.remove(outpoint);
+        }
+        assert_eq!(set.state_root(), EMPTY_ROOT);
+    }
+
+    #[test]
+    fn test_staged_root_matches_applied_root() {
+        let mut set: UtxoSet = (0..20u8).map(|i| utxo(i, 0)).collect();
+        let before = set.state_root();
+        let (created, created_utxo) = utxo(200, 1);
+        let (chained, chained_utxo) = utxo(201, 0);
+        let changes = vec![
+            UtxoChange::Spend(utxo(3, 0).0),
+            UtxoChange::Create(created, Box::new(created_utxo)),
+            UtxoChange::Create(chained, Box::new(chained_utxo)),
+            UtxoChange::Spend(chained),
+        ];
+        let staged = set.state_root_after(&[], &changes).unwrap();
+        assert_eq!(set.state_root(), before);
+
+        let spent = set.apply_block(BlockHash::new([1; 32]), Vec::new(), changes);
+        assert_eq!(spent.len(), 2);
+        assert_eq!(set.state_root(), staged);
+        assert!(!set.contains_key(&chained));
+    }
+
+    #[test]
+    fn test_utxos_at_rolls_back_later_blocks() {
+        let mut set: UtxoSet = (0..10u8).map(|i| utxo(i, 0)).collect();
+        let first = BlockHash::new([1; 32]);
+        let (created, created_utxo) = utxo(100, 0);
+        set.apply_block(
+            first,
+            Vec::new(),
+            vec![
+                UtxoChange::Spend(utxo(1, 0).0),
+                UtxoChange::Create(created, Box::new(created_utxo)),
+            ],
+        );
+        let root_after_first = set.state_root();
+
+        let (chained, chained_utxo) = utxo(101, 0);
+        set.apply_block(
+            BlockHash::new([2; 32]),
+            vec![first],
+            vec![
+                UtxoChange::Spend(created),
+                UtxoChange::Spend(utxo(2, 0).0),
+                UtxoChange::Create(chained, Box::new(chained_utxo)),
+                UtxoChange::Spend(chained),
+            ],
+        );
+        assert_ne!(set.state_root(), root_after_first);
+
+        let rebuilt = UtxoSet::from(set.utxos_at(&first).unwrap());
+        assert_eq!(rebuilt.state_root(), root_after_first);
+        assert_eq!(
+            set.blocks_after(&first),
+            Some(vec![BlockHash::new([2; 32])])
+        );
+        assert!(set.utxos_at(&BlockHash::new([3; 32])).is_none());
+
+        let second = BlockHash::new([2; 32]);
+        set.discard_undo(|id| *id == second);
+        assert!(set.utxos_at(&first).is_some());
+        set.discard_undo(|id| *id == first);
+        assert!(set.utxos_at(&first).is_none());
+        assert!(set.utxos_at(&second).is_some());
+    }
+
+    #[test]
+    fn test_root_is_relative_to_the_past_not_arrival_order() {
+        let genesis: Vec<_> = (0..10u8).map(|i| utxo(i, 0)).collect();
+        let (a_id, b_id, c_id) = (
+            BlockHash::new([1; 32]),
+            BlockHash::new([2; 32]),
+            BlockHash::new([3; 32]),
+        );
+        let (a_out, a_utxo) = utxo(100, 0);
+        let a_changes = vec![
+            UtxoChange::Spend(genesis[1].0),
+            UtxoChange::Create(a_out, Box::new(a_utxo)),
+        ];
+        let (b_out, b_utxo) = utxo(101, 0);
+        let b_changes = vec![
+            UtxoChange::Spend(genesis[2].0),
+            UtxoChange::Create(b_out, Box::new(b_utxo)),
+        ];
+        let c_changes = vec![UtxoChange::Spend(a_out), UtxoChange::Spend(b_out)];
+
+        let mut roots = Vec::new();
+        for a_first in [true, false] {
+            let mut set: UtxoSet = genesis.iter().cloned().collect();
+            let mut seen = HashMap::new();
+            let order = if a_first { [0, 1] } else { [1, 0] };
+            for block in order {
+                let (id, changes) = if block == 0 {
+                    (a_id, &a_changes)
+                } else {
+                    (b_id, &b_changes)
+                };
+                seen.insert(block, set.state_root_after(&[], changes).unwrap());
+                set.apply_block(id, Vec::new(), changes.clone());
+            }
+            let c_root = set.state_root_after(&[a_id, b_id], &c_changes).unwrap();
+            let a_only = set.state_root_after(&[a_id], &[]).unwrap();
+            set.apply_block(c_id, vec![a_id, b_id], c_changes.clone());
+            assert_eq!(set.state_root(), c_root);
+            let at_a = UtxoSet::from(set.utxos_at(&a_id).unwrap()).state_root();
+            roots.push((seen[&0].clone(), seen[&1].clone(), c_root, a_only, at_a));
+        }
+        assert_eq!(roots[0], roots[1]);
+        let (a_root, _, _, a_only, at_a) = &roots[0];
+        assert_eq!(a_only, a_root);
+        assert_eq!(at_a, a_root);
+    }
+
+    #[test]
+    fn test_past_view_hides_sibling_branches() {
+        let genesis: Vec<_> = (0..4u8).map(|i| utxo(i, 0)).collect();
+        let mut set: UtxoSet = genesis.iter().cloned().collect();
+        let (a_id, b_id) = (BlockHash::new([1; 32]), BlockHash::new([2; 32]));
+        let (a_out, a_utxo) = utxo(100, 0);
+        set.apply_block(
+            a_id,
+            Vec::new(),
+            vec![
+                UtxoChange::Spend(genesis[1].0),
+                UtxoChange::Create(a_out, Box::new(a_utxo)),
+            ],
+        );
+
+        // A sibling of A cannot spend what A created, though the set holds it.
+        let sibling = set.past(&[]).unwrap();
+        assert!(set.contains_key(&a_out));
+        assert!(sibling.get(&a_out).is_none());
+        assert!(set.past(&[a_id]).unwrap().get(&a_out).is_some());
+
+        // It can spend what A spent, as a node that never saw A would allow.
+        assert!(sibling.get(&genesis[1].0).is_some());
+        let b_changes = vec![UtxoChange::Spend(genesis[1].0)];
+        let b_root = sibling.state_root_after(&b_changes);
+        set.apply_block(b_id, Vec::new(), b_changes.clone());
+        let mut unaware: UtxoSet = genesis.iter().cloned().collect();
+        assert_eq!(unaware.state_root_after(&[], &b_changes).unwrap(), b_root);
+        unaware.apply_block(b_id, Vec::new(), b_changes);
+
+        // The output stays spent for every block with A or B in its past.
+        assert!(set.past(&[a_id]).unwrap().get(&genesis[1].0).is_none());
+        assert!(set.past(&[b_id]).unwrap().get(&genesis[1].0).is_none());
+        assert_eq!(
+            set.past(&[b_id]).unwrap().state_root_after(&[]),
+            unaware.state_root()
+        );
+    }
+
+    #[test]
+    fn test_past_must_cover_settled_blocks() {
+        let mut set: UtxoSet = (0..4u8).map(|i| utxo(i, 0)).collect();
+        let (a_id, b_id) = (BlockHash::new([1; 32]), BlockHash::new([2; 32]));
+        set.apply_block(a_id, Vec::new(), vec![UtxoChange::Spend(utxo(1, 0).0)]);
+        set.apply_block(b_id, Vec::new(), vec![UtxoChange::Spend(utxo(2, 0).0)]);
+        set.discard_undo(|id| *id == a_id);
+
+        // A's changes can no longer be taken out for a block that leaves it out.
+        assert_eq!(
+            set.past(&[b_id]).err(),
+            Some(UtxoSetError::BeyondHistory(a_id))
+        );
+        assert!(set.past(&[b_id, a_id]).is_ok());
+        assert!(set.utxos_at(&b_id).is_none());
+
+        let c_id = BlockHash::new([3; 32]);
+        set.apply_block(c_id, vec![a_id, b_id], Vec::new());
+        assert!(set.past(&[c_id]).is_ok());
+
+        // After a snapshot restore, every block must build on the checkpoint.
+        set.settle_at(c_id);
+        assert_eq!(
+            set.past(&[a_id]).err(),
+            Some(UtxoSetError::BeyondHistory(c_id))
+        );
+        assert!(set.past(&[c_id]).is_ok());
+    }
+
+    #[test]
+    fn test_unspent_proofs() {
+        let mut set: UtxoSet = (0..30u8).map(|i| utxo(i, 1)).collect();
+        let root = set.state_root();
+        for i in 0..30u8 {
+            let proof = set.prove(&utxo(i, 1).0).unwrap();
+            assert!(proof.verify(&root), "output {i}");
+        }
+        assert!(set.prove(&utxo(99, 1).0).is_none());
+
+        let proof = set.prove(&utxo(5, 1).0).unwrap();
+        let mut inflated = proof.clone();
+        inflated.utxo.amount += 1;
+        assert!(!inflated.verify(&root));
+        let mut truncated = proof.clone();
+        truncated.siblings.pop();
+        assert!(!truncated.verify(&root));
+
+        set.remove(&utxo(5, 1).0);
+        assert!(!proof.verify(&set.state_root()));
+    }
+}
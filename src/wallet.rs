@@ -10,6 +10,7 @@
 //! - DUAL KEY SUPPORT: The wallet continues to generate, store, and manage both
 //!   Ed25519 and Dilithium keypairs.
 
+use crate::types::Address;
 use aes_gcm::aead::{Aead, AeadCore, OsRng};
 use aes_gcm::{aead::generic_array, Aes256Gcm, Key, KeyInit};
 use anyhow::{Context, Result};
@@ -98,6 +99,12 @@ impl<'de> Deserialize<'de> for WalletData {
         D: serde::Deserializer<'de>,
     {
         let plain = PlainWalletData::deserialize(deserializer)?;
+        if plain.verifying_key.len() != 32 {
+            return Err(serde::de::Error::invalid_length(
+                plain.verifying_key.len(),
+                &"a 32-byte verifying key",
+            ));
+        }
         Ok(WalletData {
             signing_key: SecretVec::new(plain.signing_key),
             verifying_key: plain.verifying_key,
@@ -214,8 +221,15 @@ impl Wallet {
             .map_err(WalletError::from)
     }
 
-    pub fn address(&self) -> String {
-        hex::encode(&self.data.verifying_key)
+    /// The wallet's address, which is its Ed25519 verifying key.
+    pub fn address(&self) -> Address {
+        let bytes: [u8; 32] = self
+            .data
+            .verifying_key
+            .as_slice()
+            .try_into()
+            .expect("verifying keys are 32 bytes");
+        Address::new(bytes)
     }
 
     pub fn mnemonic(&self) -> &Secret<String> {
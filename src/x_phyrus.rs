@@ -4,6 +4,7 @@
 //! with a strong focus on post-quantum resilience, cloud-adaptive capabilities, and auto-healing.
 
 use crate::config::Config;
+use crate::data_dir::DataDir;
 use anyhow::{Context, Result};
 use log::{debug, error, info, warn};
 use rocksdb::Options;
@@ -40,7 +41,7 @@ async fn init_zero_hang_bootloader(config: &Config, wallet_path: &Path) -> Resul
     check_system_entropy()?;
     check_file_integrity(wallet_path).await?;
     check_port_availability(config).await?;
-    check_chain_state_integrity().await?;
+    check_chain_state_integrity(config).await?;
     info!("[X-PHYRUS::Zero-Hang™] Node integrity checks completed successfully.");
     Ok(())
 }
@@ -153,17 +154,23 @@ async fn check_port_availability(config: &Config) -> Result<()> {
 /// Sophistication: Beyond read-only open, could perform a lightweight consistency check
 /// (e.g., verify genesis block, latest block index, or a few random block lookups)
 /// to detect deeper corruption before full node operation.
-async fn check_chain_state_integrity() -> Result<()> {
+async fn check_chain_state_integrity(config: &Config) -> Result<()> {
     debug!("[X-PHYRUS::Zero-Hang™] Checking chain state integrity...");
-    const DB_PATH: &str = "qantodag_db_evolved";
-    if !Path::new(DB_PATH).exists() {
-        warn!("[INFO] Chain state DB not found at '{DB_PATH}'. This is normal for a first run.");
+    let db_path = DataDir::new(&config.data_dir).db();
+    if !db_path.exists() {
+        warn!(
+            "[INFO] Chain state DB not found at '{}'. This is normal for a first run.",
+            db_path.display()
+        );
         return Ok(());
     }
 
-    info!("[INFO] Found existing database at '{DB_PATH}'. Attempting to open read-only to verify integrity...");
+    info!(
+        "[INFO] Found existing database at '{}'. Attempting to open read-only to verify integrity...",
+        db_path.display()
+    );
     let opts = Options::default();
-    match rocksdb::DB::open_for_read_only(&opts, DB_PATH, false) {
+    match rocksdb::DB::open_for_read_only(&opts, &db_path, false) {
         Ok(db) => {
             // Advanced: (Conceptual) Perform a lightweight consistency check
             // For example, try to retrieve the genesis block.
@@ -180,7 +187,7 @@ async fn check_chain_state_integrity() -> Result<()> {
             error!("FATAL: Could not open existing chain state database: {e}. The database may be corrupt or locked by another process. Please resolve the issue before restarting. Halting startup.");
             Err(anyhow::anyhow!(
                 "Chain state DB at '{}' is inaccessible or corrupt: {}",
-                DB_PATH,
+                db_path.display(),
                 e
             ))
         }
@@ -271,7 +278,7 @@ async fn init_extended_protocols(config: &Config) -> Result<()> {
     init_peer_flash(config).await?;
     init_quantum_shield(config).await?;
     init_cloud_anchor().await?;
-    init_phase_trace().await?;
+    init_phase_trace(config).await?;
     init_traceforce_x().await?;
     // New: Auto-healing protocol (conceptual)
     init_auto_heal_protocols().await?;
@@ -391,8 +398,9 @@ async fn init_cloud_anchor() -> Result<()> {
 /// Sophistication: Beyond simple metadata check, this initiates a connection to a distributed
 /// graph database (e.g., Dgraph, Neo4j) to store and query block propagation paths, latency,
 /// and anomaly events for forensic analysis and real-time visualization.
-async fn init_phase_trace() -> Result<()> {
-    if fs::metadata("./qantodag_db_evolved/CURRENT").await.is_ok() {
+async fn init_phase_trace(config: &Config) -> Result<()> {
+    let db_path = DataDir::new(&config.data_dir).db();
+    if fs::metadata(db_path.join("CURRENT")).await.is_ok() {
         info!("[X-PHYRUS::PhaseTrace™] DB backend verified. Traceable block propagation graph is ACTIVE.");
         // Advanced: (Conceptual)
         // 1. Establish connection to a distributed graph database (e.g., via a gRPC client).